@@ -0,0 +1,42 @@
+//! Compares a power-of-two capacity, which wraps around with a mask, against
+//! the next smaller capacity, which falls back to division and a
+//! compare-and-reset branch, both for writing alone and for writing one item
+//! and reading it back. Run with `cargo bench`.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use spmcq::ring_buffer;
+
+const ITERATIONS: u32 = 10_000_000;
+
+fn time_per_round(capacity: usize) -> (Duration, Duration) {
+    let (mut reader, mut writer) = ring_buffer::<u64>(capacity);
+
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        writer.write(black_box(i as u64));
+    }
+    let write = start.elapsed() / ITERATIONS;
+
+    // Only the last lap is left to read, so interleave writes and reads
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        writer.write(black_box(i as u64));
+        black_box(reader.read());
+    }
+    let write_read = start.elapsed() / ITERATIONS;
+
+    (write, write_read)
+}
+
+fn main() {
+    for capacity in [64, 63] {
+        let (write, write_read) = time_per_round(capacity);
+        println!("capacity {}:", capacity);
+        println!("  write:        {:?}", write);
+        println!("  write + read: {:?}", write_read);
+    }
+}
@@ -0,0 +1,56 @@
+//! Measures the cost of writing `u64` samples while readers keep up on other
+//! threads, where small items would share cache lines without padding. Run
+//! with `cargo bench --bench false_sharing`, then again with
+//! `--no-default-features` to compare against unpadded items.
+
+use std::{
+    hint::black_box,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use spmcq::{ring_buffer, Writer};
+
+const WRITES: u32 = 10_000_000;
+
+fn time_per_write(writer: &mut Writer<u64>) -> Duration {
+    let start = Instant::now();
+    for i in 0..WRITES {
+        writer.write(black_box(i as u64));
+    }
+    start.elapsed() / WRITES
+}
+
+fn main() {
+    let (reader, mut writer) = ring_buffer::<u64>(1024);
+
+    println!(
+        "cache-padded: {}, {} bytes per item",
+        cfg!(feature = "cache-padded"),
+        writer.memory_footprint() / writer.capacity()
+    );
+    println!("  no readers busy: {:?}", time_per_write(&mut writer));
+
+    for readers in [1, 3] {
+        let stop = AtomicBool::new(false);
+        std::thread::scope(|s| {
+            for _ in 0..readers {
+                let mut reader = reader.clone();
+                let stop = &stop;
+                s.spawn(move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        black_box(reader.read());
+                    }
+                });
+            }
+
+            println!(
+                "  {} readers busy: {:?}",
+                readers,
+                time_per_write(&mut writer)
+            );
+
+            stop.store(true, Ordering::Relaxed);
+        });
+    }
+}
@@ -0,0 +1,51 @@
+//! Measures the cost of writing while several readers keep polling the queue
+//! for the next item, as they do whenever they're caught up with the writer.
+//! Readers that poll without locking the item leave its cache line alone
+//! until there is something to read. Run with `cargo bench`.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use spmcq::{ring_buffer, ReadResult};
+
+const ITERATIONS: u32 = 1_000_000;
+
+fn main() {
+    for readers in [0, 1, 3] {
+        let (reader, mut writer) = ring_buffer::<[f32; 4]>(64);
+
+        let (per_write, polls) = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..readers)
+                .map(|_| {
+                    let mut reader = reader.clone();
+                    s.spawn(move || {
+                        let mut polls: u64 = 0;
+                        loop {
+                            polls += 1;
+                            match black_box(reader.read()) {
+                                ReadResult::Closed => return polls,
+                                _ => continue,
+                            }
+                        }
+                    })
+                })
+                .collect();
+
+            let start = Instant::now();
+            for i in 0..ITERATIONS {
+                writer.write(black_box([i as f32; 4]));
+            }
+            let per_write: Duration = start.elapsed() / ITERATIONS;
+            drop(writer);
+
+            let polls: u64 = handles.into_iter().map(|h| h.join().unwrap()).sum();
+            (per_write, polls)
+        });
+
+        println!("{} readers polling:", readers);
+        println!("  write: {:?}", per_write);
+        println!("  polls per write: {:.1}", polls as f64 / ITERATIONS as f64);
+    }
+}
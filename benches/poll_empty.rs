@@ -0,0 +1,52 @@
+//! Compares the cost of polling an empty queue with `Reader::read` against
+//! `Reader::has_data`, both for a lone reader and while other readers are
+//! polling the same item from other threads. Run with `cargo bench`.
+
+use std::{
+    hint::black_box,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use spmcq::{ring_buffer, Reader};
+
+const ITERATIONS: u32 = 10_000_000;
+
+fn time_per_poll(reader: &mut Reader<[f32; 16]>, use_has_data: bool) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        if use_has_data {
+            black_box(reader.has_data());
+        } else {
+            black_box(reader.read());
+        }
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let (mut reader, _writer) = ring_buffer::<[f32; 16]>(64);
+
+    println!("one reader:");
+    println!("  read:     {:?}", time_per_poll(&mut reader, false));
+    println!("  has_data: {:?}", time_per_poll(&mut reader, true));
+
+    let stop = AtomicBool::new(false);
+    std::thread::scope(|s| {
+        for _ in 0..3 {
+            let mut other_reader = reader.clone();
+            let stop = &stop;
+            s.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    black_box(other_reader.read());
+                }
+            });
+        }
+
+        println!("four readers:");
+        println!("  read:     {:?}", time_per_poll(&mut reader, false));
+        println!("  has_data: {:?}", time_per_poll(&mut reader, true));
+
+        stop.store(true, Ordering::Relaxed);
+    });
+}
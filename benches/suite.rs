@@ -0,0 +1,254 @@
+//! The criterion benchmarks, which cover the basic operations for a few
+//! capacities so that changes to orderings, padding or the batch APIs can be
+//! compared across commits. Every benchmark is named `group/item/capacity`,
+//! e.g. `broadcast_3_readers/blob/32`, and those names must stay the same for
+//! criterion to compare against earlier runs. Items named `u64_atomic` go
+//! through a [ring_buffer_atomic] instead of the generic ring buffer. Run with
+//! `cargo bench --bench suite`, or save a baseline with
+//! `cargo bench --bench suite -- --save-baseline main` and compare against it
+//! later with `--baseline main`.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use spmcq::{ring_buffer, ring_buffer_atomic, ReadResult, Reader, Writer};
+
+const CAPACITIES: [usize; 3] = [2, 32, 1024];
+
+/// A large item, which takes a while to copy. Its contents are never looked
+/// at, only copied around.
+#[derive(Clone, Copy)]
+struct Blob(#[allow(dead_code)] [u8; 1024]);
+
+impl Default for Blob {
+    fn default() -> Self {
+        Blob([0; 1024])
+    }
+}
+
+/// Writes with nobody reading
+fn write(c: &mut Criterion) {
+    let mut group = c.benchmark_group("write");
+    group.throughput(Throughput::Elements(1));
+    for capacity in CAPACITIES {
+        let (_reader, mut writer) = ring_buffer::<u64>(capacity);
+        group.bench_function(BenchmarkId::new("u64", capacity), |b| {
+            let mut i = 0;
+            b.iter(|| {
+                writer.write(black_box(i));
+                i += 1;
+            });
+        });
+
+        let (_reader, mut writer) = ring_buffer_atomic::<u64>(capacity);
+        group.bench_function(BenchmarkId::new("u64_atomic", capacity), |b| {
+            let mut i = 0;
+            b.iter(|| {
+                writer.write(black_box(i));
+                i += 1;
+            });
+        });
+    }
+    group.finish();
+}
+
+/// Reads of items that are waiting in the queue, with the writer idle. The
+/// queue is refilled between batches of reads without being timed.
+fn read(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read");
+    group.throughput(Throughput::Elements(1));
+    for capacity in CAPACITIES {
+        let (mut reader, mut writer) = ring_buffer::<u64>(capacity);
+        group.bench_function(BenchmarkId::new("u64", capacity), |b| {
+            b.iter_custom(|iters| {
+                let mut elapsed = Duration::ZERO;
+                let mut remaining = iters;
+                while remaining > 0 {
+                    let batch = remaining.min(capacity as u64);
+                    for i in 0..batch {
+                        writer.write(i);
+                    }
+
+                    let start = Instant::now();
+                    for _ in 0..batch {
+                        black_box(reader.read());
+                    }
+                    elapsed += start.elapsed();
+                    remaining -= batch;
+                }
+                elapsed
+            });
+        });
+
+        let (mut reader, mut writer) = ring_buffer_atomic::<u64>(capacity);
+        group.bench_function(BenchmarkId::new("u64_atomic", capacity), |b| {
+            b.iter_custom(|iters| {
+                let mut elapsed = Duration::ZERO;
+                let mut remaining = iters;
+                while remaining > 0 {
+                    let batch = remaining.min(capacity as u64);
+                    for i in 0..batch {
+                        writer.write(i);
+                    }
+
+                    let start = Instant::now();
+                    for _ in 0..batch {
+                        black_box(reader.read());
+                    }
+                    elapsed += start.elapsed();
+                    remaining -= batch;
+                }
+                elapsed
+            });
+        });
+    }
+    group.finish();
+}
+
+/// The round trip of an item which another thread reads and writes back
+/// through a second ring buffer, as soon as it sees it
+fn ping_pong(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ping_pong");
+    for capacity in CAPACITIES {
+        group.bench_function(BenchmarkId::new("u64", capacity), |b| {
+            b.iter_custom(|iters| {
+                let (mut ping_reader, mut ping_writer) = ring_buffer::<u64>(capacity);
+                let (mut pong_reader, mut pong_writer) = ring_buffer::<u64>(capacity);
+
+                std::thread::scope(|s| {
+                    s.spawn(move || loop {
+                        match ping_reader.read() {
+                            ReadResult::Ok(i) | ReadResult::Dropout(i) => {
+                                pong_writer.write(i);
+                            }
+                            ReadResult::Empty => std::hint::spin_loop(),
+                            ReadResult::Closed => return,
+                        }
+                    });
+
+                    let start = Instant::now();
+                    for i in 0..iters {
+                        ping_writer.write(i);
+                        while pong_reader.read().value().is_none() {
+                            std::hint::spin_loop();
+                        }
+                    }
+                    let elapsed = start.elapsed();
+
+                    // Lets the other thread finish
+                    drop(ping_writer);
+                    elapsed
+                })
+            });
+        });
+    }
+    group.finish();
+}
+
+/// Writes of `iters` items, each of which three readers on other threads
+/// receive unless they were overtaken, timed until all readers are done
+fn broadcast<T: Copy + Default + Send>(iters: u64, capacity: usize, value: T) -> Duration {
+    let (reader, writer) = ring_buffer::<T>(capacity);
+
+    fn read_all<T: Copy>(mut reader: Reader<T>) {
+        loop {
+            match black_box(reader.read()) {
+                ReadResult::Closed => return,
+                ReadResult::Empty => std::hint::spin_loop(),
+                _ => {}
+            }
+        }
+    }
+
+    fn write_all<T: Copy>(mut writer: Writer<T>, iters: u64, value: T) {
+        for _ in 0..iters {
+            writer.write(black_box(value));
+        }
+    }
+
+    std::thread::scope(|s| {
+        let start = Instant::now();
+        for _ in 0..3 {
+            let reader = reader.clone();
+            s.spawn(move || read_all(reader));
+        }
+        drop(reader);
+        write_all(writer, iters, value);
+        start
+    })
+    .elapsed()
+}
+
+/// The same as [broadcast], through a [ring_buffer_atomic]
+fn broadcast_atomic(iters: u64, capacity: usize) -> Duration {
+    let (reader, mut writer) = ring_buffer_atomic::<u64>(capacity);
+
+    std::thread::scope(|s| {
+        let start = Instant::now();
+        for _ in 0..3 {
+            let mut reader = reader.clone();
+            s.spawn(move || loop {
+                match black_box(reader.read()) {
+                    ReadResult::Closed => return,
+                    ReadResult::Empty => std::hint::spin_loop(),
+                    _ => {}
+                }
+            });
+        }
+        drop(reader);
+        for i in 0..iters {
+            writer.write(black_box(i));
+        }
+        drop(writer);
+        start
+    })
+    .elapsed()
+}
+
+fn broadcast_3_readers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("broadcast_3_readers");
+    group.throughput(Throughput::Elements(1));
+    for capacity in CAPACITIES {
+        group.bench_function(BenchmarkId::new("u64", capacity), |b| {
+            b.iter_custom(|iters| broadcast(iters, capacity, 1u64));
+        });
+        group.bench_function(BenchmarkId::new("u64_atomic", capacity), |b| {
+            b.iter_custom(|iters| broadcast_atomic(iters, capacity));
+        });
+        group.bench_function(BenchmarkId::new("blob", capacity), |b| {
+            b.iter_custom(|iters| broadcast(iters, capacity, Blob([1; 1024])));
+        });
+    }
+    group.finish();
+}
+
+/// Reads from a queue that has nothing new, as readers that poll do
+fn empty_poll(c: &mut Criterion) {
+    let mut group = c.benchmark_group("empty_poll");
+    for capacity in CAPACITIES {
+        let (mut reader, mut writer) = ring_buffer::<u64>(capacity);
+        writer.write(0);
+        reader.read();
+        group.bench_function(BenchmarkId::new("u64", capacity), |b| {
+            b.iter(|| black_box(reader.read()));
+        });
+
+        let (mut reader, mut writer) = ring_buffer_atomic::<u64>(capacity);
+        writer.write(0);
+        reader.read();
+        group.bench_function(BenchmarkId::new("u64_atomic", capacity), |b| {
+            b.iter(|| black_box(reader.read()));
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().measurement_time(Duration::from_secs(3));
+    targets = write, read, ping_pong, broadcast_3_readers, empty_poll
+}
+criterion_main!(benches);
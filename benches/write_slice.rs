@@ -0,0 +1,55 @@
+//! Compares the cost per item of writing a frame of audio samples with one
+//! call to `Writer::write_slice` against calling `Writer::write` for every
+//! sample, with a reader keeping up on another thread. Run with
+//! `cargo bench`.
+
+use std::{
+    hint::black_box,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use spmcq::{ring_buffer, Writer};
+
+const FRAME: usize = 512;
+const FRAMES: u32 = 20_000;
+
+fn time_per_item(writer: &mut Writer<f32>, use_write_slice: bool) -> Duration {
+    let frame = [0.5_f32; FRAME];
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        if use_write_slice {
+            writer.write_slice(black_box(&frame));
+        } else {
+            for sample in black_box(&frame) {
+                writer.write(*sample);
+            }
+        }
+    }
+    start.elapsed() / (FRAMES * FRAME as u32)
+}
+
+fn main() {
+    let (reader, mut writer) = ring_buffer::<f32>(4 * FRAME);
+
+    println!("no reader busy:");
+    println!("  write:       {:?}", time_per_item(&mut writer, false));
+    println!("  write_slice: {:?}", time_per_item(&mut writer, true));
+
+    let stop = AtomicBool::new(false);
+    std::thread::scope(|s| {
+        let mut reader = reader;
+        let stop = &stop;
+        s.spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                black_box(reader.read());
+            }
+        });
+
+        println!("one reader busy:");
+        println!("  write:       {:?}", time_per_item(&mut writer, false));
+        println!("  write_slice: {:?}", time_per_item(&mut writer, true));
+
+        stop.store(true, Ordering::Relaxed);
+    });
+}
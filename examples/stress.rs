@@ -0,0 +1,19 @@
+//! Runs the stress test in `tests/stress.rs` for as long as asked, e.g. to
+//! soak test on a new platform. See `--help` for the options.
+
+#[path = "../tests/stress.rs"]
+mod stress;
+
+fn main() {
+    match stress::Config::parse(std::env::args().skip(1)) {
+        Ok(Some(config)) => {
+            println!("{:?}", config);
+            stress::run(&config);
+        }
+        Ok(None) => print!("{}", stress::HELP),
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, stress::HELP);
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,11 @@
+//! Compares a ring buffer against the reference model of `tests/model.rs`
+//! for arbitrary sequences of operations
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+#[path = "../../tests/model.rs"]
+mod model;
+
+fuzz_target!(|data: &[u8]| model::run(data));
@@ -0,0 +1,371 @@
+//! A ring buffer for values that fit into a single 64-bit word, such as
+//! numbers, timestamps or small structs. Each value is stored in an atomic of
+//! its own, so unlike the items of a [Reader](crate::Reader) and
+//! [Writer](crate::Writer), slots are never locked. Alongside each value, a
+//! stamp records which sequence number it belongs to, which readers check
+//! before and after loading the value to tell whether they were overtaken.
+
+use std::sync::Arc;
+
+use crate::{
+    storage::{Header, Indexing},
+    sync::{AtomicU64, Ordering},
+    ReadResult,
+};
+
+/// Values that can be stored in a [ring_buffer_atomic], by converting them to
+/// and from the bits of a `u64`. This is implemented for the primitive types
+/// up to 64 bits, and can be implemented for small structs as well:
+///
+/// ```
+/// use spmcq::AtomicStorable;
+///
+/// #[derive(Clone, Copy)]
+/// struct Point {
+///     x: f32,
+///     y: f32,
+/// }
+///
+/// impl AtomicStorable for Point {
+///     fn into_bits(self) -> u64 {
+///         (self.x.to_bits() as u64) << 32 | self.y.to_bits() as u64
+///     }
+///
+///     fn from_bits(bits: u64) -> Point {
+///         Point {
+///             x: f32::from_bits((bits >> 32) as u32),
+///             y: f32::from_bits(bits as u32),
+///         }
+///     }
+/// }
+/// ```
+pub trait AtomicStorable: Copy {
+    /// Convert the value into the bits that are stored
+    fn into_bits(self) -> u64;
+
+    /// Convert the stored bits back into a value. This only ever receives
+    /// bits that were returned by [AtomicStorable::into_bits].
+    fn from_bits(bits: u64) -> Self;
+}
+
+macro_rules! impl_atomic_storable {
+    ($($t:ty),*) => {
+        $(
+            impl AtomicStorable for $t {
+                #[inline]
+                fn into_bits(self) -> u64 {
+                    self as u64
+                }
+
+                #[inline]
+                fn from_bits(bits: u64) -> $t {
+                    bits as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_atomic_storable!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl AtomicStorable for f32 {
+    #[inline]
+    fn into_bits(self) -> u64 {
+        self.to_bits() as u64
+    }
+
+    #[inline]
+    fn from_bits(bits: u64) -> f32 {
+        f32::from_bits(bits as u32)
+    }
+}
+
+impl AtomicStorable for f64 {
+    #[inline]
+    fn into_bits(self) -> u64 {
+        self.to_bits()
+    }
+
+    #[inline]
+    fn from_bits(bits: u64) -> f64 {
+        f64::from_bits(bits)
+    }
+}
+
+impl AtomicStorable for bool {
+    #[inline]
+    fn into_bits(self) -> u64 {
+        self as u64
+    }
+
+    #[inline]
+    fn from_bits(bits: u64) -> bool {
+        bits != 0
+    }
+}
+
+impl AtomicStorable for char {
+    #[inline]
+    fn into_bits(self) -> u64 {
+        self as u64
+    }
+
+    #[inline]
+    fn from_bits(bits: u64) -> char {
+        char::from_u32(bits as u32).unwrap_or_default()
+    }
+}
+
+// With the `cache-padded` feature, each slot takes up a whole cache line, see
+// Item
+#[cfg_attr(feature = "cache-padded", repr(align(64)))]
+struct Slot {
+    // Which value the slot holds. Zero if it was never written, otherwise
+    // twice the sequence number of the value plus one while the writer is
+    // storing it, and plus two once it's done.
+    stamp: AtomicU64,
+
+    // The bits of the value, see AtomicStorable::into_bits
+    bits: AtomicU64,
+}
+
+/// Returns the stamp of a slot whose value with the given sequence number has
+/// been stored completely
+#[inline]
+fn written_stamp(sequence: u64) -> u64 {
+    2 * sequence + 2
+}
+
+struct Shared {
+    header: Header,
+    slots: Box<[Slot]>,
+    indexing: Indexing,
+}
+
+/// Construct a new ring buffer for word-sized values consisting of an
+/// [AtomicReader] and an [AtomicWriter]. Reading and writing behave like with
+/// [ring_buffer], except that neither readers nor the writer ever wait for
+/// each other. Reading a slot that the writer is overwriting at the same time
+/// counts as being overtaken instead.
+///
+/// ```
+/// use spmcq::{ring_buffer_atomic, ReadResult};
+///
+/// let (mut reader, mut writer) = ring_buffer_atomic::<f64>(4);
+/// writer.write(1.5);
+/// writer.write(2.5);
+///
+/// assert_eq!(reader.read(), ReadResult::Ok(1.5));
+/// assert_eq!(reader.read(), ReadResult::Ok(2.5));
+/// assert_eq!(reader.read(), ReadResult::Empty);
+/// ```
+///
+/// # Panics
+/// Panics if `capacity` is zero.
+///
+/// [ring_buffer]: crate::ring_buffer
+pub fn ring_buffer_atomic<T>(capacity: usize) -> (AtomicReader<T>, AtomicWriter<T>)
+where
+    T: AtomicStorable,
+{
+    assert!(capacity > 0, "ring buffer must have capacity of at least 1");
+
+    let shared = Arc::new(Shared {
+        header: Header::new(),
+        slots: (0..capacity)
+            .map(|_| Slot {
+                stamp: AtomicU64::new(0),
+                bits: AtomicU64::new(0),
+            })
+            .collect(),
+        indexing: Indexing::new(capacity),
+    });
+    shared.header.reader_count.fetch_add(1, Ordering::SeqCst);
+
+    let reader = AtomicReader {
+        shared: Arc::clone(&shared),
+        sequence: 0,
+        lost: false,
+        _phantom: std::marker::PhantomData,
+    };
+    let writer = AtomicWriter {
+        shared,
+        sequence: 0,
+        _phantom: std::marker::PhantomData,
+    };
+
+    (reader, writer)
+}
+
+/// The reading end of a [ring_buffer_atomic]. Readers can be cloned, and
+/// each receives every value.
+pub struct AtomicReader<T> {
+    shared: Arc<Shared>,
+
+    // The sequence number of the next value to read
+    sequence: u64,
+
+    // Whether any values were passed over since the last read
+    lost: bool,
+
+    _phantom: std::marker::PhantomData<T>,
+}
+
+/// The writing end of a [ring_buffer_atomic]. Dropping it closes the ring
+/// buffer.
+pub struct AtomicWriter<T> {
+    shared: Arc<Shared>,
+
+    // The sequence number of the next value to write
+    sequence: u64,
+
+    _phantom: std::marker::PhantomData<T>,
+}
+
+impl<T> AtomicReader<T> {
+    /// The number of values that the ring buffer holds
+    pub fn capacity(&self) -> usize {
+        self.shared.slots.len()
+    }
+
+    /// Returns whether the writer has written anything that this reader
+    /// hasn't read yet, see [Reader::has_data](crate::Reader::has_data)
+    pub fn has_data(&self) -> bool {
+        self.shared.header.write_sequence.load(Ordering::SeqCst) > self.sequence
+    }
+
+    /// Returns whether the writer has been closed or dropped, see
+    /// [Reader::is_disconnected](crate::Reader::is_disconnected)
+    pub fn is_disconnected(&self) -> bool {
+        self.shared.header.closed.load(Ordering::SeqCst)
+    }
+
+    /// Skip to the latest value, see [Reader::skip_ahead](crate::Reader::skip_ahead).
+    /// The next read returns the latest value as [ReadResult::Dropout].
+    pub fn skip_ahead(&mut self) {
+        let written = self.shared.header.write_sequence.load(Ordering::SeqCst);
+        if written == 0 {
+            return;
+        }
+        self.sequence = written - 1;
+        self.lost = true;
+    }
+}
+
+impl<T: AtomicStorable> AtomicReader<T> {
+    /// Receive the next value in the queue. Returns the same kind of result
+    /// as [Reader::read](crate::Reader::read), but never waits for the
+    /// writer.
+    pub fn read(&mut self) -> ReadResult<T> {
+        let result = self.read_slot();
+        if !result.is_empty() || !self.is_disconnected() {
+            return result;
+        }
+
+        // See Reader::unless_closed
+        match self.read_slot() {
+            ReadResult::Empty => ReadResult::Closed,
+            result => result,
+        }
+    }
+
+    fn read_slot(&mut self) -> ReadResult<T> {
+        let capacity = self.shared.slots.len() as u64;
+        loop {
+            let slot = &self.shared.slots[self.shared.indexing.index_of(self.sequence)];
+
+            // Anything less is either from the previous lap or the value
+            // that the writer is only now storing, neither of which is new
+            let stamp = slot.stamp.load(Ordering::SeqCst);
+            if stamp < written_stamp(self.sequence) {
+                return ReadResult::Empty;
+            }
+
+            if stamp.is_multiple_of(2) {
+                let bits = slot.bits.load(Ordering::SeqCst);
+
+                // Unless the writer started to overwrite the slot in the
+                // meantime, the bits belong to the stamp
+                if slot.stamp.load(Ordering::SeqCst) == stamp {
+                    let sequence = stamp / 2 - 1;
+                    let lost = self.lost || sequence != self.sequence;
+                    self.sequence = sequence + 1;
+                    self.lost = false;
+
+                    let value = T::from_bits(bits);
+                    return if lost {
+                        ReadResult::Dropout(value)
+                    } else {
+                        ReadResult::Ok(value)
+                    };
+                }
+            }
+
+            // The writer is overwriting the slot, which means that the reader
+            // was overtaken. Go on with the oldest value that the writer
+            // might not have gotten to yet rather than waiting.
+            let writing = (slot.stamp.load(Ordering::SeqCst) - 1) / 2;
+            self.sequence = writing + 1 - capacity;
+            self.lost = true;
+        }
+    }
+}
+
+impl<T> Clone for AtomicReader<T> {
+    /// Create another reader at the same position.
+    ///
+    /// # Panics
+    /// Panics if the ring buffer already has the maximum number of readers.
+    fn clone(&self) -> Self {
+        if let Err(err) = self.shared.header.add_reader() {
+            panic!("{}", err);
+        }
+        AtomicReader {
+            shared: Arc::clone(&self.shared),
+            sequence: self.sequence,
+            lost: self.lost,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for AtomicReader<T> {
+    fn drop(&mut self) {
+        self.shared.header.remove_reader();
+    }
+}
+
+impl<T> AtomicWriter<T> {
+    /// The number of values that the ring buffer holds
+    pub fn capacity(&self) -> usize {
+        self.shared.slots.len()
+    }
+}
+
+impl<T: AtomicStorable> AtomicWriter<T> {
+    /// Write a value into the next slot, overwriting the oldest value once
+    /// the ring buffer is full. This is wait-free.
+    pub fn write(&mut self, value: T) {
+        let slot = &self.shared.slots[self.shared.indexing.index_of(self.sequence)];
+
+        // Readers that load the stamp again after the bits see that the
+        // value changed under them
+        slot.stamp
+            .store(written_stamp(self.sequence) - 1, Ordering::SeqCst);
+        slot.bits.store(value.into_bits(), Ordering::SeqCst);
+        slot.stamp
+            .store(written_stamp(self.sequence), Ordering::SeqCst);
+
+        self.sequence += 1;
+        self.shared
+            .header
+            .write_sequence
+            .store(self.sequence, Ordering::SeqCst);
+    }
+}
+
+impl<T> Drop for AtomicWriter<T> {
+    fn drop(&mut self) {
+        self.shared.header.closed.store(true, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,216 @@
+//! Byte streams carried over a ring buffer of fixed-size chunks, through the
+//! [std::io::Read] and [std::io::Write] traits.
+
+use std::io;
+
+use crate::{ring_buffer, ReadResult, Reader, Writer};
+
+/// A piece of a byte stream
+#[derive(Clone, Copy)]
+struct Chunk<const N: usize> {
+    // The position in the stream of the first byte in the chunk, which is
+    // how readers tell how many bytes were lost in a dropout
+    offset: u64,
+
+    len: usize,
+
+    data: [u8; N],
+}
+
+impl<const N: usize> Default for Chunk<N> {
+    fn default() -> Self {
+        Chunk {
+            offset: 0,
+            len: 0,
+            data: [0; N],
+        }
+    }
+}
+
+impl<const N: usize> Chunk<N> {
+    fn bytes(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+/// Construct a ring buffer which carries a byte stream in `capacity`
+/// chunks of up to `N` bytes each, consisting of a [ByteReader] and a
+/// [ByteWriter]. Larger chunks amortize the cost of locking items over more
+/// bytes, but make each read and write copy more. See [ring_buffer].
+///
+/// ```
+/// use std::io::{Read, Write};
+///
+/// let (mut reader, mut writer) = spmcq::byte_ring_buffer::<256>(16);
+/// writer.write_all(b"hello").unwrap();
+/// writer.flush().unwrap();
+/// drop(writer);
+///
+/// let mut s = String::new();
+/// reader.read_to_string(&mut s).unwrap();
+/// assert_eq!(s, "hello");
+/// ```
+///
+/// Fails to compile if `N` is zero.
+///
+/// # Panics
+/// Panics if the capacity is zero.
+pub fn byte_ring_buffer<const N: usize>(capacity: usize) -> (ByteReader<N>, ByteWriter<N>) {
+    const { assert!(N > 0, "chunks must hold at least 1 byte") };
+
+    let (reader, writer) = ring_buffer(capacity);
+
+    let reader = ByteReader {
+        reader,
+        chunk: Chunk::default(),
+        position: 0,
+        lost: None,
+    };
+    let writer = ByteWriter {
+        writer,
+        chunk: Chunk::default(),
+    };
+
+    (reader, writer)
+}
+
+/// The error contained in the [io::Error] returned by [ByteReader] when the
+/// writer overtook it, which holds the number of bytes that were lost.
+/// Reading again continues with the bytes that came after them.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BytesLost(pub u64);
+
+impl std::fmt::Display for BytesLost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "reader was overtaken and lost {} bytes", self.0)
+    }
+}
+
+impl std::error::Error for BytesLost {}
+
+/// The writing end of a [byte_ring_buffer]. Like [std::io::BufWriter], it
+/// collects bytes until it has a full chunk, so call [io::Write::flush] to
+/// hand over the remaining bytes of a partial chunk to the readers. Never
+/// blocks, and dropping it flushes and closes the ring buffer.
+pub struct ByteWriter<const N: usize> {
+    writer: Writer<Chunk<N>>,
+
+    // The bytes not yet handed over to the readers
+    chunk: Chunk<N>,
+}
+
+impl<const N: usize> io::Write for ByteWriter<N> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        let chunks = std::iter::from_fn(|| {
+            let n = (N - self.chunk.len).min(rest.len());
+            let (head, tail) = rest.split_at(n);
+            self.chunk.data[self.chunk.len..][..n].copy_from_slice(head);
+            self.chunk.len += n;
+            rest = tail;
+
+            if self.chunk.len < N {
+                return None;
+            }
+            let full = self.chunk;
+            self.chunk = Chunk {
+                offset: full.offset + N as u64,
+                ..Chunk::default()
+            };
+            Some(full)
+        });
+        self.writer.write_iter(chunks);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.chunk.len > 0 {
+            let offset = self.chunk.offset + self.chunk.len as u64;
+            self.writer.write(self.chunk);
+            self.chunk = Chunk {
+                offset,
+                ..Chunk::default()
+            };
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> Drop for ByteWriter<N> {
+    fn drop(&mut self) {
+        let _ = io::Write::flush(self);
+    }
+}
+
+/// The reading end of a [byte_ring_buffer]. Reading blocks until at least
+/// one byte is available, and reaches the end of the stream once the
+/// [ByteWriter] has been dropped and every byte was read. If the writer
+/// overtakes the reader, the next read fails with an error of kind
+/// [io::ErrorKind::Other] containing [BytesLost].
+pub struct ByteReader<const N: usize> {
+    reader: Reader<Chunk<N>>,
+
+    // The chunk being read from and the number of its bytes read so far
+    chunk: Chunk<N>,
+    position: usize,
+
+    // The number of bytes lost before the current chunk, which haven't been
+    // reported yet
+    lost: Option<u64>,
+}
+
+impl<const N: usize> ByteReader<N> {
+    /// Move on to the next chunk. Returns false if there is none, either
+    /// because nothing has been written yet or because the stream ended.
+    fn next_chunk(&mut self, block: bool) -> bool {
+        let result = if block {
+            self.reader.read_blocking()
+        } else {
+            self.reader.read()
+        };
+        let (chunk, dropout) = match result {
+            ReadResult::Ok(chunk) => (chunk, false),
+            ReadResult::Dropout(chunk) => (chunk, true),
+            ReadResult::Empty | ReadResult::Closed => return false,
+        };
+
+        if dropout {
+            let expected = self.chunk.offset + self.chunk.len as u64;
+            self.lost = Some(chunk.offset.saturating_sub(expected));
+        }
+        self.chunk = chunk;
+        self.position = 0;
+        true
+    }
+}
+
+impl<const N: usize> io::Read for ByteReader<N> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut copied = 0;
+        while copied < buf.len() {
+            if let Some(lost) = self.lost {
+                // Return the bytes from before the dropout first
+                if copied > 0 {
+                    break;
+                }
+                self.lost = None;
+                return Err(io::Error::other(BytesLost(lost)));
+            }
+
+            let available = &self.chunk.bytes()[self.position..];
+            if available.is_empty() {
+                // Only block if nothing was read yet
+                if !self.next_chunk(copied == 0) {
+                    break;
+                }
+                continue;
+            }
+
+            let n = available.len().min(buf.len() - copied);
+            buf[copied..][..n].copy_from_slice(&available[..n]);
+            self.position += n;
+            copied += n;
+        }
+        Ok(copied)
+    }
+}
@@ -0,0 +1,317 @@
+//! A facade over the ring buffer in the shape of `std::sync::mpsc`, for
+//! code that is being migrated from a channel. Since the ring buffer never
+//! blocks the writer, receivers that fall behind lose messages instead,
+//! which the receiving methods report as a lag before continuing with the
+//! messages that took their place.
+
+use std::time::{Duration, Instant};
+
+use crate::{ring_buffer, Detailed, Reader, Writer};
+
+/// Create a channel with the given capacity, see [ring_buffer]. Unlike
+/// `std::sync::mpsc::channel`, there is a single [Sender], and every
+/// [Receiver] receives every message, so cloning a receiver subscribes
+/// another consumer rather than sharing the work.
+///
+/// ```
+/// use spmcq::{channel, TryRecvError};
+///
+/// let (mut tx, mut rx) = channel::<u32>(16);
+/// let mut rx2 = rx.clone();
+///
+/// tx.send(1).unwrap();
+/// assert_eq!(rx.recv(), Ok(1));
+/// assert_eq!(rx2.recv(), Ok(1));
+/// assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+/// ```
+///
+/// # Panics
+/// Panics if the capacity is zero, see [ring_buffer].
+pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>)
+where
+    T: Default,
+{
+    let (reader, writer) = ring_buffer(capacity);
+    (Sender { writer }, Receiver::from(reader))
+}
+
+/// The sending half of a [channel], wrapping a [Writer]. Dropping it
+/// disconnects all receivers once they have received any remaining messages.
+pub struct Sender<T> {
+    writer: Writer<T>,
+}
+
+/// The receiving half of a [channel], wrapping a [Reader]. Unlike
+/// `std::sync::mpsc::Receiver`, receiving needs exclusive access, like
+/// reading from a [Reader] does.
+pub struct Receiver<T> {
+    reader: Reader<T>,
+
+    // The message that was received together with a lag that was reported
+    // first, to be returned by the next receive
+    lagged: Option<T>,
+}
+
+/// An error returned from [Sender::send] when no receivers exist, which
+/// contains the message that could not be sent
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+/// An error returned from [Receiver::recv]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RecvError {
+    /// The sender is gone and every message has been received
+    Disconnected,
+
+    /// The receiver fell behind and the contained number of messages were
+    /// lost. The next receive returns the message that was found in place of
+    /// the lost ones.
+    Lagged(u64),
+}
+
+/// An error returned from [Receiver::try_recv]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TryRecvError {
+    /// No message is available right now
+    Empty,
+
+    /// The sender is gone and every message has been received
+    Disconnected,
+
+    /// The receiver fell behind and the contained number of messages were
+    /// lost, see [RecvError::Lagged]
+    Lagged(u64),
+}
+
+/// An error returned from [Receiver::recv_timeout]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RecvTimeoutError {
+    /// No message arrived before the timeout
+    Timeout,
+
+    /// The sender is gone and every message has been received
+    Disconnected,
+
+    /// The receiver fell behind and the contained number of messages were
+    /// lost, see [RecvError::Lagged]
+    Lagged(u64),
+}
+
+impl<T> Sender<T> {
+    /// Send a message to every receiver. Never blocks, but overwrites the
+    /// oldest message once the channel is full, which receivers that
+    /// haven't received it yet observe as a lag. Returns an error holding
+    /// the message if no receivers exist, as the message would never be
+    /// received.
+    ///
+    /// Unlike `std::sync::mpsc::Sender::send`, this needs exclusive access,
+    /// since there can only be a single sender.
+    pub fn send(&mut self, t: T) -> Result<(), SendError<T>> {
+        if !self.writer.has_readers() {
+            return Err(SendError(t));
+        }
+        self.writer.write(t);
+        Ok(())
+    }
+
+    /// Return the underlying [Writer]
+    pub fn into_inner(self) -> Writer<T> {
+        self.writer
+    }
+}
+
+impl<T> From<Writer<T>> for Sender<T> {
+    fn from(writer: Writer<T>) -> Sender<T> {
+        Sender { writer }
+    }
+}
+
+impl<T> From<Reader<T>> for Receiver<T> {
+    fn from(reader: Reader<T>) -> Receiver<T> {
+        Receiver {
+            reader,
+            lagged: None,
+        }
+    }
+}
+
+impl<T: Copy> Receiver<T> {
+    /// Receive the next message, blocking the current thread until one
+    /// arrives. See [Reader::read_blocking].
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        match self.recv_until(None) {
+            Ok(t) => Ok(t),
+            Err(RecvTimeoutError::Lagged(lost)) => Err(RecvError::Lagged(lost)),
+            Err(_) => Err(RecvError::Disconnected),
+        }
+    }
+
+    /// Receive the next message if one is available, without blocking.
+    /// See [Reader::read].
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        if let Some(t) = self.lagged.take() {
+            return Ok(t);
+        }
+        let result = self.reader.read_detailed();
+        self.handle(result).unwrap_or(Err(TryRecvError::Empty))
+    }
+
+    /// Receive the next message, blocking the current thread for at most
+    /// the given duration until one arrives. See [Reader::read_timeout].
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.recv_until(Instant::now().checked_add(timeout))
+    }
+
+    /// Iterate over the messages, blocking while waiting for more, until the
+    /// sender is gone. Lags are passed over silently.
+    pub fn iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        std::iter::from_fn(move || loop {
+            match self.recv() {
+                Ok(t) => return Some(t),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Disconnected) => return None,
+            }
+        })
+    }
+
+    /// Iterate over the messages that are available right now, without
+    /// blocking. Lags are passed over silently.
+    pub fn try_iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        std::iter::from_fn(move || loop {
+            match self.try_recv() {
+                Ok(t) => return Some(t),
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(_) => return None,
+            }
+        })
+    }
+
+    fn recv_until(&mut self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        if let Some(t) = self.lagged.take() {
+            return Ok(t);
+        }
+        let result = self
+            .reader
+            .wait_until(deadline, |reader| match reader.read_detailed() {
+                Detailed::Empty => None,
+                result => Some(result),
+            });
+        match result {
+            Some(result) => self.handle(result).unwrap(),
+            None => Err(RecvTimeoutError::Timeout),
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Return the underlying [Reader]. A message that was held back after
+    /// reporting a lag is discarded.
+    pub fn into_inner(self) -> Reader<T> {
+        self.reader
+    }
+}
+
+impl<T: Copy> Receiver<T> {
+    /// Turn the result of a read into the result of a receive, holding back
+    /// the message after a dropout. Returns None if the read was empty.
+    fn handle<E: From<RecvError>>(&mut self, result: Detailed<T>) -> Option<Result<T, E>> {
+        match result {
+            Detailed::Ok(t) => Some(Ok(t)),
+            Detailed::Dropout { value, info } => {
+                self.lagged = Some(value);
+                Some(Err(RecvError::Lagged(info.lost).into()))
+            }
+            Detailed::Empty => None,
+            Detailed::Closed => Some(Err(RecvError::Disconnected.into())),
+        }
+    }
+}
+
+impl<T: Copy> Clone for Receiver<T> {
+    /// Create another receiver starting at the same position, which
+    /// receives every message that this one hasn't received yet.
+    ///
+    /// # Panics
+    /// Panics if the channel already has the maximum number of receivers,
+    /// see [Reader::clone].
+    fn clone(&self) -> Self {
+        Receiver {
+            reader: self.reader.clone(),
+            lagged: self.lagged,
+        }
+    }
+}
+
+impl From<RecvError> for TryRecvError {
+    fn from(err: RecvError) -> TryRecvError {
+        match err {
+            RecvError::Disconnected => TryRecvError::Disconnected,
+            RecvError::Lagged(lost) => TryRecvError::Lagged(lost),
+        }
+    }
+}
+
+impl From<RecvError> for RecvTimeoutError {
+    fn from(err: RecvError) -> RecvTimeoutError {
+        match err {
+            RecvError::Disconnected => RecvTimeoutError::Disconnected,
+            RecvError::Lagged(lost) => RecvTimeoutError::Lagged(lost),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Like std, without requiring the message to be Debug
+        f.write_str("SendError { .. }")
+    }
+}
+
+impl<T> std::fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "sending on a channel without receivers")
+    }
+}
+
+impl<T> std::error::Error for SendError<T> {}
+
+impl std::fmt::Display for RecvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecvError::Disconnected => write!(f, "receiving on a closed channel"),
+            RecvError::Lagged(lost) => {
+                write!(f, "receiver lagged behind and lost {} messages", lost)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RecvError {}
+
+impl std::fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TryRecvError::Empty => write!(f, "receiving on an empty channel"),
+            TryRecvError::Disconnected => write!(f, "receiving on a closed channel"),
+            TryRecvError::Lagged(lost) => {
+                write!(f, "receiver lagged behind and lost {} messages", lost)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TryRecvError {}
+
+impl std::fmt::Display for RecvTimeoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecvTimeoutError::Timeout => write!(f, "timed out waiting on a channel"),
+            RecvTimeoutError::Disconnected => write!(f, "receiving on a closed channel"),
+            RecvTimeoutError::Lagged(lost) => {
+                write!(f, "receiver lagged behind and lost {} messages", lost)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RecvTimeoutError {}
@@ -0,0 +1,147 @@
+//! Readers which share a single position in the ring buffer, so that every
+//! item is received by only one of them, as in a pool of workers.
+
+use std::sync::Arc;
+
+use crate::{
+    sync::{AtomicU64, Ordering},
+    HeapStorage, ReadResult, Reader, Storage, TooManyReaders,
+};
+
+/// A reader which shares its position with all of its clones, created by
+/// [Reader::into_dispatch]. Each item is received by exactly one of them,
+/// whichever claims it first, unless the writer overwrites it before anyone
+/// does. Reading returns the same kinds of result as [Reader::read], where
+/// [ReadResult::Dropout] means that the shared position fell behind the
+/// writer and some items were lost for all of the readers.
+pub struct DispatchReader<T, S: Storage<T> = HeapStorage<T>> {
+    reader: Reader<T, S>,
+
+    // The sequence number of the next item that any of the readers will claim
+    next: Arc<AtomicU64>,
+}
+
+impl<T, S: Storage<T>> Reader<T, S> {
+    /// Turn the reader into a [DispatchReader], whose clones share its
+    /// position instead of each receiving every item. The shared position
+    /// starts where the reader is, and other readers of the same ring buffer
+    /// are unaffected. The shared position is kept in a small allocation of
+    /// its own, even for a [StaticRingBuffer](crate::StaticRingBuffer).
+    pub fn into_dispatch(self) -> DispatchReader<T, S> {
+        // After skipping ahead, the reader expects the item from one lap
+        // before the latest, see Reader::skip_ahead
+        let capacity = self.storage.items().len() as u64;
+        let next = match self.skipped_from {
+            Some(_) => self.sequence.wrapping_add(capacity),
+            None => self.sequence,
+        };
+
+        DispatchReader {
+            reader: self,
+            next: Arc::new(AtomicU64::new(next)),
+        }
+    }
+}
+
+impl<T, S: Storage<T>> DispatchReader<T, S> {
+    /// Create another reader sharing the same position, or return an error
+    /// if the buffer already has as many readers as
+    /// [Reader::max_readers] allows. [Clone::clone] panics instead.
+    pub fn try_clone(&self) -> Result<DispatchReader<T, S>, TooManyReaders> {
+        Ok(DispatchReader {
+            reader: self.reader.try_clone()?,
+            next: Arc::clone(&self.next),
+        })
+    }
+
+    /// Returns whether the writer has been closed or dropped, see
+    /// [Reader::is_disconnected]
+    pub fn is_disconnected(&self) -> bool {
+        self.reader.is_disconnected()
+    }
+}
+
+impl<T, S: Storage<T>> DispatchReader<T, S>
+where
+    T: Copy,
+{
+    /// Claim and receive the next item that none of the other readers
+    /// sharing this position have received, if anything is available. See
+    /// [Reader::read] for the possible results.
+    pub fn read(&mut self) -> ReadResult<T> {
+        match self.claim() {
+            ReadResult::Empty if self.reader.is_disconnected() => {
+                // See Reader::unless_closed
+                match self.claim() {
+                    ReadResult::Empty => ReadResult::Closed,
+                    result => result,
+                }
+            }
+            result => result,
+        }
+    }
+
+    /// Claim the next item by moving the shared position past it, and read it
+    fn claim(&mut self) -> ReadResult<T> {
+        let capacity = self.reader.storage.items().len() as u64;
+        let mut dropout = false;
+        loop {
+            let claimed = self.next.load(Ordering::SeqCst);
+
+            // Nothing before the writer was last cleared is read, see
+            // Writer::clear
+            let next = claimed.max(self.reader.cleared_at());
+            let write_sequence = self.reader.write_sequence();
+            if next == write_sequence {
+                return ReadResult::Empty;
+            }
+
+            // If the writer is more than a lap ahead, continue with the
+            // oldest item that is still there
+            let sequence = if write_sequence - next > capacity {
+                dropout = true;
+                write_sequence - capacity
+            } else {
+                next
+            };
+
+            if self
+                .next
+                .compare_exchange(claimed, sequence + 1, Ordering::SeqCst, Ordering::SeqCst)
+                .is_err()
+            {
+                // Another reader claimed an item first
+                continue;
+            }
+
+            let (value, actual_sequence, _) = self.reader.load_item(self.reader.index_of(sequence));
+            let Some(value) = value.filter(|_| actual_sequence == sequence) else {
+                // The writer overwrote the item after it was claimed. The
+                // newer item belongs to whoever claims it later, so try again.
+                dropout = true;
+                continue;
+            };
+
+            return if dropout {
+                ReadResult::Dropout(value)
+            } else {
+                ReadResult::Ok(value)
+            };
+        }
+    }
+}
+
+impl<T, S: Storage<T>> Clone for DispatchReader<T, S> {
+    /// Create another reader sharing the same position.
+    ///
+    /// # Panics
+    /// Panics if the ring buffer already has the maximum number of readers.
+    /// See [DispatchReader::try_clone] for a version that returns an error
+    /// instead.
+    fn clone(&self) -> Self {
+        match self.try_clone() {
+            Ok(reader) => reader,
+            Err(err) => panic!("{}", err),
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Saving the items that a ring buffer holds to a file or any other
+//! [io::Write], and restoring a ring buffer with the same history from it,
+//! e.g. for post-mortem debugging.
+//!
+//! A dump starts with a header of little-endian `u64`s: a magic number, the
+//! capacity, the size of an item in memory, the writer's position, i.e. the
+//! number of items written so far, and the number of items that follow, which
+//! is fewer than the capacity if the writer was cleared since. The items
+//! follow from oldest to newest, each encoded by a function that the caller
+//! passes in.
+
+use std::io;
+
+use crate::storage::sealed::Sealed;
+use crate::sync::Ordering;
+use crate::{try_ring_buffer, Reader, Storage, Writer};
+
+/// The first eight bytes of every dump, which end in the version of the format
+const MAGIC: u64 = u64::from_le_bytes(*b"spmcqdm\x01");
+
+impl<T, S: Storage<T>> Writer<T, S>
+where
+    T: Copy,
+{
+    /// Write the items that the ring buffer currently holds to `w`, from
+    /// oldest to newest, after a header that describes the ring buffer, see
+    /// [ring_buffer_restore]. Each item is written by calling `encode` with
+    /// it. The items are copied under their locks like readers do, so that
+    /// readers may keep reading in the meantime.
+    ///
+    /// ```
+    /// use std::io::{Read, Write};
+    ///
+    /// use spmcq::{ring_buffer, ring_buffer_restore};
+    ///
+    /// let (_, mut writer) = ring_buffer::<u32>(4);
+    /// for value in 0..6 {
+    ///     writer.write(value);
+    /// }
+    ///
+    /// let mut dump = Vec::new();
+    /// writer
+    ///     .dump_to(&mut dump, |value, w| w.write_all(&value.to_le_bytes()))
+    ///     .unwrap();
+    ///
+    /// let (mut reader, writer) = ring_buffer_restore(dump.as_slice(), |r| {
+    ///     let mut bytes = [0; 4];
+    ///     r.read_exact(&mut bytes)?;
+    ///     Ok(u32::from_le_bytes(bytes))
+    /// })
+    /// .unwrap();
+    /// assert_eq!(writer.next_sequence(), 6);
+    /// assert_eq!(reader.snapshot(), [2, 3, 4, 5]);
+    /// ```
+    pub fn dump_to<W: io::Write>(
+        &mut self,
+        mut w: W,
+        mut encode: impl FnMut(&T, &mut W) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let items = self.storage.items();
+        let cleared_at = self.storage.header().cleared_at.load(Ordering::Relaxed);
+        let count = (self.sequence - cleared_at).min(items.len() as u64);
+
+        for field in [
+            MAGIC,
+            items.len() as u64,
+            std::mem::size_of::<T>() as u64,
+            self.sequence,
+            count,
+        ] {
+            w.write_all(&field.to_le_bytes())?;
+        }
+
+        for sequence in (self.sequence - count)..self.sequence {
+            let item = &items[self.indexing.index_of(sequence)];
+
+            // Encoding may take a while, so copy the value out first
+            let value = {
+                let (lock, _) = item.lock_read(&self.spin_policy);
+                debug_assert_eq!(lock.sequence(), sequence);
+                lock.with_data(|data| *data)
+            };
+            let value = value.expect("item was written but holds nothing");
+            encode(&value, &mut w)?;
+        }
+
+        w.flush()
+    }
+}
+
+/// Construct a new ring buffer from a dump that [Writer::dump_to] wrote to
+/// `r`, with the same capacity and holding the same items at the same
+/// sequence numbers. Each item is read by calling `decode`, which must read
+/// exactly what the `encode` function that was passed to [Writer::dump_to]
+/// wrote. The reader starts at the front of the queue, call
+/// [Reader::seek_to_oldest] to replay the items.
+///
+/// Returns an error of kind [io::ErrorKind::InvalidData] if `r` doesn't hold
+/// a dump, or holds one of items of a different size, along with any error
+/// from reading or decoding.
+pub fn ring_buffer_restore<T, R: io::Read>(
+    mut r: R,
+    mut decode: impl FnMut(&mut R) -> io::Result<T>,
+) -> io::Result<(Reader<T>, Writer<T>)>
+where
+    T: Copy + Default,
+{
+    let mut header = [0; 5];
+    for field in &mut header {
+        let mut bytes = [0; 8];
+        r.read_exact(&mut bytes)?;
+        *field = u64::from_le_bytes(bytes);
+    }
+    let [magic, capacity, item_size, position, count] = header;
+
+    let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+    if magic != MAGIC {
+        return Err(invalid("not a ring buffer dump"));
+    }
+    if item_size != std::mem::size_of::<T>() as u64 {
+        return Err(invalid("ring buffer dump holds items of a different size"));
+    }
+    if count > position.min(capacity) {
+        return Err(invalid("ring buffer dump is inconsistent"));
+    }
+    let capacity = usize::try_from(capacity).map_err(|_| invalid("capacity is too large"))?;
+    let (mut reader, mut writer) =
+        try_ring_buffer(capacity).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    // Write the items where they were in the original, which was cleared
+    // before them if there are fewer than it could have held
+    let start = position - count;
+    writer.index = writer.indexing.index_of(start);
+    writer.sequence = start;
+    writer
+        .storage
+        .header()
+        .write_sequence
+        .store(start, Ordering::Release);
+    if count < position.min(capacity as u64) {
+        writer.clear();
+    }
+    for _ in 0..count {
+        writer.write(decode(&mut r)?);
+    }
+    writer.reset_stats();
+
+    reader.seek_to_front();
+    Ok((reader, writer))
+}
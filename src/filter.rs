@@ -0,0 +1,78 @@
+//! Readers which only receive the items that they are interested in.
+
+use crate::{HeapStorage, ReadResult, Reader, Storage};
+
+/// A reader which skips over all items that don't match a predicate, created
+/// by [Reader::filtered]
+pub struct FilteredReader<T, F, S: Storage<T> = HeapStorage<T>> {
+    reader: Reader<T, S>,
+    predicate: F,
+
+    // Whether any items were lost among those skipped since the last
+    // matching item was returned
+    dropout: bool,
+}
+
+impl<T, S: Storage<T>> Reader<T, S> {
+    /// Turn the reader into a [FilteredReader], which only returns the items
+    /// for which `predicate` returns true
+    pub fn filtered<F: FnMut(&T) -> bool>(self, predicate: F) -> FilteredReader<T, F, S> {
+        FilteredReader {
+            reader: self,
+            predicate,
+            dropout: false,
+        }
+    }
+}
+
+impl<T, F, S: Storage<T>> FilteredReader<T, F, S> {
+    /// Returns whether the writer has been closed or dropped, see
+    /// [Reader::is_disconnected]
+    pub fn is_disconnected(&self) -> bool {
+        self.reader.is_disconnected()
+    }
+
+    /// Stop filtering and return the reader, which continues after the last
+    /// item that was read
+    pub fn into_inner(self) -> Reader<T, S> {
+        self.reader
+    }
+}
+
+impl<T, F, S: Storage<T>> FilteredReader<T, F, S>
+where
+    T: Copy,
+    F: FnMut(&T) -> bool,
+{
+    /// Receive the next matching item, reading and discarding any items in
+    /// between that don't match. Returns [ReadResult::Dropout] if any items
+    /// were lost since the last matching item was returned, whether or not
+    /// the lost items would have matched, and [ReadResult::Empty] if nothing
+    /// that matches is available right now. To keep this from running for
+    /// too long while the writer keeps writing items that don't match, at
+    /// most as many items as the ring buffer holds are read per call.
+    pub fn read(&mut self) -> ReadResult<T> {
+        for _ in 0..self.reader.capacity() {
+            let (value, dropout) = match self.reader.read() {
+                ReadResult::Ok(value) => (value, false),
+                ReadResult::Dropout(value) => (value, true),
+                result => return result,
+            };
+            self.dropout |= dropout;
+
+            if (self.predicate)(&value) {
+                return if std::mem::take(&mut self.dropout) {
+                    ReadResult::Dropout(value)
+                } else {
+                    ReadResult::Ok(value)
+                };
+            }
+        }
+        ReadResult::Empty
+    }
+
+    /// Advance the reader to the front of the queue, see [Reader::skip_ahead]
+    pub fn skip_ahead(&mut self) {
+        self.reader.skip_ahead();
+    }
+}
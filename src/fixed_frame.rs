@@ -0,0 +1,274 @@
+//! A ring buffer of frames whose length is only known at runtime, such as
+//! blocks of audio samples. All frames live in one contiguous allocation,
+//! and each slot is locked on its own, as with the items of a [Reader] and
+//! [Writer](crate::Writer).
+
+use std::{cell::UnsafeCell, sync::Arc};
+
+use crate::{
+    storage::{Header, Indexing, Item, UNWRITTEN_SEQUENCE},
+    sync::Ordering,
+    ReadResult, SpinPolicy,
+};
+
+struct Shared<T> {
+    header: Header,
+
+    // The lock and sequence number of each slot. The data lives in frames
+    // instead, so the items themselves hold nothing.
+    slots: Box<[Item<()>]>,
+    indexing: Indexing,
+
+    // The frames of all slots, one after the other
+    frames: Box<[UnsafeCell<T>]>,
+
+    frame_len: usize,
+}
+
+// SAFETY: all access to a frame is guarded by the use count of its slot, as
+// for the data of an Item.
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    /// Returns a pointer to the first element of the frame in the given slot
+    fn frame_ptr(&self, index: usize) -> *mut T {
+        // SAFETY: the offset is in bounds since index is that of a slot. The
+        // pointer is derived from the whole allocation rather than from a
+        // single element, so that it can access the rest of the frame.
+        unsafe { UnsafeCell::raw_get(self.frames.as_ptr().add(index * self.frame_len)) }
+    }
+}
+
+/// Construct a new ring buffer of `slots` frames which each hold exactly
+/// `frame_len` values, consisting of a [FixedFrameReader] and a
+/// [FixedFrameWriter]. Reading and writing behave like with [ring_buffer],
+/// except that whole frames are copied in and out.
+///
+/// ```
+/// use spmcq::{ring_buffer_frames, ReadResult};
+///
+/// let (mut reader, mut writer) = ring_buffer_frames::<f32>(4, 3);
+/// writer.write_frame(&[1.0, 2.0, 3.0]);
+///
+/// let mut frame = [0.0; 3];
+/// assert_eq!(reader.read_frame(&mut frame), ReadResult::Ok(()));
+/// assert_eq!(frame, [1.0, 2.0, 3.0]);
+/// ```
+///
+/// # Panics
+/// Panics if either `slots` or `frame_len` is zero, or if the frames would
+/// not fit into memory.
+///
+/// [ring_buffer]: crate::ring_buffer
+pub fn ring_buffer_frames<T>(
+    slots: usize,
+    frame_len: usize,
+) -> (FixedFrameReader<T>, FixedFrameWriter<T>)
+where
+    T: Copy + Default,
+{
+    assert!(slots > 0, "ring buffer must have at least 1 slot");
+    assert!(frame_len > 0, "frames must hold at least 1 value");
+    let len = slots
+        .checked_mul(frame_len)
+        .expect("frames are too large to allocate");
+
+    let shared = Arc::new(Shared {
+        header: Header::new(),
+        slots: (0..slots).map(|_| Item::new()).collect(),
+        indexing: Indexing::new(slots),
+        frames: (0..len).map(|_| UnsafeCell::new(T::default())).collect(),
+        frame_len,
+    });
+    shared.header.reader_count.fetch_add(1, Ordering::SeqCst);
+
+    let reader = FixedFrameReader {
+        shared: Arc::clone(&shared),
+        index: 0,
+        sequence: 0,
+    };
+    let writer = FixedFrameWriter {
+        shared,
+        index: 0,
+        sequence: 0,
+    };
+
+    (reader, writer)
+}
+
+/// The reading end of a [ring_buffer_frames]. Readers can be cloned, and
+/// each receives every frame.
+pub struct FixedFrameReader<T> {
+    shared: Arc<Shared<T>>,
+
+    // The slot and sequence number of the next frame to read
+    index: usize,
+    sequence: u64,
+}
+
+/// The writing end of a [ring_buffer_frames]. Dropping it closes the ring
+/// buffer.
+pub struct FixedFrameWriter<T> {
+    shared: Arc<Shared<T>>,
+
+    // The slot and sequence number of the next frame to write
+    index: usize,
+    sequence: u64,
+}
+
+impl<T> FixedFrameReader<T> {
+    /// The number of values in each frame
+    pub fn frame_len(&self) -> usize {
+        self.shared.frame_len
+    }
+
+    /// Returns whether the writer has been closed or dropped, see
+    /// [Reader::is_disconnected](crate::Reader::is_disconnected)
+    pub fn is_disconnected(&self) -> bool {
+        self.shared.header.closed.load(Ordering::SeqCst)
+    }
+}
+
+impl<T: Copy> FixedFrameReader<T> {
+    /// Copy the next frame in the queue into `frame`. Returns the same kind
+    /// of result as [Reader::read](crate::Reader::read), and leaves `frame`
+    /// untouched if the result is [ReadResult::Empty] or
+    /// [ReadResult::Closed].
+    ///
+    /// # Panics
+    /// Panics if the length of `frame` differs from
+    /// [FixedFrameReader::frame_len].
+    pub fn read_frame(&mut self, frame: &mut [T]) -> ReadResult<()> {
+        assert_eq!(
+            frame.len(),
+            self.shared.frame_len,
+            "frame length doesn't match the ring buffer"
+        );
+
+        let result = self.read_slot(frame);
+        if !result.is_empty() || !self.is_disconnected() {
+            return result;
+        }
+
+        // See Reader::unless_closed
+        match self.read_slot(frame) {
+            ReadResult::Empty => ReadResult::Closed,
+            result => result,
+        }
+    }
+
+    fn read_slot(&mut self, frame: &mut [T]) -> ReadResult<()> {
+        let capacity = self.shared.slots.len();
+        let slot = &self.shared.slots[self.index];
+
+        let (lock, _) = slot.lock_read(&SpinPolicy::default());
+
+        let sequence = lock.sequence();
+        let previous_lap = sequence == UNWRITTEN_SEQUENCE
+            || sequence.wrapping_add(capacity as u64) == self.sequence;
+        if !previous_lap {
+            // SAFETY: the read lock keeps the writer from modifying the slot
+            // and its frame, see Reader::load_item
+            unsafe {
+                let src = self.shared.frame_ptr(self.index);
+                std::ptr::copy_nonoverlapping(src, frame.as_mut_ptr(), frame.len());
+            }
+        }
+
+        drop(lock);
+
+        if previous_lap {
+            // Caught up with the writer, see Reader::read_item
+            return ReadResult::Empty;
+        }
+
+        let result = if sequence == self.sequence {
+            ReadResult::Ok(())
+        } else {
+            ReadResult::Dropout(())
+        };
+
+        self.sequence = sequence.wrapping_add(1);
+        self.index += 1;
+        if self.index == capacity {
+            self.index = 0;
+        }
+
+        result
+    }
+}
+
+impl<T> Clone for FixedFrameReader<T> {
+    /// Create another reader at the same position.
+    ///
+    /// # Panics
+    /// Panics if the ring buffer already has the maximum number of readers.
+    fn clone(&self) -> Self {
+        if let Err(err) = self.shared.header.add_reader() {
+            panic!("{}", err);
+        }
+        FixedFrameReader {
+            shared: Arc::clone(&self.shared),
+            index: self.index,
+            sequence: self.sequence,
+        }
+    }
+}
+
+impl<T> Drop for FixedFrameReader<T> {
+    fn drop(&mut self) {
+        self.shared.header.remove_reader();
+    }
+}
+
+impl<T> FixedFrameWriter<T> {
+    /// The number of values in each frame
+    pub fn frame_len(&self) -> usize {
+        self.shared.frame_len
+    }
+}
+
+impl<T: Copy> FixedFrameWriter<T> {
+    /// Copy a frame into the next slot, overwriting the oldest frame once
+    /// the ring buffer is full. Never blocks, apart from spinning briefly if
+    /// a reader happens to be copying out of the same slot.
+    ///
+    /// # Panics
+    /// Panics if the length of `frame` differs from
+    /// [FixedFrameWriter::frame_len].
+    pub fn write_frame(&mut self, frame: &[T]) {
+        assert_eq!(
+            frame.len(),
+            self.shared.frame_len,
+            "frame length doesn't match the ring buffer"
+        );
+
+        let capacity = self.shared.slots.len();
+        let slot = &self.shared.slots[self.index];
+
+        slot.acquire_write(&SpinPolicy::default());
+
+        // SAFETY: the write lock gives exclusive access to the slot and its
+        // frame
+        unsafe {
+            let dst = self.shared.frame_ptr(self.index);
+            std::ptr::copy_nonoverlapping(frame.as_ptr(), dst, frame.len());
+            slot.set_sequence(self.sequence);
+        }
+
+        let lap = self.shared.indexing.lap_token(self.sequence);
+        self.sequence += 1;
+        self.index += 1;
+        if self.index == capacity {
+            self.index = 0;
+        }
+
+        slot.release_write(lap);
+    }
+}
+
+impl<T> Drop for FixedFrameWriter<T> {
+    fn drop(&mut self) {
+        self.shared.header.closed.store(true, Ordering::SeqCst);
+    }
+}
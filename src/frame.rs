@@ -0,0 +1,212 @@
+//! Variable-length messages carried over a ring buffer of small chunks, so
+//! that short messages don't need to be padded to the size of the longest.
+
+use crate::{ring_buffer, ReadResult, Reader, Writer};
+
+/// The number of message bytes that each chunk holds. Every frame starts at
+/// the beginning of a chunk, so a frame of `n` bytes occupies `n / 64`
+/// chunks, rounded up, and at least one.
+const CHUNK_SIZE: usize = 64;
+
+#[derive(Clone, Copy)]
+struct Chunk {
+    // Whether this is the first chunk of a frame
+    start: bool,
+
+    // The total length of the frame that this chunk is part of
+    len: u32,
+
+    data: [u8; CHUNK_SIZE],
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Chunk {
+            start: false,
+            len: 0,
+            data: [0; CHUNK_SIZE],
+        }
+    }
+}
+
+/// The number of chunks needed to hold a frame of the given length
+fn chunks_for(len: usize) -> usize {
+    len.div_ceil(CHUNK_SIZE).max(1)
+}
+
+/// Construct a ring buffer which carries whole messages of varying length,
+/// consisting of a [FrameReader] and a [FrameWriter]. The buffer holds at
+/// least `capacity_bytes` bytes of messages, rounded up to whole chunks of
+/// 64 bytes. Since every message starts on a new chunk, many short messages
+/// take up more space than their combined length.
+///
+/// ```
+/// use spmcq::{frame_buffer, ReadResult};
+///
+/// let (mut reader, mut writer) = frame_buffer(1024);
+/// writer.write_frame(b"hello").unwrap();
+/// writer.write_frame(b"world!").unwrap();
+///
+/// let mut frame = Vec::new();
+/// assert_eq!(reader.read_frame(&mut frame), ReadResult::Ok(5));
+/// assert_eq!(frame, b"hello");
+/// assert_eq!(reader.read_frame(&mut frame), ReadResult::Ok(6));
+/// assert_eq!(frame, b"world!");
+/// assert_eq!(reader.read_frame(&mut frame), ReadResult::Empty);
+/// ```
+///
+/// # Panics
+/// Panics if `capacity_bytes` is zero.
+pub fn frame_buffer(capacity_bytes: usize) -> (FrameReader, FrameWriter) {
+    assert!(
+        capacity_bytes > 0,
+        "frame buffer capacity must be at least 1"
+    );
+
+    let (reader, writer) = ring_buffer(chunks_for(capacity_bytes));
+
+    let reader = FrameReader {
+        reader,
+        partial: Vec::new(),
+        in_frame: false,
+        dropout: false,
+    };
+
+    (reader, FrameWriter { writer })
+}
+
+/// The error returned by [FrameWriter::write_frame] when a frame doesn't fit
+/// into the buffer at all
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FrameTooLarge {
+    /// The length of the frame
+    pub len: usize,
+
+    /// The largest frame that the buffer can hold, see
+    /// [FrameWriter::max_frame_len]
+    pub max_len: usize,
+}
+
+impl std::fmt::Display for FrameTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "frame of {} bytes is larger than the maximum of {} bytes",
+            self.len, self.max_len
+        )
+    }
+}
+
+impl std::error::Error for FrameTooLarge {}
+
+/// The writing end of a [frame_buffer]
+pub struct FrameWriter {
+    writer: Writer<Chunk>,
+}
+
+impl FrameWriter {
+    /// The length of the largest frame that fits into the buffer
+    pub fn max_frame_len(&self) -> usize {
+        (self.writer.capacity() * CHUNK_SIZE).min(u32::MAX as usize)
+    }
+
+    /// Write a whole frame, overwriting the oldest frames as needed. Never
+    /// blocks. Returns an error if the frame is larger than
+    /// [FrameWriter::max_frame_len], in which case nothing is written.
+    pub fn write_frame(&mut self, frame: &[u8]) -> Result<(), FrameTooLarge> {
+        let max_len = self.max_frame_len();
+        if frame.len() > max_len {
+            return Err(FrameTooLarge {
+                len: frame.len(),
+                max_len,
+            });
+        }
+
+        // An empty frame still takes up a chunk
+        let empty = frame.is_empty().then_some(frame);
+        let len = frame.len() as u32;
+        let chunks = frame.chunks(CHUNK_SIZE).chain(empty).enumerate();
+        let chunks = chunks.map(|(i, bytes)| {
+            let mut chunk = Chunk {
+                start: i == 0,
+                len,
+                ..Chunk::default()
+            };
+            chunk.data[..bytes.len()].copy_from_slice(bytes);
+            chunk
+        });
+        self.writer.write_iter(chunks);
+        Ok(())
+    }
+}
+
+/// The reading end of a [frame_buffer]. Readers can be cloned, as with
+/// [Reader], and each receives every frame.
+#[derive(Clone)]
+pub struct FrameReader {
+    reader: Reader<Chunk>,
+
+    // The bytes of the frame received so far
+    partial: Vec<u8>,
+
+    // Whether the next chunk continues the frame in partial. False while
+    // passing over the rest of a frame whose beginning was lost.
+    in_frame: bool,
+
+    // Whether any chunks were lost since the last frame was returned
+    dropout: bool,
+}
+
+impl FrameReader {
+    /// Receive the next whole frame into `frame`, replacing its contents,
+    /// and return its length. Returns [ReadResult::Ok] if no frames were
+    /// lost since the previous one, [ReadResult::Dropout] if one or more
+    /// frames were partly or wholly overwritten before they could be read,
+    /// [ReadResult::Empty] if no whole frame is available yet, and
+    /// [ReadResult::Closed] once the writer is gone and every frame was read.
+    /// Frames are never torn, and incomplete frames are never returned.
+    pub fn read_frame(&mut self, frame: &mut Vec<u8>) -> ReadResult<usize> {
+        loop {
+            let chunk = match self.reader.read() {
+                ReadResult::Ok(chunk) => chunk,
+                ReadResult::Dropout(chunk) => {
+                    // Whatever was received of the current frame is incomplete
+                    self.dropout = true;
+                    self.in_frame = false;
+                    chunk
+                }
+                ReadResult::Empty => return ReadResult::Empty,
+                ReadResult::Closed => return ReadResult::Closed,
+            };
+
+            if chunk.start {
+                self.partial.clear();
+                self.in_frame = true;
+            } else if !self.in_frame {
+                continue;
+            }
+
+            let len = chunk.len as usize;
+            let n = (len - self.partial.len()).min(CHUNK_SIZE);
+            self.partial.extend_from_slice(&chunk.data[..n]);
+            if self.partial.len() < len {
+                continue;
+            }
+
+            self.in_frame = false;
+            std::mem::swap(frame, &mut self.partial);
+            self.partial.clear();
+            return if std::mem::take(&mut self.dropout) {
+                ReadResult::Dropout(len)
+            } else {
+                ReadResult::Ok(len)
+            };
+        }
+    }
+
+    /// Returns whether the writer has been closed or dropped, see
+    /// [Reader::is_disconnected]
+    pub fn is_disconnected(&self) -> bool {
+        self.reader.is_disconnected()
+    }
+}
@@ -0,0 +1,285 @@
+//! Support for reading from async tasks, enabled by the `async` feature, and
+//! for using readers as streams and writers as sinks, enabled by the `futures`
+//! feature.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{ReadResult, Reader, Storage};
+
+#[cfg(feature = "futures")]
+use crate::Writer;
+
+/// The future returned by [Reader::read_async], which resolves to the next
+/// item in the queue once it becomes available.
+pub struct ReadFuture<'a, T, S: Storage<T>> {
+    reader: &'a mut Reader<T, S>,
+
+    // The key of this future's waker in the shared wait list, if registered
+    key: Option<usize>,
+}
+
+impl<T, S: Storage<T>> Reader<T, S>
+where
+    T: Copy,
+{
+    /// Receive the next item in the queue from an async task, waiting until
+    /// one becomes available. Resolves to either [ReadResult::Ok] or
+    /// [ReadResult::Dropout] with the same meaning as in [Reader::read], or
+    /// to [ReadResult::Closed] once the writer is gone and all remaining data
+    /// was read.
+    ///
+    /// While the queue is empty, the future registers its waker with the ring
+    /// buffer and is woken up by the next call to [Writer::write](crate::Writer::write)
+    /// or by the writer being closed.
+    /// Any number of readers may wait concurrently from different tasks.
+    pub fn read_async(&mut self) -> ReadFuture<'_, T, S> {
+        ReadFuture {
+            reader: self,
+            key: None,
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T, S: Storage<T>> Reader<T, S>
+where
+    T: Copy,
+{
+    /// Receive the next item in the queue from a tokio task, waiting until
+    /// one becomes available. Resolves to the same results as
+    /// [Reader::read_async].
+    ///
+    /// This is equivalent to [Reader::read_async], except that waiting is
+    /// done through a [tokio::sync::Notify] shared by all readers, which the
+    /// writer notifies after publishing new data.
+    pub async fn recv(&mut self) -> ReadResult<T> {
+        loop {
+            let result = self.read();
+            if !result.is_empty() {
+                return result;
+            }
+
+            let storage = self.storage.clone();
+            let (notified, _guard) = storage.signals().waiters.notified();
+
+            // Check again now that the notified future exists and will receive
+            // the writer's next notification
+            let result = self.read();
+            if !result.is_empty() {
+                return result;
+            }
+
+            notified.await;
+        }
+    }
+}
+
+/// Shared implementation of polling for the next item in the queue. `key`
+/// tracks the registered waker across polls and is cleared once ready.
+fn poll_read<T, S>(
+    reader: &mut Reader<T, S>,
+    key: &mut Option<usize>,
+    cx: &mut Context<'_>,
+) -> Poll<ReadResult<T>>
+where
+    T: Copy,
+    S: Storage<T>,
+{
+    let result = reader.read();
+    if result.is_empty() {
+        let waiters = &reader.storage.signals().waiters;
+        *key = Some(waiters.register_waker(*key, cx.waker()));
+
+        // Check again now that the writer is guaranteed to see the waker
+        let result = reader.read();
+        if result.is_empty() {
+            return Poll::Pending;
+        }
+        unregister(reader, key);
+        return Poll::Ready(result);
+    }
+
+    unregister(reader, key);
+    Poll::Ready(result)
+}
+
+/// Remove a waker that was registered by [poll_read], if any
+fn unregister<T, S: Storage<T>>(reader: &Reader<T, S>, key: &mut Option<usize>) {
+    if let Some(key) = key.take() {
+        reader.storage.signals().waiters.unregister_waker(key);
+    }
+}
+
+impl<T, S: Storage<T>> Future for ReadFuture<'_, T, S>
+where
+    T: Copy,
+{
+    type Output = ReadResult<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<ReadResult<T>> {
+        // ReadFuture only holds a reference and a key, neither of which care
+        // about being moved
+        let this = self.get_mut();
+        poll_read(this.reader, &mut this.key, cx)
+    }
+}
+
+impl<T, S: Storage<T>> Drop for ReadFuture<'_, T, S> {
+    fn drop(&mut self) {
+        unregister(self.reader, &mut self.key);
+    }
+}
+
+/// A [Reader] that implements [futures_core::Stream], yielding every item
+/// in the queue as it becomes available, created by [Reader::into_stream].
+/// Like [Reader::read_async], the stream yields [ReadResult::Ok] and
+/// [ReadResult::Dropout] items but never [ReadResult::Empty], and is woken
+/// up by the writer when new data arrives. The stream ends once the writer
+/// has been closed or dropped and all remaining data was read.
+#[cfg(feature = "futures")]
+pub struct AsyncReader<T, S: Storage<T> = crate::HeapStorage<T>> {
+    reader: Reader<T, S>,
+
+    // The key of this stream's waker in the shared wait list, if registered
+    key: Option<usize>,
+}
+
+#[cfg(feature = "futures")]
+impl<T, S: Storage<T>> Reader<T, S> {
+    /// Convert the reader into a [futures_core::Stream] of read results
+    pub fn into_stream(self) -> AsyncReader<T, S> {
+        AsyncReader {
+            reader: self,
+            key: None,
+        }
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<T, S: Storage<T>> AsyncReader<T, S> {
+    /// Convert the stream back into the [Reader] it came from
+    pub fn into_inner(mut self) -> Reader<T, S> {
+        unregister(&self.reader, &mut self.key);
+        // Move the reader out without running AsyncReader's Drop
+        let this = std::mem::ManuallyDrop::new(self);
+        // SAFETY: `this` is never used or dropped again after the reader is
+        // moved out of it
+        unsafe { std::ptr::read(&this.reader) }
+    }
+}
+
+// AsyncReader never hands out pinned references to its fields
+#[cfg(feature = "futures")]
+impl<T, S: Storage<T>> Unpin for AsyncReader<T, S> {}
+
+#[cfg(feature = "futures")]
+impl<T, S: Storage<T>> futures_core::Stream for AsyncReader<T, S>
+where
+    T: Copy,
+{
+    type Item = ReadResult<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<ReadResult<T>>> {
+        let this = self.get_mut();
+        poll_read(&mut this.reader, &mut this.key, cx).map(|result| match result {
+            ReadResult::Closed => None,
+            result => Some(result),
+        })
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<T, S: Storage<T>> Drop for AsyncReader<T, S> {
+    fn drop(&mut self) {
+        unregister(&self.reader, &mut self.key);
+    }
+}
+
+/// A [Writer] that implements [futures_sink::Sink], created by
+/// [Writer::into_sink]. Since writing never blocks, the sink is always
+/// ready and every item sent to it is written immediately. Once the sink
+/// has been closed, sending any further items fails with [SinkClosed].
+#[cfg(feature = "futures")]
+pub struct WriterSink<T, S: Storage<T> = crate::HeapStorage<T>> {
+    writer: Writer<T, S>,
+    closed: bool,
+}
+
+/// The error returned by [WriterSink] when sending to it after it was closed
+#[cfg(feature = "futures")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SinkClosed;
+
+#[cfg(feature = "futures")]
+impl std::fmt::Display for SinkClosed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the sink was closed")
+    }
+}
+
+#[cfg(feature = "futures")]
+impl std::error::Error for SinkClosed {}
+
+#[cfg(feature = "futures")]
+impl<T, S: Storage<T>> Writer<T, S> {
+    /// Convert the writer into a [futures_sink::Sink]
+    pub fn into_sink(self) -> WriterSink<T, S> {
+        WriterSink {
+            writer: self,
+            closed: false,
+        }
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<T, S: Storage<T>> WriterSink<T, S> {
+    /// Returns whether the sink has been closed
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Convert the sink back into the [Writer] it came from
+    pub fn into_inner(self) -> Writer<T, S> {
+        self.writer
+    }
+}
+
+// WriterSink never hands out pinned references to its fields
+#[cfg(feature = "futures")]
+impl<T, S: Storage<T>> Unpin for WriterSink<T, S> {}
+
+#[cfg(feature = "futures")]
+impl<T, S: Storage<T>> futures_sink::Sink<T> for WriterSink<T, S> {
+    type Error = SinkClosed;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), SinkClosed>> {
+        if self.closed {
+            Poll::Ready(Err(SinkClosed))
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), SinkClosed> {
+        let this = self.get_mut();
+        if this.closed {
+            return Err(SinkClosed);
+        }
+        this.writer.write(item);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), SinkClosed>> {
+        // Items are visible to readers as soon as they're sent
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), SinkClosed>> {
+        self.get_mut().closed = true;
+        Poll::Ready(Ok(()))
+    }
+}
@@ -3,65 +3,186 @@
 //! / consumers may get overtaken by the writer / producer without either of them
 //! blocking. Readers can detect when new data is available, when the queue is
 //! empty, and when they have been overtaken. Readers may also skip to the front
-//! of the queue.
+//! of the queue. Once the writer is closed or dropped, readers receive the
+//! remaining data followed by [ReadResult::Closed].
 //!
-//! Currently, hang-ups are not detected. Additionally, the stored value needs
-//! to implement `Copy` and `Default`.
+//! The stored value needs to implement `Copy` and `Default`.
 //!
 //! To use a ring buffer, call [ring_buffer] to receive a [Reader] and a [Writer].
 //! Call [Writer::write] to push new data onto the queue and [Reader::read] to
 //! receive the new data. Pass both readers and writer to different threads and
 //! clone new readers as desired.
+//!
+//! For propagating a single value that changes over time, such as
+//! configuration, the [watch] module offers a purpose-built channel that only
+//! keeps the latest value. Where a single reader needs the latest value and
+//! neither side may ever wait for the other, use a [triple_buffer] instead.
+//! Values that fit into 64 bits can go through [ring_buffer_atomic], whose
+//! readers and writer never wait for each other either.
+//!
+//! For environments where heap allocation isn't available, a [StaticRingBuffer]
+//! keeps its items inline and hands out readers and writers that borrow it. On
+//! targets without native 32-bit or 64-bit atomics, enable the `portable-atomic`
+//! feature.
+//!
+//! By default, the `cache-padded` feature aligns every item of a ring buffer to
+//! a 64-byte cache line, so that the writer and readers working on neighboring
+//! items don't slow each other down through false sharing. For small items
+//! this multiplies the memory used, so memory-constrained users may prefer to
+//! disable default features.
+//!
+//! With the `tracing` feature enabled, dropouts, calls to [Reader::skip_ahead]
+//! and writes that have to wait for a reader are reported as `tracing` events.
+//!
+//! With the `serde` feature enabled, [ReadResult] and [Snapshot] implement
+//! `Serialize` and `Deserialize`.
+//!
+//! The `shared-memory` feature adds `Writer::create_in_raw` and
+//! `Reader::attach_to_raw`, which place a ring buffer in memory provided by the
+//! caller, such as a mapping that is shared between processes.
 
 use std::{
-    cell::UnsafeCell,
-    sync::{
-        atomic::{AtomicI16, AtomicUsize, Ordering},
-        Arc,
-    },
+    cell::Cell,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    time::{Duration, Instant},
 };
 
-#[cfg(test)]
-mod test;
+mod atomic;
+mod bytes;
+mod channel;
+mod dispatch;
+mod dump;
+mod filter;
+mod fixed_frame;
+mod frame;
+mod progress;
+mod select;
+mod storage;
+mod sync;
+mod timed;
+pub mod triple;
+mod wait;
+pub mod watch;
 
-struct Item<T> {
-    // Use count by either readers or the writer, used for busy waiting and synchronization
-    // and guarding access to data and lap_count
-    //    0     -> not in use
-    // positive -> in use by that many readers
-    //   -1     -> in use by writer
-    use_count: AtomicI16,
+#[cfg(feature = "async")]
+mod future;
 
-    // A simple counter for the number of times the writer had gone through the entire array
-    // when it last wrote data to this item. Wraps upon overflow. Used to detect dropouts.
-    lap_count: UnsafeCell<u16>,
+#[cfg(all(unix, feature = "readiness"))]
+mod readiness;
 
-    // the actual data being stored
-    data: UnsafeCell<T>,
-}
+#[cfg(all(feature = "shared-memory", not(loom)))]
+mod raw;
+
+use storage::{Indexing, UNWRITTEN_SEQUENCE};
+use sync::Ordering;
+
+pub use atomic::{ring_buffer_atomic, AtomicReader, AtomicStorable, AtomicWriter};
+pub use bytes::{byte_ring_buffer, ByteReader, ByteWriter, BytesLost};
+pub use channel::{
+    channel, Receiver, RecvError, RecvTimeoutError, SendError, Sender, TryRecvError,
+};
+pub use dispatch::DispatchReader;
+pub use dump::ring_buffer_restore;
+pub use filter::FilteredReader;
+pub use fixed_frame::{ring_buffer_frames, FixedFrameReader, FixedFrameWriter};
+pub use frame::{frame_buffer, FrameReader, FrameTooLarge, FrameWriter};
+pub use select::ReadSelect;
+pub use storage::{
+    BoxedStorage, HeapStorage, Item, StaticReader, StaticRingBuffer, StaticWriter, Storage,
+};
+pub use timed::Timestamped;
+pub use triple::triple_buffer;
+
+#[cfg(feature = "async")]
+pub use future::ReadFuture;
+
+#[cfg(all(feature = "shared-memory", not(loom)))]
+pub use raw::{RawError, RawStorage, RAW_POLL_INTERVAL};
+
+#[cfg(feature = "futures")]
+pub use future::{AsyncReader, SinkClosed, WriterSink};
+
+#[cfg(all(test, not(loom)))]
+mod scenario;
+
+// The unit tests use real threads and atomics, which loom doesn't allow
+// outside of its models, see tests/loom.rs instead. The same goes for the
+// atomics of shuttle with the `shuttle-tests` feature, which then runs the
+// scenarios that it shares with the unit tests instead. Under Miri, the unit
+// tests run far fewer iterations, and those that depend on timing are
+// ignored. Check them with both aliasing models by running
+// `cargo +nightly miri test --lib`, once with and once without
+// `MIRIFLAGS=-Zmiri-tree-borrows`.
+#[cfg(all(test, not(loom), not(feature = "shuttle-tests")))]
+mod test;
+
+#[cfg(all(test, not(loom), feature = "shuttle-tests"))]
+mod shuttle_test;
 
 /// The receiving end of a ring buffer, which reads data from the [Writer] that it was
 /// created with by calling [ring_buffer]. Call [Reader::read] to receive new data if
 /// it's, available, and clone the reader to create additional readers.
-pub struct Reader<T> {
-    data: Arc<[Item<T>]>,
-    write_index: Arc<AtomicUsize>,
+pub struct Reader<T, S: Storage<T> = HeapStorage<T>> {
+    storage: S,
+    id: ReaderId,
+    indexing: Indexing,
+    spin_policy: SpinPolicy,
     read_index: usize,
-    lap_count: u16,
+
+    // The sequence number of the item that the reader expects to find at
+    // the read index
+    sequence: u64,
+
+    // The sequence number of the item that was read last, if any
+    last_sequence: Option<u64>,
+
+    // The sequence number that the reader expected before it last skipped
+    // ahead, until the next item is read
+    skipped_from: Option<u64>,
+
+    stats: ReaderStats,
+
+    // The largest lag seen by any read or by Reader::has_data, see
+    // Reader::lag_high_watermark. This is a Cell so that Reader::has_data
+    // can keep taking &self.
+    lag_high_watermark: Cell<usize>,
+
+    // Called whenever a read returns a dropout
+    on_dropout: Option<Box<dyn FnMut(DropoutEvent) + Send>>,
+
+    // Where the reader publishes its position for the writer, see
+    // Writer::slowest_reader_lag and ring_buffer_with_overwrite_policy
+    progress: progress::ProgressSlot,
+
+    #[cfg(all(unix, feature = "readiness"))]
+    readiness: Option<std::sync::Arc<readiness::Readiness>>,
+    _phantom: PhantomData<T>,
 }
 
-unsafe impl<T> Send for Reader<T> where T: Send {}
+unsafe impl<T, S: Storage<T>> Send for Reader<T, S> where T: Send {}
 
 /// The sending end of a ring buffer, which passes data to any [Reader] instances
 /// created from calling [ring_buffer]. Call [Writer::write] to make new data
 /// available, at risk of overwriting old data and overtaking readers.
-pub struct Writer<T> {
-    data: Arc<[Item<T>]>,
-    write_index: Arc<AtomicUsize>,
-    lap_count: u16,
+pub struct Writer<T, S: Storage<T> = HeapStorage<T>> {
+    storage: S,
+    indexing: Indexing,
+    spin_policy: SpinPolicy,
+    index: usize,
+    sequence: u64,
+
+    // The sequence number up to which the writer of a lossless ring buffer
+    // knows that all readers are far enough along, see PendingWrites, and
+    // how many times readers had been added or moved backwards by then
+    writable: u64,
+    moved_back: u64,
+
+    stats: WriterStats,
+    _phantom: PhantomData<T>,
 }
 
-unsafe impl<T> Send for Writer<T> where T: Send {}
+unsafe impl<T, S: Storage<T>> Send for Writer<T, S> where T: Send {}
 
 /// Construct a new ring buffer consisting of a [Reader] and a [Writer].
 /// The internal buffer will have the specified capacity, and no
@@ -71,47 +192,481 @@ unsafe impl<T> Send for Writer<T> where T: Send {}
 /// better chance of observing all data, though it also increases memory
 /// usage.
 ///
+/// A capacity of 1 is allowed and results in a single "latest value" slot,
+/// where each reader sees the most recently written value at most once and
+/// gets [ReadResult::Dropout] if the value was replaced more than once since
+/// its last read.
+///
+/// See [try_ring_buffer] for a version that returns an error instead of
+/// panicking.
+///
 /// # Panics
-/// Panics if the capacity is less than 2.
+/// Panics if the capacity is zero or if the buffer would be too
+/// large to allocate.
 pub fn ring_buffer<T>(capacity: usize) -> (Reader<T>, Writer<T>)
 where
     T: Default,
 {
-    assert!(capacity >= 2);
-
-    let mut data = Vec::<Item<T>>::new();
-    data.resize_with(capacity, || Item {
-        use_count: AtomicI16::new(0),
-        data: UnsafeCell::new(T::default()),
-        lap_count: UnsafeCell::new(0),
-    });
-
-    let data: Arc<[Item<T>]> = data.into_boxed_slice().into();
-
-    let write_index = Arc::new(AtomicUsize::new(0));
-
-    let reader = Reader {
-        data: Arc::clone(&data),
-        write_index: Arc::clone(&write_index),
-        read_index: 0,
-        // NOTE: the writer and writer lap counts must be 1 if the data lap counts are all zero,
-        // see note in Reader::read
-        lap_count: 1,
-    };
-
-    let writer = Writer {
-        data,
-        write_index,
-        // NOTE: the writer and writer lap counts must be 1 if the data lap counts are all zero,
-        // see note in Reader::read
-        lap_count: 1,
-    };
+    match try_ring_buffer(capacity) {
+        Ok(pair) => pair,
+        Err(err) => panic!("{}", err),
+    }
+}
+
+/// The error returned by [try_ring_buffer] when a ring buffer can't be
+/// constructed with the requested capacity.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CapacityError {
+    /// The requested capacity was zero.
+    TooSmall(usize),
+
+    /// The requested capacity was so large that the internal buffer
+    /// would exceed the maximum allocation size of `isize::MAX` bytes.
+    TooLarge(usize),
+}
+
+impl std::fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CapacityError::TooSmall(capacity) => write!(
+                f,
+                "ring buffer capacity must be at least 1, but {} was requested",
+                capacity
+            ),
+            CapacityError::TooLarge(capacity) => write!(
+                f,
+                "ring buffer capacity of {} is too large to allocate",
+                capacity
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CapacityError {}
+
+/// Construct a new ring buffer consisting of a [Reader] and a [Writer],
+/// or return an error if the capacity is invalid. See [ring_buffer] for
+/// details.
+///
+/// Returns [CapacityError::TooSmall] if the capacity is zero and
+/// [CapacityError::TooLarge] if the internal buffer would occupy more than
+/// `isize::MAX` bytes.
+pub fn try_ring_buffer<T>(capacity: usize) -> Result<(Reader<T>, Writer<T>), CapacityError>
+where
+    T: Default,
+{
+    if capacity == 0 {
+        return Err(CapacityError::TooSmall(capacity));
+    }
+
+    if !HeapStorage::<T>::fits(capacity) {
+        return Err(CapacityError::TooLarge(capacity));
+    }
+
+    let storage = HeapStorage::new(capacity);
+
+    let reader = Reader::new(storage.clone());
+    let writer = Writer::new(storage);
+
+    Ok((reader, writer))
+}
+
+/// Construct a new ring buffer like [ring_buffer], whose items live in the
+/// given buffer instead of an allocation of its own, e.g. one that was set
+/// aside from an arena at startup. The capacity is the length of the buffer,
+/// whose slots don't need to be initialized. Allocate the buffer with the
+/// layout from [Item::layout], or with [Box::new_uninit_slice]:
+///
+/// ```
+/// use spmcq::{ring_buffer_in, Item, ReadResult};
+///
+/// let items = Box::<[Item<u32>]>::new_uninit_slice(16);
+/// let (mut reader, mut writer) = ring_buffer_in(items);
+/// assert_eq!(writer.capacity(), 16);
+///
+/// writer.write(1);
+/// assert_eq!(reader.read(), ReadResult::Ok(1));
+/// ```
+///
+/// # Panics
+/// Panics if the buffer is empty, like [ring_buffer] does for a capacity of
+/// zero.
+pub fn ring_buffer_in<T>(
+    items: Box<[MaybeUninit<Item<T>>]>,
+) -> (Reader<T, BoxedStorage<T>>, Writer<T, BoxedStorage<T>>) {
+    if items.is_empty() {
+        panic!("{}", CapacityError::TooSmall(0));
+    }
+
+    let storage = BoxedStorage::new(items);
+
+    let reader = Reader::new(storage.clone());
+    let writer = Writer::new(storage);
+
+    (reader, writer)
+}
+
+/// Construct a new ring buffer like [ring_buffer], whose reader and writer
+/// wait for locked items according to the given [SpinPolicy]. Readers cloned
+/// from the reader use the same policy.
+///
+/// # Panics
+/// Panics if the capacity is zero, see [ring_buffer].
+pub fn ring_buffer_with_policy<T>(capacity: usize, policy: SpinPolicy) -> (Reader<T>, Writer<T>)
+where
+    T: Default,
+{
+    let (mut reader, mut writer) = ring_buffer(capacity);
+    reader.set_spin_policy(policy);
+    writer.set_spin_policy(policy);
+    (reader, writer)
+}
+
+/// Construct a new ring buffer like [ring_buffer], whose writer never
+/// overwrites anything that a reader hasn't read yet. Instead, writing waits
+/// for the slowest reader to read the item that is about to be overwritten,
+/// as the writer's [SpinPolicy] says, so that no reader ever gets a
+/// [ReadResult::Dropout] unless it skips ahead on purpose. [Writer::try_write]
+/// and [Writer::write_timeout] give up instead of waiting, as they do for a
+/// reader that is busy with the item. This is the same as
+/// [ring_buffer_with_overwrite_policy] with [OverwritePolicy::Block].
+///
+/// Every reader holds back the writer for as long as it exists, including
+/// one that never reads, so drop readers that are done. Readers publish how
+/// far they have read after every read, and the writer only looks at that
+/// once it has caught up with what it last saw of the slowest reader, or once
+/// a reader was added or moved backwards, e.g. with [Reader::rewind].
+///
+/// ```
+/// use spmcq::{ring_buffer_lossless, ReadResult};
+///
+/// let (mut reader, mut writer) = ring_buffer_lossless::<u32>(2);
+/// writer.write(1);
+/// writer.write(2);
+///
+/// // The buffer is full until the reader reads something
+/// assert!(writer.try_write(3).is_err());
+/// assert_eq!(reader.read(), ReadResult::Ok(1));
+/// assert!(writer.try_write(3).is_ok());
+///
+/// // Without any readers, there is nobody to wait for
+/// drop(reader);
+/// writer.write(4);
+/// ```
+///
+/// # Panics
+/// Panics if the capacity is zero, see [ring_buffer].
+pub fn ring_buffer_lossless<T>(capacity: usize) -> (Reader<T>, Writer<T>)
+where
+    T: Default,
+{
+    ring_buffer_with_overwrite_policy(capacity, OverwritePolicy::Block)
+}
+
+/// Construct a new ring buffer like [ring_buffer], whose writer does what the
+/// given [OverwritePolicy] says when it is about to overwrite an item that a
+/// reader hasn't read yet. The policy is stored in the ring buffer itself
+/// and can't be changed afterwards, see [Writer::overwrite_policy].
+///
+/// ```
+/// use spmcq::{ring_buffer_with_overwrite_policy, OverwritePolicy, ReadResult};
+///
+/// let (mut reader, mut writer) =
+///     ring_buffer_with_overwrite_policy::<u32>(2, OverwritePolicy::Reject);
+/// assert!(writer.write_checked(1).is_ok());
+/// assert!(writer.write_checked(2).is_ok());
+///
+/// // The reader would lose the first value, so the third one is dropped
+/// assert_eq!(writer.write_checked(3).unwrap_err().0, 3);
+/// assert_eq!(reader.read(), ReadResult::Ok(1));
+/// assert_eq!(reader.read(), ReadResult::Ok(2));
+/// ```
+///
+/// # Panics
+/// Panics if the capacity is zero, see [ring_buffer].
+pub fn ring_buffer_with_overwrite_policy<T>(
+    capacity: usize,
+    policy: OverwritePolicy,
+) -> (Reader<T>, Writer<T>)
+where
+    T: Default,
+{
+    use storage::sealed::Sealed;
 
+    let (reader, writer) = ring_buffer(capacity);
+    writer
+        .storage
+        .header()
+        .overwrite_policy
+        .store(policy.to_raw(), Ordering::SeqCst);
     (reader, writer)
 }
 
+/// The maximum number of readers that may exist at once for each ring
+/// buffer, unless changed with [Writer::set_max_readers]
+pub const DEFAULT_MAX_READERS: usize = 4096;
+
+/// The number of times that a read or write spins in a tight loop while the
+/// item that it's about to access is locked, before it starts yielding to
+/// other threads, unless changed with a [SpinPolicy]. Readers and the writer
+/// normally hold an item only for as long as it takes to copy a value, but
+/// if one was descheduled in the meantime, spinning only keeps it from
+/// running. Until then, waiting is a pure spin, so that the common case
+/// stays as fast as possible. See [ReaderStats::max_spins] and
+/// [WriterStats::max_spins].
+pub const SPIN_LIMIT: u32 = 100;
+
+/// The number of times that a read or write yields to other threads after
+/// spinning [SPIN_LIMIT] times, before it starts sleeping for [BACKOFF_SLEEP]
+/// between checks instead, unless changed with a [SpinPolicy]
+pub const YIELD_LIMIT: u32 = 10;
+
+/// How long a read or write sleeps between checks once it has spun and
+/// yielded without the item becoming available, unless changed with a
+/// [SpinPolicy], see [SPIN_LIMIT]
+pub const BACKOFF_SLEEP: Duration = Duration::from_micros(50);
+
+/// How long [Reader::health] waits for the writer to release the item that
+/// the reader is about to read before reporting it as stuck. Copying a value
+/// takes far less, so a writer that holds on to an item for this long
+/// without writing anything else has most likely stopped or died halfway.
+pub const STUCK_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How a [Reader] or [Writer] waits while the item that it's about to access
+/// is locked: first by spinning in a tight loop, then by yielding to other
+/// threads, and finally by sleeping between checks. The default spins
+/// [SPIN_LIMIT] times, yields [YIELD_LIMIT] times and then sleeps for
+/// [BACKOFF_SLEEP] at a time. Pass a policy to [ring_buffer_with_policy], or
+/// change it for a single reader or writer with [Reader::set_spin_policy]
+/// and [Writer::set_spin_policy].
+///
+/// ```
+/// use spmcq::{ring_buffer_with_policy, SpinPolicy};
+///
+/// // Never give up the CPU, for a thread that has a core to itself
+/// let (reader, writer) = ring_buffer_with_policy::<u32>(16, SpinPolicy::spin());
+/// assert_eq!(reader.spin_policy(), SpinPolicy::spin());
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SpinPolicy {
+    /// The number of times to spin before yielding. [u32::MAX] spins forever.
+    pub spin_iters: u32,
+
+    /// The number of times to yield after spinning before sleeping
+    pub yield_iters: u32,
+
+    /// How long to sleep between checks after spinning and yielding, or
+    /// None to keep yielding instead
+    pub park: Option<Duration>,
+}
+
+impl SpinPolicy {
+    /// A policy that spins forever and never yields
+    pub const fn spin() -> SpinPolicy {
+        SpinPolicy {
+            spin_iters: u32::MAX,
+            yield_iters: 0,
+            park: None,
+        }
+    }
+
+    /// Wait before checking a locked item again, after having waited `spins`
+    /// times already
+    pub(crate) fn backoff(&self, spins: u64) {
+        // Under loom and shuttle, waiting must always yield to the model, see
+        // sync::spin_loop
+        if sync::MODELED || self.is_spinning(spins) {
+            sync::spin_loop();
+            return;
+        }
+
+        let spin_iters = u64::from(self.spin_iters);
+        match self.park {
+            Some(duration) if spins >= spin_iters + u64::from(self.yield_iters) => {
+                std::thread::sleep(duration)
+            }
+            _ => std::thread::yield_now(),
+        }
+    }
+
+    /// Returns whether [SpinPolicy::backoff] still spins in a tight loop
+    /// after having waited `spins` times already
+    pub(crate) fn is_spinning(&self, spins: u64) -> bool {
+        self.spin_iters == u32::MAX || spins < u64::from(self.spin_iters)
+    }
+}
+
+impl Default for SpinPolicy {
+    fn default() -> Self {
+        SpinPolicy {
+            spin_iters: SPIN_LIMIT,
+            yield_iters: YIELD_LIMIT,
+            park: Some(BACKOFF_SLEEP),
+        }
+    }
+}
+
+/// What a [Writer] does when it is about to overwrite an item that a reader
+/// hasn't read yet, see [ring_buffer_with_overwrite_policy]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum OverwritePolicy {
+    /// Overwrite the item, so that the reader gets a [ReadResult::Dropout]
+    /// once it catches up. The writer never waits for slow readers.
+    #[default]
+    Overwrite,
+
+    /// Wait for the slowest reader to read the item first, see
+    /// [ring_buffer_lossless]
+    Block,
+
+    /// Don't write the value at all, see [Writer::write_checked]. Like
+    /// [OverwritePolicy::Block], the writer keeps track of the slowest
+    /// reader, but it never waits for it, which suits realtime producers
+    /// that would rather drop values at the source.
+    Reject,
+}
+
+impl OverwritePolicy {
+    /// The policy's representation in the ring buffer's header
+    fn to_raw(self) -> usize {
+        match self {
+            OverwritePolicy::Overwrite => 0,
+            OverwritePolicy::Block => 1,
+            OverwritePolicy::Reject => 2,
+        }
+    }
+
+    /// The inverse of [OverwritePolicy::to_raw]
+    fn from_raw(raw: usize) -> OverwritePolicy {
+        match raw {
+            1 => OverwritePolicy::Block,
+            2 => OverwritePolicy::Reject,
+            _ => OverwritePolicy::Overwrite,
+        }
+    }
+}
+
+/// The error returned by [Reader::try_clone] when the ring buffer already
+/// has the maximum number of readers, which is contained in the error.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TooManyReaders(pub usize);
+
+impl std::fmt::Display for TooManyReaders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ring buffer already has the maximum of {} readers",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for TooManyReaders {}
+
+/// The error returned by [Reader::try_promote_writer] when the ring buffer
+/// still has a writer
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct WriterStillAlive;
+
+impl std::fmt::Display for WriterStillAlive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the ring buffer's writer has not been closed or dropped")
+    }
+}
+
+impl std::error::Error for WriterStillAlive {}
+
+/// The error returned by [Writer::try_write] when a reader is busy with the
+/// item that would be overwritten, which contains the value that could not
+/// be written
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct TryWriteError<T>(pub T);
+
+impl<T> std::fmt::Debug for TryWriteError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Like SendError, without requiring the value to be Debug
+        f.write_str("TryWriteError { .. }")
+    }
+}
+
+impl<T> std::fmt::Display for TryWriteError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "a reader is busy with the item to be overwritten")
+    }
+}
+
+impl<T> std::error::Error for TryWriteError<T> {}
+
+/// The error returned by [Writer::write_timeout] when a reader was still
+/// busy with the item that would be overwritten once the timeout passed,
+/// which contains the value that could not be written
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct WriteTimeout<T>(pub T);
+
+impl<T> std::fmt::Debug for WriteTimeout<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("WriteTimeout { .. }")
+    }
+}
+
+impl<T> std::fmt::Display for WriteTimeout<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "timed out waiting for a reader to release the item")
+    }
+}
+
+impl<T> std::error::Error for WriteTimeout<T> {}
+
+/// The error returned by [Writer::write_checked] when writing would overwrite
+/// an item that a reader hasn't read yet in a ring buffer with
+/// [OverwritePolicy::Reject], which contains the value that was not written
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct WouldOvertake<T>(pub T);
+
+impl<T> std::fmt::Debug for WouldOvertake<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("WouldOvertake { .. }")
+    }
+}
+
+impl<T> std::fmt::Display for WouldOvertake<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "writing would overwrite an item that a reader hasn't read"
+        )
+    }
+}
+
+impl<T> std::error::Error for WouldOvertake<T> {}
+
+/// The items that a ring buffer held at one point, along with where they
+/// were and where the reader and writer were, see
+/// [Reader::snapshot_detailed]
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot<T> {
+    /// The number of items that the ring buffer can hold
+    pub capacity: usize,
+
+    /// The sequence number of the oldest item, which is the first of
+    /// [Snapshot::items]
+    pub first_sequence: u64,
+
+    /// The sequence number that the writer was going to write next, i.e. the
+    /// number of items written so far, which is one past the newest item
+    pub write_sequence: u64,
+
+    /// The sequence number that the reader was going to read next
+    pub read_sequence: u64,
+
+    /// The items from oldest to newest, with consecutive sequence numbers
+    pub items: Vec<T>,
+}
+
 /// The result of reading from a ring buffer by [Reader::read]
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ReadResult<T> {
     /// New data was received, and the reader is somewhere in the middle of the queue.
     Ok(T),
@@ -124,31 +679,32 @@ pub enum ReadResult<T> {
 
     /// The reader is at the very front of the queue and no new data is available.
     Empty,
+
+    /// The reader is at the very front of the queue and the writer has been
+    /// closed or dropped, so no new data will ever become available, unless
+    /// a new writer is created with [Reader::try_promote_writer].
+    Closed,
 }
 
 impl<T> ReadResult<T> {
     /// Returns whether self is [ReadResult::Ok]
     pub fn is_ok(&self) -> bool {
-        match self {
-            ReadResult::Ok(_) => true,
-            _ => false,
-        }
+        matches!(self, ReadResult::Ok(_))
     }
 
     /// Returns whether self is [ReadResult::Dropout]
     pub fn is_dropout(&self) -> bool {
-        match self {
-            ReadResult::Dropout(_) => true,
-            _ => false,
-        }
+        matches!(self, ReadResult::Dropout(_))
     }
 
     /// Returns whether self is [ReadResult::Empty]
     pub fn is_empty(&self) -> bool {
-        match self {
-            ReadResult::Empty => true,
-            _ => false,
-        }
+        matches!(self, ReadResult::Empty)
+    }
+
+    /// Returns whether self is [ReadResult::Closed]
+    pub fn is_closed(&self) -> bool {
+        matches!(self, ReadResult::Closed)
     }
 
     /// If self is [ReadResult::Ok] or [ReadResult::Dropout], returns the
@@ -158,174 +714,2906 @@ impl<T> ReadResult<T> {
             ReadResult::Ok(v) => Some(v),
             ReadResult::Dropout(v) => Some(v),
             ReadResult::Empty => None,
+            ReadResult::Closed => None,
         }
     }
-}
 
-impl<T> Reader<T>
-where
-    T: Copy,
-{
-    /// Receive the next item in the queue if anything is available.
-    /// If the reader is somewhere in the middle of the queue, returns
-    /// [ReadResult::Ok] with the next item. If the reader has beenovertaken
-    /// by the writer since its last read, returns [ReadResult::Dropout]
-    /// with a more recent item to indicate that some items were lost.
-    /// Otherwise, if the reader is fully caught up to writer and no new
-    /// data is available, returns [ReadResult::Empty].
+    /// Apply a function to the received value, if there is one, keeping
+    /// the kind of result the same
     ///
-    /// This method uses a spin lock and may busy-wait for a short duration
-    /// if the writer happens to be writing to the same position as the
-    /// reader. The guarded section performs only a trivial copy of the data.
-    pub fn read(&mut self) -> ReadResult<T> {
-        // Get the item to be read from
-        let item = &self.data[self.read_index];
-
-        // try to increment the use count, spin until the old use count was definitely positive
-        let mut expected_use_count = 0;
-        while let Err(actual_use_count) = item.use_count.compare_exchange(
-            expected_use_count,
-            expected_use_count + 1,
-            Ordering::SeqCst,
-            Ordering::SeqCst,
-        ) {
-            debug_assert!(actual_use_count >= -1, "Invalid use count");
-            debug_assert!(actual_use_count < i16::MAX, "Reader overflow");
-            expected_use_count = actual_use_count.max(0);
-            std::hint::spin_loop();
-        }
-
-        // SAFETY: the spin loop above ensures that the use count wasn't -1 before and is positive
-        // now. Thus, the writer will block until the use count is decremented again, thus this
-        // read is guarded. Mutation is not safe because there could be multiple readers.
-
-        // Copy the value then immediately leave the locked section to release the lock again to
-        // prevent holding up the writer. T must be Copy for this reason.
-        let value = unsafe { *item.data.get() };
-
-        let value_lap_count = unsafe { *item.lap_count.get() };
-
-        // Read lock is released here
-        let final_use_count = item.use_count.fetch_sub(1, Ordering::SeqCst);
-        debug_assert!(final_use_count >= 0);
-
-        let expected_lap_count = self.lap_count;
-
-        if value_lap_count.wrapping_add(1) == expected_lap_count {
-            // If the lap count is exactly one behind the expected lap count,
-            // we just overtook the writer. Discard the value because it's
-            // old and don't move.
-            // NOTE that if all value lap counts are set to 0 initially, the
-            // reader and writer must start with a lap count of 1 for the
-            // buffer to appear empty to the reader when it is first constructed.
-            return ReadResult::Empty;
-        } else if value_lap_count != expected_lap_count {
-            // If the lap count is off, we lost some values. Overwrite
-            // the lap count to attempt to catch up with the reader.
-            self.lap_count = value_lap_count;
+    /// ```
+    /// use spmcq::ReadResult;
+    ///
+    /// assert_eq!(ReadResult::Ok(2).map(|v| v * 10), ReadResult::Ok(20));
+    /// assert_eq!(ReadResult::Dropout(2).map(|v| v * 10), ReadResult::Dropout(20));
+    /// assert_eq!(ReadResult::<i32>::Empty.map(|v| v * 10), ReadResult::Empty);
+    /// ```
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> ReadResult<U> {
+        match self {
+            ReadResult::Ok(v) => ReadResult::Ok(f(v)),
+            ReadResult::Dropout(v) => ReadResult::Dropout(f(v)),
+            ReadResult::Empty => ReadResult::Empty,
+            ReadResult::Closed => ReadResult::Closed,
         }
+    }
 
-        // Move one index forward
-        self.read_index += 1;
-        if self.read_index == self.data.len() {
-            self.read_index = 0;
-            self.lap_count = self.lap_count.wrapping_add(1);
+    /// Borrow the received value, if there is one, keeping the kind of
+    /// result the same
+    ///
+    /// ```
+    /// use spmcq::ReadResult;
+    ///
+    /// let result = ReadResult::Dropout(String::from("hi"));
+    /// assert_eq!(result.as_ref().map(|s| s.len()), ReadResult::Dropout(2));
+    /// assert_eq!(result.value().as_deref(), Some("hi"));
+    /// ```
+    pub fn as_ref(&self) -> ReadResult<&T> {
+        match self {
+            ReadResult::Ok(v) => ReadResult::Ok(v),
+            ReadResult::Dropout(v) => ReadResult::Dropout(v),
+            ReadResult::Empty => ReadResult::Empty,
+            ReadResult::Closed => ReadResult::Closed,
         }
+    }
 
-        if value_lap_count == expected_lap_count {
-            // If the lap count matches what we expected, all is normal.
-            ReadResult::Ok(value)
-        } else {
-            // If the lap count is off, we lost some values in between
-            ReadResult::Dropout(value)
+    /// Returns the received value, whether it came with a dropout or not.
+    ///
+    /// ```
+    /// use spmcq::ReadResult;
+    ///
+    /// assert_eq!(ReadResult::Ok(1).unwrap(), 1);
+    /// assert_eq!(ReadResult::Dropout(2).unwrap(), 2);
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if self is [ReadResult::Empty] or [ReadResult::Closed].
+    #[track_caller]
+    pub fn unwrap(self) -> T {
+        self.expect("called `ReadResult::unwrap()` without a value")
+    }
+
+    /// Returns the received value, whether it came with a dropout or not.
+    ///
+    /// ```
+    /// use spmcq::ReadResult;
+    ///
+    /// assert_eq!(ReadResult::Dropout(2).expect("nothing to read"), 2);
+    /// ```
+    ///
+    /// # Panics
+    /// Panics with the given message if self is [ReadResult::Empty] or
+    /// [ReadResult::Closed].
+    #[track_caller]
+    pub fn expect(self, msg: &str) -> T {
+        match self {
+            ReadResult::Ok(v) | ReadResult::Dropout(v) => v,
+            ReadResult::Empty => panic!("{}: the queue is empty", msg),
+            ReadResult::Closed => panic!("{}: the queue is closed", msg),
         }
     }
 
-    /// Immediately advance the reader to the front of the queue and catch
-    /// up with the reader. This method should ideally only be used right
-    /// before a call to [Reader::read], since otherwise the reader could
-    /// overtake the writer again. The next result of reading will always be
-    /// [ReadResult::Dropout] regardless of whether data was actually lost.
+    /// Returns the received value if there is one, and `default` otherwise
     ///
-    /// Calling this method multiple times in between reads may result
-    /// in the same item being observed multiple times.
-    pub fn skip_ahead(&mut self) {
-        // Because the write_index typically points to the index that the
-        // writer is _going_ to write to, subtract one so that we point
-        // the most-recently written item if not the second-most recent.
-        self.read_index = self.write_index.load(Ordering::SeqCst);
-        self.read_index = if self.read_index == 0 {
-            self.data.len()
-        } else {
-            self.read_index
-        } - 1;
+    /// ```
+    /// use spmcq::ReadResult;
+    ///
+    /// assert_eq!(ReadResult::Dropout(2).unwrap_or(0), 2);
+    /// assert_eq!(ReadResult::Empty.unwrap_or(0), 0);
+    /// ```
+    pub fn unwrap_or(self, default: T) -> T {
+        self.value().unwrap_or(default)
+    }
 
-        // Also adjust the lap count to (effectively) guarantee that the
-        // next read returns Dropout
-        self.lap_count = self.lap_count.wrapping_sub(1);
+    /// Returns the received value if there is one, and `err` otherwise
+    ///
+    /// ```
+    /// use spmcq::ReadResult;
+    ///
+    /// assert_eq!(ReadResult::Ok(1).ok_or("nothing"), Ok(1));
+    /// assert_eq!(ReadResult::<i32>::Closed.ok_or("nothing"), Err("nothing"));
+    /// ```
+    pub fn ok_or<E>(self, err: E) -> Result<T, E> {
+        self.value().ok_or(err)
     }
 }
 
-impl<T> Clone for Reader<T> {
-    fn clone(&self) -> Self {
-        Self {
-            data: Arc::clone(&self.data),
-            write_index: Arc::clone(&self.write_index),
-            read_index: self.read_index,
-            lap_count: self.lap_count,
-        }
+impl<T> From<ReadResult<T>> for Option<T> {
+    /// The same as [ReadResult::value]
+    fn from(result: ReadResult<T>) -> Option<T> {
+        result.value()
     }
 }
 
-impl<T> Writer<T> {
-    /// Write new data onto the queue, possibly overwriting old data. Any readers
-    /// that were fully caught up will see the new data with [ReadResult::Ok],
-    /// while any readers that get overtaken will see the new data but with
-    /// [ReadResult::Dropout] instead.
-    ///
-    /// This method uses a spin lock and may busy-wait for a short duration if
-    /// any readers happen to be actively reading from the very back of the
-    /// queue. The guarded section is performs only a trivial copy of the data.
-    pub fn write(&mut self, value: T) {
-        // Get the current write index
-        let index = self.write_index.load(Ordering::SeqCst);
+/// The result of reading from a ring buffer by [Reader::read_detailed],
+/// which is the same as [ReadResult] except that dropouts also report how
+/// many items were lost and where the reader picked up again
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Detailed<T> {
+    /// New data was received without anything being lost, see [ReadResult::Ok]
+    Ok(T),
 
-        // fetch the item about to be written to
-        let item = &self.data[index];
+    /// New data was received, but some items that came before it were
+    /// never received by the reader, as described by `info`, see
+    /// [ReadResult::Dropout]
+    Dropout { value: T, info: DropoutInfo },
 
-        // spin until use count is zero, write -1
-        while let Err(actual_use_count) =
-            item.use_count
-                .compare_exchange(0, -1, Ordering::SeqCst, Ordering::SeqCst)
-        {
-            debug_assert!(actual_use_count > 0, "Invalid use count");
+    /// No new data is available yet, see [ReadResult::Empty]
+    Empty,
+
+    /// No new data will ever become available, see [ReadResult::Closed]
+    Closed,
+}
+
+impl<T> Detailed<T> {
+    /// If self is [Detailed::Ok] or [Detailed::Dropout], returns the
+    /// received value. Otherwise, returns None.
+    pub fn value(self) -> Option<T> {
+        match self {
+            Detailed::Ok(v) => Some(v),
+            Detailed::Dropout { value, .. } => Some(value),
+            Detailed::Empty => None,
+            Detailed::Closed => None,
+        }
+    }
+
+    /// Returns the number of items lost if self is [Detailed::Dropout],
+    /// and zero otherwise
+    pub fn lost(&self) -> u64 {
+        match self {
+            Detailed::Dropout { info, .. } => info.lost,
+            _ => 0,
+        }
+    }
+
+    /// Returns what was lost if self is [Detailed::Dropout], and None
+    /// otherwise
+    pub fn dropout_info(&self) -> Option<DropoutInfo> {
+        match self {
+            Detailed::Dropout { info, .. } => Some(*info),
+            _ => None,
+        }
+    }
+}
+
+impl<T> Detailed<T> {
+    /// Apply a function to the received value, if there is one, keeping
+    /// the kind of result the same
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Detailed<U> {
+        match self {
+            Detailed::Ok(v) => Detailed::Ok(f(v)),
+            Detailed::Dropout { value, info } => Detailed::Dropout {
+                value: f(value),
+                info,
+            },
+            Detailed::Empty => Detailed::Empty,
+            Detailed::Closed => Detailed::Closed,
+        }
+    }
+}
+
+/// Describes the items that a reader lost in a dropout, and where it picked
+/// up again, see [Detailed::Dropout]. Laps count how many times the writer
+/// had wrapped around the ring buffer when it wrote an item, so that a
+/// reader that was lapped once expected an item from one lap before the
+/// one it received.
+///
+/// ```
+/// use spmcq::{ring_buffer, Detailed};
+///
+/// let (mut reader, mut writer) = ring_buffer::<u32>(4);
+/// writer.write(0);
+/// assert_eq!(reader.read_detailed(), Detailed::Ok(0));
+/// for value in 1..9 {
+///     writer.write(value);
+/// }
+///
+/// // The item that the reader expected was overwritten by the one a lap
+/// // later, and the items in between are lost
+/// let Detailed::Dropout { value, info } = reader.read_detailed() else {
+///     panic!("the reader wasn't overtaken");
+/// };
+/// assert_eq!(value, 5);
+/// assert_eq!(info.lost, 4);
+/// assert_eq!((info.expected_sequence, info.expected_lap), (1, 0));
+/// assert_eq!((info.sequence, info.lap, info.index), (5, 1, 1));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DropoutInfo {
+    /// The number of items that were lost. This is exact as long as the
+    /// writer isn't writing concurrently, and otherwise is an upper bound.
+    pub lost: u64,
+
+    /// The sequence number of the item that the reader expected to read
+    /// next, see [Reader::read_indexed]. After [Reader::skip_ahead], this is
+    /// where the reader was before skipping.
+    pub expected_sequence: u64,
+
+    /// The lap of the item that the reader expected to read next
+    pub expected_lap: u64,
+
+    /// The sequence number of the item that was delivered instead
+    pub sequence: u64,
+
+    /// The lap of the item that was delivered
+    pub lap: u64,
+
+    /// The index in the ring buffer of the item that was delivered, where
+    /// the reader continues from
+    pub index: usize,
+}
+
+impl<T> From<Detailed<T>> for ReadResult<T> {
+    fn from(result: Detailed<T>) -> ReadResult<T> {
+        match result {
+            Detailed::Ok(v) => ReadResult::Ok(v),
+            Detailed::Dropout { value, .. } => ReadResult::Dropout(value),
+            Detailed::Empty => ReadResult::Empty,
+            Detailed::Closed => ReadResult::Closed,
+        }
+    }
+}
+
+/// The result of reading from a ring buffer by [Reader::try_read], which is
+/// the same as [ReadResult] except that it may also report that the writer
+/// was in the way
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TryReadResult<T> {
+    /// New data was received without anything being lost, see [ReadResult::Ok]
+    Ok(T),
+
+    /// New data was received, but some data before it was lost, see
+    /// [ReadResult::Dropout]
+    Dropout(T),
+
+    /// No new data is available yet, see [ReadResult::Empty]
+    Empty,
+
+    /// No new data will ever become available, see [ReadResult::Closed]
+    Closed,
+
+    /// The writer was busy with the next item, so nothing was read and the
+    /// reader stays where it is. Reading again later will find the item.
+    Busy,
+}
+
+impl<T> TryReadResult<T> {
+    /// Returns whether self is [TryReadResult::Busy]
+    pub fn is_busy(&self) -> bool {
+        matches!(self, TryReadResult::Busy)
+    }
+
+    /// If self is [TryReadResult::Ok] or [TryReadResult::Dropout], returns
+    /// the received value. Otherwise, returns None.
+    pub fn value(self) -> Option<T> {
+        match self {
+            TryReadResult::Ok(v) => Some(v),
+            TryReadResult::Dropout(v) => Some(v),
+            TryReadResult::Empty => None,
+            TryReadResult::Closed => None,
+            TryReadResult::Busy => None,
+        }
+    }
+}
+
+impl<T> From<ReadResult<T>> for TryReadResult<T> {
+    fn from(result: ReadResult<T>) -> TryReadResult<T> {
+        match result {
+            ReadResult::Ok(v) => TryReadResult::Ok(v),
+            ReadResult::Dropout(v) => TryReadResult::Dropout(v),
+            ReadResult::Empty => TryReadResult::Empty,
+            ReadResult::Closed => TryReadResult::Closed,
+        }
+    }
+}
+
+/// Whether a [Reader] can make progress, see [Reader::health]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReaderHealth {
+    /// Nothing is holding up the reader
+    Ok,
+
+    /// The writer has held the item at `index`, which the reader is going to
+    /// read next, for longer than [STUCK_TIMEOUT] without writing anything
+    /// else. Reading waits until the writer releases the item, which may
+    /// never happen, see [Reader::force_unlock].
+    SlotStuck { index: usize },
+}
+
+/// A place in the sequence of items that a ring buffer's writer writes, see
+/// [Reader::position] and [Writer::position]. Positions further along
+/// compare as greater, regardless of how often the writer wrapped around
+/// the ring buffer.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Position(u64);
+
+impl Position {
+    /// Returns the sequence number of the item at this position, see
+    /// [Reader::read_indexed]
+    pub fn sequence(self) -> u64 {
+        self.0
+    }
+}
+
+/// Identifies a [Reader] among all readers of the same ring buffer, e.g. in
+/// logs or metrics, see [Reader::id]. Readers are numbered in the order in
+/// which they are created, starting from zero.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct ReaderId(u64);
+
+impl ReaderId {
+    /// Returns the number of the reader
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for ReaderId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "reader {}", self.0)
+    }
+}
+
+/// Where [Reader::seek_to_sequence] moved the reader
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SeekResult {
+    /// The item with the requested sequence number is still held in the ring
+    /// buffer, and the reader is going to read it next
+    Ok,
+
+    /// The item with the requested sequence number was already overwritten,
+    /// and the reader moved to the oldest item that is still held instead,
+    /// see [Reader::seek_to_oldest]
+    TooOld,
+
+    /// The item with the requested sequence number hasn't been written yet,
+    /// and the reader moved to the front of the queue instead, where it
+    /// reads whatever the writer writes next
+    NotYetWritten,
+}
+
+/// Counters of what a [Reader] has read, see [Reader::stats]. Each reader
+/// keeps its own, and a cloned reader starts counting from zero.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ReaderStats {
+    /// The number of reads, of any result. Blocking and async reads count
+    /// once for every time they check the queue.
+    pub reads: u64,
+
+    /// The number of reads that returned [ReadResult::Ok]
+    pub ok: u64,
+
+    /// The number of reads that returned [ReadResult::Dropout] because the
+    /// writer overtook the reader
+    pub dropouts: u64,
+
+    /// The number of reads that returned [ReadResult::Empty]
+    pub empty: u64,
+
+    /// The number of items that were lost in dropouts, see
+    /// [Detailed::Dropout]
+    pub lost: u64,
+
+    /// The number of calls to [Reader::skip_ahead]. The dropouts that are
+    /// read right after skipping ahead are counted here instead of in
+    /// `dropouts`, and the items they pass over in `skipped`.
+    pub skips: u64,
+
+    /// The number of items passed over by skipping ahead
+    pub skipped: u64,
+
+    /// The number of reads that had to wait because the writer was busy
+    /// with the item about to be read
+    pub contended_reads: u64,
+
+    /// The largest number of times that any single read had to check the
+    /// item again before it could go ahead, including the times it yielded
+    /// or slept, see [SPIN_LIMIT]
+    pub max_spins: u64,
+
+    /// The number of calls to [Reader::try_read] that returned
+    /// [TryReadResult::Busy] instead of waiting for the writer
+    pub busy: u64,
+}
+
+impl ReaderStats {
+    /// Count the result of a read, which may have skipped ahead before
+    fn record<T>(&mut self, result: &Detailed<T>, skipped: bool) {
+        self.reads += 1;
+        match result {
+            Detailed::Ok(_) => self.ok += 1,
+            Detailed::Dropout { info, .. } if skipped => self.skipped += info.lost,
+            Detailed::Dropout { info, .. } => {
+                self.dropouts += 1;
+                self.lost += info.lost;
+            }
+            Detailed::Empty => self.empty += 1,
+            Detailed::Closed => {}
+        }
+    }
+
+    /// Count a read that had to check the item `spins` times while the
+    /// writer was busy with it
+    fn record_spins(&mut self, spins: u64) {
+        if spins > 0 {
+            self.contended_reads += 1;
+            self.max_spins = self.max_spins.max(spins);
+        }
+    }
+}
+
+/// The outcome of reading many items at once by [Reader::read_into]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ReadBatch {
+    /// The number of items that were stored at the start of the output
+    pub count: usize,
+
+    /// Whether any of the items read was a dropout, i.e. whether some
+    /// items were lost before or in between the ones received
+    pub dropout: bool,
+
+    /// Whether the batch stopped because the writer has been closed and
+    /// all remaining data was read
+    pub closed: bool,
+}
+
+/// Describes a dropout that a [Reader] has just read, see
+/// [Reader::set_on_dropout]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DropoutEvent {
+    /// The reader that read the dropout
+    pub reader: ReaderId,
+
+    /// The sequence number of the item that was read, see
+    /// [Reader::read_indexed]
+    pub sequence: u64,
+
+    /// The index in the ring buffer of the item that was read
+    pub index: usize,
+
+    /// The number of items that were lost, see [Detailed::Dropout]
+    pub lost: u64,
+
+    /// Whether the dropout was caused by calling [Reader::skip_ahead]
+    pub skipped: bool,
+}
+
+impl<T, S: Storage<T>> Reader<T, S> {
+    fn new(storage: S) -> Reader<T, S> {
+        storage.header().reader_count.fetch_add(1, Ordering::SeqCst);
+        Reader::counted(storage)
+    }
+
+    /// Create a reader at the start of the given storage, which already
+    /// counts it among its readers
+    fn counted(storage: S) -> Reader<T, S> {
+        // Starts out at the first item, which is where the reader is
+        let progress = storage.signals().progress.add();
+        Reader {
+            id: ReaderId(storage.header().take_reader_id()),
+            indexing: Indexing::new(storage.items().len()),
+            spin_policy: SpinPolicy::default(),
+            storage,
+            read_index: 0,
+            sequence: 0,
+            last_sequence: None,
+            skipped_from: None,
+            stats: ReaderStats::default(),
+            lag_high_watermark: Cell::new(0),
+            on_dropout: None,
+            progress,
+            #[cfg(all(unix, feature = "readiness"))]
+            readiness: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Create another reader for the same ring buffer at the same position,
+    /// or return an error if the buffer already has as many readers as
+    /// [Reader::max_readers] allows. [Clone::clone] panics instead.
+    pub fn try_clone(&self) -> Result<Reader<T, S>, TooManyReaders> {
+        self.storage.header().add_reader()?;
+
+        let reader = Reader {
+            storage: self.storage.clone(),
+            id: ReaderId(self.storage.header().take_reader_id()),
+            indexing: self.indexing,
+            spin_policy: self.spin_policy,
+            read_index: self.read_index,
+            sequence: self.sequence,
+            last_sequence: self.last_sequence,
+            skipped_from: self.skipped_from,
+            stats: ReaderStats::default(),
+            lag_high_watermark: Cell::new(0),
+            on_dropout: None,
+            progress: self.storage.signals().progress.add(),
+            #[cfg(all(unix, feature = "readiness"))]
+            readiness: None,
+            _phantom: PhantomData,
+        };
+        reader.publish_progress();
+        Ok(reader)
+    }
+
+    /// Create another reader for the same ring buffer at the front of the
+    /// queue, so that it reads [ReadResult::Empty] until the writer writes
+    /// something new, which it then reads with [ReadResult::Ok]. Unlike
+    /// [Reader::skip_ahead], the first read is never a dropout.
+    ///
+    /// # Panics
+    /// Panics if the ring buffer already has the maximum number of readers,
+    /// like [Clone::clone].
+    pub fn clone_at_front(&self) -> Reader<T, S> {
+        let mut reader = self.clone();
+        reader.seek_to_front();
+        reader
+    }
+
+    /// Create another reader for the same ring buffer at the oldest item
+    /// that is still held in it, see [Reader::seek_to_oldest], so that it
+    /// reads as much history as possible.
+    ///
+    /// # Panics
+    /// Panics if the ring buffer already has the maximum number of readers,
+    /// like [Clone::clone].
+    pub fn clone_at_back(&self) -> Reader<T, S> {
+        let mut reader = self.clone();
+        reader.seek_to_oldest();
+        reader
+    }
+
+    /// Create a new writer for the ring buffer once its previous writer has
+    /// been closed or dropped, for example because the thread that owned it
+    /// panicked. The new writer carries on right after the last item that
+    /// the previous writer wrote, so readers keep receiving one seamless
+    /// stream, and readers that already received [ReadResult::Closed]
+    /// receive new data again. Returns an error while the ring buffer has a
+    /// writer, including one that was just created by another reader, so
+    /// that there is never more than one writer at a time.
+    ///
+    /// The writer starts out with the default [SpinPolicy] and no
+    /// [WriterStats]. A writer in another process that exited without
+    /// dropping it, for a ring buffer in shared memory, still counts as
+    /// alive.
+    pub fn try_promote_writer(&self) -> Result<Writer<T, S>, WriterStillAlive> {
+        let header = self.storage.header();
+
+        // Taking back the closed flag makes this the only writer. Pairs with
+        // the store in Writer::drop, which comes after every write of the
+        // previous writer was published.
+        if header
+            .closed
+            .compare_exchange(true, false, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return Err(WriterStillAlive);
+        }
+
+        let mut writer = Writer::new(self.storage.clone());
+        let sequence = header.write_sequence.load(Ordering::Acquire);
+        writer.index = writer.indexing.index_of(sequence);
+        writer.sequence = sequence;
+        Ok(writer)
+    }
+
+    /// Let the writer know where the reader is, which it needs to do after
+    /// every move, see [Writer::slowest_reader_lag]
+    fn publish_progress(&self) {
+        self.storage
+            .signals()
+            .progress
+            .publish(&self.progress, self.position().sequence());
+    }
+
+    /// Returns the id of this reader, which stays the same for as long as it
+    /// exists and is different from that of every other reader created for
+    /// the same ring buffer, including its clones. A reader keeps its id
+    /// when it is retargeted to a different ring buffer, see
+    /// [Reader::retarget].
+    pub fn id(&self) -> ReaderId {
+        self.id
+    }
+
+    /// Returns the maximum number of readers that may exist at once for
+    /// this ring buffer. See [Writer::set_max_readers].
+    pub fn max_readers(&self) -> usize {
+        self.storage.header().max_readers.load(Ordering::SeqCst)
+    }
+
+    /// Returns how this reader waits while the writer is busy with the item
+    /// about to be read, see [SpinPolicy]
+    pub fn spin_policy(&self) -> SpinPolicy {
+        self.spin_policy
+    }
+
+    /// Change how this reader waits while the writer is busy with the item
+    /// about to be read. Readers cloned from this one afterwards use the same
+    /// policy.
+    pub fn set_spin_policy(&mut self, policy: SpinPolicy) {
+        self.spin_policy = policy;
+    }
+
+    /// Returns the number of items that the ring buffer can hold
+    pub fn capacity(&self) -> usize {
+        self.storage.items().len()
+    }
+
+    /// Returns the number of bytes occupied by the ring buffer's items
+    pub fn memory_footprint(&self) -> usize {
+        self.capacity() * std::mem::size_of::<Item<T>>()
+    }
+
+    /// Returns whether both readers read from the same ring buffer
+    pub fn same_buffer(&self, other: &Reader<T, S>) -> bool {
+        std::ptr::eq(self.storage.header(), other.storage.header())
+    }
+
+    /// Attach this reader to the same ring buffer as `source`, detaching it
+    /// from the buffer it was reading before. The reader starts at the front
+    /// of the new buffer, so that the next read returns [ReadResult::Empty]
+    /// until new data is written, regardless of where `source` is.
+    ///
+    /// # Panics
+    /// Panics if the new buffer already has the maximum number of readers.
+    pub fn retarget(&mut self, source: &Reader<T, S>) {
+        self.set_storage(source.storage.clone());
+        self.seek_to_front();
+    }
+
+    /// Attach this reader to the ring buffer that `writer` writes to. See
+    /// [Reader::retarget].
+    ///
+    /// # Panics
+    /// Panics if the new buffer already has the maximum number of readers.
+    pub fn retarget_to_writer(&mut self, writer: &Writer<T, S>) {
+        self.set_storage(writer.storage.clone());
+        self.seek_to_front();
+    }
+
+    /// Move the reader over to a different buffer, keeping the reader
+    /// counts of both buffers up to date
+    fn set_storage(&mut self, storage: S) {
+        if std::ptr::eq(storage.header(), self.storage.header()) {
+            return;
+        }
+
+        if let Err(err) = storage.header().add_reader() {
+            panic!("{}", err);
+        }
+        let old_storage = std::mem::replace(&mut self.storage, storage);
+        old_storage.header().remove_reader();
+
+        // The position is published by seeking in the new buffer afterwards
+        old_storage.signals().progress.remove(&self.progress);
+        self.progress = self.storage.signals().progress.add();
+
+        // The file descriptor is now signalled by the new writer instead
+        #[cfg(all(unix, feature = "readiness"))]
+        if let Some(readiness) = &self.readiness {
+            old_storage.signals().readiness.remove(readiness);
+            self.storage.signals().readiness.add(readiness.clone());
+        }
+    }
+
+    /// Returns whether the writer has been closed or dropped. Once this
+    /// returns true, it only returns false again if a new writer is created
+    /// with [Reader::try_promote_writer], and reading returns
+    /// [ReadResult::Closed] as soon as any remaining data was read. An idle
+    /// writer that is still alive never counts as disconnected.
+    pub fn is_disconnected(&self) -> bool {
+        self.storage.header().closed.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of items that the reader can currently read before
+    /// the queue appears empty, which is at most the capacity of the ring
+    /// buffer. If the reader has been overtaken by the writer, this counts
+    /// only the items that are still in the buffer. The writer may write more
+    /// at any moment, so the result may be out of date right away, but it is
+    /// never greater than zero when the reader is fully caught up.
+    pub fn available(&self) -> usize {
+        let capacity = self.storage.items().len();
+        let write_sequence = self.write_sequence();
+
+        // The next read starts after the items that the writer cleared
+        let (sequence, read_index) = match self.cleared_since() {
+            Some(cleared_at) => (cleared_at, self.index_of(cleared_at)),
+            None => (self.sequence, self.read_index),
+        };
+        let distance = write_sequence.wrapping_sub(sequence);
+        if distance <= capacity as u64 {
+            return distance as usize;
+        }
+
+        // The reader was overtaken. Its next read catches up to the writer's
+        // current lap, after which it reads the rest of the buffer up to the
+        // write index.
+        match (self.index_of(write_sequence) + capacity - read_index) % capacity {
+            0 => capacity,
+            n => n,
+        }
+    }
+
+    /// Returns how many items behind the writer this reader is, counting
+    /// both the items that are waiting to be read and any that were lost
+    /// because the writer overtook the reader, up to the capacity of the
+    /// ring buffer. Like [Reader::available], this never waits for the
+    /// writer and is exact unless the writer is writing concurrently.
+    ///
+    /// Because the next read after [Reader::skip_ahead] is a dropout, the
+    /// lag is reported as the full capacity until then.
+    pub fn lag(&self) -> usize {
+        let write_sequence = self.write_sequence();
+        let distance = match self.cleared_since() {
+            Some(cleared_at) => write_sequence.wrapping_sub(cleared_at),
+            None => self.distance_to(write_sequence),
+        };
+        distance.min(self.storage.items().len() as u64) as usize
+    }
+
+    /// Returns the largest [Reader::lag] seen since the reader was created,
+    /// or since the last call to [Reader::reset_lag_high_watermark]. The lag
+    /// is measured at the start of every read and whenever
+    /// [Reader::has_data] is called, so it tells how far behind the reader
+    /// fell at worst between two scrapes of a metric, unlike the lag itself.
+    /// Cloned readers start out at zero.
+    pub fn lag_high_watermark(&self) -> usize {
+        self.lag_high_watermark.get()
+    }
+
+    /// Set the lag high watermark back to zero, see
+    /// [Reader::lag_high_watermark]
+    pub fn reset_lag_high_watermark(&mut self) {
+        self.lag_high_watermark.set(0);
+    }
+
+    /// Measure the lag and raise the high watermark to it, if needed. Returns
+    /// the lag.
+    fn observe_lag(&self) -> usize {
+        let lag = self.lag();
+        if lag > self.lag_high_watermark.get() {
+            self.lag_high_watermark.set(lag);
+        }
+        lag
+    }
+
+    /// Check whether the writer is holding up the reader. If the writer has
+    /// locked the item that the reader is going to read next, this waits for
+    /// up to [STUCK_TIMEOUT] for the writer to release it or to write
+    /// anything else, as the reader's [SpinPolicy] says, and otherwise
+    /// reports the item as stuck. This lets a supervisor tell a writer that
+    /// died while writing apart from one that is merely slow, instead of
+    /// reading and waiting forever.
+    ///
+    /// Writes that keep their items locked for a while on purpose, such as
+    /// [Writer::reserve] and [Writer::write_iter] with a slow iterator, are
+    /// reported as stuck as well if they take longer than that.
+    pub fn health(&self) -> ReaderHealth {
+        let index = self.read_index;
+        let item = &self.storage.items()[index];
+        if !item.is_write_locked() {
+            return ReaderHealth::Ok;
+        }
+
+        // A writer that keeps writing is alive, even if it happens to hold
+        // the same item again whenever it is checked
+        let write_sequence = self.write_sequence();
+        let deadline = Instant::now() + STUCK_TIMEOUT;
+        let mut spins = 0;
+        while item.is_write_locked() && self.write_sequence() == write_sequence {
+            if Instant::now() >= deadline {
+                return ReaderHealth::SlotStuck { index };
+            }
+            self.spin_policy.backoff(spins);
+            spins += 1;
+        }
+        ReaderHealth::Ok
+    }
+
+    /// Release the writer's lock on the item at `index`, as reported by
+    /// [ReaderHealth::SlotStuck], so that readers can get past it again. The
+    /// item goes back to holding what it held before the writer locked it.
+    /// Returns whether the item was locked.
+    ///
+    /// # Safety
+    /// The writer must never access the ring buffer again, for example
+    /// because the thread that owned it has died. Otherwise, the writer and
+    /// readers would access the item at the same time. The value in the
+    /// item may have been partially overwritten, and readers that are a lap
+    /// behind receive it as it is.
+    ///
+    /// # Panics
+    /// Panics if `index` is not less than the capacity.
+    pub unsafe fn force_unlock(&self, index: usize) -> bool {
+        let item = &self.storage.items()[index];
+        if !item.is_write_locked() {
+            return false;
+        }
+
+        // The writer hasn't published the item yet, so it's from the lap
+        // before the one that the write sequence is in, or after which it
+        // would wrap around to this index
+        let capacity = self.storage.items().len();
+        let write_sequence = self.write_sequence();
+        let ahead = (index + capacity - self.index_of(write_sequence)) % capacity;
+        let previous_sequence = self.indexing.previous_lap(write_sequence + ahead as u64);
+
+        // SAFETY: the writer holds the lock, but the caller guarantees that
+        // it's gone, so nothing else accesses the item
+        unsafe { item.set_sequence(previous_sequence) };
+        item.release_write(self.indexing.lap_token(previous_sequence));
+        true
+    }
+
+    /// Returns the sequence number of the item that was read last, which is
+    /// the number of items that the writer had written before it. This is
+    /// None until the reader reads anything.
+    pub fn last_sequence(&self) -> Option<u64> {
+        self.last_sequence
+    }
+
+    /// Returns where the reader is, which is the position of the item that
+    /// it expects to read next. A reader that was overtaken by the writer
+    /// only finds out how far it has to jump ahead when it reads next, and
+    /// until then stays where it was. After [Reader::skip_ahead], this is
+    /// the position of the most recently written item.
+    pub fn position(&self) -> Position {
+        match self.skipped_from {
+            // The reader expects the item from one lap before the one that
+            // it is going to read, see Reader::skip_ahead
+            Some(_) => Position(
+                self.sequence
+                    .wrapping_add(self.storage.items().len() as u64),
+            ),
+            None => Position(self.sequence),
+        }
+    }
+
+    /// Returns how many items this reader is ahead of `other`, which is
+    /// negative if it is behind instead, see [Reader::position]. Both
+    /// readers need to read from the same ring buffer.
+    pub fn distance_from(&self, other: &Reader<T, S>) -> i64 {
+        self.position()
+            .sequence()
+            .wrapping_sub(other.position().sequence()) as i64
+    }
+
+    /// Returns the number of items that the writer has written in total,
+    /// which is the sequence number of the item that it is going to write
+    /// next, see [Writer::total_written]
+    pub fn total_written(&self) -> u64 {
+        self.write_sequence()
+    }
+
+    /// Returns the counters of everything that this reader has read so far,
+    /// or since the last call to [Reader::reset_stats]
+    pub fn stats(&self) -> ReaderStats {
+        self.stats
+    }
+
+    /// Set all of the reader's statistics back to zero
+    pub fn reset_stats(&mut self) {
+        self.stats = ReaderStats::default();
+    }
+
+    /// Call `f` whenever a read of any kind returns a dropout, e.g. to count
+    /// it in a metric or log it in one place. The callback runs after the
+    /// item was copied out of the ring buffer and never holds up the writer,
+    /// but it does delay the read that triggered it. This replaces any
+    /// callback set before. Cloned readers start out without a callback.
+    pub fn set_on_dropout(&mut self, f: impl FnMut(DropoutEvent) + Send + 'static) {
+        self.on_dropout = Some(Box::new(f));
+    }
+
+    /// Remove the callback set by [Reader::set_on_dropout]
+    pub fn clear_on_dropout(&mut self) {
+        self.on_dropout = None;
+    }
+
+    /// Returns the number of writes that the writer, which is going to write
+    /// the item with the given sequence number next, is ahead of the reader
+    fn distance_to(&self, write_sequence: u64) -> u64 {
+        write_sequence.wrapping_sub(self.sequence)
+    }
+
+    /// Returns where in the ring buffer the item with the given sequence
+    /// number is stored
+    fn index_of(&self, sequence: u64) -> usize {
+        self.indexing.index_of(sequence)
+    }
+
+    /// Move the reader back by up to `n` items, so that items it has already
+    /// read are read again, and return how far it actually moved. The reader
+    /// only moves back as far as items are still held in the ring buffer,
+    /// except for the oldest one, which the writer is about to overwrite
+    /// next. Readers that have been overtaken by the writer don't move.
+    ///
+    /// Reading after rewinding returns the historical items with
+    /// [ReadResult::Ok], unless the writer overwrites them in the meantime,
+    /// in which case the reader catches up with [ReadResult::Dropout].
+    pub fn rewind(&mut self, n: usize) -> usize {
+        let capacity = self.storage.items().len();
+        let write_sequence = self.write_sequence();
+        let distance = self.distance_to(write_sequence);
+
+        // Nothing before the writer's very first item was ever written, and
+        // nothing before it was last cleared is read again
+        let retained = write_sequence.saturating_sub(self.cleared_at());
+        let safe_to_reread = retained.min(capacity as u64 - 1);
+
+        let steps = (n as u64).min(safe_to_reread.saturating_sub(distance)) as usize;
+        if steps > self.read_index {
+            self.read_index += capacity;
+        }
+        self.read_index -= steps;
+        self.sequence -= steps as u64;
+        self.publish_progress();
+
+        steps
+    }
+
+    /// Move the reader to the oldest item that is still held in the ring
+    /// buffer, so that the following reads return all retained items in
+    /// order with [ReadResult::Ok]. The oldest item is left out because the
+    /// writer is about to overwrite it next, see [Reader::rewind]. This is
+    /// the opposite of [Reader::skip_ahead], and gives a newly cloned reader
+    /// as much history as possible.
+    pub fn seek_to_oldest(&mut self) {
+        self.seek_to_front();
+        self.rewind(usize::MAX);
+    }
+
+    /// Move the reader to the item with the given sequence number, so that
+    /// the next read returns it with [ReadResult::Ok], e.g. to resume after
+    /// the last item that a previous reader received, see
+    /// [Reader::last_sequence]. Like [Reader::rewind], this only reaches back
+    /// as far as [Reader::seek_to_oldest], and the reader instead moves there
+    /// if the item is older. If the item hasn't been written yet, including
+    /// if it is the very next one to be written, the reader moves to the
+    /// front of the queue instead, and reads the item there once the writer
+    /// gets to it.
+    ///
+    /// If the writer overwrites the item before the reader gets to it, the
+    /// reader catches up with [ReadResult::Dropout].
+    pub fn seek_to_sequence(&mut self, sequence: u64) -> SeekResult {
+        let write_sequence = self.write_sequence();
+        let oldest = write_sequence
+            .saturating_sub(self.storage.items().len() as u64 - 1)
+            .max(self.cleared_at());
+
+        if sequence >= write_sequence {
+            self.seek_to(write_sequence);
+            SeekResult::NotYetWritten
+        } else if sequence < oldest {
+            self.seek_to(oldest);
+            SeekResult::TooOld
+        } else {
+            self.seek_to(sequence);
+            SeekResult::Ok
+        }
+    }
+
+    /// Returns whether the reader can currently read anything, i.e. whether
+    /// [Reader::read] would return [ReadResult::Ok] or [ReadResult::Dropout].
+    /// Unlike reading, this only loads the writer's position and never waits
+    /// for the writer, which makes it cheap to poll while the queue is empty.
+    /// It may miss data that is being written concurrently, but never returns
+    /// true when the reader is fully caught up.
+    pub fn has_data(&self) -> bool {
+        self.observe_lag() > 0
+    }
+
+    /// Load the sequence number that the writer is going to write next
+    fn write_sequence(&self) -> u64 {
+        // Acquire pairs with the release when the writer publishes, see
+        // PendingWrites::drop
+        self.storage.header().write_sequence.load(Ordering::Acquire)
+    }
+
+    /// Move the reader to the position that the writer will write to next,
+    /// expecting the sequence number that the writer will write there, such
+    /// that the next read returns [ReadResult::Empty] until the writer
+    /// writes again.
+    fn seek_to_front(&mut self) {
+        self.seek_to(self.write_sequence());
+    }
+
+    /// Move the reader to where the item with the given sequence number
+    /// will be, expecting to read it there next
+    fn seek_to(&mut self, sequence: u64) {
+        self.read_index = self.index_of(sequence);
+        self.sequence = sequence;
+        self.skipped_from = None;
+        self.publish_progress();
+    }
+
+    /// Load the sequence number before which the writer cleared everything,
+    /// see [Writer::clear]
+    fn cleared_at(&self) -> u64 {
+        // Acquire pairs with the release in Writer::clear
+        self.storage.header().cleared_at.load(Ordering::Acquire)
+    }
+
+    /// Returns where the reader continues because the writer cleared the
+    /// ring buffer since the reader last moved, if it did
+    fn cleared_since(&self) -> Option<u64> {
+        let cleared_at = self.cleared_at();
+        (self.position().sequence() < cleared_at).then_some(cleared_at)
+    }
+
+    /// Move the reader past anything that the writer cleared, which is
+    /// neither read nor counted as lost, see [Writer::clear]
+    fn skip_cleared(&mut self) {
+        if let Some(cleared_at) = self.cleared_since() {
+            self.seek_to(cleared_at);
+        }
+    }
+}
+
+impl<T, S: Storage<T>> Reader<T, S>
+where
+    T: Copy,
+{
+    /// Receive the next item in the queue if anything is available.
+    /// If the reader is somewhere in the middle of the queue, returns
+    /// [ReadResult::Ok] with the next item. If the reader has beenovertaken
+    /// by the writer since its last read, returns [ReadResult::Dropout]
+    /// with a more recent item to indicate that some items were lost.
+    /// Otherwise, if the reader is fully caught up to writer and no new
+    /// data is available, returns [ReadResult::Empty], or
+    /// [ReadResult::Closed] if the writer has been closed or dropped.
+    ///
+    /// This method uses a spin lock and may busy-wait for a short duration
+    /// if the writer happens to be overwriting the item that the reader is
+    /// about to read. If the writer takes longer, for example because it was
+    /// descheduled, the reader yields and then sleeps between checks instead,
+    /// see [SPIN_LIMIT]. The guarded section performs only a trivial copy of
+    /// the data. If the reader is caught up, the item isn't locked at all, so
+    /// polling an empty queue only loads from memory and never contends with
+    /// other readers.
+    pub fn read(&mut self) -> ReadResult<T> {
+        ReadResult::from(self.read_next()).map(|(_, value)| value)
+    }
+
+    /// Receive the next item in the queue along with its sequence number,
+    /// which is the number of items that the writer had written before it.
+    /// Returns the same kind of result as [Reader::read]. Sequence numbers
+    /// of consecutive [ReadResult::Ok] results are consecutive as well,
+    /// while a [ReadResult::Dropout] carries the sequence number of the item
+    /// actually delivered, so that the gap to [Reader::last_sequence] before
+    /// the read tells exactly which items were lost. Because every reader of
+    /// a ring buffer sees the same sequence numbers, they can also be used
+    /// to deduplicate items that were received by several readers.
+    pub fn read_indexed(&mut self) -> ReadResult<(u64, T)> {
+        self.read_next().into()
+    }
+
+    /// Receive the next item in the queue like [Reader::read], but report
+    /// how many items were lost along with each dropout, and where the reader
+    /// picked up again, see [DropoutInfo]. The count is exact as long as the
+    /// writer isn't writing concurrently, and otherwise is an upper bound.
+    /// Items that were passed over by [Reader::skip_ahead] count as lost as
+    /// well, since the reader never received them.
+    pub fn read_detailed(&mut self) -> Detailed<T> {
+        self.read_next().map(|(_, value)| value)
+    }
+
+    /// Receive the next item in the queue like [Reader::read], but instead of
+    /// copying it out, call `f` with a reference to it while it's locked and
+    /// return what `f` returns. This avoids copying all of a large item when
+    /// only part of it is needed. `f` is only called if there is a new item
+    /// to read, and the writer waits for it to return before overwriting the
+    /// item, so keep it short. If `f` panics, the item is unlocked again and
+    /// the reader stays where it was.
+    pub fn read_with<R>(&mut self, f: impl FnOnce(&T) -> R) -> ReadResult<R> {
+        self.observe_lag();
+        self.skip_cleared();
+        let skipped_from = self.skipped_from;
+        let expected = skipped_from.unwrap_or(self.sequence);
+
+        // Reading again after the writer was closed only happens if the
+        // first attempt found nothing to call f on
+        let mut f = Some(f);
+        let result = self.unless_closed(|reader| reader.read_item_with(&mut f));
+        ReadResult::from(self.finish_read(result, expected, skipped_from.is_some()))
+            .map(|(_, value)| value)
+    }
+
+    /// Receive the next item in the queue like [Reader::read], but never
+    /// wait for the writer. The item is locked with a single attempt, without
+    /// spinning, yielding or sleeping, so that the call finishes in a bounded
+    /// amount of time, as is needed in realtime threads. If the writer is
+    /// busy with the item, returns [TryReadResult::Busy] and leaves the
+    /// reader where it is, so that the caller can try again later. Another
+    /// reader locking or releasing the same item at the same moment can also
+    /// make the attempt fail.
+    pub fn try_read(&mut self) -> TryReadResult<T> {
+        self.observe_lag();
+        self.skip_cleared();
+        let skipped_from = self.skipped_from;
+        let expected = skipped_from.unwrap_or(self.sequence);
+
+        // See Reader::unless_closed
+        let result = match self.try_read_item() {
+            Some(ReadResult::Empty) if self.storage.header().closed.load(Ordering::SeqCst) => {
+                match self.try_read_item() {
+                    Some(ReadResult::Empty) => Some(ReadResult::Closed),
+                    result => result,
+                }
+            }
+            result => result,
+        };
+
+        match result {
+            Some(result) => {
+                let result = self.finish_read(result, expected, skipped_from.is_some());
+                ReadResult::from(result).map(|(_, value)| value).into()
+            }
+            None => {
+                self.stats.reads += 1;
+                self.stats.busy += 1;
+                TryReadResult::Busy
+            }
+        }
+    }
+
+    /// Iterate over copies of the items that the reader can currently read,
+    /// in order, without moving the reader, so that the next read behaves
+    /// exactly as if the iteration never happened. Yields at most as many
+    /// items as the ring buffer holds. The iterator ends early as soon as an
+    /// item turns out to have been overwritten, which means that it yields
+    /// nothing at all if the reader has been overtaken by the writer or has
+    /// just skipped ahead, since the next read would be a dropout.
+    pub fn iter_available(&self) -> impl Iterator<Item = T> + '_ {
+        let capacity = self.storage.items().len();
+        let (mut index, mut sequence) = match self.cleared_since() {
+            Some(cleared_at) => (self.index_of(cleared_at), cleared_at),
+            None => (self.read_index, self.sequence),
+        };
+
+        std::iter::from_fn(move || {
+            let (value, actual_sequence, _) = self.load_item(index);
+            if actual_sequence != sequence {
+                return None;
+            }
+            let value = value?;
+
+            sequence = sequence.wrapping_add(1);
+            index = self.indexing.next(index);
+            Some(value)
+        })
+        .take(capacity)
+    }
+
+    /// Collect copies of all items that the ring buffer currently holds, from
+    /// oldest to newest, without moving the reader. This is meant for
+    /// debugging, and isn't an atomic snapshot if the writer is writing
+    /// concurrently. Every item is still copied under its lock, and any
+    /// items that the writer overwrites before they are copied are left out,
+    /// so that the result is always in order and without duplicates.
+    pub fn snapshot(&self) -> Vec<T> {
+        let mut values = Vec::new();
+        self.load_latest(usize::MAX, self.write_sequence(), &mut values);
+        values
+    }
+
+    /// Collect copies of all items that the ring buffer currently holds like
+    /// [Reader::snapshot], along with the positions of the reader and the
+    /// writer, without moving the reader.
+    ///
+    /// ```
+    /// use spmcq::ring_buffer;
+    ///
+    /// let (mut reader, mut writer) = ring_buffer::<u32>(4);
+    /// writer.write_slice(&[1, 2, 3, 4, 5]);
+    /// reader.read();
+    ///
+    /// let snapshot = reader.snapshot_detailed();
+    /// assert_eq!(snapshot.items, [2, 3, 4, 5]);
+    /// assert_eq!(snapshot.first_sequence, 1);
+    /// assert_eq!(snapshot.write_sequence, 5);
+    /// assert_eq!(snapshot.read_sequence, 5);
+    /// ```
+    pub fn snapshot_detailed(&self) -> Snapshot<T> {
+        let write_sequence = self.write_sequence();
+        let mut items = Vec::new();
+        self.load_latest(usize::MAX, write_sequence, &mut items);
+
+        Snapshot {
+            capacity: self.capacity(),
+            first_sequence: write_sequence - items.len() as u64,
+            write_sequence,
+            read_sequence: self.position().sequence(),
+            items,
+        }
+    }
+
+    /// Copy up to `n` of the items written before the given write sequence
+    /// into `out`, from oldest to newest, leaving out any that were
+    /// overwritten already. See [Reader::snapshot].
+    fn load_latest(&self, n: usize, write_sequence: u64, out: &mut Vec<T>) {
+        let capacity = self.storage.items().len() as u64;
+        let retained = write_sequence.saturating_sub(self.cleared_at());
+        let count = retained.min(capacity).min(n as u64);
+
+        // Walk backwards from the newest item. The writer overwrites items
+        // from the oldest onwards, so once an item turns out to have been
+        // overwritten, all older ones have been as well.
+        let start = out.len();
+        out.reserve(count as usize);
+        for sequence in ((write_sequence - count)..write_sequence).rev() {
+            let (Some(value), actual_sequence, _) = self.load_item(self.index_of(sequence)) else {
+                break;
+            };
+            if actual_sequence != sequence {
+                break;
+            }
+            out.push(value);
+        }
+
+        out[start..].reverse();
+    }
+
+    /// Replace the contents of `out` with up to `n` of the most recently
+    /// written items, from oldest to newest, and move the reader to the
+    /// front of the queue after them, so that the next read only returns
+    /// items written afterwards. There are never more items than the ring
+    /// buffer holds, nor than have been written so far. Like
+    /// [Reader::snapshot], this leaves out any items that the writer
+    /// overwrites before they could be copied. Reusing `out` between calls
+    /// avoids allocating every time.
+    pub fn read_last_n(&mut self, n: usize, out: &mut Vec<T>) {
+        out.clear();
+        let write_sequence = self.write_sequence();
+        self.load_latest(n, write_sequence, out);
+
+        self.seek_to(write_sequence);
+        if !out.is_empty() {
+            self.last_sequence = Some(write_sequence - 1);
+        }
+    }
+
+    /// Receive as many items as are currently available, up to the length
+    /// of `out`, and store them in `out` from oldest to newest. Stops as
+    /// soon as the queue is empty instead of waiting for more data. Returns
+    /// how many items were stored and whether any of them were dropouts,
+    /// which may happen anywhere in the batch if the writer overtakes the
+    /// reader while reading. Each item is read exactly like [Reader::read]
+    /// does, so any items lost in a dropout are simply skipped.
+    pub fn read_into(&mut self, out: &mut [T]) -> ReadBatch {
+        let mut batch = ReadBatch::default();
+        for slot in out.iter_mut() {
+            match self.read() {
+                ReadResult::Ok(value) => *slot = value,
+                ReadResult::Dropout(value) => {
+                    *slot = value;
+                    batch.dropout = true;
+                }
+                ReadResult::Empty => break,
+                ReadResult::Closed => {
+                    batch.closed = true;
+                    break;
+                }
+            }
+            batch.count += 1;
+        }
+        batch
+    }
+
+    /// Returns an iterator that reads items for as long as any are available,
+    /// like [std::sync::mpsc::Receiver::try_iter], and ends as soon as the
+    /// queue is empty or closed instead of waiting. Dropouts don't end the
+    /// iterator, and their values are yielded like any others, but
+    /// [TryIter::dropped] tells whether there were any. Calling this again
+    /// later continues with whatever was written in the meantime.
+    pub fn try_iter(&mut self) -> TryIter<'_, T, S> {
+        TryIter {
+            reader: self,
+            dropped: false,
+        }
+    }
+
+    /// Receive everything that is currently available, in order, along with
+    /// whether any of it was a dropout. Reads at most as many items as the
+    /// ring buffer holds, so that this returns even if the writer keeps
+    /// writing faster than the reader can read. If the writer overtakes the
+    /// reader partway through, the reader catches up like [Reader::read]
+    /// does and keeps going. This allocates, see [Reader::read_into] for a
+    /// version that doesn't.
+    pub fn read_all_available(&mut self) -> (Vec<T>, bool) {
+        let capacity = self.storage.items().len();
+        let mut values = Vec::with_capacity(self.available());
+        let mut dropout = false;
+        while values.len() < capacity {
+            match self.read() {
+                ReadResult::Ok(value) => values.push(value),
+                ReadResult::Dropout(value) => {
+                    values.push(value);
+                    dropout = true;
+                }
+                ReadResult::Empty | ReadResult::Closed => break,
+            }
+        }
+        (values, dropout)
+    }
+
+    /// Shared implementation of all reads, which receives the next item and
+    /// its sequence number, counts the lost items and updates the statistics
+    fn read_next(&mut self) -> Detailed<(u64, T)> {
+        self.observe_lag();
+        self.skip_cleared();
+
+        // Count from where the reader was before skipping ahead, if it did
+        let skipped_from = self.skipped_from;
+        let expected = skipped_from.unwrap_or(self.sequence);
+
+        let result = self.unless_closed(Self::read_item);
+        self.finish_read(result, expected, skipped_from.is_some())
+    }
+
+    /// Work out how many items were lost before the result of a read, given
+    /// the sequence number that the reader expected before it, and whether
+    /// it skipped ahead since the previous read. Then count the result and
+    /// report any dropout.
+    fn finish_read<U>(
+        &mut self,
+        result: ReadResult<(u64, U)>,
+        expected: u64,
+        skipped: bool,
+    ) -> Detailed<(u64, U)> {
+        let result = match result {
+            ReadResult::Ok(item) => Detailed::Ok(item),
+            ReadResult::Dropout(item) => Detailed::Dropout {
+                info: DropoutInfo {
+                    // Skipping ahead while caught up rereads the latest item
+                    lost: item.0.saturating_sub(expected),
+                    expected_sequence: expected,
+                    expected_lap: self.indexing.lap_of(expected),
+                    sequence: item.0,
+                    lap: self.indexing.lap_of(item.0),
+                    index: self.index_of(item.0),
+                },
+                value: item,
+            },
+            ReadResult::Empty => Detailed::Empty,
+            ReadResult::Closed => Detailed::Closed,
+        };
+
+        self.stats.record(&result, skipped);
+
+        if let Detailed::Dropout { info, .. } = result {
+            // Only counted, so there is nothing to order it with
+            self.storage
+                .header()
+                .dropouts
+                .fetch_add(1, Ordering::Relaxed);
+
+            let DropoutInfo {
+                sequence,
+                index,
+                lost,
+                ..
+            } = info;
+            let event = DropoutEvent {
+                reader: self.id,
+                sequence,
+                index,
+                lost,
+                skipped,
+            };
+
+            #[cfg(feature = "tracing")]
+            tracing::trace!(
+                reader = self.id.0,
+                sequence,
+                index = event.index,
+                lost,
+                laps = lost / self.storage.items().len() as u64,
+                skipped = event.skipped,
+                "reader dropout"
+            );
+
+            if let Some(on_dropout) = &mut self.on_dropout {
+                on_dropout(event);
+            }
+        }
+
+        result
+    }
+
+    /// Look at the next item in the queue without consuming it. Returns the
+    /// same result as [Reader::read] would right now, but leaves the reader
+    /// where it is, so that repeated peeks return the same result and the
+    /// next read returns it as well, unless the writer overwrites the item in
+    /// the meantime. Peeking after being overtaken returns
+    /// [ReadResult::Dropout] without catching the reader up, which only
+    /// happens on the next read.
+    pub fn peek(&mut self) -> ReadResult<T> {
+        self.unless_closed(Self::peek_item)
+    }
+
+    /// Call `read` and replace [ReadResult::Empty] with [ReadResult::Closed]
+    /// if the writer has been closed
+    fn unless_closed<U>(
+        &mut self,
+        mut read: impl FnMut(&mut Self) -> ReadResult<U>,
+    ) -> ReadResult<U> {
+        let result = read(self);
+        if !result.is_empty() || !self.storage.header().closed.load(Ordering::SeqCst) {
+            return result;
+        }
+
+        // The writer may have written more data after the item was checked
+        // and before it was closed. Now that the closed flag has been seen,
+        // any such data is guaranteed to be visible, so check once more.
+        match read(self) {
+            ReadResult::Empty => ReadResult::Closed,
+            result => result,
+        }
+    }
+
+    /// Copy the value out of the item at the given index, along with its
+    /// sequence number and the number of times that it had to wait for the
+    /// writer, see [Item::acquire_read]. There is no value if the item was
+    /// never written to, in which case the sequence number is
+    /// [UNWRITTEN_SEQUENCE].
+    fn load_item(&self, index: usize) -> (Option<T>, u64, u64) {
+        // Get the item to be read from
+        let item = &self.storage.items()[index];
+
+        let (lock, spins) = item.lock_read(&self.spin_policy);
+
+        // Copy the value then immediately leave the locked section to release the lock again to
+        // prevent holding up the writer. T must be Copy for this reason.
+        (lock.with_data(|data| *data), lock.sequence(), spins)
+    }
+
+    /// Copy the value out of the item at the given index like
+    /// [Reader::load_item], but give up right away if the item can't be
+    /// locked, see [Item::try_acquire_read]
+    fn try_load_item(&self, index: usize) -> Option<(Option<T>, u64)> {
+        let lock = self.storage.items()[index].try_lock_read()?;
+        Some((lock.with_data(|data| *data), lock.sequence()))
+    }
+
+    /// Returns whether an item with the given sequence number at the read
+    /// index is from the lap before the one that the reader expects, which
+    /// means that the reader has caught up to the writer
+    fn is_previous_lap(&self, sequence: u64) -> bool {
+        // NOTE that items which were never written count as being from an
+        // imaginary lap before the first, whichever lap the reader expects.
+        // Readers never get ahead of the writer, so they can only find such
+        // an item once they have caught up.
+        sequence == UNWRITTEN_SEQUENCE
+            || sequence.wrapping_add(self.storage.items().len() as u64) == self.sequence
+    }
+
+    /// Returns whether the item at the read index is from the lap before the
+    /// one that the reader expects, like [Reader::is_previous_lap], but
+    /// without locking the item. This lets readers poll an empty queue
+    /// without any read-modify-write operations, which would otherwise make
+    /// them contend with each other and with the writer.
+    fn is_caught_up(&self) -> bool {
+        let capacity = self.storage.items().len() as u64;
+        let item = &self.storage.items()[self.read_index];
+        let previous_lap = self
+            .indexing
+            .lap_token(self.sequence.wrapping_sub(capacity));
+        if item.lap() != previous_lap {
+            return false;
+        }
+
+        // The lap is truncated, so it also matches if the writer is some
+        // multiple of 2^31 laps ahead. In that case, the write sequence, which
+        // is published before the item is released, is far ahead as well.
+        self.distance_to(self.write_sequence()) <= capacity
+    }
+
+    /// Peek at the next item without regard for whether the writer was closed
+    fn peek_item(&mut self) -> ReadResult<T> {
+        self.skip_cleared();
+        if self.is_caught_up() {
+            return ReadResult::Empty;
+        }
+
+        let (Some(value), sequence, _) = self.load_item(self.read_index) else {
+            return ReadResult::Empty;
+        };
+
+        if self.is_previous_lap(sequence) {
+            ReadResult::Empty
+        } else if sequence == self.sequence {
+            ReadResult::Ok(value)
+        } else {
+            ReadResult::Dropout(value)
+        }
+    }
+
+    /// Read the next item and its sequence number without regard for
+    /// whether the writer was closed
+    fn read_item(&mut self) -> ReadResult<(u64, T)> {
+        if self.is_caught_up() {
+            return ReadResult::Empty;
+        }
+
+        // The sequence number is copied in the same guarded section as
+        // the value, so the two always belong together
+        let (value, sequence, spins) = self.load_item(self.read_index);
+        self.stats.record_spins(spins);
+
+        match value {
+            Some(value) => self.take_item(value, sequence),
+            None => ReadResult::Empty,
+        }
+    }
+
+    /// Read the next item like [Reader::read_item], but call `f` on it in
+    /// place instead of copying it, which happens only if the item is new.
+    /// `f` is taken out of the option when it is called.
+    fn read_item_with<R>(&mut self, f: &mut Option<impl FnOnce(&T) -> R>) -> ReadResult<(u64, R)> {
+        if self.is_caught_up() {
+            return ReadResult::Empty;
+        }
+
+        let (lock, spins) = self.storage.items()[self.read_index].lock_read(&self.spin_policy);
+        let sequence = lock.sequence();
+        let value = if self.is_previous_lap(sequence) {
+            None
+        } else {
+            // If f panics, the lock is released while unwinding, and the
+            // reader stays where it was
+            let f = f.take().expect("item read twice");
+            lock.with_data(f)
+        };
+        drop(lock);
+        self.stats.record_spins(spins);
+
+        match value {
+            Some(value) => self.take_item(value, sequence),
+            None => ReadResult::Empty,
+        }
+    }
+
+    /// Read the next item like [Reader::read_item], but return None instead
+    /// of waiting if the item can't be locked right away
+    fn try_read_item(&mut self) -> Option<ReadResult<(u64, T)>> {
+        if self.is_caught_up() {
+            return Some(ReadResult::Empty);
+        }
+
+        let (value, sequence) = self.try_load_item(self.read_index)?;
+        Some(match value {
+            Some(value) => self.take_item(value, sequence),
+            None => ReadResult::Empty,
+        })
+    }
+
+    /// Move the reader past an item that was just copied from the read
+    /// index, unless it turns out to be from the previous lap
+    fn take_item<U>(&mut self, value: U, sequence: u64) -> ReadResult<(u64, U)> {
+        if self.is_previous_lap(sequence) {
+            // We just overtook the writer. Discard the value because it's
+            // old and don't move.
+            return ReadResult::Empty;
+        }
+
+        let result = if sequence == self.sequence {
+            // If the sequence number matches what we expected, all is normal.
+            ReadResult::Ok((sequence, value))
+        } else {
+            // If the sequence number is off, we lost some values in between
+            ReadResult::Dropout((sequence, value))
+        };
+
+        // Continue after the item just read, which catches up with the
+        // writer after a dropout
+        self.last_sequence = Some(sequence);
+        self.sequence = sequence.wrapping_add(1);
+        self.skipped_from = None;
+
+        // Move one index forward
+        self.read_index = self.indexing.next(self.read_index);
+        self.publish_progress();
+
+        result
+    }
+
+    /// Receive the next item in the queue, blocking the current thread until
+    /// one becomes available. Returns either [ReadResult::Ok] or
+    /// [ReadResult::Dropout] with the same meaning as in [Reader::read], or
+    /// [ReadResult::Closed] once the writer has been closed or dropped and
+    /// all remaining data was read.
+    ///
+    /// While the queue is empty, the calling thread is parked and is woken up
+    /// again by the next call to [Writer::write] or [Writer::close]. This
+    /// avoids the CPU usage and added latency of polling [Reader::read] in a
+    /// loop with a sleep, at the cost of waking threads up from within
+    /// [Writer::write].
+    pub fn read_blocking(&mut self) -> ReadResult<T> {
+        self.read_until(None)
+    }
+
+    /// Receive the next item in the queue, blocking the current thread for at
+    /// most the given duration until one becomes available. Returns
+    /// [ReadResult::Empty] if nothing was written in time, and otherwise
+    /// behaves like [Reader::read_blocking].
+    pub fn read_timeout(&mut self, timeout: Duration) -> ReadResult<T> {
+        // If the deadline can't be represented, it's effectively never reached
+        self.read_until(Instant::now().checked_add(timeout))
+    }
+
+    /// Shared implementation of the blocking reads. Parks the current thread
+    /// until new data arrives or the deadline, if there is one, has passed.
+    fn read_until(&mut self, deadline: Option<Instant>) -> ReadResult<T> {
+        self.wait_until(deadline, |reader| {
+            Some(reader.read()).filter(|result| !result.is_empty())
+        })
+        .unwrap_or(ReadResult::Empty)
+    }
+
+    /// Call `read` until it returns something, parking the current thread
+    /// in between until new data arrives. Returns None if the deadline, if
+    /// there is one, passed first.
+    fn wait_until<U>(
+        &mut self,
+        deadline: Option<Instant>,
+        mut read: impl FnMut(&mut Self) -> Option<U>,
+    ) -> Option<U> {
+        if let Some(result) = read(self) {
+            return Some(result);
+        }
+
+        loop {
+            self.storage.signals().waiters.register();
+
+            // Check again now that the writer is guaranteed to see this thread
+            if let Some(result) = read(self) {
+                self.storage.signals().waiters.unregister();
+                return Some(result);
+            }
+
+            // Without a writer to wake it up, the thread checks again every
+            // so often
+            let mut timeout = self.storage.signals().poll_interval;
+            if let Some(deadline) = deadline {
+                // Wakeups may be spurious, so recompute the remaining time on
+                // every iteration
+                let now = Instant::now();
+                if now >= deadline {
+                    self.storage.signals().waiters.unregister();
+                    return None;
+                }
+                timeout = Some(timeout.map_or(deadline - now, |t| t.min(deadline - now)));
+            }
+            match timeout {
+                None => std::thread::park(),
+                Some(timeout) => std::thread::park_timeout(timeout),
+            }
+        }
+    }
+
+    /// Receive the most recently written item, skipping over any older items
+    /// that haven't been read yet. Returns [ReadResult::Ok] if the latest
+    /// item was also the next one in the queue, so that nothing was skipped,
+    /// and [ReadResult::Dropout] if any items were skipped or lost. Returns
+    /// [ReadResult::Empty] if nothing new has been written since the last
+    /// read, including when nothing has been written at all.
+    ///
+    /// If the writer is writing concurrently, the item returned may already
+    /// have been superseded by the time this returns.
+    pub fn read_latest(&mut self) -> ReadResult<T> {
+        // Nothing that the writer cleared counts as skipped
+        self.skip_cleared();
+        if self.distance_to(self.write_sequence()) > 1 {
+            self.skip_ahead();
+        }
+        self.read()
+    }
+
+    /// Immediately advance the reader to the front of the queue and catch
+    /// up with the reader. This method should ideally only be used right
+    /// before a call to [Reader::read], since otherwise the reader could
+    /// overtake the writer again. The next result of reading will always be
+    /// [ReadResult::Dropout] regardless of whether data was actually lost.
+    /// If nothing has been written yet, the reader stays at the front and
+    /// keeps reading [ReadResult::Empty] until the first write.
+    ///
+    /// Calling this method multiple times in between reads may result
+    /// in the same item being observed multiple times.
+    pub fn skip_ahead(&mut self) {
+        // Skipping over what the writer cleared doesn't lose anything
+        self.skip_cleared();
+        self.stats.skips += 1;
+
+        let write_sequence = self.write_sequence();
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            reader = self.id.0,
+            sequence = self.sequence,
+            write_sequence,
+            "reader skipping ahead"
+        );
+
+        // There is no most-recently written item until the writer has
+        // written something
+        if write_sequence == 0 {
+            self.seek_to_front();
+            return;
+        }
+
+        // Remember the original position if skipping ahead repeatedly
+        if self.skipped_from.is_none() {
+            self.skipped_from = Some(self.sequence);
+        }
+
+        // Because the write sequence is the one that the writer is _going_
+        // to write next, subtract one so that we point to the most-recently
+        // written item if not the second-most recent.
+        let latest_sequence = write_sequence - 1;
+        self.read_index = self.index_of(latest_sequence);
+
+        // Also expect the item from one lap earlier to guarantee that the
+        // next read returns Dropout
+        self.sequence = latest_sequence.wrapping_sub(self.storage.items().len() as u64);
+        self.publish_progress();
+    }
+}
+
+/// An iterator over the items that a [Reader] can currently read, see
+/// [Reader::try_iter]
+pub struct TryIter<'a, T, S: Storage<T> = HeapStorage<T>> {
+    reader: &'a mut Reader<T, S>,
+    dropped: bool,
+}
+
+impl<T, S: Storage<T>> TryIter<'_, T, S> {
+    /// Returns whether any of the items yielded so far was a dropout
+    pub fn dropped(&self) -> bool {
+        self.dropped
+    }
+}
+
+impl<T: Copy, S: Storage<T>> Iterator for TryIter<'_, T, S> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        match self.reader.read() {
+            ReadResult::Ok(value) => Some(value),
+            ReadResult::Dropout(value) => {
+                self.dropped = true;
+                Some(value)
+            }
+            ReadResult::Empty | ReadResult::Closed => None,
+        }
+    }
+}
+
+/// What the iterator returned by [Reader::into_iter] does when it reads a
+/// dropout
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum DropoutPolicy {
+    /// Yield the value that was received despite the dropout
+    #[default]
+    Yield,
+
+    /// Discard the value that was received and wait for the next one
+    Skip,
+
+    /// End the iteration
+    Stop,
+}
+
+/// A blocking iterator over everything that a [Reader] receives, which ends
+/// once the writer has been closed or dropped. Created by [Reader::into_iter].
+pub struct IntoIter<T, S: Storage<T> = HeapStorage<T>> {
+    reader: Reader<T, S>,
+    policy: DropoutPolicy,
+    stopped: bool,
+}
+
+impl<T, S: Storage<T>> IntoIter<T, S> {
+    /// Choose what happens when a dropout is read, which is
+    /// [DropoutPolicy::Yield] by default
+    pub fn with_dropout_policy(mut self, policy: DropoutPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Stop iterating and return the reader
+    pub fn into_inner(self) -> Reader<T, S> {
+        self.reader
+    }
+}
+
+impl<T: Copy, S: Storage<T>> Iterator for IntoIter<T, S> {
+    type Item = T;
+
+    /// Wait for the next item like [Reader::read_blocking], and return None
+    /// once the writer has been closed and all remaining items were read
+    fn next(&mut self) -> Option<T> {
+        while !self.stopped {
+            match self.reader.read_blocking() {
+                ReadResult::Ok(value) => return Some(value),
+                ReadResult::Dropout(value) => match self.policy {
+                    DropoutPolicy::Yield => return Some(value),
+                    DropoutPolicy::Skip => continue,
+                    DropoutPolicy::Stop => self.stopped = true,
+                },
+                ReadResult::Empty => continue,
+                ReadResult::Closed => self.stopped = true,
+            }
+        }
+        None
+    }
+}
+
+impl<T: Copy, S: Storage<T>> IntoIterator for Reader<T, S> {
+    type Item = T;
+    type IntoIter = IntoIter<T, S>;
+
+    /// Turn the reader into a blocking iterator, so that it can be used in a
+    /// `for` loop which ends once the writer has been closed or dropped
+    fn into_iter(self) -> IntoIter<T, S> {
+        IntoIter {
+            reader: self,
+            policy: DropoutPolicy::default(),
+            stopped: false,
+        }
+    }
+}
+
+impl<T, S: Storage<T>> Clone for Reader<T, S> {
+    /// Create another reader for the same ring buffer at the same position.
+    ///
+    /// # Panics
+    /// Panics if the ring buffer already has the maximum number of readers.
+    /// See [Reader::try_clone] for a version that returns an error instead.
+    fn clone(&self) -> Self {
+        match self.try_clone() {
+            Ok(reader) => reader,
+            Err(err) => panic!("{}", err),
+        }
+    }
+}
+
+impl<T, S: Storage<T>> Drop for Reader<T, S> {
+    fn drop(&mut self) {
+        self.storage.header().remove_reader();
+
+        // A reader that is gone no longer holds back the writer
+        self.storage.signals().progress.remove(&self.progress);
+
+        #[cfg(all(unix, feature = "readiness"))]
+        if let Some(readiness) = &self.readiness {
+            self.storage.signals().readiness.remove(readiness);
+        }
+    }
+}
+
+impl<T, S: Storage<T>> std::fmt::Debug for Reader<T, S> {
+    /// Shows where the reader and the writer are, without requiring the
+    /// items to be Debug. This only loads the writer's position and never
+    /// locks or looks at any items.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let write_sequence = self.write_sequence();
+        let sequence = self.position().sequence();
+        f.debug_struct("Reader")
+            .field("id", &self.id.0)
+            .field("capacity", &self.capacity())
+            .field("read_index", &self.read_index)
+            .field("lap", &self.indexing.lap_of(sequence))
+            .field("sequence", &sequence)
+            .field("write_index", &self.indexing.index_of(write_sequence))
+            .field("write_sequence", &write_sequence)
+            .field("behind", &self.lag())
+            .finish()
+    }
+}
+
+/// Counters of what a [Writer] has written, see [Writer::stats]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct WriterStats {
+    /// The number of items written
+    pub writes: u64,
+
+    /// The number of times that the writer wrapped around from the end of
+    /// the ring buffer back to the start
+    pub laps: u64,
+
+    /// The number of writes that had to spin because at least one reader
+    /// was reading the item about to be overwritten
+    pub contended_writes: u64,
+
+    /// The largest number of times that any single write had to check the
+    /// item again before it could go ahead, including the times it yielded
+    /// or slept, see [SPIN_LIMIT]. Anything above [SPIN_LIMIT] means that a
+    /// reader held up the writer for longer than copying an item takes.
+    pub max_spins: u64,
+
+    /// The number of calls to [Writer::try_write] or [Writer::write_timeout]
+    /// that gave up because a reader was busy with the item about to be
+    /// overwritten, or hadn't read it yet in a ring buffer that doesn't
+    /// overwrite unread items, see [ring_buffer_with_overwrite_policy]
+    pub busy: u64,
+
+    /// The number of values that were dropped instead of overwriting an item
+    /// that a reader hadn't read yet, see [OverwritePolicy::Reject]
+    pub rejected: u64,
+}
+
+impl WriterStats {
+    /// Count a number of writes, of which `contended` had to spin, at most
+    /// `max_spins` times
+    fn record(&mut self, writes: u64, contended: u64, max_spins: u64) {
+        self.writes += writes;
+        if contended > 0 {
+            self.contended_writes += contended;
+            self.max_spins = self.max_spins.max(max_spins);
+
+            #[cfg(feature = "tracing")]
+            tracing::trace!(
+                monotonic_counter.contended_writes = contended,
+                spins = max_spins,
+                "writer waited for a reader"
+            );
+        }
+    }
+}
+
+impl<T, S: Storage<T>> Writer<T, S> {
+    fn new(storage: S) -> Writer<T, S> {
+        Writer {
+            indexing: Indexing::new(storage.items().len()),
+            spin_policy: SpinPolicy::default(),
+            storage,
+            index: 0,
+            sequence: 0,
+            writable: 0,
+            moved_back: 0,
+            stats: WriterStats::default(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the number of items that the ring buffer can hold
+    pub fn capacity(&self) -> usize {
+        self.storage.items().len()
+    }
+
+    /// Returns the number of bytes occupied by the ring buffer's items
+    pub fn memory_footprint(&self) -> usize {
+        self.capacity() * std::mem::size_of::<Item<T>>()
+    }
+
+    /// Returns the sequence number that the next item written will have.
+    /// Every item is numbered by how many items were written before it,
+    /// starting from zero, so this is also the number of items written so
+    /// far.
+    pub fn next_sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Returns the sequence number of the most recently written item, or
+    /// None if nothing has been written yet
+    pub fn last_sequence(&self) -> Option<u64> {
+        self.sequence.checked_sub(1)
+    }
+
+    /// Returns where the writer is, which is the position of the item that
+    /// it is going to write next. A reader that is fully caught up is at the
+    /// same position, see [Reader::position].
+    pub fn position(&self) -> Position {
+        Position(self.sequence)
+    }
+
+    /// Returns the number of items that this writer has written in total,
+    /// which readers can find out as well with [Reader::total_written]. This
+    /// is the same as [Writer::next_sequence].
+    pub fn total_written(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Returns the number of times that any reader of this ring buffer has
+    /// read a [ReadResult::Dropout], by any kind of read, including readers
+    /// that no longer exist. Readers count their dropouts without ordering
+    /// them with anything else, so this may lag behind slightly.
+    pub fn observed_dropouts(&self) -> u64 {
+        self.storage.header().dropouts.load(Ordering::Relaxed)
+    }
+
+    /// Returns the counters of everything that this writer has written so
+    /// far, or since the last call to [Writer::reset_stats]. A high number
+    /// of contended writes hints at a reader that keeps reading the oldest
+    /// item right as the writer overwrites it.
+    pub fn stats(&self) -> WriterStats {
+        self.stats
+    }
+
+    /// Set all of the writer's statistics back to zero
+    pub fn reset_stats(&mut self) {
+        self.stats = WriterStats::default();
+    }
+
+    /// Returns whether `reader` reads from the ring buffer that this writer
+    /// writes to
+    pub fn is_writer_for(&self, reader: &Reader<T, S>) -> bool {
+        std::ptr::eq(self.storage.header(), reader.storage.header())
+    }
+
+    /// Returns the number of readers of this ring buffer that currently
+    /// exist. This is only advisory, since readers may be cloned or dropped
+    /// on other threads at any moment, or retargeted to this buffer with
+    /// [Reader::retarget_to_writer].
+    pub fn reader_count(&self) -> usize {
+        self.storage.header().reader_count.load(Ordering::SeqCst)
+    }
+
+    /// Returns how many items behind the writer the slowest reader is, like
+    /// [Reader::lag] of that reader, or None if there are no readers. Each
+    /// reader publishes its position whenever it reads or moves, so this
+    /// only scans the readers and is exact unless they read concurrently.
+    /// Readers in other processes that share the ring buffer through shared
+    /// memory aren't counted. Nothing that the writer cleared counts towards
+    /// the lag, see [Writer::clear].
+    pub fn slowest_reader_lag(&self) -> Option<usize> {
+        // Readers that haven't moved since a clear are only behind by what
+        // was written afterwards. Only the writer itself changes this.
+        let cleared_at = self.storage.header().cleared_at.load(Ordering::Relaxed);
+        let slowest = self.storage.signals().progress.slowest()?.max(cleared_at);
+        let distance = self.sequence.saturating_sub(slowest);
+        Some(distance.min(self.storage.items().len() as u64) as usize)
+    }
+
+    /// Returns the maximum number of readers that may exist at once for
+    /// this ring buffer, which is [DEFAULT_MAX_READERS] unless changed by
+    /// [Writer::set_max_readers].
+    pub fn max_readers(&self) -> usize {
+        self.storage.header().max_readers.load(Ordering::SeqCst)
+    }
+
+    /// Returns how the writer waits while readers are busy with the item
+    /// about to be written, see [SpinPolicy]
+    pub fn spin_policy(&self) -> SpinPolicy {
+        self.spin_policy
+    }
+
+    /// Change how the writer waits while readers are busy with the item
+    /// about to be written
+    pub fn set_spin_policy(&mut self, policy: SpinPolicy) {
+        self.spin_policy = policy;
+    }
+
+    /// Returns what the writer does when it is about to overwrite an item
+    /// that a reader hasn't read yet, see [ring_buffer_with_overwrite_policy]
+    pub fn overwrite_policy(&self) -> OverwritePolicy {
+        OverwritePolicy::from_raw(
+            self.storage
+                .header()
+                .overwrite_policy
+                .load(Ordering::Relaxed),
+        )
+    }
+
+    /// Change the maximum number of readers that may exist at once for this
+    /// ring buffer. Once the limit is reached, [Reader::try_clone] fails,
+    /// while cloning or retargeting readers panics. Lowering the limit
+    /// below the current [Writer::reader_count] doesn't affect any existing
+    /// readers.
+    ///
+    /// # Panics
+    /// Panics if `max_readers` is larger than the number of concurrent
+    /// readers that the ring buffer supports, which is `i32::MAX`.
+    pub fn set_max_readers(&mut self, max_readers: usize) {
+        assert!(
+            max_readers <= storage::READER_LIMIT,
+            "a ring buffer supports at most {} readers, but {} were requested",
+            storage::READER_LIMIT,
+            max_readers
+        );
+        self.storage
+            .header()
+            .max_readers
+            .store(max_readers, Ordering::SeqCst);
+    }
+
+    /// Returns whether any readers of this ring buffer currently exist.
+    /// Once this returns false, anything written is never going to be
+    /// read, and the writer may stop producing data. See
+    /// [Writer::reader_count].
+    pub fn has_readers(&self) -> bool {
+        self.reader_count() > 0
+    }
+
+    /// Close the ring buffer. Readers can still read any data that was
+    /// written before, after which they receive [ReadResult::Closed]. Any
+    /// blocked readers are woken up. Dropping the writer has the same effect.
+    pub fn close(self) {
+        drop(self);
+    }
+
+    /// Discard everything written so far, so that every reader finds the
+    /// queue empty until the writer writes again, without reallocating
+    /// anything. The items stay in memory, but no reader returns any of them
+    /// again: a reader that hasn't read everything yet, including one that
+    /// is partway through the old items or has just skipped ahead, moves to
+    /// the front on its next read and reads [ReadResult::Empty] instead of a
+    /// dropout, and the items it skips don't count as lost. Rewinding,
+    /// seeking and snapshots don't reach back past the clear either.
+    ///
+    /// Sequence numbers carry on from where they were, so items written
+    /// afterwards are read with [ReadResult::Ok] as usual. A reader that is
+    /// reading concurrently may still return one item that was written
+    /// before the clear, as if it had read it just before. In a ring buffer
+    /// that doesn't overwrite unread items, see
+    /// [ring_buffer_with_overwrite_policy], the writer no longer waits for
+    /// readers to read the cleared items.
+    pub fn clear(&mut self) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(sequence = self.sequence, "writer cleared the ring buffer");
+
+        // Release pairs with the acquire in Reader::cleared_at
+        self.storage
+            .header()
+            .cleared_at
+            .store(self.sequence, Ordering::Release);
+    }
+
+    /// Close the ring buffer like [Writer::close] and return a new reader at
+    /// the oldest item that is still held in it, see [Reader::seek_to_oldest],
+    /// so that the thread that wrote everything can go over the retained
+    /// history afterwards. Like any other reader, it receives
+    /// [ReadResult::Closed] once it has read everything.
+    ///
+    /// # Panics
+    /// Panics if the ring buffer already has as many readers as it may have
+    /// at once, see [Writer::set_max_readers].
+    pub fn into_reader(self) -> Reader<T, S> {
+        let mut reader = self.add_reader();
+        drop(self);
+        reader.seek_to_oldest();
+        reader
+    }
+
+    /// Create a new reader for this ring buffer at the front of the queue,
+    /// so that it reads [ReadResult::Empty] until the writer writes
+    /// something new, without needing another reader to clone it from.
+    ///
+    /// # Panics
+    /// Panics if the ring buffer already has the maximum number of readers.
+    /// See [Writer::try_add_reader] for a version that returns an error
+    /// instead.
+    pub fn add_reader(&self) -> Reader<T, S> {
+        match self.try_add_reader() {
+            Ok(reader) => reader,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Create a new reader for this ring buffer like [Writer::add_reader],
+    /// or return an error if the ring buffer already has as many readers as
+    /// [Writer::max_readers] allows
+    pub fn try_add_reader(&self) -> Result<Reader<T, S>, TooManyReaders> {
+        self.storage.header().add_reader()?;
+        let mut reader = Reader::counted(self.storage.clone());
+        reader.seek_to_front();
+        Ok(reader)
+    }
+
+    /// Write new data onto the queue, possibly overwriting old data. Any readers
+    /// that were fully caught up will see the new data with [ReadResult::Ok],
+    /// while any readers that get overtaken will see the new data but with
+    /// [ReadResult::Dropout] instead.
+    ///
+    /// This method uses a spin lock and may busy-wait for a short duration if
+    /// any readers happen to be actively reading from the very back of the
+    /// queue. If a reader takes longer, for example because it was
+    /// descheduled, the writer yields and then sleeps between checks instead,
+    /// see [SPIN_LIMIT]. The guarded section is performs only a trivial copy
+    /// of the data. In a lossless ring buffer, this waits in the same way
+    /// for as long as any reader hasn't read the old data yet, see
+    /// [ring_buffer_lossless]. With [OverwritePolicy::Reject], a value that
+    /// would overwrite an item that a reader hasn't read yet is dropped
+    /// instead and counted in [WriterStats::rejected], see
+    /// [Writer::write_checked].
+    ///
+    /// Returns the sequence number of the written item, which is the same
+    /// sequence number that readers receive along with it from
+    /// [Reader::read_indexed]. A value that was dropped would have had that
+    /// sequence number.
+    pub fn write(&mut self, value: T) -> u64 {
+        match self.write_checked(value) {
+            Ok(sequence) => sequence,
+            Err(_) => self.sequence,
+        }
+    }
+
+    /// Write new data onto the queue like [Writer::write], but return the
+    /// value in the error if the ring buffer has [OverwritePolicy::Reject]
+    /// and the value would overwrite an item that a reader hasn't read yet.
+    /// This never waits for a reader to catch up, though it may still wait
+    /// briefly for a reader that is busy copying the item. With the other
+    /// policies, this always succeeds.
+    pub fn write_checked(&mut self, value: T) -> Result<u64, WouldOvertake<T>> {
+        let sequence = self.sequence;
+        PendingWrites::new(self)
+            .push_checked(value)
+            .map(|()| sequence)
+            .map_err(WouldOvertake)
+    }
+
+    /// Write new data onto the queue like [Writer::write], and return the value
+    /// that it overwrites. Returns None if the item being overwritten was
+    /// never written before, such as during the first lap around the ring
+    /// buffer.
+    /// Readers may or may not have read the returned value, unless the ring
+    /// buffer has an [OverwritePolicy] other than [OverwritePolicy::Overwrite].
+    /// A value that is rejected, see [Writer::write], returns None as well.
+    pub fn write_returning_evicted(&mut self, value: T) -> Option<T> {
+        let mut pending = PendingWrites::new(self);
+        if pending.rejects() {
+            pending.stats.rejected += 1;
+            return None;
+        }
+        pending.push_replacing(value)
+    }
+
+    /// Write new data onto the queue like [Writer::write], but never wait
+    /// for readers. The item is locked with a single attempt, without
+    /// spinning, yielding or sleeping. If a reader is busy with the item
+    /// that would be overwritten, nothing is written and the value is
+    /// returned in the error, so that a realtime producer can drop it or
+    /// try again later instead of risking a wait.
+    pub fn try_write(&mut self, value: T) -> Result<(), TryWriteError<T>> {
+        let result = PendingWrites::new(self).try_push(value);
+        if result.is_err() {
+            self.stats.busy += 1;
+        }
+        result.map_err(TryWriteError)
+    }
+
+    /// Write new data onto the queue like [Writer::write], but wait for
+    /// readers for at most the given duration. If a reader is still busy
+    /// with the item that would be overwritten once the timeout has passed,
+    /// nothing is written and the value is returned in the error. The clock
+    /// is only read once the writer has to wait, and then only every so
+    /// often while spinning, so that giving up may take slightly longer than
+    /// the timeout.
+    pub fn write_timeout(&mut self, value: T, timeout: Duration) -> Result<(), WriteTimeout<T>> {
+        let result = PendingWrites::new(self).push_within(value, timeout);
+        if result.is_err() {
+            self.stats.busy += 1;
+        }
+        result.map_err(WriteTimeout)
+    }
+
+    /// Write a new item onto the queue by letting `f` fill in the item's memory
+    /// in the ring buffer directly, which avoids building a large value
+    /// elsewhere and copying it in. `f` receives whatever the item held
+    /// before, which is an older value or the default value. Otherwise, this
+    /// behaves like [Writer::write].
+    ///
+    /// Because `f` runs while the item is locked, any reader that was
+    /// overtaken and reaches the item in the meantime spins until `f`
+    /// returns, while caught up readers find the queue empty, so keep it
+    /// short and never wait for anything inside it. If `f` panics, the item
+    /// is reset to its default value and written as such, so that readers
+    /// never observe a partially filled value, and the writer remains
+    /// usable. If the write is rejected, see [Writer::write], `f` isn't
+    /// called.
+    pub fn write_with(&mut self, f: impl FnOnce(&mut T))
+    where
+        T: Default,
+    {
+        let mut pending = PendingWrites::new(self);
+        if pending.rejects() {
+            pending.stats.rejected += 1;
+            return;
+        }
+        pending.push_with(f);
+    }
+
+    /// Write every item of `iter` onto the queue, oldest first, with the same
+    /// result as calling [Writer::write] for each of them, and return how
+    /// many items were written. Like [Writer::write_slice], items are made
+    /// visible to readers in chunks up to the end of the ring buffer rather
+    /// than one at a time. Since the iterator produces the next item while
+    /// earlier items of the chunk are still locked, it should be cheap, e.g.
+    /// not wait for anything. If it panics, the items that it produced so
+    /// far are still written. Rejected items, see [Writer::write], are
+    /// dropped and not counted.
+    pub fn write_iter<I: IntoIterator<Item = T>>(&mut self, iter: I) -> usize {
+        let mut iter = iter.into_iter();
+        let mut written = 0;
+        loop {
+            let mut pending = PendingWrites::new(self);
+            let space = pending.space();
+            let mut exhausted = false;
+            while pending.count < space {
+                match iter.next() {
+                    Some(value) => {
+                        let _ = pending.push_checked(value);
+                    }
+                    None => {
+                        exhausted = true;
+                        break;
+                    }
+                }
+            }
+
+            written += pending.count;
+            drop(pending);
+
+            if exhausted {
+                return written;
+            }
+        }
+    }
+}
 
-            std::hint::spin_loop();
+impl<T, S: Storage<T>> Writer<T, S>
+where
+    T: Copy,
+{
+    /// Returns a copy of the most recently written item, or None if nothing has
+    /// been written yet. This reads the item straight from the ring buffer
+    /// without locking it, which is sound because only the writer ever
+    /// modifies items, and it can't be writing while this is called.
+    pub fn last_written(&self) -> Option<T> {
+        // Nothing was written, at least since the ring buffer was cleared
+        if self.sequence == self.storage.header().cleared_at.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let items = self.storage.items();
+        let item = &items[(self.index + items.len() - 1) % items.len()];
+
+        // SAFETY: other than the writer itself, which is borrowed here, only
+        // readers access the item, and they never modify it. The item was
+        // written to, since it was the last one.
+        Some(item.data.with(|data| unsafe { (*data).assume_init() }))
+    }
+
+    /// Lock the next item for writing and return a guard through which it
+    /// can be modified in place for as long as needed. The item becomes
+    /// visible to readers only once [WriteGuard::commit] is called, while
+    /// dropping the guard without committing leaves the item as it was and
+    /// writes nothing, unless [WriteGuard::set_commit_on_drop] was used.
+    ///
+    /// Until then, caught up readers find the queue empty, while any reader
+    /// that was overtaken and reaches the item spins until the guard is gone,
+    /// like it would while [Writer::write] is busy, so don't hold on to the
+    /// guard for long. An item that was never written to before holds
+    /// `T::default()`. With [OverwritePolicy::Reject], this waits for the
+    /// slowest reader like [OverwritePolicy::Block], since there is no value
+    /// to drop.
+    pub fn reserve(&mut self) -> WriteGuard<'_, T>
+    where
+        T: Default,
+    {
+        let mut pending = PendingWrites::new(self);
+        let (item, held) = pending.lock_next();
+
+        // SAFETY: see PendingWrites::lock_next
+        let previous = item.data.with_mut(|data| unsafe {
+            let previous = *data;
+            if !held {
+                (*data).write(T::default());
+            }
+            previous
+        });
+
+        WriteGuard {
+            pending,
+            item,
+            previous,
+            commit_on_drop: false,
+            committed: false,
+        }
+    }
+
+    /// Write many values onto the queue at once, oldest first, with the same
+    /// result as calling [Writer::write] for each of them. Rather than
+    /// publishing every value on its own, the writer locks all items up to
+    /// the end of the ring buffer, fills them, and makes them visible to
+    /// readers together, which is cheaper per item. Caught up readers find
+    /// the queue empty until the whole chunk is written, and readers that
+    /// were overtaken spin until then if they reach one of those items.
+    ///
+    /// If `values` is longer than the capacity of the ring buffer, only the
+    /// final `capacity` values are ever observable by readers, since the
+    /// earlier ones would be overwritten within the same call anyway. The
+    /// earlier values still count as written, so readers see them as lost.
+    /// Unless the ring buffer has [OverwritePolicy::Overwrite], all values are
+    /// written, or rejected one by one, see [ring_buffer_with_overwrite_policy].
+    pub fn write_slice(&mut self, values: &[T]) {
+        let capacity = self.storage.items().len();
+
+        // Skip over the values that nobody could ever read, unless the
+        // writer has to check every value against the readers, see
+        // ring_buffer_with_overwrite_policy
+        let skipped = if self.overwrite_policy() == OverwritePolicy::Overwrite {
+            values.len().saturating_sub(capacity)
+        } else {
+            0
+        };
+        let mut values = &values[skipped..];
+        self.sequence += skipped as u64;
+        let position = (self.index + skipped) as u64;
+        self.stats.laps += self.indexing.lap_of(position);
+        self.index = self.indexing.index_of(position);
+        self.stats.record(skipped as u64, 0, 0);
+
+        while !values.is_empty() {
+            let mut pending = PendingWrites::new(self);
+            let (chunk, rest) = values.split_at(values.len().min(pending.space()));
+            for value in chunk {
+                let _ = pending.push_checked(*value);
+            }
+            values = rest;
+        }
+    }
+}
+
+/// The next item of a ring buffer, locked for writing by [Writer::reserve].
+/// Dereferences to the item's value, which is the item's previous contents
+/// at first.
+pub struct WriteGuard<'a, T: Copy> {
+    pending: PendingWrites<'a, T>,
+    item: &'a Item<T>,
+
+    // The item's value from before it was reserved, to restore if the
+    // guard is dropped without committing. Uninitialized if the item was
+    // never written to.
+    previous: MaybeUninit<T>,
+
+    commit_on_drop: bool,
+    committed: bool,
+}
+
+impl<T: Copy> WriteGuard<'_, T> {
+    /// Write the item, making it visible to readers
+    pub fn commit(mut self) {
+        self.committed = true;
+    }
+
+    /// Choose whether dropping the guard writes the item like
+    /// [WriteGuard::commit] or leaves it as it was before, which is the
+    /// default. Dropping the guard while panicking never writes the item.
+    pub fn set_commit_on_drop(&mut self, commit_on_drop: bool) {
+        self.commit_on_drop = commit_on_drop;
+    }
+}
+
+impl<T: Copy> std::ops::Deref for WriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the item stays locked for writing for as long as the guard
+        // exists, see PendingWrites::lock_next, and was initialized by
+        // Writer::reserve
+        self.item
+            .data
+            .with(|data| unsafe { (*data).assume_init_ref() })
+    }
+}
+
+impl<T: Copy> std::ops::DerefMut for WriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: see WriteGuard::deref
+        self.item
+            .data
+            .with_mut(|data| unsafe { (*data).assume_init_mut() })
+    }
+}
+
+impl<T: Copy> Drop for WriteGuard<'_, T> {
+    fn drop(&mut self) {
+        let commit = self.committed || (self.commit_on_drop && !std::thread::panicking());
+        if !commit {
+            self.pending.unlock_last(self.previous);
+        }
+        // Otherwise, the pending write is published right after this
+    }
+}
+
+/// Consecutive items that a [Writer] has locked and filled, starting at its
+/// write index. Dropping this publishes them to readers all at once and
+/// unlocks them, which also happens if the code producing the values panics.
+struct PendingWrites<'a, T> {
+    header: &'a storage::Header,
+    signals: &'a storage::Signals,
+    items: &'a [Item<T>],
+    indexing: Indexing,
+    spin_policy: SpinPolicy,
+    index: &'a mut usize,
+    sequence: &'a mut u64,
+    stats: &'a mut WriterStats,
+
+    // What to do about items that the slowest reader hasn't read yet, and
+    // the sequence number up to which there are none, see
+    // ring_buffer_with_overwrite_policy
+    policy: OverwritePolicy,
+    writable: &'a mut u64,
+    moved_back: &'a mut u64,
+
+    // The number of items filled so far
+    count: usize,
+
+    // The number of items for which the writer had to wait for readers,
+    // and the longest wait
+    contended: u64,
+    max_spins: u64,
+}
+
+impl<'a, T> PendingWrites<'a, T> {
+    fn new<S: Storage<T>>(writer: &'a mut Writer<T, S>) -> PendingWrites<'a, T> {
+        let Writer {
+            storage,
+            indexing,
+            spin_policy,
+            index,
+            sequence,
+            stats,
+            writable,
+            moved_back,
+            ..
+        } = writer;
+        PendingWrites {
+            // Only ever set before the ring buffer is shared
+            policy: OverwritePolicy::from_raw(
+                storage.header().overwrite_policy.load(Ordering::Relaxed),
+            ),
+            writable,
+            moved_back,
+            header: storage.header(),
+            signals: storage.signals(),
+            items: storage.items(),
+            indexing: *indexing,
+            spin_policy: *spin_policy,
+            index,
+            sequence,
+            stats,
+            count: 0,
+            contended: 0,
+            max_spins: 0,
+        }
+    }
+
+    /// Returns how many items can be filled in total before reaching the
+    /// end of the ring buffer
+    fn space(&self) -> usize {
+        self.items.len() - *self.index
+    }
+
+    /// Lock and fill the next item. There must be space left.
+    fn push(&mut self, value: T) {
+        let (item, held) = self.lock_next();
+        Self::fill(item, held, value);
+    }
+
+    /// Returns whether the next value has to be dropped because it would
+    /// overwrite an item that a reader hasn't read yet, see
+    /// [OverwritePolicy::Reject]
+    fn rejects(&mut self) -> bool {
+        self.policy == OverwritePolicy::Reject && !self.may_overwrite()
+    }
+
+    /// Lock and fill the next item like [PendingWrites::push], unless the
+    /// value is rejected, see [PendingWrites::rejects], in which case it is
+    /// returned instead and counted
+    fn push_checked(&mut self, value: T) -> Result<(), T> {
+        if self.rejects() {
+            self.stats.rejected += 1;
+            return Err(value);
+        }
+        self.push(value);
+        Ok(())
+    }
+
+    /// Lock and fill the next item like [PendingWrites::push], unless a
+    /// reader is busy with it, in which case the value is returned instead
+    fn try_push(&mut self, value: T) -> Result<(), T> {
+        let Some((item, held)) = self.try_lock_next() else {
+            return Err(value);
+        };
+        Self::fill(item, held, value);
+        Ok(())
+    }
+
+    /// Lock and fill the next item like [PendingWrites::push], unless a
+    /// reader is still busy with it after the timeout, in which case the
+    /// value is returned instead
+    fn push_within(&mut self, value: T, timeout: Duration) -> Result<(), T> {
+        let Some((item, held)) = self.lock_next_within(timeout) else {
+            return Err(value);
+        };
+        Self::fill(item, held, value);
+        Ok(())
+    }
+
+    /// Lock and fill the next item, and return its previous value, unless
+    /// it was never written to. There must be space left.
+    fn push_replacing(&mut self, value: T) -> Option<T> {
+        let (item, held) = self.lock_next();
+
+        // SAFETY: see PendingWrites::lock_next. The data is initialized if
+        // the item held any.
+        item.data.with_mut(|data| unsafe {
+            let previous = std::mem::replace(&mut *data, MaybeUninit::new(value));
+            held.then(|| previous.assume_init())
+        })
+    }
+
+    /// Store a value in an item that was just locked, dropping the value
+    /// that it held, if any
+    fn fill(item: &Item<T>, held: bool, value: T) {
+        // SAFETY: see PendingWrites::lock_next. The data is initialized if
+        // the item held any.
+        item.data.with_mut(|data| unsafe {
+            if held {
+                *(*data).assume_init_mut() = value;
+            } else {
+                (*data).write(value);
+            }
+        });
+    }
+
+    /// Lock the next item and let `f` fill it in place. There must be space
+    /// left.
+    fn push_with(&mut self, f: impl FnOnce(&mut T))
+    where
+        T: Default,
+    {
+        /// Resets the item to its default value when dropped, i.e. if `f` panics
+        struct ResetOnPanic<'b, T: Default>(&'b Item<T>);
+
+        impl<T: Default> Drop for ResetOnPanic<'_, T> {
+            fn drop(&mut self) {
+                // SAFETY: the item is still locked, see PendingWrites::lock_next,
+                // and was initialized before calling f
+                self.0
+                    .data
+                    .with_mut(|data| unsafe { *(*data).assume_init_mut() = T::default() });
+            }
+        }
+
+        let (item, held) = self.lock_next();
+        if !held {
+            Self::fill(item, held, T::default());
+        }
+        let reset = ResetOnPanic(item);
+
+        // SAFETY: see PendingWrites::lock_next. The reference doesn't outlive
+        // this call, and the item stays locked until the writes are published.
+        item.data
+            .with_mut(|data| f(unsafe { (*data).assume_init_mut() }));
+
+        std::mem::forget(reset);
+    }
+
+    /// Undo [PendingWrites::lock_next] for the last item, restoring its value
+    /// and sequence number and unlocking it without publishing anything
+    fn unlock_last(&mut self, previous: MaybeUninit<T>) {
+        self.count -= 1;
+        let item = &self.items[*self.index + self.count];
+
+        // The item always held the value from exactly one lap earlier, or
+        // none at all
+        let sequence = *self.sequence + self.count as u64;
+        let previous_sequence = self.indexing.previous_lap(sequence);
+
+        // SAFETY: the item is still locked, see PendingWrites::lock_next
+        unsafe {
+            item.data.with_mut(|data| *data = previous);
+            item.set_sequence(previous_sequence);
+        }
+
+        item.release_write(self.indexing.lap_token(previous_sequence));
+    }
+
+    /// Lock the next item for writing and stamp it with its sequence number.
+    /// The item counts as filled right away, so that it is published and
+    /// unlocked when this is dropped, and the caller must fill it. Also
+    /// returns whether the item held a value before, since its data is only
+    /// initialized if so.
+    fn lock_next(&mut self) -> (&'a Item<T>, bool) {
+        debug_assert!(self.count < self.space());
+
+        let (waited, _) = self
+            .wait_for_readers(None)
+            .expect("waiting without a timeout never gives up");
+
+        // fetch the item about to be written to
+        let item = &self.items[*self.index + self.count];
+
+        let spins = waited + item.acquire_write(&self.spin_policy);
+        self.contended += u64::from(spins > 0);
+        self.max_spins = self.max_spins.max(spins);
+
+        self.stamp(item)
+    }
+
+    /// Lock the next item like [PendingWrites::lock_next], but return None
+    /// if a reader is still busy with it after the timeout
+    fn lock_next_within(&mut self, timeout: Duration) -> Option<(&'a Item<T>, bool)> {
+        debug_assert!(self.count < self.space());
+
+        if self.rejects() {
+            return None;
+        }
+        let (waited, timeout) = self.wait_for_readers(Some(timeout))?;
+
+        let item = &self.items[*self.index + self.count];
+        let spins = waited + item.acquire_write_within(&self.spin_policy, timeout)?;
+        self.contended += u64::from(spins > 0);
+        self.max_spins = self.max_spins.max(spins);
+
+        Some(self.stamp(item))
+    }
+
+    /// Lock the next item like [PendingWrites::lock_next], but return None
+    /// instead of waiting if a reader is busy with it
+    fn try_lock_next(&mut self) -> Option<(&'a Item<T>, bool)> {
+        debug_assert!(self.count < self.space());
+
+        if !self.may_overwrite() {
+            return None;
+        }
+
+        let item = &self.items[*self.index + self.count];
+        if !item.try_acquire_write() {
+            return None;
+        }
+        Some(self.stamp(item))
+    }
+
+    /// Returns whether the next item may be written without overwriting
+    /// anything that a reader hasn't read yet, unless the policy is to
+    /// overwrite it anyway
+    fn may_overwrite(&mut self) -> bool {
+        let sequence = *self.sequence + self.count as u64;
+        if self.policy == OverwritePolicy::Overwrite {
+            return true;
+        }
+
+        // A reader that moved backwards or was added since may be behind
+        // what the writer last saw of the slowest reader
+        let moved_back = self.signals.progress.moved_back();
+        if sequence < *self.writable && moved_back == *self.moved_back {
+            return true;
         }
+        *self.moved_back = moved_back;
 
-        // SAFETY: the spin loop above ensures that the use count was zero before and is now -1
+        // Without any readers, nothing can be lost. A reader that is added
+        // later starts at the front at the earliest, which is where the
+        // pending items begin. Readers never read anything that the writer
+        // cleared, which only the writer itself changes.
+        let cleared_at = self.header.cleared_at.load(Ordering::Relaxed);
+        let slowest = match self.signals.progress.slowest() {
+            Some(slowest) => slowest.max(cleared_at),
+            None => *self.sequence,
+        };
+        *self.writable = slowest + self.items.len() as u64;
+        sequence < *self.writable
+    }
+
+    /// Wait until the next item may be written, see
+    /// [PendingWrites::may_overwrite], as the spin policy says. Returns how
+    /// many times the writer had to check again and how much of the timeout,
+    /// if any, is left, or None if the timeout passed first.
+    fn wait_for_readers(&mut self, timeout: Option<Duration>) -> Option<(u64, Option<Duration>)> {
+        let mut spins = 0;
+        let mut deadline = None;
+        while !self.may_overwrite() {
+            // If the deadline can't be represented, it's never reached
+            if let Some(timeout) = timeout {
+                let deadline = *deadline.get_or_insert_with(|| Instant::now().checked_add(timeout));
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    return None;
+                }
+            }
+
+            self.spin_policy.backoff(spins);
+            spins += 1;
+        }
+
+        let timeout = match deadline {
+            Some(Some(deadline)) => Some(deadline.saturating_duration_since(Instant::now())),
+            _ => timeout,
+        };
+        Some((spins, timeout))
+    }
+
+    /// Stamp an item that was just locked for writing with its sequence
+    /// number, and count it as filled. Returns whether the item held a value
+    /// before.
+    fn stamp(&mut self, item: &'a Item<T>) -> (&'a Item<T>, bool) {
+        // SAFETY: acquire_write ensures that the use count was zero before and is now -1
         // This value indicates to all readers that the writer is busy here, and they will block
         // until it's non-negative again. Thus, there is no data race.
-        unsafe {
-            *item.data.get() = value;
+        let sequence = *self.sequence + self.count as u64;
+        let held = unsafe {
+            let held = item.sequence() != UNWRITTEN_SEQUENCE;
+            item.set_sequence(sequence);
+            held
+        };
 
-            *item.lap_count.get() = self.lap_count;
+        self.count += 1;
+        (item, held)
+    }
+}
+
+impl<T> Drop for PendingWrites<'_, T> {
+    fn drop(&mut self) {
+        if self.count == 0 {
+            return;
+        }
+
+        // All items up to the end of the ring buffer are from the same lap
+        let start = *self.index;
+        let lap = self.indexing.lap_token(*self.sequence);
+        *self.sequence += self.count as u64;
+        *self.index += self.count;
+        if *self.index == self.items.len() {
+            *self.index = 0;
+            self.stats.laps += 1;
         }
 
-        // If the index wraps around, increment the lap count
-        let mut next_index = index + 1;
-        if next_index == self.data.len() {
-            next_index = 0;
-            self.lap_count = self.lap_count.wrapping_add(1);
+        // update the write sequence to be visible by readers. This happens
+        // before unlocking any of the items, so that readers never get ahead
+        // of the write sequence: a reader that locked one of the items
+        // synchronizes with its release below, after which this store is
+        // visible as well. Release pairs with the acquire in
+        // Reader::write_sequence, for readers that only look at the sequence.
+        self.header
+            .write_sequence
+            .store(*self.sequence, Ordering::Release);
+
+        // release the write locks on the items by assigning zero back to the use count.
+        for item in &self.items[start..(start + self.count)] {
+            item.release_write(lap);
         }
 
-        // update the write index to be visible by readers
-        self.write_index.store(next_index, Ordering::SeqCst);
+        // count the writes only now, so as not to hold up readers any longer
+        self.stats
+            .record(self.count as u64, self.contended, self.max_spins);
+
+        // wake up any readers blocked in Reader::read_blocking or Reader::read_async
+        self.signals.waiters.wake_all();
+
+        // signal any readers waiting on a file descriptor
+        #[cfg(all(unix, feature = "readiness"))]
+        self.signals.readiness.signal_all();
+    }
+}
+
+impl<T, S: Storage<T>> std::fmt::Debug for Writer<T, S> {
+    /// Shows where the writer is, without requiring the items to be Debug
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Writer")
+            .field("capacity", &self.capacity())
+            .field("index", &self.index)
+            .field("lap", &self.indexing.lap_of(self.sequence))
+            .field("sequence", &self.sequence)
+            .field("writes", &self.stats.writes)
+            .field("readers", &self.reader_count())
+            .finish()
+    }
+}
+
+impl<T, S: Storage<T>> Drop for Writer<T, S> {
+    fn drop(&mut self) {
+        let header = self.storage.header();
+
+        // Readers check the closed flag only after finding the queue empty, so
+        // it must be set before waking them up for them to see it. This must
+        // be SeqCst for the same reason as releasing an item, see
+        // WaitList::wake_all.
+        header.closed.store(true, Ordering::SeqCst);
+
+        let signals = self.storage.signals();
+        signals.waiters.wake_all();
 
-        // release the write lock on the current item by assigning zero back to the use count.
-        // The use count must still be -1, nothing should have modified it during writing.
-        item.use_count
-            .compare_exchange(-1, 0, Ordering::SeqCst, Ordering::SeqCst)
-            .unwrap();
+        #[cfg(all(unix, feature = "readiness"))]
+        signals.readiness.signal_all();
     }
 }
@@ -0,0 +1,162 @@
+//! A small registry of how far each reader of a ring buffer has read, so that
+//! the writer can find the slowest of them, see
+//! [Writer::slowest_reader_lag](crate::Writer::slowest_reader_lag), and wait
+//! for it instead of overwriting anything unread, see
+//! [ring_buffer_lossless](crate::ring_buffer_lossless).
+//!
+//! The first readers at once take one of the slots that the registry keeps
+//! inline, so that creating and cloning readers doesn't allocate, which a
+//! [StaticRingBuffer](crate::StaticRingBuffer) relies on. Only the readers
+//! beyond those allocate a slot of their own.
+
+use std::sync::{Arc, Mutex};
+
+use crate::sync::{AtomicU64, Ordering};
+
+/// The number of readers whose progress is kept inline, one bit each of
+/// ProgressList::taken
+const INLINE_SLOTS: usize = 64;
+
+pub(crate) struct ProgressList {
+    // Which of the inline slots belong to a reader
+    taken: AtomicU64,
+
+    // The sequence number of the item that each reader is going to read
+    // next. Each reader publishes its own without locking. Free slots hold
+    // zero, see ProgressList::add.
+    slots: [AtomicU64; INLINE_SLOTS],
+
+    // The slots of readers that didn't find a free inline slot, which the
+    // writer only locks when it has to find the slowest reader again
+    spilled: Mutex<Vec<Arc<AtomicU64>>>,
+
+    // The number of times that a reader was added or moved backwards, after
+    // which the writer can't rely on what it last saw of the slowest reader
+    moved_back: AtomicU64,
+}
+
+/// Where a reader publishes its progress, see [ProgressList::add]
+pub(crate) enum ProgressSlot {
+    Inline(usize),
+    Spilled(Arc<AtomicU64>),
+}
+
+impl ProgressList {
+    pub(crate) fn new() -> ProgressList {
+        ProgressList {
+            taken: AtomicU64::new(0),
+            slots: std::array::from_fn(|_| AtomicU64::new(0)),
+            spilled: Mutex::new(Vec::new()),
+            moved_back: AtomicU64::new(0),
+        }
+    }
+
+    /// Forget about all readers. Requires exclusive access.
+    pub(crate) fn reset(&mut self) {
+        self.taken = AtomicU64::new(0);
+        for slot in &mut self.slots {
+            *slot = AtomicU64::new(0);
+        }
+        self.spilled
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+        self.moved_back = AtomicU64::new(0);
+    }
+
+    /// Add a reader. Until it publishes its actual progress, it holds back
+    /// the writer at the very first item, so that the writer can't run past
+    /// it in the meantime.
+    pub(crate) fn add(&self) -> ProgressSlot {
+        let mut taken = self.taken.load(Ordering::Relaxed);
+        while taken != u64::MAX {
+            let index = (!taken).trailing_zeros() as usize;
+            // Acquire pairs with the release in ProgressList::remove, after
+            // which the slot holds zero again. Release passes that on to the
+            // writer in ProgressList::slowest.
+            match self.taken.compare_exchange_weak(
+                taken,
+                taken | (1 << index),
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    self.moved_back.fetch_add(1, Ordering::Release);
+                    return ProgressSlot::Inline(index);
+                }
+                Err(actual) => taken = actual,
+            }
+        }
+
+        let progress = Arc::new(AtomicU64::new(0));
+        self.lock().push(Arc::clone(&progress));
+        self.moved_back.fetch_add(1, Ordering::Release);
+        ProgressSlot::Spilled(progress)
+    }
+
+    /// Remove a reader that was added before
+    pub(crate) fn remove(&self, slot: &ProgressSlot) {
+        match slot {
+            ProgressSlot::Inline(index) => {
+                self.slots[*index].store(0, Ordering::Relaxed);
+                self.taken.fetch_and(!(1 << *index), Ordering::Release);
+            }
+            ProgressSlot::Spilled(progress) => {
+                let mut spilled = self.lock();
+                if let Some(i) = spilled.iter().position(|p| Arc::ptr_eq(p, progress)) {
+                    spilled.swap_remove(i);
+                }
+            }
+        }
+    }
+
+    /// Publish the sequence number of the item that the reader is going to
+    /// read next, after it is done with every item before it
+    pub(crate) fn publish(&self, slot: &ProgressSlot, sequence: u64) {
+        let progress = match slot {
+            ProgressSlot::Inline(index) => &self.slots[*index],
+            ProgressSlot::Spilled(progress) => progress,
+        };
+        // Release pairs with the acquire in ProgressList::slowest
+        let previous = progress.swap(sequence, Ordering::Release);
+        if sequence < previous {
+            // Release pairs with the acquire in ProgressList::moved_back, so
+            // that the writer finds the reader where it moved to
+            self.moved_back.fetch_add(1, Ordering::Release);
+        }
+    }
+
+    /// Returns the number of times that a reader was added or moved
+    /// backwards, which the writer compares to what it was when it last
+    /// found the slowest reader
+    pub(crate) fn moved_back(&self) -> u64 {
+        self.moved_back.load(Ordering::Acquire)
+    }
+
+    /// Returns the smallest sequence number that any reader is going to read
+    /// next, or None if there are no readers
+    pub(crate) fn slowest(&self) -> Option<u64> {
+        // Acquire pairs with the release in ProgressList::publish, after
+        // which the reader is done with every item before its progress
+        let mut taken = self.taken.load(Ordering::Acquire);
+        let mut slowest = None;
+        while taken != 0 {
+            let index = taken.trailing_zeros() as usize;
+            taken &= taken - 1;
+            let progress = self.slots[index].load(Ordering::Acquire);
+            slowest = Some(slowest.map_or(progress, |s: u64| s.min(progress)));
+        }
+
+        let spilled = self.lock().iter().map(|p| p.load(Ordering::Acquire)).min();
+        match (slowest, spilled) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Arc<AtomicU64>>> {
+        // Nothing panics while holding the lock, but a poisoned list is
+        // still consistent either way
+        self.spilled.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
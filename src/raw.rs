@@ -0,0 +1,293 @@
+//! Ring buffers in memory that the caller provides, such as a mapping that is
+//! shared between processes through `memfd_create` or `shm_open`. Enabled by
+//! the `shared-memory` feature.
+//!
+//! The region starts with a `repr(C)` header, which identifies the ring buffer
+//! and holds the same shared state as the header of any other ring buffer, and
+//! is followed by the items. Only the writer and readers themselves live in each
+//! process, so that a [Writer] created in one process with
+//! [Writer::create_in_raw] can be read from other processes by attaching
+//! readers with [Reader::attach_to_raw].
+
+use std::{alloc::Layout, marker::PhantomData, ptr::NonNull, sync::Arc, time::Duration};
+
+use crate::{
+    storage::{sealed, Header, Item, Signals},
+    sync::{AtomicU64, Ordering},
+    CapacityError, Reader, Storage, TooManyReaders, Writer,
+};
+
+/// Identifies a region that holds a ring buffer, and the version of its
+/// layout. Stored last when a ring buffer is created, so that readers never
+/// attach to one that is only half initialized.
+const MAGIC: u64 = u64::from_le_bytes(*b"spmcq\0\0\x06");
+
+/// How often a reader that is blocked in [Reader::read_blocking] or
+/// [Reader::read_timeout] checks for new data. A writer in another process
+/// has no way of waking it up.
+pub const RAW_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+// The start of a region that holds a ring buffer, which the items follow at
+// the next multiple of their alignment
+#[repr(C)]
+struct RawHeader {
+    // MAGIC once the ring buffer is initialized
+    magic: AtomicU64,
+
+    // The number of items
+    capacity: u64,
+
+    // The size and alignment of a single item, so that readers for a
+    // different type of item or from a build with a different layout are
+    // turned away
+    item_size: u64,
+    item_align: u64,
+
+    // The write sequence number, which also determines the writer's index
+    // and lap, the reader count, and so on
+    header: Header,
+}
+
+/// The error returned by [Writer::create_in_raw] and [Reader::attach_to_raw]
+/// when a ring buffer can't be created in or attached to a region.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RawError {
+    /// The capacity is invalid, see [try_ring_buffer](crate::try_ring_buffer)
+    Capacity(CapacityError),
+
+    /// The region is shorter than the number of bytes contained in the
+    /// error, which the ring buffer needs, see [RawStorage::layout]
+    TooShort(usize),
+
+    /// The region doesn't start at a multiple of the alignment contained in
+    /// the error, see [RawStorage::layout]
+    Misaligned(usize),
+
+    /// The region doesn't hold a ring buffer, or holds one for items of a
+    /// different size or alignment
+    NotRingBuffer,
+
+    /// The ring buffer already has the maximum number of readers
+    TooManyReaders(TooManyReaders),
+}
+
+impl std::fmt::Display for RawError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RawError::Capacity(err) => err.fmt(f),
+            RawError::TooShort(len) => {
+                write!(
+                    f,
+                    "region is too short for the ring buffer of {} bytes",
+                    len
+                )
+            }
+            RawError::Misaligned(align) => {
+                write!(f, "region isn't aligned to {} bytes", align)
+            }
+            RawError::NotRingBuffer => write!(f, "region doesn't hold a matching ring buffer"),
+            RawError::TooManyReaders(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for RawError {}
+
+/// The storage of a ring buffer that lives in a region of memory provided by
+/// the caller, see [Writer::create_in_raw] and [Reader::attach_to_raw]. The
+/// region is never freed by the ring buffer.
+pub struct RawStorage<T> {
+    region: NonNull<RawHeader>,
+    items: NonNull<[Item<T>]>,
+    signals: Arc<Signals>,
+    _phantom: PhantomData<T>,
+}
+
+// SAFETY: the region is shared like the allocation of a HeapStorage, see
+// Writer::create_in_raw for what the caller guarantees
+unsafe impl<T: Send> Send for RawStorage<T> {}
+unsafe impl<T: Send> Sync for RawStorage<T> {}
+
+impl<T> RawStorage<T> {
+    /// The memory layout of a region that holds a ring buffer of the given
+    /// capacity, along with the offset of the items. Returns an error for
+    /// the same capacities that [try_ring_buffer](crate::try_ring_buffer)
+    /// does.
+    pub fn layout(capacity: usize) -> Result<(Layout, usize), CapacityError> {
+        let items = Item::<T>::layout(capacity)?;
+        let (layout, offset) = Layout::new::<RawHeader>()
+            .extend(items)
+            .map_err(|_| CapacityError::TooLarge(capacity))?;
+        Ok((layout.pad_to_align(), offset))
+    }
+
+    /// Point the storage at a region that holds a ring buffer of the given
+    /// capacity, after checking that it's large enough and aligned
+    fn new(ptr: *mut u8, len: usize, capacity: usize) -> Result<RawStorage<T>, RawError> {
+        let (layout, offset) = Self::layout(capacity).map_err(RawError::Capacity)?;
+        if len < layout.size() {
+            return Err(RawError::TooShort(layout.size()));
+        }
+        if !ptr.addr().is_multiple_of(layout.align()) {
+            return Err(RawError::Misaligned(layout.align()));
+        }
+        let region = NonNull::new(ptr).ok_or(RawError::Misaligned(layout.align()))?;
+
+        // SAFETY: the items are within the region, which the caller
+        // guarantees to be valid
+        let items = unsafe {
+            std::ptr::slice_from_raw_parts_mut(ptr.add(offset).cast::<Item<T>>(), capacity)
+        };
+
+        Ok(RawStorage {
+            region: region.cast(),
+            // SAFETY: the region isn't null, so neither are the items
+            items: unsafe { NonNull::new_unchecked(items) },
+            signals: Arc::new(Signals {
+                poll_interval: Some(RAW_POLL_INTERVAL),
+                ..Signals::new()
+            }),
+            _phantom: PhantomData,
+        })
+    }
+
+    fn raw_header(&self) -> &RawHeader {
+        // SAFETY: the region outlives the storage, see Writer::create_in_raw
+        unsafe { self.region.as_ref() }
+    }
+}
+
+impl<T> Clone for RawStorage<T> {
+    fn clone(&self) -> Self {
+        Self {
+            region: self.region,
+            items: self.items,
+            signals: Arc::clone(&self.signals),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> sealed::Sealed<T> for RawStorage<T> {
+    fn header(&self) -> &Header {
+        &self.raw_header().header
+    }
+
+    fn signals(&self) -> &Signals {
+        &self.signals
+    }
+
+    fn items(&self) -> &[Item<T>] {
+        // SAFETY: the region outlives the storage, see Writer::create_in_raw
+        unsafe { self.items.as_ref() }
+    }
+}
+
+impl<T> Storage<T> for RawStorage<T> {}
+
+impl<T: Copy> Writer<T, RawStorage<T>> {
+    /// Create a new ring buffer with the given capacity in the region of
+    /// `len` bytes at `ptr`, and return its writer. Readers can then be
+    /// attached to the same region with [Reader::attach_to_raw], including
+    /// from other processes that map the same memory, or cloned from each
+    /// other as usual. The region must be at least as large and aligned as
+    /// the layout from [RawStorage::layout]. Anything that the region held
+    /// before is overwritten.
+    ///
+    /// Readers that are blocked in [Reader::read_blocking] or
+    /// [Reader::read_timeout] check for new data every
+    /// [RAW_POLL_INTERVAL], since the writer may be in another process. The
+    /// writer doesn't wake up readers in any other way, so async reads,
+    /// [ReadSelect](crate::ReadSelect) and readiness notifications are not
+    /// supported for ring buffers in raw memory.
+    ///
+    /// # Safety
+    /// - The region must be valid for reads and writes of `len` bytes for
+    ///   as long as the writer or any reader of the ring buffer exists, in
+    ///   every process that uses it.
+    /// - The region must only ever be accessed through the writer and
+    ///   readers of the ring buffer until they are all gone, and there must
+    ///   be no other writer or reader of the region while this is called.
+    /// - Every process that attaches to the region must have been built from
+    ///   the same version of this crate with the same features, and read the
+    ///   same type `T`, which must be valid to share between processes, i.e.
+    ///   contain no pointers or references and have no invalid bit patterns.
+    pub unsafe fn create_in_raw(
+        ptr: *mut u8,
+        len: usize,
+        capacity: usize,
+    ) -> Result<Writer<T, RawStorage<T>>, RawError> {
+        let storage = RawStorage::new(ptr, len, capacity)?;
+        let region = storage.region.as_ptr();
+
+        // SAFETY: the region is large enough and aligned, and not in use by
+        // anyone else, as guaranteed by the caller
+        unsafe {
+            (&raw mut (*region).magic).write(AtomicU64::new(0));
+            (&raw mut (*region).capacity).write(capacity as u64);
+            (&raw mut (*region).item_size).write(std::mem::size_of::<Item<T>>() as u64);
+            (&raw mut (*region).item_align).write(std::mem::align_of::<Item<T>>() as u64);
+            (&raw mut (*region).header).write(Header::new());
+            let items = storage.items.as_ptr().cast::<Item<T>>();
+            for index in 0..capacity {
+                items.add(index).write(Item::new());
+            }
+        }
+
+        // Release pairs with the acquire in attach_to_raw, so that readers
+        // see everything that was just written
+        storage.raw_header().magic.store(MAGIC, Ordering::Release);
+
+        Ok(Writer::new(storage))
+    }
+}
+
+impl<T: Copy> Reader<T, RawStorage<T>> {
+    /// Attach a new reader to the ring buffer that [Writer::create_in_raw]
+    /// created in the region of `len` bytes at `ptr`, which may be a mapping
+    /// of the same memory in another process. The reader starts at the
+    /// front of the queue, so that it only receives what the writer writes
+    /// from now on.
+    ///
+    /// Returns an error if the region doesn't hold a ring buffer for items
+    /// of the same size and alignment as `T`, or if it already has the
+    /// maximum number of readers. Readers that were never dropped, e.g.
+    /// because their process was killed, still count toward the maximum.
+    ///
+    /// # Safety
+    /// The same requirements apply as for [Writer::create_in_raw], and the
+    /// region must hold a ring buffer that was created by it, or be zeroed.
+    pub unsafe fn attach_to_raw(
+        ptr: *mut u8,
+        len: usize,
+    ) -> Result<Reader<T, RawStorage<T>>, RawError> {
+        if len < std::mem::size_of::<RawHeader>() {
+            return Err(RawError::TooShort(std::mem::size_of::<RawHeader>()));
+        }
+        if !ptr.addr().is_multiple_of(std::mem::align_of::<RawHeader>()) {
+            return Err(RawError::Misaligned(std::mem::align_of::<RawHeader>()));
+        }
+
+        // SAFETY: the region is large enough and aligned, and valid as
+        // guaranteed by the caller
+        let raw_header = unsafe { &*ptr.cast::<RawHeader>() };
+        if raw_header.magic.load(Ordering::Acquire) != MAGIC
+            || raw_header.item_size != std::mem::size_of::<Item<T>>() as u64
+            || raw_header.item_align != std::mem::align_of::<Item<T>>() as u64
+        {
+            return Err(RawError::NotRingBuffer);
+        }
+        let capacity = usize::try_from(raw_header.capacity).map_err(|_| RawError::NotRingBuffer)?;
+
+        let storage = RawStorage::new(ptr, len, capacity)?;
+        storage
+            .raw_header()
+            .header
+            .add_reader()
+            .map_err(RawError::TooManyReaders)?;
+
+        let mut reader = Reader::counted(storage);
+        reader.seek_to_front();
+        Ok(reader)
+    }
+}
@@ -0,0 +1,207 @@
+//! Readiness notifications through file descriptors, so that readers can be
+//! integrated into poll-based event loops such as epoll or mio. Enabled by the
+//! `readiness` feature on Unix platforms.
+//!
+//! Each reader that asks for a file descriptor gets its own non-blocking pipe.
+//! The writer signals the pipe at most once after each time that the reader
+//! clears it, so the file descriptor stays readable for as long as new data
+//! may be available, without the writer having to signal on every write.
+
+use std::{
+    io,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    sync::{AtomicBool, AtomicUsize, Ordering},
+    Reader, Storage,
+};
+
+/// The readiness pipe of a single reader
+pub(crate) struct Readiness {
+    // Whether the reader has cleared the pipe and is waiting for the writer
+    // to signal it again
+    armed: AtomicBool,
+
+    read_fd: OwnedFd,
+    write_fd: OwnedFd,
+}
+
+impl Readiness {
+    fn new() -> io::Result<Readiness> {
+        let mut fds: [libc::c_int; 2] = [0; 2];
+
+        // SAFETY: fds has room for the two file descriptors
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // SAFETY: pipe() succeeded, so both file descriptors are open and owned by nobody else
+        let (read_fd, write_fd) =
+            unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+
+        for fd in [&read_fd, &write_fd] {
+            let fd = fd.as_raw_fd();
+            // SAFETY: fd is a valid file descriptor
+            unsafe {
+                let flags = libc::fcntl(fd, libc::F_GETFL);
+                if flags == -1
+                    || libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) == -1
+                    || libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) == -1
+                {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+        }
+
+        Ok(Readiness {
+            armed: AtomicBool::new(false),
+            read_fd,
+            write_fd,
+        })
+    }
+
+    /// Make the read end of the pipe readable
+    fn signal(&self) {
+        let byte = 1u8;
+        // SAFETY: the buffer is a single valid byte. The pipe can't be full
+        // because it's only ever signalled once before being drained again,
+        // and errors are ignored since there's nothing the writer can do.
+        unsafe {
+            libc::write(
+                self.write_fd.as_raw_fd(),
+                &byte as *const u8 as *const libc::c_void,
+                1,
+            );
+        }
+    }
+
+    /// Empty the pipe without blocking
+    fn drain(&self) {
+        let mut buffer = [0u8; 64];
+        loop {
+            // SAFETY: the buffer is valid for its entire length
+            let n = unsafe {
+                libc::read(
+                    self.read_fd.as_raw_fd(),
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    buffer.len(),
+                )
+            };
+            if n <= 0 {
+                break;
+            }
+        }
+    }
+
+    #[cfg(all(test, not(feature = "shuttle-tests")))]
+    pub(crate) fn pending_bytes(&self) -> usize {
+        let mut count: libc::c_int = 0;
+        // SAFETY: FIONREAD writes a single c_int
+        unsafe { libc::ioctl(self.read_fd.as_raw_fd(), libc::FIONREAD, &mut count) };
+        count as usize
+    }
+}
+
+/// The readiness pipes of all readers of a ring buffer
+pub(crate) struct ReadinessList {
+    // The number of registered pipes, readable without locking so that the
+    // writer can cheaply skip signalling when nobody is interested
+    count: AtomicUsize,
+
+    entries: Mutex<Vec<Arc<Readiness>>>,
+}
+
+impl ReadinessList {
+    pub(crate) fn new() -> ReadinessList {
+        ReadinessList {
+            count: AtomicUsize::new(0),
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Remove all pipes. Requires exclusive access.
+    pub(crate) fn reset(&mut self) {
+        self.count = AtomicUsize::new(0);
+        self.entries
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    pub(crate) fn add(&self, readiness: Arc<Readiness>) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.push(readiness);
+        self.count.store(entries.len(), Ordering::SeqCst);
+    }
+
+    pub(crate) fn remove(&self, readiness: &Arc<Readiness>) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|r| !Arc::ptr_eq(r, readiness));
+        self.count.store(entries.len(), Ordering::SeqCst);
+    }
+
+    /// Signal every pipe whose reader is waiting. When no pipes are
+    /// registered, this costs only a single atomic load.
+    #[inline]
+    pub(crate) fn signal_all(&self) {
+        // NOTE: as with the wait list, this load must be SeqCst and come after
+        // the writer has released the item it just wrote
+        if self.count.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        self.signal_all_slow();
+    }
+
+    #[cold]
+    fn signal_all_slow(&self) {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        for readiness in entries.iter() {
+            if readiness.armed.swap(false, Ordering::SeqCst) {
+                readiness.signal();
+            }
+        }
+    }
+}
+
+impl<T, S: Storage<T>> Reader<T, S> {
+    /// Get a file descriptor that becomes readable when new data may be
+    /// available to this reader, for use with `poll`, `epoll`, mio and the
+    /// like. The file descriptor is created on the first call and belongs
+    /// to this reader only, clones of the reader don't share it. It stays
+    /// valid until the reader is dropped.
+    ///
+    /// The file descriptor is readable right away after it is created. Once
+    /// it is reported readable, call [Reader::clear_readiness] and then read
+    /// until [ReadResult::Empty](crate::ReadResult::Empty) or
+    /// [ReadResult::Closed](crate::ReadResult::Closed) is returned. The
+    /// writer signals it again at most once after every time it's cleared.
+    pub fn as_raw_fd(&mut self) -> io::Result<RawFd> {
+        if let Some(readiness) = &self.readiness {
+            return Ok(readiness.read_fd.as_raw_fd());
+        }
+
+        let readiness = Arc::new(Readiness::new()?);
+
+        // Start out signalled, since there may already be data to read
+        readiness.signal();
+
+        self.storage.signals().readiness.add(Arc::clone(&readiness));
+        let fd = readiness.read_fd.as_raw_fd();
+        self.readiness = Some(readiness);
+        Ok(fd)
+    }
+
+    /// Clear the file descriptor returned by [Reader::as_raw_fd] so that it
+    /// is no longer readable, and let the writer signal it again on its next
+    /// write. This must be called before reading all available data, so that
+    /// no writes in between go unnoticed. Does nothing if there is no file
+    /// descriptor.
+    pub fn clear_readiness(&mut self) {
+        if let Some(readiness) = &self.readiness {
+            readiness.drain();
+            readiness.armed.store(true, Ordering::SeqCst);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Concurrent scenarios which are shared between the unit tests, which run
+//! them on real threads for many iterations, and the shuttle tests, which run
+//! them for only a few iterations but under many different schedules.
+
+use crate::{sync, ReadResult, Reader, Storage, Writer};
+
+/// Each value consists of two copies of the number of the write, so that a
+/// read which overlaps with a write shows up as two different halves
+pub(crate) type Pair = [u64; 2];
+
+/// Runs each closure on a thread of its own and returns once all of them
+/// have finished, panicking if any of them panicked. This is either a scope
+/// of real threads or of shuttle's.
+pub(crate) type Threads = for<'a> fn(Vec<Box<dyn FnOnce() + Send + 'a>>);
+
+/// One writer and two readers, which each check every item that they read
+pub(crate) fn two_readers<S: Storage<Pair>>(
+    reader1: Reader<Pair, S>,
+    writer: Writer<Pair, S>,
+    iterations: u64,
+    threads: Threads,
+) {
+    let reader2 = reader1.clone();
+    threads(vec![
+        Box::new(move || read_all(reader1, iterations, 0)),
+        Box::new(move || read_all(reader2, iterations, 0)),
+        Box::new(move || write_all(writer, iterations)),
+    ]);
+}
+
+/// One writer and one reader, which runs into dropouts constantly if the
+/// capacity is small
+pub(crate) fn one_reader<S: Storage<Pair>>(
+    reader: Reader<Pair, S>,
+    writer: Writer<Pair, S>,
+    iterations: u64,
+    threads: Threads,
+) {
+    threads(vec![
+        Box::new(move || read_all(reader, iterations, 0)),
+        Box::new(move || write_all(writer, iterations)),
+    ]);
+}
+
+/// One writer and one reader, which skips ahead after every few reads
+pub(crate) fn skip_ahead<S: Storage<Pair>>(
+    reader: Reader<Pair, S>,
+    writer: Writer<Pair, S>,
+    iterations: u64,
+    threads: Threads,
+) {
+    threads(vec![
+        Box::new(move || read_all(reader, iterations, 3)),
+        Box::new(move || write_all(writer, iterations)),
+    ]);
+}
+
+fn write_all<S: Storage<Pair>>(mut writer: Writer<Pair, S>, iterations: u64) {
+    for i in 0..iterations {
+        writer.write([i; 2]);
+    }
+}
+
+/// Read until the writer is closed, checking that nothing is torn or out of
+/// order and that the last item is received in the end. Skips ahead after
+/// every `skip_every` reads, unless that's zero.
+fn read_all<S: Storage<Pair>>(mut reader: Reader<Pair, S>, iterations: u64, skip_every: u64) {
+    let mut next = 0;
+    let mut reads = 0;
+    let mut skipped = false;
+    loop {
+        match reader.read() {
+            ReadResult::Ok([a, b]) => {
+                assert_eq!(a, b, "torn read");
+                assert_eq!(a, next);
+                next = a + 1;
+            }
+            ReadResult::Dropout([a, b]) => {
+                assert_eq!(a, b, "torn read");
+
+                // Skipping ahead while caught up reads the latest item again
+                let earliest = if skipped {
+                    next.saturating_sub(1)
+                } else {
+                    next + 1
+                };
+                assert!(a >= earliest, "read {} but expected {}", a, earliest);
+                next = a + 1;
+            }
+            ReadResult::Empty => {
+                sync::spin_loop();
+                continue;
+            }
+            ReadResult::Closed => break,
+        }
+
+        reads += 1;
+        skipped = skip_every != 0 && reads % skip_every == 0;
+        if skipped {
+            reader.skip_ahead();
+        }
+    }
+    assert_eq!(next, iterations);
+}
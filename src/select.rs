@@ -0,0 +1,146 @@
+//! Waiting on several readers at once, for consuming from multiple ring
+//! buffers in a single thread.
+
+use crate::{HeapStorage, ReadResult, Reader, Storage};
+
+/// A set of readers that can be waited on together, returning data from
+/// whichever of them has some. The readers are checked in turns, starting
+/// after the one that was returned last, so that a busy reader can't starve
+/// the others.
+///
+/// ```
+/// use spmcq::{ring_buffer, ReadResult, ReadSelect};
+///
+/// let (audio, mut audio_writer) = ring_buffer::<u32>(16);
+/// let (control, mut control_writer) = ring_buffer::<u32>(16);
+///
+/// let mut select = ReadSelect::new(vec![audio, control]);
+///
+/// control_writer.write(1);
+/// assert_eq!(select.select(), (1, ReadResult::Ok(1)));
+///
+/// audio_writer.write(2);
+/// control_writer.write(3);
+/// assert_eq!(select.select(), (0, ReadResult::Ok(2)));
+/// assert_eq!(select.select(), (1, ReadResult::Ok(3)));
+/// ```
+pub struct ReadSelect<T, S: Storage<T> = HeapStorage<T>> {
+    readers: Vec<Reader<T, S>>,
+
+    // The index of the reader to check first
+    next: usize,
+}
+
+impl<T, S: Storage<T>> ReadSelect<T, S> {
+    /// Create a set of readers, which are identified by their index in
+    /// `readers` when selecting
+    pub fn new(readers: Vec<Reader<T, S>>) -> ReadSelect<T, S> {
+        ReadSelect { readers, next: 0 }
+    }
+
+    /// Add another reader and return its index
+    pub fn push(&mut self, reader: Reader<T, S>) -> usize {
+        self.readers.push(reader);
+        self.readers.len() - 1
+    }
+
+    /// Remove the reader at the given index and return it. The readers after
+    /// it move down by one index, as with [Vec::remove].
+    ///
+    /// # Panics
+    /// Panics if the index is out of bounds.
+    pub fn remove(&mut self, index: usize) -> Reader<T, S> {
+        let reader = self.readers.remove(index);
+        if self.next > index {
+            self.next -= 1;
+        }
+        reader
+    }
+
+    /// Returns a mutable reference to the reader at the given index, if
+    /// there is one
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Reader<T, S>> {
+        self.readers.get_mut(index)
+    }
+
+    /// The number of readers in the set
+    pub fn len(&self) -> usize {
+        self.readers.len()
+    }
+
+    /// Returns whether the set has no readers
+    pub fn is_empty(&self) -> bool {
+        self.readers.is_empty()
+    }
+
+    /// Return all readers in order of their index
+    pub fn into_inner(self) -> Vec<Reader<T, S>> {
+        self.readers
+    }
+
+    fn register(&self) {
+        for reader in &self.readers {
+            reader.storage.signals().waiters.register();
+        }
+    }
+
+    fn unregister(&self) {
+        for reader in &self.readers {
+            reader.storage.signals().waiters.unregister();
+        }
+    }
+}
+
+impl<T, S: Storage<T>> ReadSelect<T, S>
+where
+    T: Copy,
+{
+    /// Receive the next item from whichever reader has one, without
+    /// blocking. Returns the index of the reader together with the result of
+    /// reading from it, which is never [ReadResult::Empty], or None if all
+    /// readers are empty.
+    pub fn try_select(&mut self) -> Option<(usize, ReadResult<T>)> {
+        let len = self.readers.len();
+        for offset in 0..len {
+            let index = (self.next + offset) % len;
+            let result = self.readers[index].read();
+            if !result.is_empty() {
+                self.next = (index + 1) % len;
+                return Some((index, result));
+            }
+        }
+        None
+    }
+
+    /// Receive the next item from whichever reader has one, blocking the
+    /// current thread until any of them does. Returns the index of the
+    /// reader together with the result of reading from it, with the same
+    /// meaning as in [Reader::read_blocking].
+    ///
+    /// A reader whose writer is gone keeps returning [ReadResult::Closed]
+    /// whenever it's its turn, without blocking. Remove such readers with
+    /// [ReadSelect::remove] to keep waiting on the others.
+    ///
+    /// # Panics
+    /// Panics if the set has no readers, since nothing could ever arrive.
+    pub fn select(&mut self) -> (usize, ReadResult<T>) {
+        assert!(!self.readers.is_empty(), "ReadSelect has no readers");
+
+        if let Some(selected) = self.try_select() {
+            return selected;
+        }
+
+        loop {
+            self.register();
+
+            // Check again now that every writer is guaranteed to see this
+            // thread, see Reader::read_blocking
+            if let Some(selected) = self.try_select() {
+                self.unregister();
+                return selected;
+            }
+
+            std::thread::park();
+        }
+    }
+}
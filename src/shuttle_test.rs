@@ -0,0 +1,76 @@
+//! Runs the scenarios of the concurrent unit tests under shuttle, which picks
+//! a different interleaving of the threads on every schedule. Random
+//! schedules cover a broad range, while PCT favours the rare few preemptions
+//! at just the wrong moment that an operating system hardly ever produces.
+//! Run them with
+//!
+//! ```text
+//! cargo test --release --features shuttle-tests
+//! ```
+
+use shuttle::{check_pct, check_random};
+
+use crate::ring_buffer;
+use crate::scenario::{self, Pair};
+
+/// The number of schedules to try for each scenario and scheduler
+const SCHEDULES: usize = 10_000;
+
+/// The number of priority changes per schedule for PCT. Most of the steps of
+/// a schedule are readers polling an empty ring buffer, so it takes quite a
+/// few changes for one to land while the writer holds an item.
+const PCT_DEPTH: usize = 20;
+
+/// Runs the threads of a [scenario] under shuttle
+fn shuttle_threads(tasks: Vec<Box<dyn FnOnce() + Send + '_>>) {
+    shuttle::thread::scope(|s| {
+        for task in tasks {
+            s.spawn(task);
+        }
+    });
+}
+
+fn two_readers() {
+    let (reader, writer) = ring_buffer::<Pair>(4);
+    scenario::two_readers(reader, writer, 12, shuttle_threads);
+}
+
+fn skip_ahead() {
+    let (reader, writer) = ring_buffer::<Pair>(2);
+    scenario::skip_ahead(reader, writer, 12, shuttle_threads);
+}
+
+fn capacity_one() {
+    let (reader, writer) = ring_buffer::<Pair>(1);
+    scenario::one_reader(reader, writer, 12, shuttle_threads);
+}
+
+#[test]
+fn test_two_readers_random() {
+    check_random(two_readers, SCHEDULES);
+}
+
+#[test]
+fn test_two_readers_pct() {
+    check_pct(two_readers, SCHEDULES, PCT_DEPTH);
+}
+
+#[test]
+fn test_skip_ahead_random() {
+    check_random(skip_ahead, SCHEDULES);
+}
+
+#[test]
+fn test_skip_ahead_pct() {
+    check_pct(skip_ahead, SCHEDULES, PCT_DEPTH);
+}
+
+#[test]
+fn test_capacity_one_random() {
+    check_random(capacity_one, SCHEDULES);
+}
+
+#[test]
+fn test_capacity_one_pct() {
+    check_pct(capacity_one, SCHEDULES, PCT_DEPTH);
+}
@@ -0,0 +1,968 @@
+//! The different kinds of memory that a ring buffer's items can live in.
+//! [Reader] and [Writer] are generic over their storage so that the same
+//! read and write algorithms serve the default heap-allocated ring buffer
+//! created by [ring_buffer](crate::ring_buffer), the [BoxedStorage] of
+//! [ring_buffer_in](crate::ring_buffer_in), whose items live in a buffer that
+//! the caller allocated, and the inline [StaticRingBuffer], which never
+//! allocates.
+
+use std::{
+    alloc::Layout,
+    mem::MaybeUninit,
+    ptr::NonNull,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    progress::ProgressList,
+    sync::{spin_loop, AtomicBool, AtomicU64, AtomicUsize, Ordering, UnsafeCell, MODELED},
+    wait::WaitList,
+    CapacityError, Reader, SpinPolicy, TooManyReaders, Writer,
+};
+
+/// The largest number of readers that the use count of an [Item] can keep
+/// track of at once
+pub(crate) const READER_LIMIT: usize = i32::MAX as usize;
+
+/// The bits of the truncated lap of an [Item], see Indexing::lap_token. The
+/// remaining top bit of the lap's half of the state word is taken by
+/// [WRITER_WAITING].
+const LAP_MASK: u32 = u32::MAX >> 1;
+
+/// The truncated lap of an [Item] that has never been written to, which is
+/// the imaginary lap before the writer's first one, see Indexing::lap_token
+pub(crate) const UNWRITTEN_LAP: u32 = LAP_MASK;
+
+/// The sequence number of an [Item] that has never been written to. Real
+/// sequence numbers never get this far.
+pub(crate) const UNWRITTEN_SEQUENCE: u64 = u64::MAX;
+
+/// Set in the state of an [Item] while the writer is waiting for readers to
+/// release it. New readers hold off until the writer has gotten through, so
+/// that readers which keep coming back to the same item can't starve it.
+pub(crate) const WRITER_WAITING: u64 = 1 << 63;
+
+/// How many times a write with a timeout spins between reading the clock,
+/// which costs a lot more than checking the item once
+const DEADLINE_CHECK_INTERVAL: u64 = 64;
+
+/// One slot of a ring buffer, holding a value along with what readers and
+/// the writer need to share it. Items can't be created or accessed directly,
+/// only allocated for [ring_buffer_in](crate::ring_buffer_in), see
+/// [Item::layout].
+// With the `cache-padded` feature, each item takes up at least a whole cache
+// line, so that the writer locking one item doesn't contend with readers of
+// its neighbours
+#[cfg_attr(feature = "cache-padded", repr(align(64)))]
+pub struct Item<T> {
+    // The use count and the lap of the item, packed into one word so that readers can tell
+    // without locking the item whether it holds anything new for them.
+    //
+    // The low 32 bits hold the use count by either readers or the writer, used for busy
+    // waiting and synchronization and guarding access to data and sequence
+    //    0     -> not in use
+    // positive -> in use by that many readers
+    //   -1     -> in use by writer
+    //
+    // The next 31 bits hold the lap that the data is from, truncated, see Indexing::lap_token.
+    // The writer only changes it when releasing the item, so that it never describes data
+    // that readers can't have yet.
+    //
+    // The top bit is WRITER_WAITING, which only the writer sets and clears.
+    //
+    // The lap is stored inverted, so that an item whose bytes are all zero has never been
+    // written to, see HeapStorage::new.
+    pub(crate) state: AtomicU64,
+
+    // The sequence number of the data stored here, which is the number of items that the
+    // writer had written before it. Used to detect dropouts. Inverted like the lap, see
+    // Item::sequence.
+    sequence: UnsafeCell<u64>,
+
+    // the actual data being stored, which is only initialized once the sequence number is
+    // no longer UNWRITTEN_SEQUENCE
+    pub(crate) data: UnsafeCell<MaybeUninit<T>>,
+}
+
+// SAFETY: all access to the data and sequence number is guarded by the use count,
+// which allows either many readers copying the data out or a single writer.
+unsafe impl<T: Send> Sync for Item<T> {}
+
+impl<T> Item<T> {
+    /// The memory layout of a buffer of the given number of items, for
+    /// allocating one to pass to [ring_buffer_in](crate::ring_buffer_in).
+    /// Returns an error for the same capacities that
+    /// [try_ring_buffer](crate::try_ring_buffer) does.
+    ///
+    /// ```
+    /// use std::mem::MaybeUninit;
+    /// use spmcq::Item;
+    ///
+    /// let layout = Item::<u64>::layout(256).unwrap();
+    ///
+    /// // SAFETY: a Box may own memory from the global allocator with the
+    /// // layout of its contents
+    /// let items: Box<[MaybeUninit<Item<u64>>]> = unsafe {
+    ///     let ptr = std::alloc::alloc(layout).cast::<MaybeUninit<Item<u64>>>();
+    ///     assert!(!ptr.is_null());
+    ///     Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, 256))
+    /// };
+    /// assert_eq!(items.len(), 256);
+    /// ```
+    pub fn layout(capacity: usize) -> Result<Layout, CapacityError> {
+        if capacity == 0 {
+            return Err(CapacityError::TooSmall(capacity));
+        }
+        Layout::array::<Item<T>>(capacity).map_err(|_| CapacityError::TooLarge(capacity))
+    }
+
+    /// Create an item which has never been written to. Its bytes are all
+    /// zero, apart from the atomics of loom and shuttle.
+    pub(crate) fn new() -> Item<T> {
+        Item {
+            state: AtomicU64::new(Self::pack(UNWRITTEN_LAP, 0)),
+            data: UnsafeCell::new(MaybeUninit::uninit()),
+            sequence: UnsafeCell::new(!UNWRITTEN_SEQUENCE),
+        }
+    }
+
+    fn pack(lap: u32, use_count: i32) -> u64 {
+        debug_assert!(lap <= LAP_MASK, "Lap out of range");
+        (u64::from(lap ^ LAP_MASK) << 32) | u64::from(use_count as u32)
+    }
+
+    fn lap_of(state: u64) -> u32 {
+        ((state >> 32) as u32 & LAP_MASK) ^ LAP_MASK
+    }
+
+    /// Returns the sequence number of the data that the item holds, or
+    /// [UNWRITTEN_SEQUENCE]
+    ///
+    /// # Safety
+    /// The item must be locked, or otherwise not be written concurrently.
+    pub(crate) unsafe fn sequence(&self) -> u64 {
+        // SAFETY: guaranteed by the caller
+        !self.sequence.with(|sequence| unsafe { *sequence })
+    }
+
+    /// Set the sequence number of the data that the item holds
+    ///
+    /// # Safety
+    /// The item must be locked for writing, or otherwise not be accessed
+    /// concurrently.
+    pub(crate) unsafe fn set_sequence(&self, sequence: u64) {
+        // SAFETY: guaranteed by the caller
+        self.sequence
+            .with_mut(|stamped| unsafe { *stamped = !sequence });
+    }
+
+    fn use_count_of(state: u64) -> i32 {
+        state as u32 as i32
+    }
+
+    /// Returns the truncated lap of the data that the item currently holds,
+    /// without locking it, see Indexing::lap_token. The writer may already be
+    /// busy overwriting it.
+    pub(crate) fn lap(&self) -> u32 {
+        // This stands in for locking the item when a reader finds nothing new,
+        // so it must be SeqCst like acquire_read, see WaitList::wake_all
+        Self::lap_of(self.state.load(Ordering::SeqCst))
+    }
+
+    /// Returns whether the writer currently holds the item
+    pub(crate) fn is_write_locked(&self) -> bool {
+        Self::use_count_of(self.state.load(Ordering::Relaxed)) < 0
+    }
+
+    /// Lock the item for reading, alongside any other readers. Waits while
+    /// the writer is busy with the item or waiting for it, as the policy
+    /// says. Returns the number of times that it had to check the item
+    /// again.
+    pub(crate) fn acquire_read(&self, policy: &SpinPolicy) -> u64 {
+        // try to increment the use count, spin until the old use count was definitely positive.
+        // Acquire would be enough to see the data and sequence number written before
+        // release_write, but a reader that blocks relies on this being SeqCst, see
+        // WaitList::wake_all. A failed attempt reads nothing that needs to be visible, hence
+        // Relaxed. The expected state never has WRITER_WAITING set, so that new readers can't
+        // get in while the writer waits for the current ones to leave.
+        let mut expected = Self::pack(Self::lap_of(self.state.load(Ordering::Relaxed)), 0);
+        let mut spins = 0;
+        while let Err(actual) =
+            self.state
+                .compare_exchange(expected, expected + 1, Ordering::SeqCst, Ordering::Relaxed)
+        {
+            let actual_use_count = Self::use_count_of(actual);
+            debug_assert!(actual_use_count >= -1, "Invalid use count");
+            debug_assert!(actual_use_count < i32::MAX, "Reader overflow");
+            expected = Self::pack(Self::lap_of(actual), actual_use_count.max(0));
+
+            // Only back off while the writer holds or waits for the item.
+            // Other readers only ever hold it for a moment, and merely
+            // changed the count.
+            if actual_use_count < 0 || actual & WRITER_WAITING != 0 {
+                policy.backoff(spins);
+                spins += 1;
+            } else {
+                spin_loop();
+            }
+        }
+        spins
+    }
+
+    /// Try to lock the item for reading like [Item::acquire_read], but with
+    /// a single attempt. Returns false instead of waiting if the writer is
+    /// busy with the item or waiting for it, or if another reader changed
+    /// the use count at the same moment.
+    pub(crate) fn try_acquire_read(&self) -> bool {
+        // See acquire_read for the orderings
+        let state = self.state.load(Ordering::Relaxed);
+        let use_count = Self::use_count_of(state);
+        debug_assert!(use_count < i32::MAX, "Reader overflow");
+        if use_count < 0 || state & WRITER_WAITING != 0 {
+            return false;
+        }
+        self.state
+            .compare_exchange(state, state + 1, Ordering::SeqCst, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    /// Lock the item for reading like [Item::acquire_read], and return a
+    /// guard that releases the lock when dropped, along with the number of
+    /// times that it had to check the item again
+    pub(crate) fn lock_read(&self, policy: &SpinPolicy) -> (ReadLock<'_, T>, u64) {
+        let spins = self.acquire_read(policy);
+        (ReadLock { item: self }, spins)
+    }
+
+    /// Lock the item for reading like [Item::try_acquire_read], and return a
+    /// guard that releases the lock when dropped
+    pub(crate) fn try_lock_read(&self) -> Option<ReadLock<'_, T>> {
+        self.try_acquire_read().then(|| ReadLock { item: self })
+    }
+
+    /// Release a read lock acquired by [Item::acquire_read] or
+    /// [Item::try_acquire_read]
+    pub(crate) fn release_read(&self) {
+        // Release pairs with the acquire in acquire_write, so that the copy made by the reader
+        // is complete before the writer can modify the item again. The use count is positive,
+        // so this never borrows from the lap.
+        let previous = self.state.fetch_sub(1, Ordering::Release);
+        debug_assert!(Self::use_count_of(previous) >= 0);
+    }
+
+    /// Lock the item for writing, waiting while any readers are busy with
+    /// it in the same way as [Item::acquire_read]. Readers that come along
+    /// in the meantime wait for the writer instead. Returns the number of
+    /// times that it had to check the item again.
+    pub(crate) fn acquire_write(&self, policy: &SpinPolicy) -> u64 {
+        match self.acquire_write_within(policy, None) {
+            Some(spins) => spins,
+            None => unreachable!("gave up waiting without a timeout"),
+        }
+    }
+
+    /// Lock the item for writing like [Item::acquire_write], but give up
+    /// once readers have held it for longer than the timeout, if there is
+    /// one. Returns the number of times that it had to check the item
+    /// again, or None if it gave up.
+    pub(crate) fn acquire_write_within(
+        &self,
+        policy: &SpinPolicy,
+        timeout: Option<Duration>,
+    ) -> Option<u64> {
+        // spin until use count is zero, write -1. Acquire pairs with the release in
+        // release_read, see there. Only the writer changes the lap, so it stays the same.
+        let lap = Self::lap_of(self.state.load(Ordering::Relaxed));
+        let mut expected = Self::pack(lap, 0);
+        let mut spins = 0;
+        let mut deadline = None;
+        while let Err(actual) = self.state.compare_exchange(
+            expected,
+            Self::pack(lap, -1),
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        ) {
+            debug_assert!(Self::use_count_of(actual) > 0, "Invalid use count");
+
+            // Keep new readers out, so that the use count drains to zero even
+            // if readers keep coming back to this item. Taking the lock above
+            // clears the flag again. Nothing is published by setting it, hence
+            // Relaxed.
+            if expected & WRITER_WAITING == 0 {
+                self.state.fetch_or(WRITER_WAITING, Ordering::Relaxed);
+                expected |= WRITER_WAITING;
+            }
+
+            // The deadline is only computed once the writer has to wait,
+            // and the clock read only every so often while spinning. Once
+            // the writer yields or sleeps, that costs more than the clock.
+            // If the deadline can't be represented, it's effectively never
+            // reached.
+            if let Some(timeout) = timeout {
+                let deadline = *deadline.get_or_insert_with(|| Instant::now().checked_add(timeout));
+                let check = spins % DEADLINE_CHECK_INTERVAL == 0 || !policy.is_spinning(spins);
+                if check && deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    // Let new readers in again, since the writer is leaving
+                    self.state.fetch_and(!WRITER_WAITING, Ordering::Relaxed);
+                    return None;
+                }
+            }
+
+            policy.backoff(spins);
+            spins += 1;
+        }
+        Some(spins)
+    }
+
+    /// Try to lock the item for writing like [Item::acquire_write], but with
+    /// a single attempt. Returns false instead of waiting if any readers are
+    /// busy with the item. Unlike acquire_write, this doesn't keep new
+    /// readers out, since the writer isn't going to wait for them.
+    pub(crate) fn try_acquire_write(&self) -> bool {
+        // See acquire_write for the orderings
+        let lap = Self::lap_of(self.state.load(Ordering::Relaxed));
+        self.state
+            .compare_exchange(
+                Self::pack(lap, 0),
+                Self::pack(lap, -1),
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+    }
+
+    /// Release a write lock acquired by [Item::acquire_write] or
+    /// [Item::try_acquire_write], and record the lap of the data that the
+    /// item now holds, see Indexing::lap_token
+    pub(crate) fn release_write(&self, lap: u32) {
+        // As with acquire_read, Release would be enough for readers to see the new data, but
+        // waking blocked readers relies on this being SeqCst.
+        let previous = self.state.swap(Self::pack(lap, 0), Ordering::SeqCst);
+
+        // The use count must still be -1, nothing should have modified it during writing.
+        assert_eq!(Self::use_count_of(previous), -1, "Invalid use count");
+        debug_assert_eq!(previous & WRITER_WAITING, 0);
+    }
+
+    /// Mark the item as never having been written to, dropping any data
+    /// that it holds. Requires exclusive access, so that no locking is
+    /// needed.
+    pub(crate) fn reset(&mut self) {
+        self.drop_data();
+        self.state = AtomicU64::new(Self::pack(UNWRITTEN_LAP, 0));
+        self.sequence = UnsafeCell::new(!UNWRITTEN_SEQUENCE);
+    }
+
+    /// Drop the data that the item holds, if it was ever written to.
+    /// Requires exclusive access.
+    fn drop_data(&mut self) {
+        // SAFETY: nothing else accesses the item, and the data is initialized
+        // once it has a sequence number
+        unsafe {
+            if std::mem::needs_drop::<T>() && self.sequence() != UNWRITTEN_SEQUENCE {
+                self.data.with_mut(|data| (*data).assume_init_drop());
+            }
+        }
+    }
+}
+
+impl<T> Drop for Item<T> {
+    fn drop(&mut self) {
+        self.drop_data();
+    }
+}
+
+/// A read lock on an [Item], which is released when dropped. Copying a value
+/// out can't panic, but code that runs while the lock is held otherwise
+/// might, and unwinding past a lock that was never released would keep the
+/// writer waiting on the item forever.
+pub(crate) struct ReadLock<'a, T> {
+    item: &'a Item<T>,
+}
+
+impl<T> ReadLock<'_, T> {
+    /// Call `f` with the data that the item holds, unless it was never
+    /// written to
+    pub(crate) fn with_data<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        if self.sequence() == UNWRITTEN_SEQUENCE {
+            return None;
+        }
+
+        // SAFETY: the read lock keeps the writer from modifying the data for as long as the
+        // reference lives. Mutation is not safe because there could be multiple readers.
+        // The data is initialized, since the item was written to.
+        Some(
+            self.item
+                .data
+                .with(|data| f(unsafe { (*data).assume_init_ref() })),
+        )
+    }
+
+    /// The sequence number of the data that the item holds, or
+    /// [UNWRITTEN_SEQUENCE]
+    pub(crate) fn sequence(&self) -> u64 {
+        // SAFETY: the read lock keeps the writer away
+        unsafe { self.item.sequence() }
+    }
+}
+
+impl<T> Drop for ReadLock<'_, T> {
+    fn drop(&mut self) {
+        self.item.release_read();
+    }
+}
+
+/// How the sequence numbers of a ring buffer map to the indices of its
+/// items. This is chosen once from the capacity, so that power-of-two
+/// capacities wrap around with a mask and a shift instead of a division or
+/// a compare-and-reset branch.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Indexing {
+    /// The capacity is `1 << shift`
+    PowerOfTwo { shift: u32 },
+
+    /// Any other capacity
+    Modulo { capacity: usize },
+}
+
+impl Indexing {
+    pub(crate) fn new(capacity: usize) -> Indexing {
+        debug_assert!(capacity > 0);
+        if capacity.is_power_of_two() {
+            Indexing::PowerOfTwo {
+                shift: capacity.trailing_zeros(),
+            }
+        } else {
+            Indexing::Modulo { capacity }
+        }
+    }
+
+    /// Returns the index of the item holding the given sequence number
+    #[inline]
+    pub(crate) fn index_of(self, sequence: u64) -> usize {
+        match self {
+            Indexing::PowerOfTwo { shift } => (sequence & ((1 << shift) - 1)) as usize,
+            Indexing::Modulo { capacity } => (sequence % capacity as u64) as usize,
+        }
+    }
+
+    /// Returns how many times the writer had wrapped around when it wrote
+    /// the given sequence number
+    #[inline]
+    pub(crate) fn lap_of(self, sequence: u64) -> u64 {
+        match self {
+            Indexing::PowerOfTwo { shift } => sequence >> shift,
+            Indexing::Modulo { capacity } => sequence / capacity as u64,
+        }
+    }
+
+    pub(crate) fn capacity(self) -> usize {
+        match self {
+            Indexing::PowerOfTwo { shift } => 1 << shift,
+            Indexing::Modulo { capacity } => capacity,
+        }
+    }
+
+    /// Returns the lap of the given sequence number, truncated to 31 bits to
+    /// fit alongside the use count of an [Item]. Sequence numbers from the
+    /// lap before the first, such as [UNWRITTEN_SEQUENCE], wrap around to
+    /// [UNWRITTEN_LAP].
+    #[inline]
+    pub(crate) fn lap_token(self, sequence: u64) -> u32 {
+        let next_lap = self.lap_of(sequence.wrapping_add(self.capacity() as u64));
+        (next_lap as u32).wrapping_sub(1) & LAP_MASK
+    }
+
+    /// Returns the sequence number written one lap before the given one to
+    /// the same item, or [UNWRITTEN_SEQUENCE] if there was none
+    #[inline]
+    pub(crate) fn previous_lap(self, sequence: u64) -> u64 {
+        sequence
+            .checked_sub(self.capacity() as u64)
+            .unwrap_or(UNWRITTEN_SEQUENCE)
+    }
+
+    /// Returns the index after the given one, wrapping around at the end
+    #[inline]
+    pub(crate) fn next(self, index: usize) -> usize {
+        match self {
+            Indexing::PowerOfTwo { shift } => (index + 1) & ((1 << shift) - 1),
+            Indexing::Modulo { capacity } => {
+                if index + 1 == capacity {
+                    0
+                } else {
+                    index + 1
+                }
+            }
+        }
+    }
+}
+
+// The shared state of a ring buffer other than its items. This and [Signals]
+// are only public so that they can appear in the sealed [Storage] trait. It
+// holds nothing but atomics so that it can be shared between processes, see
+// RawStorage.
+#[repr(C)]
+pub struct Header {
+    // The sequence number that the writer is going to write next, which is
+    // also the number of items written so far
+    pub(crate) write_sequence: AtomicU64,
+
+    // Whether the writer has been closed or dropped
+    pub(crate) closed: AtomicBool,
+
+    // The number of readers that currently exist
+    pub(crate) reader_count: AtomicUsize,
+
+    // The number of readers that may exist at once
+    pub(crate) max_readers: AtomicUsize,
+
+    // The id of the next reader to be created, see ReaderId
+    pub(crate) next_reader_id: AtomicU64,
+
+    // The number of dropouts that all readers together have read, see
+    // Writer::observed_dropouts
+    pub(crate) dropouts: AtomicU64,
+
+    // What the writer does about items that a reader hasn't read yet, as an
+    // OverwritePolicy, see ring_buffer_with_overwrite_policy
+    pub(crate) overwrite_policy: AtomicUsize,
+
+    // The sequence number that the writer was going to write next when it
+    // was last cleared, before which readers don't read anything, see
+    // Writer::clear
+    pub(crate) cleared_at: AtomicU64,
+}
+
+impl Header {
+    pub(crate) fn new() -> Header {
+        Header {
+            write_sequence: AtomicU64::new(0),
+            closed: AtomicBool::new(false),
+            reader_count: AtomicUsize::new(0),
+            max_readers: AtomicUsize::new(crate::DEFAULT_MAX_READERS),
+            next_reader_id: AtomicU64::new(0),
+            dropouts: AtomicU64::new(0),
+            overwrite_policy: AtomicUsize::new(0),
+            cleared_at: AtomicU64::new(0),
+        }
+    }
+
+    /// Return to the initial state. Requires exclusive access.
+    pub(crate) fn reset(&mut self) {
+        self.write_sequence = AtomicU64::new(0);
+        self.closed = AtomicBool::new(false);
+        self.reader_count = AtomicUsize::new(0);
+        self.max_readers = AtomicUsize::new(crate::DEFAULT_MAX_READERS);
+        self.next_reader_id = AtomicU64::new(0);
+        self.dropouts = AtomicU64::new(0);
+        self.overwrite_policy = AtomicUsize::new(0);
+        self.cleared_at = AtomicU64::new(0);
+    }
+
+    /// Count one more reader, unless the maximum number of readers exist already
+    pub(crate) fn add_reader(&self) -> Result<(), TooManyReaders> {
+        let max_readers = self.max_readers.load(Ordering::SeqCst);
+        let mut count = self.reader_count.load(Ordering::SeqCst);
+        loop {
+            if count >= max_readers {
+                return Err(TooManyReaders(max_readers));
+            }
+            match self.reader_count.compare_exchange_weak(
+                count,
+                count + 1,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return Ok(()),
+                Err(actual_count) => count = actual_count,
+            }
+        }
+    }
+
+    /// Take the id for a newly created reader
+    pub(crate) fn take_reader_id(&self) -> u64 {
+        self.next_reader_id.fetch_add(1, Ordering::SeqCst)
+    }
+
+    /// Count one less reader
+    pub(crate) fn remove_reader(&self) {
+        let old_count = self.reader_count.fetch_sub(1, Ordering::SeqCst);
+        debug_assert!(old_count > 0);
+    }
+}
+
+// How the writer of a ring buffer wakes up the readers that wait for it, and
+// how it finds out how far they have read, which unlike the Header only works
+// within a single process
+pub struct Signals {
+    // Readers that are blocked waiting for the writer
+    pub(crate) waiters: WaitList,
+
+    // How often blocked readers check for new data by themselves, for
+    // writers that can't wake them up, see RawStorage
+    pub(crate) poll_interval: Option<Duration>,
+
+    // Readers that want to be signalled through a file descriptor
+    #[cfg(all(unix, feature = "readiness"))]
+    pub(crate) readiness: crate::readiness::ReadinessList,
+
+    // How far each reader has read, see Writer::slowest_reader_lag
+    pub(crate) progress: ProgressList,
+}
+
+impl Signals {
+    pub(crate) fn new() -> Signals {
+        Signals {
+            waiters: WaitList::new(),
+            poll_interval: None,
+            #[cfg(all(unix, feature = "readiness"))]
+            readiness: crate::readiness::ReadinessList::new(),
+            progress: ProgressList::new(),
+        }
+    }
+
+    /// Forget about all waiting readers. Requires exclusive access.
+    pub(crate) fn reset(&mut self) {
+        self.waiters.reset();
+        #[cfg(all(unix, feature = "readiness"))]
+        self.readiness.reset();
+        self.progress.reset();
+    }
+}
+
+pub(crate) mod sealed {
+    use super::{Header, Item, Signals};
+
+    pub trait Sealed<T> {
+        fn header(&self) -> &Header;
+
+        fn signals(&self) -> &Signals;
+
+        fn items(&self) -> &[Item<T>];
+    }
+}
+
+/// The shared memory behind a [Reader] and [Writer]. This trait is sealed
+/// and is only implemented by [HeapStorage], [BoxedStorage] and references
+/// to [StaticRingBuffer].
+pub trait Storage<T>: sealed::Sealed<T> + Clone {}
+
+/// The default storage, where the items are kept in a reference-counted
+/// heap allocation that is freed once the writer and all readers are gone.
+pub struct HeapStorage<T> {
+    inner: NonNull<HeapInner<[Item<T>]>>,
+}
+
+// SAFETY: the storage is shared like an Arc<HeapInner<[Item<T>]>>, which is
+// Send and Sync as long as the items are
+unsafe impl<T: Send> Send for HeapStorage<T> {}
+unsafe impl<T: Send> Sync for HeapStorage<T> {}
+
+// The header and the items of a HeapStorage, which share a single allocation
+// so that reaching either takes only one pointer. This is reference-counted
+// by hand rather than with Arc, which can only take ownership of a value by
+// moving it into an allocation of its own, and so would touch every item.
+#[repr(C)]
+struct HeapInner<I: ?Sized> {
+    // The number of HeapStorage instances pointing here
+    references: AtomicUsize,
+
+    // Where the allocation starts, which is before the HeapInner itself if
+    // the allocator didn't align it already, see HeapLayout
+    allocation: *mut u8,
+
+    header: Header,
+    signals: Signals,
+    items: I,
+}
+
+// Where everything goes in the allocation of a HeapStorage. The allocation
+// asks for no alignment and has room to align the HeapInner by hand, because
+// std zeroes memory with an alignment larger than malloc's by writing to all
+// of it, while calloc can hand out pages that the OS already zeroed.
+struct HeapLayout {
+    allocation: Layout,
+    inner_align: usize,
+    header: usize,
+    signals: usize,
+    items: usize,
+}
+
+impl<T> HeapStorage<T> {
+    /// The memory layout of the storage for the given number of items, or
+    /// None if the allocation would be too large
+    fn layout(capacity: usize) -> Option<HeapLayout> {
+        let items = Layout::array::<Item<T>>(capacity).ok()?;
+        let (layout, _) = Layout::new::<AtomicUsize>()
+            .extend(Layout::new::<*mut u8>())
+            .ok()?;
+        let (layout, header) = layout.extend(Layout::new::<Header>()).ok()?;
+        let (layout, signals) = layout.extend(Layout::new::<Signals>()).ok()?;
+        let (layout, offset) = layout.extend(items).ok()?;
+        let inner = layout.pad_to_align();
+
+        let size = inner.size().checked_add(inner.align() - 1)?;
+        Some(HeapLayout {
+            allocation: Layout::from_size_align(size, 1).ok()?,
+            inner_align: inner.align(),
+            header,
+            signals,
+            items: offset,
+        })
+    }
+
+    /// Returns whether storage for the given number of items can be
+    /// allocated at all
+    pub(crate) fn fits(capacity: usize) -> bool {
+        Self::layout(capacity).is_some()
+    }
+
+    /// Allocate the storage for the given number of items, which must be
+    /// nonzero. The items are left zeroed, which is how [Item::new] starts
+    /// out, so that the memory isn't touched until the writer gets to it,
+    /// however large the items are.
+    ///
+    /// # Panics
+    /// Panics unless [HeapStorage::fits] the capacity.
+    pub(crate) fn new(capacity: usize) -> HeapStorage<T> {
+        let layout = Self::layout(capacity).expect("ring buffer is too large");
+
+        // SAFETY: the offsets are those of HeapInner<[Item<T>]> with the
+        // given number of items, since HeapInner is repr(C), and the
+        // allocation leaves room to align it. Every field is initialized
+        // before the storage takes ownership, the items by zeroing them
+        // except for the atomics of loom and shuttle, which keep state of
+        // their own.
+        unsafe {
+            let allocation = std::alloc::alloc_zeroed(layout.allocation);
+            if allocation.is_null() {
+                std::alloc::handle_alloc_error(layout.allocation);
+            }
+            let address = allocation.addr();
+            let ptr = allocation.add(address.next_multiple_of(layout.inner_align) - address);
+
+            ptr.cast::<AtomicUsize>().write(AtomicUsize::new(1));
+            ptr.add(std::mem::size_of::<AtomicUsize>())
+                .cast::<*mut u8>()
+                .write(allocation);
+            ptr.add(layout.header).cast::<Header>().write(Header::new());
+            ptr.add(layout.signals)
+                .cast::<Signals>()
+                .write(Signals::new());
+            if MODELED {
+                let items = ptr.add(layout.items).cast::<Item<T>>();
+                for index in 0..capacity {
+                    items.add(index).write(Item::new());
+                }
+            }
+
+            // Casting from a slice keeps the length as the number of items
+            let inner =
+                std::ptr::slice_from_raw_parts_mut(ptr, capacity) as *mut HeapInner<[Item<T>]>;
+            HeapStorage {
+                inner: NonNull::new_unchecked(inner),
+            }
+        }
+    }
+
+    fn inner(&self) -> &HeapInner<[Item<T>]> {
+        // SAFETY: the allocation lives for as long as any reference to it
+        unsafe { self.inner.as_ref() }
+    }
+}
+
+impl<T> Clone for HeapStorage<T> {
+    fn clone(&self) -> Self {
+        // Relaxed like Arc::clone, since the new reference is derived from
+        // an existing one
+        self.inner().references.fetch_add(1, Ordering::Relaxed);
+        Self { inner: self.inner }
+    }
+}
+
+impl<T> Drop for HeapStorage<T> {
+    fn drop(&mut self) {
+        // AcqRel like dropping an Arc, so that whoever frees the allocation
+        // sees everything that the others did with it
+        if self.inner().references.fetch_sub(1, Ordering::AcqRel) != 1 {
+            return;
+        }
+
+        // SAFETY: this was the last reference, and the allocation was made
+        // with the same layout in HeapStorage::new
+        unsafe {
+            let allocation = self.inner().allocation;
+            let layout = Self::layout(self.inner().items.len()).unwrap();
+            std::ptr::drop_in_place(self.inner.as_ptr());
+            std::alloc::dealloc(allocation, layout.allocation);
+        }
+    }
+}
+
+impl<T> sealed::Sealed<T> for HeapStorage<T> {
+    fn header(&self) -> &Header {
+        &self.inner().header
+    }
+
+    fn signals(&self) -> &Signals {
+        &self.inner().signals
+    }
+
+    fn items(&self) -> &[Item<T>] {
+        &self.inner().items
+    }
+}
+
+impl<T> Storage<T> for HeapStorage<T> {}
+
+/// The storage of [ring_buffer_in](crate::ring_buffer_in), whose items live
+/// in a buffer that the caller allocated. Only the header takes a small heap
+/// allocation of its own. Both are freed once the writer and all readers are
+/// gone.
+pub struct BoxedStorage<T> {
+    inner: Arc<BoxedInner<T>>,
+}
+
+struct BoxedInner<T> {
+    header: Header,
+    signals: Signals,
+    items: Box<[Item<T>]>,
+}
+
+impl<T> BoxedStorage<T> {
+    /// Take over the given buffer, which must not be empty, by writing a
+    /// new item into each of its slots
+    pub(crate) fn new(mut items: Box<[MaybeUninit<Item<T>>]>) -> BoxedStorage<T> {
+        for item in items.iter_mut() {
+            item.write(Item::new());
+        }
+
+        BoxedStorage {
+            inner: Arc::new(BoxedInner {
+                header: Header::new(),
+                signals: Signals::new(),
+                // SAFETY: every item was just initialized
+                items: unsafe { items.assume_init() },
+            }),
+        }
+    }
+}
+
+impl<T> Clone for BoxedStorage<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T> sealed::Sealed<T> for BoxedStorage<T> {
+    fn header(&self) -> &Header {
+        &self.inner.header
+    }
+
+    fn signals(&self) -> &Signals {
+        &self.inner.signals
+    }
+
+    fn items(&self) -> &[Item<T>] {
+        &self.inner.items
+    }
+}
+
+impl<T> Storage<T> for BoxedStorage<T> {}
+
+/// A ring buffer with a capacity of `N` whose items live inline, e.g. on
+/// the stack or in memory reserved at startup, instead of behind a heap
+/// allocation. Call [StaticRingBuffer::split] to receive a [Reader] and a
+/// [Writer] which borrow the buffer, and use scoped threads or a
+/// `&'static mut` reference to pass them to other threads.
+///
+/// ```
+/// use spmcq::{ReadResult, StaticRingBuffer};
+///
+/// let mut buffer = StaticRingBuffer::<u32, 16>::new();
+/// let (mut reader, mut writer) = buffer.split();
+///
+/// std::thread::scope(|s| {
+///     s.spawn(move || writer.write(1));
+/// });
+///
+/// assert_eq!(reader.read(), ReadResult::Ok(1));
+/// ```
+pub struct StaticRingBuffer<T, const N: usize> {
+    header: Header,
+    signals: Signals,
+    items: [Item<T>; N],
+}
+
+/// A [Reader] that borrows a [StaticRingBuffer]
+pub type StaticReader<'a, T, const N: usize> = Reader<T, &'a StaticRingBuffer<T, N>>;
+
+/// A [Writer] that borrows a [StaticRingBuffer]
+pub type StaticWriter<'a, T, const N: usize> = Writer<T, &'a StaticRingBuffer<T, N>>;
+
+impl<T, const N: usize> StaticRingBuffer<T, N>
+where
+    T: Default,
+{
+    /// Create a new, empty buffer. Fails to compile if `N` is zero.
+    pub fn new() -> StaticRingBuffer<T, N> {
+        const { assert!(N > 0, "StaticRingBuffer capacity must be at least 1") };
+
+        StaticRingBuffer {
+            header: Header::new(),
+            signals: Signals::new(),
+            items: std::array::from_fn(|_| Item::new()),
+        }
+    }
+}
+
+impl<T, const N: usize> Default for StaticRingBuffer<T, N>
+where
+    T: Default,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> StaticRingBuffer<T, N> {
+    /// Split the buffer into a [Reader] and a [Writer] that borrow it.
+    /// Because this requires exclusive access to the buffer, there can
+    /// only ever be a single writer at a time. Any data left behind by
+    /// readers and writers from an earlier split is discarded, and the
+    /// new reader will see an empty queue.
+    pub fn split(&mut self) -> (StaticReader<'_, T, N>, StaticWriter<'_, T, N>) {
+        self.header.reset();
+        self.signals.reset();
+        for item in &mut self.items {
+            item.reset();
+        }
+
+        let storage: &StaticRingBuffer<T, N> = self;
+
+        let reader = Reader::new(storage);
+        let writer = Writer::new(storage);
+
+        (reader, writer)
+    }
+}
+
+impl<T, const N: usize> sealed::Sealed<T> for &StaticRingBuffer<T, N> {
+    fn header(&self) -> &Header {
+        &self.header
+    }
+
+    fn signals(&self) -> &Signals {
+        &self.signals
+    }
+
+    fn items(&self) -> &[Item<T>] {
+        &self.items
+    }
+}
+
+impl<T, const N: usize> Storage<T> for &StaticRingBuffer<T, N> {}
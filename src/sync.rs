@@ -0,0 +1,73 @@
+//! The synchronization primitives used throughout the crate. By default these
+//! are the native atomics from `core`, but they can be swapped out for their
+//! `portable_atomic` equivalents on targets that lack native 32-bit or 64-bit
+//! atomics by enabling the `portable-atomic` feature, or for the models from
+//! `loom` when building with `--cfg loom`, see `tests/loom.rs`. The unit tests
+//! use shuttle's atomics instead with the `shuttle-tests` feature, see
+//! `shuttle_test.rs`.
+
+#[cfg(not(any(
+    loom,
+    all(test, feature = "shuttle-tests"),
+    feature = "portable-atomic"
+)))]
+pub(crate) use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+#[cfg(all(
+    feature = "portable-atomic",
+    not(any(loom, all(test, feature = "shuttle-tests")))
+))]
+pub(crate) use portable_atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+#[cfg(all(test, feature = "shuttle-tests", not(loom)))]
+pub(crate) use shuttle::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+#[cfg(loom)]
+pub(crate) use loom::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+#[cfg(loom)]
+pub(crate) use loom::cell::UnsafeCell;
+
+/// A `core::cell::UnsafeCell` with the closure-based interface of loom's, so
+/// that loom can check every access to the data of an item
+#[cfg(not(loom))]
+pub(crate) struct UnsafeCell<T>(core::cell::UnsafeCell<T>);
+
+#[cfg(not(loom))]
+impl<T> UnsafeCell<T> {
+    pub(crate) fn new(value: T) -> UnsafeCell<T> {
+        UnsafeCell(core::cell::UnsafeCell::new(value))
+    }
+
+    /// Call `f` with a pointer through which to read the value
+    #[inline]
+    pub(crate) fn with<R>(&self, f: impl FnOnce(*const T) -> R) -> R {
+        f(self.0.get())
+    }
+
+    /// Call `f` with a pointer through which to modify the value
+    #[inline]
+    pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+        f(self.0.get())
+    }
+}
+
+/// Whether the atomics are those of loom or shuttle, whose schedulers only
+/// switch threads at their own operations. Waiting must then always go
+/// through [spin_loop] rather than the thread functions of `std`.
+pub(crate) const MODELED: bool = cfg!(any(loom, all(test, feature = "shuttle-tests")));
+
+/// Signal that the current thread is waiting for another thread in a spin
+/// loop. Under loom, this yields to the other threads instead, since loom
+/// would explore a loop that doesn't forever, and likewise under shuttle.
+#[inline]
+pub(crate) fn spin_loop() {
+    #[cfg(not(any(loom, all(test, feature = "shuttle-tests"))))]
+    std::hint::spin_loop();
+
+    #[cfg(all(test, feature = "shuttle-tests", not(loom)))]
+    shuttle::hint::spin_loop();
+
+    #[cfg(loom)]
+    loom::thread::yield_now();
+}
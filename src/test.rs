@@ -1,116 +1,198 @@
 use std::time::Duration;
 
-use crate::{ring_buffer, ReadResult};
+use crate::scenario::{self, Pair};
+use crate::storage::{Indexing, UNWRITTEN_LAP, UNWRITTEN_SEQUENCE, WRITER_WAITING};
+use crate::{
+    byte_ring_buffer, channel, frame_buffer, ring_buffer, ring_buffer_atomic, ring_buffer_frames,
+    ring_buffer_lossless, ring_buffer_restore, ring_buffer_with_overwrite_policy,
+    ring_buffer_with_policy, try_ring_buffer, AtomicStorable, BytesLost, CapacityError, Detailed,
+    DispatchReader, DropoutEvent, DropoutInfo, DropoutPolicy, FrameTooLarge, OverwritePolicy,
+    ReadBatch, ReadResult, ReadSelect, ReaderHealth, ReaderStats, RecvError, RecvTimeoutError,
+    SeekResult, SendError, SpinPolicy, StaticRingBuffer, Timestamped, TooManyReaders,
+    TryReadResult, TryRecvError, TryWriteError, WouldOvertake, WriteTimeout, Writer, WriterStats,
+    WriterStillAlive,
+};
 
-#[test]
-fn test_basic_use_one_thread() {
-    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+/// Picks the number of iterations of a test, which is much smaller under Miri
+/// since it runs orders of magnitude slower
+const fn iterations(normal: usize, miri: usize) -> usize {
+    if cfg!(miri) {
+        miri
+    } else {
+        normal
+    }
+}
 
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
+/// How often the single-threaded tests repeat a pattern of reads and writes
+const ROUNDS: usize = iterations(1024, 8);
 
-    writer.write(1);
+/// Runs the threads of a [scenario] for real
+fn std_threads(tasks: Vec<Box<dyn FnOnce() + Send + '_>>) {
+    std::thread::scope(|s| {
+        for task in tasks {
+            s.spawn(task);
+        }
+    });
+}
 
-    assert_eq!(reader.read(), ReadResult::Ok(1));
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
+/// Returns the value and the number of lost items of a dropout, and panics if
+/// the result isn't one
+fn dropout<T: std::fmt::Debug>(result: Detailed<T>) -> (T, u64) {
+    match result {
+        Detailed::Dropout { value, info } => (value, info.lost),
+        other => panic!("expected a dropout, got {:?}", other),
+    }
+}
 
-    writer.write(2);
+/// Defines a module containing four tests which run the same body, once against
+/// a heap-allocated ring buffer, once against the same without the power-of-two
+/// indexing fast path, once against the same with a [SpinPolicy] that sleeps
+/// right away, and once against a [StaticRingBuffer], each with the given item
+/// type and capacity. Any attributes are applied to all four tests.
+macro_rules! storage_test {
+    (
+        $(#[$attr:meta])*
+        fn $name:ident($reader:ident, $writer:ident: $t:ty, $capacity:literal) $body:block
+    ) => {
+        mod $name {
+            #[allow(unused_imports)]
+            use super::*;
 
-    assert_eq!(reader.read(), ReadResult::Ok(2));
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
+            #[test]
+            $(#[$attr])*
+            fn heap() {
+                #[allow(unused_mut)]
+                let (mut $reader, mut $writer) = ring_buffer::<$t>($capacity);
+                $body
+            }
 
-    writer.write(3);
+            #[test]
+            $(#[$attr])*
+            fn heap_modulo() {
+                #[allow(unused_mut)]
+                let (mut $reader, mut $writer) = ring_buffer::<$t>($capacity);
+                $reader.indexing = Indexing::Modulo {
+                    capacity: $capacity,
+                };
+                $writer.indexing = Indexing::Modulo {
+                    capacity: $capacity,
+                };
+                $body
+            }
 
-    assert_eq!(reader.read(), ReadResult::Ok(3));
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
+            #[test]
+            $(#[$attr])*
+            fn heap_parking() {
+                let policy = SpinPolicy {
+                    spin_iters: 0,
+                    yield_iters: 0,
+                    park: Some(Duration::from_micros(1)),
+                };
+                #[allow(unused_mut)]
+                let (mut $reader, mut $writer) = ring_buffer_with_policy::<$t>($capacity, policy);
+                $body
+            }
 
-    writer.write(4);
+            #[test]
+            $(#[$attr])*
+            fn static_storage() {
+                let mut buffer = StaticRingBuffer::<$t, $capacity>::new();
+                #[allow(unused_mut)]
+                let (mut $reader, mut $writer) = buffer.split();
+                $body
+            }
+        }
+    };
+}
 
-    assert_eq!(reader.read(), ReadResult::Ok(4));
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
+storage_test! {
+    fn test_basic_use_one_thread(reader, writer: usize, 32) {
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
 
-    writer.write(5);
-    writer.write(6);
+        writer.write(1);
 
-    assert_eq!(reader.read(), ReadResult::Ok(5));
-    assert_eq!(reader.read(), ReadResult::Ok(6));
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-
-    writer.write(7);
-    writer.write(8);
-    writer.write(9);
-    writer.write(10);
+        assert_eq!(reader.read(), ReadResult::Ok(1));
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
 
-    assert_eq!(reader.read(), ReadResult::Ok(7));
-    assert_eq!(reader.read(), ReadResult::Ok(8));
-    assert_eq!(reader.read(), ReadResult::Ok(9));
-    assert_eq!(reader.read(), ReadResult::Ok(10));
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
+        writer.write(2);
 
-    writer.write(11);
-    writer.write(12);
+        assert_eq!(reader.read(), ReadResult::Ok(2));
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
+
+        writer.write(3);
 
-    assert_eq!(reader.read(), ReadResult::Ok(11));
+        assert_eq!(reader.read(), ReadResult::Ok(3));
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
 
-    writer.write(13);
-    writer.write(14);
-    writer.write(15);
+        writer.write(4);
 
-    assert_eq!(reader.read(), ReadResult::Ok(12));
+        assert_eq!(reader.read(), ReadResult::Ok(4));
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
 
-    writer.write(16);
-    writer.write(17);
-    writer.write(18);
-    writer.write(19);
+        writer.write(5);
+        writer.write(6);
 
-    assert_eq!(reader.read(), ReadResult::Ok(13));
-    assert_eq!(reader.read(), ReadResult::Ok(14));
-    assert_eq!(reader.read(), ReadResult::Ok(15));
-    assert_eq!(reader.read(), ReadResult::Ok(16));
-    assert_eq!(reader.read(), ReadResult::Ok(17));
-    assert_eq!(reader.read(), ReadResult::Ok(18));
-    assert_eq!(reader.read(), ReadResult::Ok(19));
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-    assert!(reader.read().is_empty());
-}
+        assert_eq!(reader.read(), ReadResult::Ok(5));
+        assert_eq!(reader.read(), ReadResult::Ok(6));
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
+        assert!(reader.read().is_empty());
 
-#[test]
-fn test_wraparound_keeping_pace_one_thread() {
-    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+        writer.write(7);
+        writer.write(8);
+        writer.write(9);
+        writer.write(10);
 
-    for i in 0..1024 {
+        assert_eq!(reader.read(), ReadResult::Ok(7));
+        assert_eq!(reader.read(), ReadResult::Ok(8));
+        assert_eq!(reader.read(), ReadResult::Ok(9));
+        assert_eq!(reader.read(), ReadResult::Ok(10));
         assert!(reader.read().is_empty());
         assert!(reader.read().is_empty());
         assert!(reader.read().is_empty());
         assert!(reader.read().is_empty());
 
-        writer.write(i);
+        writer.write(11);
+        writer.write(12);
 
-        assert_eq!(reader.read(), ReadResult::Ok(i));
+        assert_eq!(reader.read(), ReadResult::Ok(11));
+
+        writer.write(13);
+        writer.write(14);
+        writer.write(15);
+
+        assert_eq!(reader.read(), ReadResult::Ok(12));
+
+        writer.write(16);
+        writer.write(17);
+        writer.write(18);
+        writer.write(19);
+
+        assert_eq!(reader.read(), ReadResult::Ok(13));
+        assert_eq!(reader.read(), ReadResult::Ok(14));
+        assert_eq!(reader.read(), ReadResult::Ok(15));
+        assert_eq!(reader.read(), ReadResult::Ok(16));
+        assert_eq!(reader.read(), ReadResult::Ok(17));
+        assert_eq!(reader.read(), ReadResult::Ok(18));
+        assert_eq!(reader.read(), ReadResult::Ok(19));
         assert!(reader.read().is_empty());
         assert!(reader.read().is_empty());
         assert!(reader.read().is_empty());
@@ -118,305 +200,442 @@ fn test_wraparound_keeping_pace_one_thread() {
     }
 }
 
-#[test]
-fn test_dropouts_lapped_once_one_thread() {
-    let (mut reader, mut writer) = ring_buffer::<usize>(32);
-
-    // one read, capacity+1 writes
-    for i in 0..1024 {
-        assert_eq!(reader.read(), ReadResult::Empty);
+storage_test! {
+    fn test_wraparound_keeping_pace_one_thread(reader, writer: usize, 32) {
+        for i in 0..ROUNDS {
+            assert!(reader.read().is_empty());
+            assert!(reader.read().is_empty());
+            assert!(reader.read().is_empty());
+            assert!(reader.read().is_empty());
 
-        for _ in 0..33 {
             writer.write(i);
+
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+            assert!(reader.read().is_empty());
+            assert!(reader.read().is_empty());
+            assert!(reader.read().is_empty());
+            assert!(reader.read().is_empty());
         }
+    }
+}
 
-        assert_eq!(reader.read(), ReadResult::Dropout(i));
-        assert_eq!(reader.read(), ReadResult::Empty);
+storage_test! {
+    fn test_dropouts_lapped_once_one_thread(reader, writer: usize, 32) {
+        // one read, capacity+1 writes
+        for i in 0..ROUNDS {
+            assert_eq!(reader.read(), ReadResult::Empty);
+
+            for _ in 0..33 {
+                writer.write(i);
+            }
+
+            assert_eq!(reader.read(), ReadResult::Dropout(i));
+            assert_eq!(reader.read(), ReadResult::Empty);
+            assert_eq!(writer.total_written(), (i as u64 + 1) * 33);
+            assert_eq!(reader.total_written(), (i as u64 + 1) * 33);
+            assert_eq!(writer.observed_dropouts(), i as u64 + 1);
+        }
+
+        // the same again, counting the lost items
+        for i in 0..ROUNDS {
+            let expected = writer.next_sequence();
+            for _ in 0..33 {
+                writer.write(i);
+            }
+
+            let result = reader.read_detailed();
+            let info = DropoutInfo {
+                lost: 32,
+                expected_sequence: expected,
+                expected_lap: expected / 32,
+                sequence: expected + 32,
+                lap: expected / 32 + 1,
+                index: expected as usize % 32,
+            };
+            assert_eq!(result, Detailed::Dropout { value: i, info });
+            assert_eq!(result.lost(), 32);
+            assert_eq!(result.dropout_info(), Some(info));
+            assert_eq!(reader.read_detailed(), Detailed::Empty);
+            assert_eq!(reader.total_written(), expected + 33);
+            assert_eq!(writer.observed_dropouts(), (ROUNDS + i + 1) as u64);
+        }
+
+        // Every reader's dropouts count, even once it's gone
+        let mut reader2 = reader.clone();
+        for _ in 0..33 {
+            writer.write(0);
+        }
+        assert!(reader.read().is_dropout());
+        assert!(reader2.read().is_dropout());
+        drop(reader2);
+        assert_eq!(writer.observed_dropouts(), 2 * ROUNDS as u64 + 2);
     }
 }
 
-#[test]
-fn test_dropouts_lapped_twice_one_thread() {
-    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+storage_test! {
+    fn test_dropouts_lapped_twice_one_thread(reader, writer: usize, 32) {
+        // one read, 2*capacity+1 writes
+        for i in 0..ROUNDS {
+            assert_eq!(reader.read(), ReadResult::Empty);
 
-    // one read, 2*capacity+1 writes
-    for i in 0..1024 {
-        assert_eq!(reader.read(), ReadResult::Empty);
+            for _ in 0..65 {
+                writer.write(i);
+            }
 
-        for _ in 0..65 {
-            writer.write(i);
+            assert_eq!(reader.read(), ReadResult::Dropout(i));
+            assert_eq!(reader.read(), ReadResult::Empty);
+            assert_eq!(writer.total_written(), (i as u64 + 1) * 65);
+            assert_eq!(reader.total_written(), (i as u64 + 1) * 65);
+            assert_eq!(writer.observed_dropouts(), i as u64 + 1);
         }
 
-        assert_eq!(reader.read(), ReadResult::Dropout(i));
-        assert_eq!(reader.read(), ReadResult::Empty);
+        // the same again, counting the lost items
+        for i in 0..ROUNDS {
+            let expected = writer.next_sequence();
+            for _ in 0..65 {
+                writer.write(i);
+            }
+
+            let result = reader.read_detailed();
+            let info = DropoutInfo {
+                lost: 64,
+                expected_sequence: expected,
+                expected_lap: expected / 32,
+                sequence: expected + 64,
+                lap: expected / 32 + 2,
+                index: expected as usize % 32,
+            };
+            assert_eq!(result, Detailed::Dropout { value: i, info });
+            assert_eq!(result.lost(), 64);
+            assert_eq!(result.dropout_info(), Some(info));
+            assert_eq!(reader.read_detailed(), Detailed::Empty);
+            assert_eq!(reader.total_written(), expected + 65);
+            assert_eq!(writer.observed_dropouts(), (ROUNDS + i + 1) as u64);
+        }
     }
 }
 
-#[test]
-fn test_skip_ahead_basic_one_thread() {
-    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+storage_test! {
+    fn test_skip_ahead_basic_one_thread(reader, writer: usize, 32) {
+        writer.write(1);
+        writer.write(2);
+        writer.write(3);
+        writer.write(4);
 
-    writer.write(1);
-    writer.write(2);
-    writer.write(3);
-    writer.write(4);
+        assert_eq!(reader.read(), ReadResult::Ok(1));
+        reader.skip_ahead();
+        assert_eq!(reader.read(), ReadResult::Dropout(4));
+        assert_eq!(reader.read(), ReadResult::Empty);
 
-    assert_eq!(reader.read(), ReadResult::Ok(1));
-    reader.skip_ahead();
-    assert_eq!(reader.read(), ReadResult::Dropout(4));
-    assert_eq!(reader.read(), ReadResult::Empty);
+        writer.write(5);
 
-    writer.write(5);
+        reader.skip_ahead();
+        // Might seem a bit silly to return Dropout instead
+        // of Ok if there weren't actually any items skipped,
+        // but to call skip_ahead is basically to ask for items
+        // to be skipped and its effect can't generally be know
+        // ahead of time.
+        assert_eq!(reader.read(), ReadResult::Dropout(5));
+        assert_eq!(reader.read(), ReadResult::Empty);
 
-    reader.skip_ahead();
-    // Might seem a bit silly to return Dropout instead
-    // of Ok if there weren't actually any items skipped,
-    // but to call skip_ahead is basically to ask for items
-    // to be skipped and its effect can't generally be know
-    // ahead of time.
-    assert_eq!(reader.read(), ReadResult::Dropout(5));
-    assert_eq!(reader.read(), ReadResult::Empty);
+        writer.write(6);
+        writer.write(7);
 
-    writer.write(6);
-    writer.write(7);
+        reader.skip_ahead();
 
-    reader.skip_ahead();
+        writer.write(8);
+        writer.write(9);
 
-    writer.write(8);
-    writer.write(9);
+        reader.skip_ahead();
+        assert_eq!(reader.read(), ReadResult::Dropout(9));
+        assert_eq!(reader.read(), ReadResult::Empty);
 
-    reader.skip_ahead();
-    assert_eq!(reader.read(), ReadResult::Dropout(9));
-    assert_eq!(reader.read(), ReadResult::Empty);
+        // Unlike skip_ahead, read_latest only reports a dropout if items
+        // were actually skipped
+        writer.write(10);
+        assert_eq!(reader.read_latest(), ReadResult::Ok(10));
+        assert_eq!(reader.read_latest(), ReadResult::Empty);
+
+        writer.write(11);
+        writer.write(12);
+        assert_eq!(reader.read_latest(), ReadResult::Dropout(12));
+        assert_eq!(reader.read_latest(), ReadResult::Empty);
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        writer.write(13);
+        assert_eq!(reader.read_latest(), ReadResult::Ok(13));
+        assert_eq!(reader.read(), ReadResult::Empty);
+    }
 }
 
-#[test]
-fn test_skip_ahead_lapped_one_thread() {
-    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+storage_test! {
+    fn test_read_latest_one_thread(reader, writer: usize, 8) {
+        assert_eq!(reader.read_latest(), ReadResult::Empty);
+
+        // Lapped several times
+        for i in 0..30 {
+            writer.write(i);
+        }
+        assert_eq!(reader.read_latest(), ReadResult::Dropout(29));
+        assert_eq!(reader.read_latest(), ReadResult::Empty);
 
-    // one read, 2*capacity+1 writes
-    for i in 0..1024 {
-        for _ in 0..65 {
+        // Exactly at the wrap-around boundary
+        for i in 30..32 {
             writer.write(i);
+            assert_eq!(reader.read_latest(), ReadResult::Ok(i));
         }
+        writer.write(32);
+        assert_eq!(reader.read_latest(), ReadResult::Ok(32));
+
+        drop(writer);
+        assert_eq!(reader.read_latest(), ReadResult::Closed);
+    }
+}
+
+storage_test! {
+    fn test_skip_ahead_before_first_write_one_thread(reader, writer: usize, 32) {
+        reader.skip_ahead();
+        assert_eq!(reader.read(), ReadResult::Empty);
+        reader.skip_ahead();
+        reader.skip_ahead();
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        // The first real write is read normally, nothing was skipped
+        writer.write(1);
+        assert_eq!(reader.read(), ReadResult::Ok(1));
+        assert_eq!(reader.read(), ReadResult::Empty);
+    }
+}
+
+storage_test! {
+    fn test_skip_ahead_after_construction_one_thread(reader, writer: usize, 32) {
+        reader.skip_ahead();
+        writer.write(1);
+        writer.write(2);
+        assert_eq!(reader.read(), ReadResult::Ok(1));
+        assert_eq!(reader.read(), ReadResult::Ok(2));
 
+        // Once something was written, skipping ahead works as usual
         reader.skip_ahead();
-        assert_eq!(reader.read(), ReadResult::Dropout(i));
+        assert_eq!(reader.read(), ReadResult::Dropout(2));
         assert_eq!(reader.read(), ReadResult::Empty);
     }
 }
 
 #[test]
-fn test_two_readers_one_thread() {
-    let (mut reader1, mut writer) = ring_buffer::<usize>(32);
-    let mut reader2 = reader1.clone();
+fn test_skip_ahead_before_first_write_capacity_one() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(1);
 
-    assert_eq!(reader1.read(), ReadResult::Empty);
-    assert_eq!(reader2.read(), ReadResult::Empty);
+    reader.skip_ahead();
+    assert_eq!(reader.read(), ReadResult::Empty);
 
     writer.write(1);
+    reader.skip_ahead();
+    assert_eq!(reader.read(), ReadResult::Dropout(1));
+    assert_eq!(reader.read(), ReadResult::Empty);
+}
 
-    assert_eq!(reader1.read(), ReadResult::Ok(1));
-    assert_eq!(reader1.read(), ReadResult::Empty);
+storage_test! {
+    fn test_skip_ahead_lapped_one_thread(reader, writer: usize, 32) {
+        // one read, 2*capacity+1 writes
+        for i in 0..ROUNDS {
+            for _ in 0..65 {
+                writer.write(i);
+            }
 
-    assert_eq!(reader2.read(), ReadResult::Ok(1));
-    assert_eq!(reader2.read(), ReadResult::Empty);
+            reader.skip_ahead();
+            assert_eq!(reader.read(), ReadResult::Dropout(i));
+            assert_eq!(reader.read(), ReadResult::Empty);
+        }
+    }
+}
 
-    writer.write(2);
+storage_test! {
+    fn test_two_readers_one_thread(reader1, writer: usize, 32) {
+        let mut reader2 = reader1.clone();
 
-    assert_eq!(reader1.read(), ReadResult::Ok(2));
-    assert_eq!(reader1.read(), ReadResult::Empty);
+        assert_eq!(reader1.read(), ReadResult::Empty);
+        assert_eq!(reader2.read(), ReadResult::Empty);
 
-    writer.write(3);
+        writer.write(1);
 
-    assert_eq!(reader1.read(), ReadResult::Ok(3));
-    assert_eq!(reader1.read(), ReadResult::Empty);
+        assert_eq!(reader1.read(), ReadResult::Ok(1));
+        assert_eq!(reader1.read(), ReadResult::Empty);
 
-    writer.write(4);
+        assert_eq!(reader2.read(), ReadResult::Ok(1));
+        assert_eq!(reader2.read(), ReadResult::Empty);
 
-    assert_eq!(reader1.read(), ReadResult::Ok(4));
-    assert_eq!(reader1.read(), ReadResult::Empty);
+        writer.write(2);
 
-    assert_eq!(reader2.read(), ReadResult::Ok(2));
-    assert_eq!(reader2.read(), ReadResult::Ok(3));
-    assert_eq!(reader2.read(), ReadResult::Ok(4));
-    assert_eq!(reader2.read(), ReadResult::Empty);
+        assert_eq!(reader1.read(), ReadResult::Ok(2));
+        assert_eq!(reader1.read(), ReadResult::Empty);
 
-    writer.write(5);
-    writer.write(6);
-    writer.write(7);
-    writer.write(8);
+        writer.write(3);
 
-    reader2.skip_ahead();
-    assert_eq!(reader2.read(), ReadResult::Dropout(8));
-    assert_eq!(reader2.read(), ReadResult::Empty);
+        assert_eq!(reader1.read(), ReadResult::Ok(3));
+        assert_eq!(reader1.read(), ReadResult::Empty);
 
-    assert_eq!(reader1.read(), ReadResult::Ok(5));
-    assert_eq!(reader1.read(), ReadResult::Ok(6));
-    assert_eq!(reader1.read(), ReadResult::Ok(7));
-    assert_eq!(reader1.read(), ReadResult::Ok(8));
-    assert_eq!(reader1.read(), ReadResult::Empty);
-}
+        writer.write(4);
 
-#[test]
-fn test_one_reader_two_threads() {
-    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+        assert_eq!(reader1.read(), ReadResult::Ok(4));
+        assert_eq!(reader1.read(), ReadResult::Empty);
 
-    let reader_thread = std::thread::spawn(move || {
-        for i in 0..1024 {
-            loop {
-                match reader.read() {
-                    ReadResult::Ok(j) => {
-                        assert_eq!(i, j);
-                        break;
+        assert_eq!(reader2.read(), ReadResult::Ok(2));
+        assert_eq!(reader2.read(), ReadResult::Ok(3));
+        assert_eq!(reader2.read(), ReadResult::Ok(4));
+        assert_eq!(reader2.read(), ReadResult::Empty);
+
+        writer.write(5);
+        writer.write(6);
+        writer.write(7);
+        writer.write(8);
+
+        reader2.skip_ahead();
+        assert_eq!(reader2.read(), ReadResult::Dropout(8));
+        assert_eq!(reader2.read(), ReadResult::Empty);
+
+        assert_eq!(reader1.read(), ReadResult::Ok(5));
+        assert_eq!(reader1.read(), ReadResult::Ok(6));
+        assert_eq!(reader1.read(), ReadResult::Ok(7));
+        assert_eq!(reader1.read(), ReadResult::Ok(8));
+        assert_eq!(reader1.read(), ReadResult::Empty);
+    }
+}
+
+storage_test! {
+    fn test_one_reader_two_threads(reader, writer: usize, 32) {
+        std::thread::scope(|s| {
+            let reader_thread = s.spawn(move || {
+                for i in 0..ROUNDS {
+                    loop {
+                        match reader.read() {
+                            ReadResult::Ok(j) => {
+                                assert_eq!(i, j);
+                                break;
+                            }
+                            ReadResult::Dropout(_) => panic!(),
+                            ReadResult::Empty => std::thread::sleep(Duration::from_millis(1)),
+                            ReadResult::Closed => panic!(),
+                        }
                     }
-                    ReadResult::Dropout(_) => panic!(),
-                    ReadResult::Empty => std::thread::sleep(Duration::from_millis(1)),
                 }
-            }
-        }
-    });
+            });
 
-    let writer_thread = std::thread::spawn(move || {
-        for i in 0..1024 {
-            writer.write(i);
-            std::thread::sleep(Duration::from_millis(1));
-        }
-    });
+            let writer_thread = s.spawn(move || {
+                for i in 0..ROUNDS {
+                    writer.write(i);
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            });
 
-    reader_thread.join().unwrap();
-    writer_thread.join().unwrap();
+            reader_thread.join().unwrap();
+            writer_thread.join().unwrap();
+        });
+    }
 }
 
-#[test]
-fn test_two_readers_three_threads() {
-    let (mut reader1, mut writer) = ring_buffer::<usize>(32);
-    let mut reader2 = reader1.clone();
+storage_test! {
+    fn test_two_readers_three_threads(reader1, writer: usize, 32) {
+        let mut reader2 = reader1.clone();
 
-    let reader1_thread = std::thread::spawn(move || {
-        for i in 0..1024 {
-            loop {
-                match reader1.read() {
-                    ReadResult::Ok(j) => {
-                        assert_eq!(i, j);
-                        break;
+        std::thread::scope(|s| {
+            let reader1_thread = s.spawn(move || {
+                for i in 0..ROUNDS {
+                    loop {
+                        match reader1.read() {
+                            ReadResult::Ok(j) => {
+                                assert_eq!(i, j);
+                                break;
+                            }
+                            ReadResult::Dropout(_) => panic!(),
+                            ReadResult::Empty => std::thread::sleep(Duration::from_millis(1)),
+                            ReadResult::Closed => panic!(),
+                        }
                     }
-                    ReadResult::Dropout(_) => panic!(),
-                    ReadResult::Empty => std::thread::sleep(Duration::from_millis(1)),
                 }
-            }
-        }
-    });
+            });
 
-    let reader2_thread = std::thread::spawn(move || {
-        for i in 0..1024 {
-            loop {
-                match reader2.read() {
-                    ReadResult::Ok(j) => {
-                        assert_eq!(i, j);
-                        break;
+            let reader2_thread = s.spawn(move || {
+                for i in 0..ROUNDS {
+                    loop {
+                        match reader2.read() {
+                            ReadResult::Ok(j) => {
+                                assert_eq!(i, j);
+                                break;
+                            }
+                            ReadResult::Dropout(_) => panic!(),
+                            ReadResult::Empty => std::thread::sleep(Duration::from_millis(1)),
+                            ReadResult::Closed => panic!(),
+                        }
                     }
-                    ReadResult::Dropout(_) => panic!(),
-                    ReadResult::Empty => std::thread::sleep(Duration::from_millis(1)),
                 }
-            }
-        }
-    });
+            });
 
-    let writer_thread = std::thread::spawn(move || {
-        for i in 0..1024 {
-            writer.write(i);
-            std::thread::sleep(Duration::from_millis(1));
-        }
-    });
+            let writer_thread = s.spawn(move || {
+                for i in 0..ROUNDS {
+                    writer.write(i);
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            });
 
-    reader1_thread.join().unwrap();
-    reader2_thread.join().unwrap();
-    writer_thread.join().unwrap();
+            reader1_thread.join().unwrap();
+            reader2_thread.join().unwrap();
+            writer_thread.join().unwrap();
+        });
+    }
 }
 
-#[test]
-fn test_one_reader_two_threads_high_throughput() {
-    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+storage_test! {
+    fn test_one_reader_two_threads_high_throughput(reader, writer: usize, 32) {
+        const ITERATIONS: usize = iterations(1024 * 1024 * 64, 1000);
 
-    const ITERATIONS: usize = 1024 * 1024 * 64;
+        std::thread::scope(|s| {
+            let reader_thread = s.spawn(move || {
+                for _ in 0..ITERATIONS {
+                    let Some(value) = reader.read().value() else {
+                        continue;
+                    };
+                    let bytes = value.to_be_bytes();
 
-    let reader_thread = std::thread::spawn(move || {
-        for _ in 0..ITERATIONS {
-            let Some(value) = reader.read().value() else {
-                continue;
-            };
-            let bytes = value.to_be_bytes();
+                    // Expect the same bit pattern in all bytes
+                    assert!(bytes.iter().all(|b| *b == bytes[0]));
+                }
+            });
 
-            // Expect the same bit pattern in all bytes
-            assert!(bytes.iter().all(|b| *b == bytes[0]));
-        }
-    });
+            let writer_thread = s.spawn(move || {
+                for i in 0..ITERATIONS {
+                    // Copy the same bit pattern accross all bytes
+                    let b = (i & 0xff) as u8;
+                    let value = usize::from_be_bytes([b; 8]);
 
-    let writer_thread = std::thread::spawn(move || {
-        for i in 0..ITERATIONS {
-            // Copy the same bit pattern accross all bytes
-            let b = (i & 0xff) as u8;
-            let value = usize::from_be_bytes([b; 8]);
+                    writer.write(value);
+                }
+            });
 
-            writer.write(value);
-        }
-    });
+            reader_thread.join().unwrap();
+            writer_thread.join().unwrap();
+        });
+    }
+}
 
-    reader_thread.join().unwrap();
-    writer_thread.join().unwrap();
+storage_test! {
+    fn test_two_readers_three_threads_high_throughput(reader, writer: Pair, 32) {
+        let iterations = iterations(1024 * 1024 * 64, 1000) as u64;
+        scenario::two_readers(reader, writer, iterations, std_threads);
+    }
 }
 
-#[test]
-fn test_two_readers_three_threads_high_throughput() {
-    let (mut reader1, mut writer) = ring_buffer::<usize>(32);
-    let mut reader2 = reader1.clone();
+storage_test! {
+    fn test_skip_ahead_two_threads_high_throughput(reader, writer: Pair, 32) {
+        let iterations = iterations(1024 * 1024 * 4, 1000) as u64;
+        scenario::skip_ahead(reader, writer, iterations, std_threads);
+    }
+}
 
-    const ITERATIONS: usize = 1024 * 1024 * 64;
-
-    let reader1_thread = std::thread::spawn(move || {
-        for _ in 0..ITERATIONS {
-            let Some(value) = reader1.read().value() else {
-                continue;
-            };
-            let bytes = value.to_be_bytes();
-
-            // Expect the same bit pattern in all bytes
-            assert!(bytes.iter().all(|b| *b == bytes[0]));
-        }
-    });
-
-    let reader2_thread = std::thread::spawn(move || {
-        for _ in 0..ITERATIONS {
-            let Some(value) = reader2.read().value() else {
-                continue;
-            };
-            let bytes = value.to_be_bytes();
-
-            // Expect the same bit pattern in all bytes
-            assert!(bytes.iter().all(|b| *b == bytes[0]));
-        }
-    });
-
-    let writer_thread = std::thread::spawn(move || {
-        for i in 0..ITERATIONS {
-            // Copy the same bit pattern accross all bytes
-            let b = (i & 0xff) as u8;
-            let value = usize::from_be_bytes([b; 8]);
-
-            writer.write(value);
-        }
-    });
-
-    reader1_thread.join().unwrap();
-    reader2_thread.join().unwrap();
-    writer_thread.join().unwrap();
-}
-
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-struct Blob {
-    data: [u8; 1024],
-}
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Blob {
+    data: [u8; 1024],
+}
 
 impl Blob {
     fn new(value: u8) -> Blob {
@@ -440,88 +659,374 @@ impl Default for Blob {
     }
 }
 
+storage_test! {
+    fn test_custom_data_type_one_thread(reader, writer: Blob, 32) {
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        writer.write(Blob::new(0));
+
+        assert_eq!(reader.read(), ReadResult::Ok(Blob::new(0)));
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        writer.write(Blob::new(1));
+
+        assert_eq!(reader.read(), ReadResult::Ok(Blob::new(1)));
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        for _ in 0..64 {
+            writer.write(Blob::new(3));
+        }
+
+        assert_eq!(reader.read(), ReadResult::Dropout(Blob::new(3)));
+
+        reader.skip_ahead();
+        assert_eq!(reader.read(), ReadResult::Dropout(Blob::new(3)));
+        assert_eq!(reader.read(), ReadResult::Empty);
+    }
+}
+
+storage_test! {
+    fn test_custom_data_type_one_reader_two_threads_high_throughput(reader, writer: Blob, 32) {
+        const ITERATIONS: usize = iterations(1024 * 1024 * 64, 1000);
+
+        std::thread::scope(|s| {
+            let reader_thread = s.spawn(move || {
+                for _ in 0..ITERATIONS {
+                    let Some(value) = reader.read().value() else {
+                        continue;
+                    };
+                    assert!(value.all_equal());
+                }
+            });
+
+            let writer_thread = s.spawn(move || {
+                for i in 0..ITERATIONS {
+                    let b = (i & 0xff) as u8;
+                    writer.write(Blob::new(b));
+                }
+            });
+
+            reader_thread.join().unwrap();
+            writer_thread.join().unwrap();
+        });
+    }
+}
+
+storage_test! {
+    fn test_custom_data_type_two_readers_three_threads_high_throughput(reader1, writer: Blob, 32) {
+        let mut reader2 = reader1.clone();
+
+        const ITERATIONS: usize = iterations(1024 * 1024 * 64, 1000);
+
+        std::thread::scope(|s| {
+            let reader1_thread = s.spawn(move || {
+                for _ in 0..ITERATIONS {
+                    let Some(value) = reader1.read().value() else {
+                        continue;
+                    };
+                    assert!(value.all_equal());
+                }
+            });
+
+            let reader2_thread = s.spawn(move || {
+                for _ in 0..ITERATIONS {
+                    let Some(value) = reader2.read().value() else {
+                        continue;
+                    };
+                    assert!(value.all_equal());
+                }
+            });
+
+            let writer_thread = s.spawn(move || {
+                for i in 0..ITERATIONS {
+                    let b = (i & 0xff) as u8;
+                    writer.write(Blob::new(b));
+                }
+            });
+
+            reader1_thread.join().unwrap();
+            reader2_thread.join().unwrap();
+            writer_thread.join().unwrap();
+        });
+    }
+}
+
+#[test]
+fn test_try_ring_buffer_capacity_zero() {
+    let result = try_ring_buffer::<usize>(0);
+    assert_eq!(result.err(), Some(CapacityError::TooSmall(0)));
+}
+
+#[test]
+fn test_try_ring_buffer_capacity_overflow() {
+    let result = try_ring_buffer::<usize>(usize::MAX);
+    assert_eq!(result.err(), Some(CapacityError::TooLarge(usize::MAX)));
+
+    // Doesn't overflow usize but exceeds isize::MAX bytes
+    let result = try_ring_buffer::<Blob>(isize::MAX as usize / 1024);
+    assert_eq!(
+        result.err(),
+        Some(CapacityError::TooLarge(isize::MAX as usize / 1024))
+    );
+}
+
+#[test]
+fn test_heap_storage_dropped_once() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static CREATED: AtomicUsize = AtomicUsize::new(0);
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Counted;
+
+    impl Counted {
+        fn new() -> Counted {
+            CREATED.fetch_add(1, Ordering::SeqCst);
+            Counted
+        }
+    }
+
+    impl Default for Counted {
+        fn default() -> Self {
+            Counted::new()
+        }
+    }
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    // Items are only created once they're written
+    let (reader, mut writer) = ring_buffer::<Counted>(16);
+    assert_eq!(CREATED.load(Ordering::SeqCst), 0);
+    for _ in 0..16 {
+        writer.write(Counted::new());
+    }
+    assert_eq!(CREATED.load(Ordering::SeqCst), 16);
+    let clones: Vec<_> = (0..4).map(|_| reader.clone()).collect();
+
+    drop(writer);
+    drop(reader);
+    assert_eq!(DROPPED.load(Ordering::SeqCst), 0);
+
+    let last = std::thread::spawn(move || {
+        let mut clones = clones;
+        let last = clones.pop().unwrap();
+        drop(clones);
+        last
+    })
+    .join()
+    .unwrap();
+    assert_eq!(DROPPED.load(Ordering::SeqCst), 0);
+
+    // The last reader frees every item exactly once
+    drop(last);
+    assert_eq!(DROPPED.load(Ordering::SeqCst), 16);
+    assert_eq!(CREATED.load(Ordering::SeqCst), 16);
+}
+
+#[test]
+fn test_try_ring_buffer_valid_capacity() {
+    let (mut reader, mut writer) = try_ring_buffer::<usize>(2).unwrap();
+
+    assert_eq!(reader.read(), ReadResult::Empty);
+    writer.write(1);
+    assert_eq!(reader.read(), ReadResult::Ok(1));
+    assert_eq!(reader.read(), ReadResult::Empty);
+}
+
+#[test]
+#[should_panic]
+fn test_ring_buffer_panics_on_bad_capacity() {
+    let _ = ring_buffer::<usize>(0);
+}
+
 #[test]
-fn test_custom_data_type_one_thread() {
-    let (mut reader, mut writer) = ring_buffer::<Blob>(32);
+fn test_capacity_one_one_thread() {
+    let (mut reader, mut writer) = try_ring_buffer::<usize>(1).unwrap();
+
+    assert_eq!(reader.read(), ReadResult::Empty);
+    assert_eq!(reader.read(), ReadResult::Empty);
+
+    writer.write(1);
+
+    assert_eq!(reader.read(), ReadResult::Ok(1));
+    assert_eq!(reader.read(), ReadResult::Empty);
+    assert_eq!(reader.read(), ReadResult::Empty);
+
+    writer.write(2);
 
+    assert_eq!(reader.read(), ReadResult::Ok(2));
     assert_eq!(reader.read(), ReadResult::Empty);
 
-    writer.write(Blob::new(0));
+    // Replacing the value more than once since the last read is a dropout
+    writer.write(3);
+    writer.write(4);
 
-    assert_eq!(reader.read(), ReadResult::Ok(Blob::new(0)));
+    assert_eq!(reader.read(), ReadResult::Dropout(4));
     assert_eq!(reader.read(), ReadResult::Empty);
 
-    writer.write(Blob::new(1));
+    writer.write(5);
 
-    assert_eq!(reader.read(), ReadResult::Ok(Blob::new(1)));
+    assert_eq!(reader.read(), ReadResult::Ok(5));
     assert_eq!(reader.read(), ReadResult::Empty);
 
-    for _ in 0..64 {
-        writer.write(Blob::new(3));
+    for i in 6..100 {
+        writer.write(i);
     }
 
-    assert_eq!(reader.read(), ReadResult::Dropout(Blob::new(3)));
+    assert_eq!(reader.read(), ReadResult::Dropout(99));
+    assert_eq!(reader.read(), ReadResult::Empty);
+}
+
+#[test]
+fn test_capacity_one_two_readers_one_thread() {
+    let (mut reader1, mut writer) = try_ring_buffer::<usize>(1).unwrap();
+    let mut reader2 = reader1.clone();
+
+    writer.write(1);
+
+    assert_eq!(reader1.read(), ReadResult::Ok(1));
+    assert_eq!(reader1.read(), ReadResult::Empty);
+
+    writer.write(2);
+
+    assert_eq!(reader1.read(), ReadResult::Ok(2));
+    assert_eq!(reader2.read(), ReadResult::Dropout(2));
+    assert_eq!(reader1.read(), ReadResult::Empty);
+    assert_eq!(reader2.read(), ReadResult::Empty);
+}
+
+#[test]
+fn test_capacity_one_skip_ahead_one_thread() {
+    let (mut reader, mut writer) = try_ring_buffer::<usize>(1).unwrap();
+
+    writer.write(1);
+    writer.write(2);
 
     reader.skip_ahead();
-    assert_eq!(reader.read(), ReadResult::Dropout(Blob::new(3)));
+    assert_eq!(reader.read(), ReadResult::Dropout(2));
+    assert_eq!(reader.read(), ReadResult::Empty);
+
+    writer.write(3);
+
+    assert_eq!(reader.read(), ReadResult::Ok(3));
+    assert_eq!(reader.read(), ReadResult::Empty);
+}
+
+#[test]
+fn test_capacity_one_two_threads() {
+    let (reader, writer) = try_ring_buffer::<Pair>(1).unwrap();
+    let iterations = iterations(50_000, 1000) as u64;
+    scenario::one_reader(reader, writer, iterations, std_threads);
+}
+
+#[test]
+fn test_static_split_twice() {
+    let mut buffer = StaticRingBuffer::<usize, 4>::new();
+
+    {
+        let (mut reader, mut writer) = buffer.split();
+        writer.write(1);
+        writer.write(2);
+        writer.write(3);
+        assert_eq!(reader.read(), ReadResult::Ok(1));
+    }
+
+    // Splitting again discards everything written before
+    let (mut reader, mut writer) = buffer.split();
+    assert_eq!(reader.read(), ReadResult::Empty);
+
+    writer.write(4);
+    assert_eq!(reader.read(), ReadResult::Ok(4));
+    assert_eq!(reader.read(), ReadResult::Empty);
+}
+
+#[test]
+fn test_static_capacity_one() {
+    let mut buffer = StaticRingBuffer::<usize, 1>::new();
+    let (mut reader, mut writer) = buffer.split();
+
+    assert_eq!(reader.read(), ReadResult::Empty);
+    writer.write(1);
+    assert_eq!(reader.read(), ReadResult::Ok(1));
+    assert_eq!(reader.read(), ReadResult::Empty);
+    writer.write(2);
+    writer.write(3);
+    assert_eq!(reader.read(), ReadResult::Dropout(3));
+    assert_eq!(reader.read(), ReadResult::Empty);
+}
+
+#[cfg(feature = "portable-atomic")]
+#[test]
+fn test_portable_atomic_read_write() {
+    assert!(std::any::type_name::<crate::sync::AtomicU64>().starts_with("portable_atomic"));
+    assert!(std::any::type_name::<crate::sync::AtomicUsize>().starts_with("portable_atomic"));
+
+    let (mut reader, mut writer) = ring_buffer::<usize>(4);
+
+    assert_eq!(reader.read(), ReadResult::Empty);
+    for i in 0..6 {
+        writer.write(i);
+    }
+    assert_eq!(reader.read(), ReadResult::Dropout(4));
+    assert_eq!(reader.read(), ReadResult::Ok(5));
     assert_eq!(reader.read(), ReadResult::Empty);
 }
 
 #[test]
-fn test_custom_data_type_one_reader_two_threads_high_throughput() {
-    let (mut reader, mut writer) = ring_buffer::<Blob>(32);
+fn test_read_blocking_data_available() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+
+    writer.write(1);
+    writer.write(2);
+
+    assert_eq!(reader.read_blocking(), ReadResult::Ok(1));
+    assert_eq!(reader.read_blocking(), ReadResult::Ok(2));
+    assert_eq!(reader.read(), ReadResult::Empty);
+}
 
-    const ITERATIONS: usize = 1024 * 1024 * 64;
+#[test]
+fn test_read_blocking_before_first_write() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(32);
 
     let reader_thread = std::thread::spawn(move || {
-        for _ in 0..ITERATIONS {
-            let Some(value) = reader.read().value() else {
-                continue;
-            };
-            assert!(value.all_equal());
-        }
+        let start = std::time::Instant::now();
+        let result = reader.read_blocking();
+        (result, start.elapsed())
     });
 
-    let writer_thread = std::thread::spawn(move || {
-        for i in 0..ITERATIONS {
-            let b = (i & 0xff) as u8;
-            writer.write(Blob::new(b));
-        }
-    });
+    std::thread::sleep(Duration::from_millis(300));
+    writer.write(1);
 
-    reader_thread.join().unwrap();
-    writer_thread.join().unwrap();
+    let (result, elapsed) = reader_thread.join().unwrap();
+    assert_eq!(result, ReadResult::Ok(1));
+    assert!(elapsed >= Duration::from_millis(250));
 }
 
 #[test]
-fn test_custom_data_type_two_readers_three_threads_high_throughput() {
-    let (mut reader1, mut writer) = ring_buffer::<Blob>(32);
+fn test_read_blocking_two_readers_three_threads() {
+    let (mut reader1, mut writer) = ring_buffer::<usize>(32);
     let mut reader2 = reader1.clone();
 
-    const ITERATIONS: usize = 1024 * 1024 * 64;
-
     let reader1_thread = std::thread::spawn(move || {
-        for _ in 0..ITERATIONS {
-            let Some(value) = reader1.read().value() else {
-                continue;
-            };
-            assert!(value.all_equal());
+        for i in 0..256 {
+            assert_eq!(reader1.read_blocking(), ReadResult::Ok(i));
         }
     });
 
     let reader2_thread = std::thread::spawn(move || {
-        for _ in 0..ITERATIONS {
-            let Some(value) = reader2.read().value() else {
-                continue;
-            };
-            assert!(value.all_equal());
+        for i in 0..256 {
+            assert_eq!(reader2.read_blocking(), ReadResult::Ok(i));
         }
     });
 
     let writer_thread = std::thread::spawn(move || {
-        for i in 0..ITERATIONS {
-            let b = (i & 0xff) as u8;
-            writer.write(Blob::new(b));
+        for i in 0..256 {
+            writer.write(i);
+            std::thread::sleep(Duration::from_millis(1));
         }
     });
 
@@ -529,3 +1034,5157 @@ fn test_custom_data_type_two_readers_three_threads_high_throughput() {
     reader2_thread.join().unwrap();
     writer_thread.join().unwrap();
 }
+
+#[test]
+fn test_read_blocking_high_throughput() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+
+    const ITERATIONS: usize = iterations(1024 * 1024, 1000);
+
+    let reader_thread = std::thread::spawn(move || {
+        let mut last_value = None;
+        while last_value != Some(ITERATIONS - 1) {
+            let value = reader.read_blocking().value().unwrap();
+            if let Some(last_value) = last_value {
+                assert!(value > last_value);
+            }
+            last_value = Some(value);
+        }
+    });
+
+    let writer_thread = std::thread::spawn(move || {
+        for i in 0..ITERATIONS {
+            writer.write(i);
+        }
+    });
+
+    reader_thread.join().unwrap();
+    writer_thread.join().unwrap();
+}
+
+#[test]
+fn test_read_timeout_data_available() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+
+    writer.write(1);
+
+    let start = std::time::Instant::now();
+    assert_eq!(
+        reader.read_timeout(Duration::from_secs(10)),
+        ReadResult::Ok(1)
+    );
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn test_read_timeout_nothing_written() {
+    let (mut reader, _writer) = ring_buffer::<usize>(32);
+
+    let start = std::time::Instant::now();
+    assert_eq!(
+        reader.read_timeout(Duration::from_millis(200)),
+        ReadResult::Empty
+    );
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(200));
+    assert!(elapsed < Duration::from_secs(2));
+
+    // A zero timeout doesn't block at all
+    assert_eq!(reader.read_timeout(Duration::ZERO), ReadResult::Empty);
+
+    // Timing out leaves nothing behind for the writer to wake up
+    use crate::storage::sealed::Sealed;
+    assert!(reader.storage.signals().waiters.is_empty());
+}
+
+#[test]
+fn test_read_timeout_woken_by_writer() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+
+    let reader_thread = std::thread::spawn(move || {
+        let start = std::time::Instant::now();
+        let result = reader.read_timeout(Duration::from_secs(10));
+        (result, start.elapsed())
+    });
+
+    std::thread::sleep(Duration::from_millis(200));
+    writer.write(1);
+
+    let (result, elapsed) = reader_thread.join().unwrap();
+    assert_eq!(result, ReadResult::Ok(1));
+    assert!(elapsed >= Duration::from_millis(150));
+    assert!(elapsed < Duration::from_secs(5));
+}
+
+#[test]
+fn test_retarget_mid_stream() {
+    let (mut reader1, mut writer1) = ring_buffer::<usize>(8);
+    let (reader2, mut writer2) = ring_buffer::<usize>(8);
+
+    writer1.write(1);
+    writer1.write(2);
+    writer1.write(3);
+    assert_eq!(reader1.read(), ReadResult::Ok(1));
+
+    writer2.write(101);
+    writer2.write(102);
+
+    // Neither the unread values from the old buffer nor the existing
+    // values in the new buffer are seen after retargeting
+    reader1.retarget(&reader2);
+    assert_eq!(reader1.read(), ReadResult::Empty);
+
+    writer1.write(4);
+    assert_eq!(reader1.read(), ReadResult::Empty);
+
+    writer2.write(103);
+    assert_eq!(reader1.read(), ReadResult::Ok(103));
+    assert_eq!(reader1.read(), ReadResult::Empty);
+}
+
+#[test]
+fn test_retarget_wrapped_buffer() {
+    let (mut reader1, _writer1) = ring_buffer::<usize>(8);
+    let (_reader2, mut writer2) = ring_buffer::<usize>(8);
+
+    for i in 0..21 {
+        writer2.write(i);
+    }
+
+    reader1.retarget_to_writer(&writer2);
+    assert_eq!(reader1.read(), ReadResult::Empty);
+
+    for i in 21..30 {
+        writer2.write(i);
+        assert_eq!(reader1.read(), ReadResult::Ok(i));
+        assert_eq!(reader1.read(), ReadResult::Empty);
+    }
+}
+
+#[test]
+fn test_retarget_to_same_buffer() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(8);
+    let other = reader.clone();
+
+    writer.write(1);
+    writer.write(2);
+
+    // Retargeting to the same buffer skips to the front
+    reader.retarget(&other);
+    assert_eq!(reader.read(), ReadResult::Empty);
+
+    writer.write(3);
+    assert_eq!(reader.read(), ReadResult::Ok(3));
+}
+
+#[test]
+fn test_retarget_capacity_one() {
+    let (mut reader1, _writer1) = ring_buffer::<usize>(1);
+    let (_reader2, mut writer2) = ring_buffer::<usize>(1);
+
+    writer2.write(1);
+    writer2.write(2);
+
+    reader1.retarget_to_writer(&writer2);
+    assert_eq!(reader1.read(), ReadResult::Empty);
+
+    writer2.write(3);
+    assert_eq!(reader1.read(), ReadResult::Ok(3));
+    assert_eq!(reader1.read(), ReadResult::Empty);
+}
+
+#[test]
+fn test_retarget_while_writing() {
+    let (mut reader, _writer1) = ring_buffer::<usize>(32);
+    let (source, mut writer2) = ring_buffer::<usize>(32);
+
+    let writer_thread = std::thread::spawn(move || {
+        for i in 1..=iterations(100_000, 1000) {
+            writer2.write(i);
+        }
+    });
+
+    // Retarget repeatedly while the writer is running. Values read afterwards
+    // must always come from the new buffer, which never contains 0.
+    for _ in 0..iterations(1000, 100) {
+        reader.retarget(&source);
+        if let Some(value) = reader.read().value() {
+            assert_ne!(value, 0);
+        }
+    }
+
+    writer_thread.join().unwrap();
+}
+
+storage_test! {
+    fn test_close_after_writes(reader, writer: usize, 32) {
+        for i in 0..10 {
+            writer.write(i);
+        }
+        drop(writer);
+
+        // Everything written before closing can still be read
+        for i in 0..10 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        for _ in 0..10 {
+            assert_eq!(reader.read(), ReadResult::Closed);
+        }
+
+        // Cloned readers see the same
+        let mut reader2 = reader.clone();
+        assert!(reader2.read().is_closed());
+        assert_eq!(reader2.read().value(), None);
+    }
+}
+
+#[test]
+fn test_is_disconnected() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+    let reader2 = reader.clone();
+
+    assert!(!reader.is_disconnected());
+    writer.write(1);
+
+    // An idle writer is still connected
+    std::thread::sleep(Duration::from_millis(10));
+    assert!(!reader.is_disconnected());
+
+    std::thread::spawn(move || drop(writer)).join().unwrap();
+
+    assert!(reader.is_disconnected());
+    assert!(reader2.is_disconnected());
+
+    // Data written before the writer was dropped is still available
+    assert_eq!(reader.read(), ReadResult::Ok(1));
+    assert_eq!(reader.read(), ReadResult::Closed);
+    assert!(reader.is_disconnected());
+}
+
+#[test]
+fn test_close_lapped_reader() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(4);
+
+    for i in 0..10 {
+        writer.write(i);
+    }
+    writer.close();
+
+    assert_eq!(reader.read(), ReadResult::Dropout(8));
+    assert_eq!(reader.read(), ReadResult::Ok(9));
+    assert_eq!(reader.read(), ReadResult::Closed);
+}
+
+#[test]
+fn test_close_wakes_blocked_readers() {
+    let (mut reader1, mut writer) = ring_buffer::<usize>(32);
+    let mut reader2 = reader1.clone();
+
+    writer.write(0);
+
+    std::thread::scope(|s| {
+        let reader_thread1 = s.spawn(move || {
+            assert_eq!(reader1.read_blocking(), ReadResult::Ok(0));
+            assert_eq!(reader1.read_blocking(), ReadResult::Closed);
+            assert_eq!(reader1.read_blocking(), ReadResult::Closed);
+        });
+        let reader_thread2 = s.spawn(move || {
+            assert_eq!(
+                reader2.read_timeout(Duration::from_secs(10)),
+                ReadResult::Ok(0)
+            );
+            assert_eq!(
+                reader2.read_timeout(Duration::from_secs(10)),
+                ReadResult::Closed
+            );
+        });
+
+        std::thread::sleep(Duration::from_millis(50));
+        writer.close();
+
+        reader_thread1.join().unwrap();
+        reader_thread2.join().unwrap();
+    });
+}
+
+storage_test! {
+    fn test_reader_count(reader, writer: usize, 32) {
+        assert_eq!(writer.reader_count(), 1);
+
+        let readers = [reader.clone(), reader.clone(), reader.clone()];
+        assert_eq!(writer.reader_count(), 4);
+        drop(reader);
+        assert_eq!(writer.reader_count(), 3);
+
+        std::thread::scope(|s| {
+            for mut reader in readers {
+                s.spawn(move || {
+                    assert_eq!(reader.read(), ReadResult::Empty);
+                });
+            }
+        });
+
+        assert_eq!(writer.reader_count(), 0);
+        assert!(!writer.has_readers());
+
+        // Writing without any readers is fine
+        writer.write(1);
+    }
+}
+
+#[test]
+fn test_reader_count_retarget() {
+    let (mut reader1, writer1) = ring_buffer::<usize>(32);
+    let (reader2, writer2) = ring_buffer::<usize>(32);
+
+    reader1.retarget(&reader2);
+    assert_eq!(writer1.reader_count(), 0);
+    assert_eq!(writer2.reader_count(), 2);
+
+    reader1.retarget_to_writer(&writer2);
+    assert_eq!(writer2.reader_count(), 2);
+
+    reader1.retarget_to_writer(&writer1);
+    assert_eq!(writer1.reader_count(), 1);
+    assert_eq!(writer2.reader_count(), 1);
+}
+
+storage_test! {
+    fn test_try_clone_reader_limit(reader, writer: usize, 32) {
+        assert_eq!(reader.max_readers(), crate::DEFAULT_MAX_READERS);
+
+        writer.set_max_readers(3);
+        assert_eq!(reader.max_readers(), 3);
+        assert_eq!(writer.max_readers(), 3);
+
+        let reader2 = reader.try_clone().unwrap();
+        let reader3 = reader2.try_clone().unwrap();
+        assert_eq!(reader.try_clone().err(), Some(TooManyReaders(3)));
+        assert_eq!(writer.reader_count(), 3);
+
+        // Dropping a reader makes room for another one
+        drop(reader3);
+        let _reader3 = reader.try_clone().unwrap();
+        assert!(reader2.try_clone().is_err());
+
+        // Lowering the limit leaves existing readers alone
+        writer.set_max_readers(1);
+        assert_eq!(writer.reader_count(), 3);
+        assert!(reader.try_clone().is_err());
+    }
+}
+
+#[test]
+#[should_panic(expected = "maximum of 1 readers")]
+fn test_clone_reader_limit_panics() {
+    let (reader, mut writer) = ring_buffer::<usize>(32);
+    writer.set_max_readers(1);
+    let _ = reader.clone();
+}
+
+#[test]
+#[should_panic(expected = "maximum of 1 readers")]
+fn test_retarget_reader_limit_panics() {
+    let (mut reader1, _writer1) = ring_buffer::<usize>(32);
+    let (_reader2, mut writer2) = ring_buffer::<usize>(32);
+    writer2.set_max_readers(1);
+
+    // Retargeting to the same buffer doesn't count as another reader
+    reader1.retarget(&reader1.clone());
+    reader1.retarget_to_writer(&writer2);
+}
+
+#[test]
+#[should_panic]
+fn test_set_max_readers_too_large() {
+    let (_reader, mut writer) = ring_buffer::<usize>(32);
+    writer.set_max_readers(usize::MAX);
+}
+
+#[test]
+fn test_many_readers_small_buffer() {
+    const READERS: usize = iterations(128, 8);
+    const ITERATIONS: usize = iterations(20_000, 200);
+
+    let (reader, mut writer) = ring_buffer::<usize>(4);
+
+    std::thread::scope(|s| {
+        for _ in 0..READERS {
+            let mut reader = reader.clone();
+            s.spawn(move || {
+                let mut last_value: Option<usize> = None;
+                loop {
+                    let expected = last_value.map_or(0, |v| v + 1);
+                    match reader.read_blocking() {
+                        ReadResult::Ok(v) => {
+                            assert_eq!(v, expected);
+                            last_value = Some(v);
+                        }
+                        ReadResult::Dropout(v) => {
+                            assert!(v > expected);
+                            last_value = Some(v);
+                        }
+                        ReadResult::Empty => panic!(),
+                        ReadResult::Closed => break,
+                    }
+                }
+                assert_eq!(last_value, Some(ITERATIONS - 1));
+            });
+        }
+        drop(reader);
+
+        s.spawn(move || {
+            for i in 0..ITERATIONS {
+                writer.write(i);
+            }
+        });
+    });
+}
+
+storage_test! {
+    fn test_peek_one_thread(reader, writer: usize, 32) {
+        assert_eq!(reader.peek(), ReadResult::Empty);
+
+        writer.write(0);
+        writer.write(1);
+
+        // Peeking repeatedly shows the same item until it's read
+        assert_eq!(reader.peek(), ReadResult::Ok(0));
+        assert_eq!(reader.peek(), ReadResult::Ok(0));
+        assert_eq!(reader.read(), ReadResult::Ok(0));
+        assert_eq!(reader.peek(), ReadResult::Ok(1));
+        assert_eq!(reader.read(), ReadResult::Ok(1));
+        assert_eq!(reader.peek(), ReadResult::Empty);
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        // Peeking across the wrap-around boundary
+        for i in 2..32 {
+            writer.write(i);
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        writer.write(32);
+        assert_eq!(reader.peek(), ReadResult::Ok(32));
+        assert_eq!(reader.read(), ReadResult::Ok(32));
+        assert_eq!(reader.peek(), ReadResult::Empty);
+
+        drop(writer);
+        assert_eq!(reader.peek(), ReadResult::Closed);
+        assert_eq!(reader.read(), ReadResult::Closed);
+    }
+}
+
+storage_test! {
+    fn test_peek_dropouts_one_thread(reader, writer: usize, 32) {
+        for i in 0..40 {
+            writer.write(i);
+        }
+
+        // Peeking after being lapped doesn't catch the reader up
+        assert_eq!(reader.peek(), ReadResult::Dropout(32));
+        assert_eq!(reader.peek(), ReadResult::Dropout(32));
+        assert_eq!(reader.read(), ReadResult::Dropout(32));
+        assert_eq!(reader.peek(), ReadResult::Ok(33));
+
+        // If the peeked item gets overwritten, the read sees the new item
+        for i in 40..72 {
+            writer.write(i);
+        }
+        assert_eq!(reader.peek(), ReadResult::Dropout(65));
+        for i in 72..104 {
+            writer.write(i);
+        }
+        assert_eq!(reader.read(), ReadResult::Dropout(97));
+        assert_eq!(reader.peek(), ReadResult::Ok(98));
+    }
+}
+
+storage_test! {
+    fn test_peek_skip_ahead_one_thread(reader, writer: usize, 32) {
+        for i in 0..10 {
+            writer.write(i);
+        }
+
+        reader.skip_ahead();
+        assert_eq!(reader.peek(), ReadResult::Dropout(9));
+        assert_eq!(reader.read(), ReadResult::Dropout(9));
+        assert_eq!(reader.peek(), ReadResult::Empty);
+
+        writer.write(10);
+        reader.skip_ahead();
+        assert_eq!(reader.peek(), ReadResult::Dropout(10));
+        writer.write(11);
+        assert_eq!(reader.read(), ReadResult::Dropout(10));
+        assert_eq!(reader.peek(), ReadResult::Ok(11));
+    }
+}
+
+storage_test! {
+    fn test_available_one_thread(reader, writer: usize, 8) {
+        assert_eq!(reader.available(), 0);
+
+        for i in 0..5 {
+            writer.write(i);
+        }
+        assert_eq!(reader.available(), 5);
+        assert_eq!(reader.read(), ReadResult::Ok(0));
+        assert_eq!(reader.available(), 4);
+
+        // Wrapped around, but not lapped
+        for i in 5..9 {
+            writer.write(i);
+        }
+        assert_eq!(reader.available(), 8);
+        for i in 1..9 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(reader.available(), 0);
+
+        // Lapped several times, only what's still in the buffer counts
+        for i in 9..45 {
+            writer.write(i);
+        }
+        assert_eq!(reader.available(), 4);
+        assert_eq!(reader.read(), ReadResult::Dropout(41));
+        assert_eq!(reader.available(), 3);
+
+        // After skipping ahead, only the latest item is left to read
+        reader.skip_ahead();
+        assert_eq!(reader.available(), 1);
+        assert_eq!(reader.read(), ReadResult::Dropout(44));
+        assert_eq!(reader.available(), 0);
+    }
+}
+
+storage_test! {
+    fn test_rewind_one_thread(reader, writer: usize, 8) {
+        assert_eq!(reader.rewind(5), 0);
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        // Within a partial first lap, only written items can be reread
+        for i in 0..5 {
+            writer.write(i);
+        }
+        for i in 0..5 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(reader.rewind(0), 0);
+        assert_eq!(reader.rewind(2), 2);
+        assert_eq!(reader.read(), ReadResult::Ok(3));
+        assert_eq!(reader.rewind(10), 4);
+        for i in 0..5 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        // Across the wrap-around boundary, leaving the writer's next item alone
+        for i in 5..12 {
+            writer.write(i);
+        }
+        for i in 5..12 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(reader.rewind(3), 3);
+        for i in 9..12 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(reader.rewind(100), 7);
+        assert_eq!(reader.rewind(1), 0);
+        for i in 5..12 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        // Rewinding a reader that hasn't read everything yet
+        writer.write(12);
+        writer.write(13);
+        assert_eq!(reader.rewind(100), 5);
+        for i in 7..14 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+
+        // Items that get overwritten after rewinding are reported as lost
+        assert_eq!(reader.rewind(3), 3);
+        for i in 14..20 {
+            writer.write(i);
+        }
+        assert_eq!(reader.read(), ReadResult::Dropout(19));
+
+        // Lapped readers stay where they are
+        for i in 20..40 {
+            writer.write(i);
+        }
+        assert_eq!(reader.rewind(3), 0);
+        assert_eq!(reader.read(), ReadResult::Dropout(36));
+    }
+}
+
+storage_test! {
+    fn test_seek_to_oldest_one_thread(reader, writer: usize, 32) {
+        // Before wrapping around, everything written is still there
+        for i in 0..10 {
+            writer.write(i);
+        }
+        let mut reader2 = reader.clone();
+        for i in 0..10 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        reader.seek_to_oldest();
+        for i in 0..10 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        // After wrapping around, all but the oldest item can be replayed
+        for i in 10..48 {
+            writer.write(i);
+        }
+        reader2.seek_to_oldest();
+        for i in 17..48 {
+            assert_eq!(reader2.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(reader2.read(), ReadResult::Empty);
+
+        // Readers that were overtaken by the writer move there as well
+        reader.seek_to_oldest();
+        for i in 17..48 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(reader.read(), ReadResult::Empty);
+    }
+}
+
+storage_test! {
+    fn test_seek_to_sequence_one_thread(reader, writer: usize, 8) {
+        assert_eq!(reader.seek_to_sequence(0), SeekResult::NotYetWritten);
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        // Before wrapping around, everything written is still there
+        for i in 0..5 {
+            writer.write(10 * i);
+        }
+        assert_eq!(reader.seek_to_sequence(0), SeekResult::Ok);
+        assert_eq!(reader.read_indexed(), ReadResult::Ok((0, 0)));
+        assert_eq!(reader.seek_to_sequence(3), SeekResult::Ok);
+        assert_eq!(reader.read_indexed(), ReadResult::Ok((3, 30)));
+        assert_eq!(reader.read_indexed(), ReadResult::Ok((4, 40)));
+        assert_eq!(reader.read_indexed(), ReadResult::Empty);
+
+        // After wrapping around, items 13 to 19 are retained
+        for i in 5..20 {
+            writer.write(10 * i);
+        }
+        assert_eq!(reader.seek_to_sequence(15), SeekResult::Ok);
+        for i in 15..20 {
+            assert_eq!(reader.read_indexed(), ReadResult::Ok((i, 10 * i as usize)));
+        }
+        assert_eq!(reader.read_indexed(), ReadResult::Empty);
+
+        // Exactly at the retention boundary
+        assert_eq!(reader.seek_to_sequence(13), SeekResult::Ok);
+        assert_eq!(reader.read_indexed(), ReadResult::Ok((13, 130)));
+
+        // Just past it, the reader moves to the oldest retained item instead
+        assert_eq!(reader.seek_to_sequence(12), SeekResult::TooOld);
+        assert_eq!(reader.read_indexed(), ReadResult::Ok((13, 130)));
+        assert_eq!(reader.seek_to_sequence(0), SeekResult::TooOld);
+        for i in 13..20 {
+            assert_eq!(reader.read_indexed(), ReadResult::Ok((i, 10 * i as usize)));
+        }
+        assert_eq!(reader.read_indexed(), ReadResult::Empty);
+
+        // Items that haven't been written move the reader to the front
+        assert_eq!(reader.seek_to_sequence(100), SeekResult::NotYetWritten);
+        assert_eq!(reader.read_indexed(), ReadResult::Empty);
+        assert_eq!(reader.seek_to_sequence(20), SeekResult::NotYetWritten);
+        assert_eq!(reader.read_indexed(), ReadResult::Empty);
+        writer.write(200);
+        assert_eq!(reader.read_indexed(), ReadResult::Ok((20, 200)));
+
+        // Seeking from a reader that was overtaken
+        for i in 21..40 {
+            writer.write(10 * i);
+        }
+        assert_eq!(reader.seek_to_sequence(35), SeekResult::Ok);
+        assert_eq!(reader.read_indexed(), ReadResult::Ok((35, 350)));
+    }
+}
+
+#[test]
+fn test_seek_to_sequence_capacity_one() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(1);
+
+    // The only item is always the next to be overwritten
+    writer.write(1);
+    assert_eq!(reader.seek_to_sequence(0), SeekResult::TooOld);
+    assert_eq!(reader.read(), ReadResult::Empty);
+}
+
+#[test]
+fn test_rewind_capacity_one() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(1);
+
+    writer.write(1);
+    assert_eq!(reader.read(), ReadResult::Ok(1));
+
+    // The only item is always the next to be overwritten
+    assert_eq!(reader.rewind(1), 0);
+    assert_eq!(reader.read(), ReadResult::Empty);
+}
+
+storage_test! {
+    fn test_lag_one_thread(reader, writer: usize, 8) {
+        assert_eq!(reader.lag(), 0);
+
+        for i in 0..6 {
+            writer.write(i);
+            assert_eq!(reader.lag(), i + 1);
+        }
+        for i in 0..6 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+            assert_eq!(reader.lag(), 5 - i);
+        }
+        assert_eq!(reader.lag(), 0);
+
+        // Exactly one full buffer behind, across the wrap-around
+        for i in 6..14 {
+            writer.write(i);
+        }
+        assert_eq!(reader.lag(), 8);
+        for i in 6..14 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(reader.lag(), 0);
+
+        // Lapped once and then many times
+        for i in 14..23 {
+            writer.write(i);
+        }
+        assert_eq!(reader.lag(), 8);
+        for i in 23..100 {
+            writer.write(i);
+        }
+        assert_eq!(reader.lag(), 8);
+
+        // Reading after a dropout catches up to the writer's lap
+        assert_eq!(reader.read(), ReadResult::Dropout(94));
+        assert_eq!(reader.lag(), 5);
+
+        reader.skip_ahead();
+        assert_eq!(reader.lag(), 8);
+        assert_eq!(reader.read(), ReadResult::Dropout(99));
+        assert_eq!(reader.lag(), 0);
+    }
+}
+
+storage_test! {
+    fn test_lag_high_watermark_one_thread(reader, writer: usize, 32) {
+        assert_eq!(reader.lag_high_watermark(), 0);
+
+        // Fall 20 items behind, then catch up
+        for i in 0..20 {
+            writer.write(i);
+            assert!(reader.has_data());
+        }
+        assert_eq!(reader.lag_high_watermark(), 20);
+        for i in 0..20 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(reader.lag(), 0);
+        assert_eq!(reader.lag_high_watermark(), 20);
+
+        // Smaller lags don't lower it
+        for i in 20..25 {
+            writer.write(i);
+        }
+        assert_eq!(reader.read_all_available().0.len(), 5);
+        assert_eq!(reader.lag_high_watermark(), 20);
+
+        reader.reset_lag_high_watermark();
+        assert_eq!(reader.lag_high_watermark(), 0);
+        assert!(!reader.has_data());
+        assert_eq!(reader.lag_high_watermark(), 0);
+
+        // Every kind of read measures the lag before reading
+        writer.write(25);
+        writer.write(26);
+        assert_eq!(reader.try_read(), TryReadResult::Ok(25));
+        assert_eq!(reader.lag_high_watermark(), 2);
+        for i in 27..30 {
+            writer.write(i);
+        }
+        assert_eq!(reader.read_with(|v| *v), ReadResult::Ok(26));
+        assert_eq!(reader.lag_high_watermark(), 4);
+
+        // Clones start from zero
+        assert_eq!(reader.clone().lag_high_watermark(), 0);
+    }
+}
+
+storage_test! {
+    fn test_position_one_thread(reader, writer: usize, 8) {
+        let mut reader2 = reader.clone();
+        assert_eq!(reader.position(), writer.position());
+        assert_eq!(reader.distance_from(&reader2), 0);
+
+        // Advance one reader by k reads, across several wrap-arounds
+        for k in 1..6 {
+            for _ in 0..5 {
+                writer.write(0);
+            }
+            assert_eq!(reader.read_all_available().0.len(), 5);
+            assert_eq!(reader.position(), writer.position());
+            for _ in 0..5 - k {
+                assert_eq!(reader2.read(), ReadResult::Ok(0));
+            }
+            assert!(reader.position() > reader2.position());
+            assert_eq!(reader.distance_from(&reader2), k as i64);
+            assert_eq!(reader2.distance_from(&reader), -(k as i64));
+
+            // Catch up again
+            assert_eq!(reader2.read_all_available().0.len(), k);
+            assert_eq!(reader.distance_from(&reader2), 0);
+        }
+        assert_eq!(writer.position().sequence(), 25);
+
+        // An overtaken reader stays where it was until it reads
+        for _ in 0..20 {
+            writer.write(0);
+        }
+        assert_eq!(reader.distance_from(&reader2), 0);
+        assert_eq!(reader.read(), ReadResult::Dropout(0));
+        assert_eq!(reader.position().sequence(), 42);
+
+        // After skipping ahead, the reader is at the latest item
+        reader2.skip_ahead();
+        assert_eq!(reader2.position().sequence(), 44);
+        assert_eq!(reader.distance_from(&reader2), -2);
+    }
+}
+
+#[test]
+fn test_position_skip_ahead_first_lap() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(8);
+
+    writer.write(0);
+    writer.write(1);
+    reader.skip_ahead();
+    assert_eq!(reader.position().sequence(), 1);
+    assert!(reader.position() < writer.position());
+}
+
+storage_test! {
+    fn test_has_data_wraparound_one_thread(reader, writer: usize, 4) {
+        assert!(!reader.has_data());
+
+        // Keep pace with the writer across several wrap-arounds
+        for i in 0..12 {
+            writer.write(i);
+            assert!(reader.has_data());
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+            assert!(!reader.has_data());
+        }
+
+        // Fill the buffer up to the wrap-around boundary and beyond
+        for i in 12..16 {
+            writer.write(i);
+            assert!(reader.has_data());
+        }
+        for i in 12..16 {
+            assert!(reader.has_data());
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        assert!(!reader.has_data());
+
+        // Lapped readers have data, and so do readers that skipped ahead
+        for i in 16..30 {
+            writer.write(i);
+        }
+        assert!(reader.has_data());
+        reader.skip_ahead();
+        assert!(reader.has_data());
+        assert_eq!(reader.read(), ReadResult::Dropout(29));
+        assert!(!reader.has_data());
+
+        drop(writer);
+        assert!(!reader.has_data());
+    }
+}
+
+storage_test! {
+    fn test_available_matches_reads(reader, writer: usize, 8) {
+        // A simple deterministic pseudo-random sequence of operations
+        let mut state: u32 = 12345;
+        let mut next = move || {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            state >> 16
+        };
+
+        let mut value = 0;
+        for _ in 0..iterations(2000, 200) {
+            match next() % 4 {
+                0 => {
+                    for _ in 0..next() % 20 {
+                        writer.write(value);
+                        value += 1;
+                    }
+                }
+                1 => {
+                    for _ in 0..next() % 5 {
+                        reader.read();
+                    }
+                }
+                2 => reader.skip_ahead(),
+                _ => {}
+            }
+
+            let mut clone = reader.clone();
+            let mut count = 0;
+            while clone.read().value().is_some() {
+                count += 1;
+            }
+            assert_eq!(reader.available(), count);
+            assert_eq!(reader.has_data(), count > 0);
+        }
+    }
+}
+
+storage_test! {
+    fn test_capacity_and_footprint(reader, writer: usize, 16) {
+        assert_eq!(reader.capacity(), 16);
+        assert_eq!(reader.clone().capacity(), 16);
+        assert_eq!(writer.capacity(), 16);
+
+        let footprint = 16 * std::mem::size_of::<crate::storage::Item<usize>>();
+        assert_eq!(reader.memory_footprint(), footprint);
+        assert_eq!(writer.memory_footprint(), footprint);
+        assert!(footprint >= 16 * std::mem::size_of::<usize>());
+    }
+}
+
+#[test]
+fn test_capacity_zero_sized_type() {
+    let (reader, writer) = ring_buffer::<()>(1000);
+    assert_eq!(reader.capacity(), 1000);
+    assert_eq!(writer.capacity(), 1000);
+
+    // Even zero-sized items need room for their lock and lap count
+    let footprint = reader.memory_footprint();
+    assert_eq!(
+        footprint,
+        1000 * std::mem::size_of::<crate::storage::Item<()>>()
+    );
+    assert!(footprint > 0);
+    assert_eq!(writer.memory_footprint(), footprint);
+}
+
+#[test]
+fn test_same_buffer() {
+    let (reader1, writer1) = ring_buffer::<usize>(8);
+    let (mut reader2, writer2) = ring_buffer::<usize>(8);
+
+    let clone = reader1.clone();
+    assert!(reader1.same_buffer(&reader1));
+    assert!(reader1.same_buffer(&clone));
+    assert!(clone.same_buffer(&reader1));
+    assert!(!reader1.same_buffer(&reader2));
+
+    assert!(writer1.is_writer_for(&reader1));
+    assert!(writer1.is_writer_for(&clone));
+    assert!(!writer1.is_writer_for(&reader2));
+    assert!(writer2.is_writer_for(&reader2));
+
+    // Retargeting changes which buffer a reader belongs to
+    reader2.retarget_to_writer(&writer1);
+    assert!(reader2.same_buffer(&reader1));
+    assert!(writer1.is_writer_for(&reader2));
+    assert!(!writer2.is_writer_for(&reader2));
+
+    // Zero-sized items don't make independent buffers look the same
+    let (reader3, _writer3) = ring_buffer::<()>(4);
+    let (reader4, _writer4) = ring_buffer::<()>(4);
+    assert!(!reader3.same_buffer(&reader4));
+    assert!(reader3.same_buffer(&reader3.clone()));
+}
+
+#[test]
+fn test_same_buffer_static() {
+    let mut buffer1 = StaticRingBuffer::<usize, 4>::new();
+    let mut buffer2 = StaticRingBuffer::<usize, 4>::new();
+    let (reader1, writer1) = buffer1.split();
+    let (reader2, _writer2) = buffer2.split();
+
+    assert!(reader1.same_buffer(&reader1.clone()));
+    assert!(!reader1.same_buffer(&reader2));
+    assert!(writer1.is_writer_for(&reader1));
+    assert!(!writer1.is_writer_for(&reader2));
+}
+
+storage_test! {
+    fn test_sequence_numbers_one_thread(reader, writer: usize, 8) {
+        assert_eq!(writer.next_sequence(), 0);
+        assert_eq!(writer.last_sequence(), None);
+        assert_eq!(reader.last_sequence(), None);
+
+        writer.write(0);
+        assert_eq!(writer.next_sequence(), 1);
+        assert_eq!(writer.last_sequence(), Some(0));
+        assert_eq!(reader.read(), ReadResult::Ok(0));
+        assert_eq!(reader.last_sequence(), Some(0));
+
+        // Empty reads don't change the sequence number
+        assert_eq!(reader.read(), ReadResult::Empty);
+        assert_eq!(reader.last_sequence(), Some(0));
+
+        // Dropouts report the sequence number of the item actually read
+        for i in 1..20 {
+            writer.write(i);
+        }
+        assert_eq!(reader.read(), ReadResult::Dropout(17));
+        assert_eq!(reader.last_sequence(), Some(17));
+        assert_eq!(reader.read(), ReadResult::Ok(18));
+        assert_eq!(reader.last_sequence(), Some(18));
+    }
+}
+
+#[test]
+fn test_sequence_numbers_strictly_increasing() {
+    let (mut reader, mut writer) = ring_buffer::<u64>(4);
+
+    // A million writes wrap around a 16-bit lap count many times over
+    let mut last_sequence = None;
+    for i in 0..iterations(1_000_000, 1000) as u64 {
+        assert_eq!(writer.next_sequence(), i);
+        writer.write(i);
+        assert_eq!(writer.last_sequence(), Some(i));
+
+        assert_eq!(reader.read(), ReadResult::Ok(i));
+        let sequence = reader.last_sequence().unwrap();
+        assert!(last_sequence < Some(sequence));
+        assert_eq!(sequence, i);
+        last_sequence = Some(sequence);
+    }
+}
+
+#[test]
+fn test_sequence_numbers_lapped_65536_times() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(2);
+
+    writer.write(0);
+    assert_eq!(reader.read(), ReadResult::Ok(0));
+
+    // Being lapped exactly 65536 times is still detected as a dropout
+    for i in 1..(2 * 65536 + 2) {
+        writer.write(i);
+    }
+    assert_eq!(reader.read(), ReadResult::Dropout(2 * 65536 + 1));
+    assert_eq!(reader.last_sequence(), Some(2 * 65536 + 1));
+    assert_eq!(reader.read(), ReadResult::Empty);
+}
+
+storage_test! {
+    fn test_reader_stats_lapped_once_one_thread(reader, writer: usize, 32) {
+        assert_eq!(reader.stats(), ReaderStats::default());
+
+        // one read, capacity+1 writes
+        for i in 0..ROUNDS {
+            assert_eq!(reader.read(), ReadResult::Empty);
+
+            for _ in 0..33 {
+                writer.write(i);
+            }
+
+            assert_eq!(reader.read(), ReadResult::Dropout(i));
+            assert_eq!(reader.read(), ReadResult::Empty);
+        }
+
+        assert_eq!(
+            reader.stats(),
+            ReaderStats {
+                reads: 3 * ROUNDS as u64,
+                ok: 0,
+                dropouts: ROUNDS as u64,
+                empty: 2 * ROUNDS as u64,
+                lost: 32 * ROUNDS as u64,
+                skips: 0,
+                skipped: 0,
+                contended_reads: 0,
+                max_spins: 0,
+                busy: 0,
+            }
+        );
+
+        // Clones count for themselves
+        let clone = reader.clone();
+        assert_eq!(clone.stats(), ReaderStats::default());
+
+        reader.reset_stats();
+        assert_eq!(reader.stats(), ReaderStats::default());
+
+        // Skipping ahead is counted separately from dropouts
+        for i in 0..10 {
+            writer.write(i);
+        }
+        assert_eq!(reader.read(), ReadResult::Ok(0));
+        reader.skip_ahead();
+        assert_eq!(reader.read(), ReadResult::Dropout(9));
+        assert_eq!(
+            reader.stats(),
+            ReaderStats {
+                reads: 2,
+                ok: 1,
+                dropouts: 0,
+                empty: 0,
+                lost: 0,
+                skips: 1,
+                skipped: 8,
+                contended_reads: 0,
+                max_spins: 0,
+                busy: 0,
+            }
+        );
+    }
+}
+
+storage_test! {
+    fn test_writer_stats_one_thread(reader, writer: usize, 8) {
+        assert_eq!(writer.stats(), WriterStats::default());
+
+        for i in 0..20 {
+            writer.write(i);
+        }
+        assert_eq!(
+            writer.stats(),
+            WriterStats {
+                writes: 20,
+                laps: 2,
+                contended_writes: 0,
+                max_spins: 0,
+                busy: 0,
+                rejected: 0,
+            }
+        );
+
+        writer.reset_stats();
+        assert_eq!(writer.stats(), WriterStats::default());
+        assert_eq!(reader.read(), ReadResult::Dropout(16));
+    }
+}
+
+storage_test! {
+    #[cfg_attr(miri, ignore = "depends on timing, which Miri doesn't reproduce")]
+    fn test_writer_stats_contended(reader, writer: usize, 4) {
+        use crate::storage::sealed::Sealed;
+
+        // Hold a read lock on the item that the writer writes to next, as
+        // if a reader was busy copying it
+        let item = &reader.storage.items()[0];
+        item.acquire_read(&SpinPolicy::default());
+
+        std::thread::scope(|s| {
+            let writer_thread = s.spawn(move || {
+                writer.write(1);
+                writer.stats()
+            });
+
+            std::thread::sleep(Duration::from_millis(10));
+            assert_eq!(reader.write_sequence(), 0);
+            item.release_read();
+
+            let stats = writer_thread.join().unwrap();
+            assert_eq!(stats.writes, 1);
+            assert_eq!(stats.contended_writes, 1);
+            assert!(stats.max_spins > 0);
+        });
+
+        assert_eq!(reader.read(), ReadResult::Ok(1));
+    }
+}
+
+storage_test! {
+    fn test_on_dropout_one_thread(reader, writer: usize, 8) {
+        use std::sync::{Arc, Mutex};
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events_in_callback = Arc::clone(&events);
+        reader.set_on_dropout(move |event| events_in_callback.lock().unwrap().push(event));
+
+        // Lapped by differing amounts, read with both read and read_latest
+        let mut dropouts = 0;
+        let mut value = 0;
+        for i in 0..100 {
+            for _ in 0..(i % 13) {
+                writer.write(value);
+                value += 1;
+            }
+            let result = if i % 3 == 0 {
+                reader.read_latest()
+            } else {
+                reader.read()
+            };
+            if result.is_dropout() {
+                dropouts += 1;
+            }
+            assert_eq!(events.lock().unwrap().len(), dropouts);
+        }
+        assert!(dropouts > 0);
+
+        // The event describes the item read
+        while reader.read().value().is_some() {}
+        events.lock().unwrap().clear();
+        for _ in 0..20 {
+            writer.write(value);
+            value += 1;
+        }
+        reader.skip_ahead();
+        assert_eq!(reader.read(), ReadResult::Dropout(value - 1));
+        let sequence = value as u64 - 1;
+        assert_eq!(
+            *events.lock().unwrap(),
+            [DropoutEvent {
+                reader: reader.id(),
+                sequence,
+                index: (sequence % 8) as usize,
+                lost: 19,
+                skipped: true,
+            }]
+        );
+
+        reader.clear_on_dropout();
+        reader.skip_ahead();
+        assert!(reader.read().is_dropout());
+        assert_eq!(events.lock().unwrap().len(), 1);
+    }
+}
+
+storage_test! {
+    fn test_read_into_one_thread(reader, writer: usize, 8) {
+        let mut out = [0; 5];
+        assert_eq!(reader.read_into(&mut out), ReadBatch::default());
+
+        // Partially filled output
+        for i in 0..3 {
+            writer.write(i);
+        }
+        let batch = reader.read_into(&mut out);
+        assert_eq!(batch.count, 3);
+        assert!(!batch.dropout);
+        assert_eq!(out[..3], [0, 1, 2]);
+
+        // Wrapping around in the middle of the batch
+        for i in 3..10 {
+            writer.write(i);
+        }
+        let batch = reader.read_into(&mut out);
+        assert_eq!(batch.count, 5);
+        assert!(!batch.dropout);
+        assert_eq!(out, [3, 4, 5, 6, 7]);
+        let batch = reader.read_into(&mut out);
+        assert_eq!(batch.count, 2);
+        assert_eq!(out[..2], [8, 9]);
+
+        // Being lapped
+        for i in 10..30 {
+            writer.write(i);
+        }
+        let mut out = [0; 16];
+        let batch = reader.read_into(&mut out);
+        assert_eq!(batch.count, 4);
+        assert!(batch.dropout);
+        assert_eq!(out[..4], [26, 27, 28, 29]);
+
+        // An empty output reads nothing
+        writer.write(30);
+        assert_eq!(reader.read_into(&mut []), ReadBatch::default());
+
+        writer.close();
+        let batch = reader.read_into(&mut out);
+        assert_eq!(batch.count, 1);
+        assert!(batch.closed);
+        assert_eq!(out[0], 30);
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore = "depends on timing, which Miri doesn't reproduce")]
+fn test_read_into_dropout_mid_batch() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    // The writer can only overtake the reader in the middle of a batch while
+    // the reader is busy copying it. Make the ring buffer large enough that
+    // the reader spends most of its time copying batches, so that this also
+    // happens when both threads share a single CPU.
+    let (mut reader, mut writer) = ring_buffer::<u64>(1 << 16);
+    let done = &AtomicBool::new(false);
+
+    std::thread::scope(|s| {
+        s.spawn(move || {
+            // Keep writing until the reader saw what it was waiting for, but
+            // give up eventually
+            let mut i = 0;
+            while !done.load(Ordering::Relaxed) && i < 100_000_000 {
+                writer.write(i);
+                i += 1;
+            }
+        });
+
+        let mut out = vec![0; 4096];
+        let mut next = 0;
+        let mut seen_dropout_mid_batch = false;
+        while !seen_dropout_mid_batch {
+            let batch = reader.read_into(&mut out);
+            let values = &out[..batch.count];
+            assert!(!batch.closed, "the writer gave up");
+            if values.is_empty() {
+                std::thread::yield_now();
+                continue;
+            }
+
+            // Values are derived from their sequence numbers, so the batch
+            // must be increasing, with gaps only if there was a dropout
+            assert!(values.windows(2).all(|w| w[0] < w[1]));
+            let contiguous = values[0] == next && values.windows(2).all(|w| w[0] + 1 == w[1]);
+            assert_eq!(batch.dropout, !contiguous);
+
+            seen_dropout_mid_batch = batch.dropout && values[0] == next;
+            next = values[batch.count - 1] + 1;
+        }
+        done.store(true, Ordering::Relaxed);
+    });
+}
+
+storage_test! {
+    fn test_read_all_available_one_thread(reader, writer: usize, 8) {
+        assert_eq!(reader.read_all_available(), (vec![], false));
+
+        for i in 0..5 {
+            writer.write(i);
+        }
+        assert_eq!(reader.read_all_available(), (vec![0, 1, 2, 3, 4], false));
+        assert_eq!(reader.read_all_available(), (vec![], false));
+
+        // A full buffer, wrapping around
+        for i in 5..13 {
+            writer.write(i);
+        }
+        assert_eq!(
+            reader.read_all_available(),
+            (vec![5, 6, 7, 8, 9, 10, 11, 12], false)
+        );
+
+        // Lapped
+        for i in 13..30 {
+            writer.write(i);
+        }
+        assert_eq!(reader.read_all_available(), (vec![29], true));
+
+        writer.write(30);
+        writer.close();
+        assert_eq!(reader.read_all_available(), (vec![30], false));
+        assert_eq!(reader.read(), ReadResult::Closed);
+    }
+}
+
+#[test]
+fn test_read_all_available_lapped_mid_drain() {
+    let (mut reader, mut writer) = ring_buffer::<u64>(64);
+    let count = iterations(1_000_000, 1000) as u64;
+
+    std::thread::scope(|s| {
+        s.spawn(move || {
+            for i in 0..count {
+                writer.write(i);
+            }
+        });
+
+        let mut next = 0;
+        loop {
+            let (values, dropout) = reader.read_all_available();
+            assert!(values.len() <= 64);
+            if values.is_empty() {
+                if reader.is_disconnected() && reader.read().is_closed() {
+                    break;
+                }
+                std::thread::yield_now();
+                continue;
+            }
+
+            // Values are derived from their sequence numbers, so gaps
+            // anywhere in the result mean that there was a dropout
+            assert!(values.windows(2).all(|w| w[0] < w[1]));
+            let contiguous = values[0] == next && values.windows(2).all(|w| w[0] + 1 == w[1]);
+            assert_eq!(dropout, !contiguous);
+            next = values[values.len() - 1] + 1;
+        }
+        assert_eq!(next, count);
+    });
+}
+
+storage_test! {
+    fn test_write_slice_one_thread(reader, writer: usize, 8) {
+        writer.write_slice(&[]);
+        assert_eq!(writer.next_sequence(), 0);
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        writer.write_slice(&[0, 1, 2, 3, 4]);
+        assert_eq!(reader.read_all_available(), (vec![0, 1, 2, 3, 4], false));
+
+        // Spanning the wrap
+        writer.write_slice(&[5, 6, 7, 8, 9, 10, 11]);
+        assert_eq!(writer.next_sequence(), 12);
+        assert_eq!(reader.available(), 7);
+        let mut out = [0; 7];
+        assert_eq!(reader.read_into(&mut out).count, 7);
+        assert_eq!(out, [5, 6, 7, 8, 9, 10, 11]);
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        // Longer than the buffer, only the last 8 values are kept
+        let values: Vec<usize> = (12..32).collect();
+        writer.write_slice(&values);
+        assert_eq!(writer.next_sequence(), 32);
+        assert_eq!(dropout(reader.read_detailed()), (28, 16));
+        assert_eq!(reader.read_all_available(), (vec![29, 30, 31], false));
+
+        reader.seek_to_oldest();
+        assert_eq!(
+            reader.read_all_available(),
+            (vec![25, 26, 27, 28, 29, 30, 31], false)
+        );
+
+        let stats = writer.stats();
+        assert_eq!(stats.writes, 32);
+        assert_eq!(stats.laps, 4);
+        assert_eq!(stats.contended_writes, 0);
+    }
+}
+
+storage_test! {
+    fn test_write_slice_matches_write(reader, writer: usize, 8) {
+        let (mut loop_reader, mut loop_writer) = ring_buffer::<usize>(8);
+
+        // Interleave slices of all sorts of lengths with reads
+        let mut value = 0;
+        for i in 0..200 {
+            let values: Vec<usize> = (value..(value + i % 19)).collect();
+            value += values.len();
+
+            writer.write_slice(&values);
+            for v in &values {
+                loop_writer.write(*v);
+            }
+            assert_eq!(writer.next_sequence(), loop_writer.next_sequence());
+
+            for _ in 0..(i % 5) {
+                assert_eq!(reader.read_detailed(), loop_reader.read_detailed());
+            }
+        }
+    }
+}
+
+storage_test! {
+    fn test_write_iter_one_thread(reader, writer: usize, 8) {
+        assert_eq!(writer.write_iter(std::iter::empty()), 0);
+        assert_eq!(writer.next_sequence(), 0);
+
+        assert_eq!(writer.write_iter(0..5), 5);
+        assert_eq!(reader.read_all_available(), (vec![0, 1, 2, 3, 4], false));
+
+        // Filling exactly up to the end of the buffer
+        assert_eq!(writer.write_iter(5..8), 3);
+        assert_eq!(reader.read_all_available(), (vec![5, 6, 7], false));
+
+        // Longer than the buffer
+        assert_eq!(writer.write_iter(8..28), 20);
+        assert_eq!(writer.next_sequence(), 28);
+        assert_eq!(dropout(reader.read_detailed()), (24, 16));
+        assert_eq!(reader.read_all_available(), (vec![25, 26, 27], false));
+        assert_eq!(writer.stats().writes, 28);
+        assert_eq!(writer.stats().laps, 3);
+    }
+}
+
+storage_test! {
+    fn test_write_iter_panic(reader, writer: usize, 8) {
+        // The items produced before the panic are written and unlocked
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            writer.write_iter((0..5).map(|i| if i < 3 { i } else { panic!() }));
+        }));
+        assert!(result.is_err());
+        assert_eq!(writer.next_sequence(), 3);
+        assert_eq!(reader.read_all_available(), (vec![0, 1, 2], false));
+
+        writer.write(3);
+        assert_eq!(reader.read(), ReadResult::Ok(3));
+    }
+}
+
+#[test]
+fn test_write_iter_concurrent_reader() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+
+    std::thread::scope(|s| {
+        let reader_thread = s.spawn(move || {
+            let mut last_value = None;
+            loop {
+                match reader.read() {
+                    ReadResult::Ok(v) | ReadResult::Dropout(v) => {
+                        assert!(last_value < Some(v));
+                        last_value = Some(v);
+                    }
+                    ReadResult::Empty => std::thread::yield_now(),
+                    ReadResult::Closed => break,
+                }
+            }
+            last_value
+        });
+
+        // Decode in batches of varying sizes
+        let count = iterations(100_000, 1000);
+        let mut value = 0;
+        for i in 0..1000 {
+            let end = (value + i % 200).min(count);
+            assert_eq!(writer.write_iter(value..end), end - value);
+            value = end;
+        }
+        assert_eq!(writer.write_iter(value..count), count - value);
+        writer.close();
+
+        assert_eq!(reader_thread.join().unwrap(), Some(count - 1));
+    });
+}
+
+storage_test! {
+    fn test_write_with_one_thread(reader, writer: [usize; 4], 4) {
+        writer.write_with(|value| value[1] = 1);
+        assert_eq!(reader.read(), ReadResult::Ok([0, 1, 0, 0]));
+
+        // The closure sees the previous contents of the item
+        for i in 0..4 {
+            writer.write([i; 4]);
+        }
+        writer.write_with(|value| value[0] = 9);
+        assert_eq!(reader.read(), ReadResult::Dropout([9, 0, 0, 0]));
+        assert_eq!(writer.next_sequence(), 6);
+    }
+}
+
+storage_test! {
+    fn test_write_with_panic(reader, writer: [usize; 4], 4) {
+        writer.write([1; 4]);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            writer.write_with(|value| {
+                value[0] = 2;
+                panic!("failed to fill the item");
+            });
+        }));
+        assert!(result.is_err());
+
+        // The item was reset and written, and the writer is still usable
+        assert_eq!(reader.read(), ReadResult::Ok([1; 4]));
+        assert_eq!(reader.read(), ReadResult::Ok([0; 4]));
+        writer.write_with(|value| *value = [3; 4]);
+        assert_eq!(reader.read(), ReadResult::Ok([3; 4]));
+    }
+}
+
+storage_test! {
+    fn test_read_with(reader, writer: [usize; 4], 4) {
+        assert_eq!(reader.read_with(|_| unreachable!()), ReadResult::Empty);
+
+        writer.write([1, 2, 3, 4]);
+        assert_eq!(reader.read_with(|value| value[2]), ReadResult::Ok(3));
+        assert_eq!(reader.read_with(|_| unreachable!()), ReadResult::Empty);
+
+        for i in 0..6 {
+            writer.write([i; 4]);
+        }
+        let expected = reader.clone().read().map(|value| value[0]);
+        assert!(expected.is_dropout());
+        assert_eq!(reader.read_with(|value| value[0]), expected);
+
+        drop(writer);
+        while reader.read_with(|_| ()).value().is_some() {}
+        assert_eq!(reader.read_with(|_| unreachable!()), ReadResult::Closed);
+    }
+}
+
+storage_test! {
+    fn test_read_with_panic(reader, writer: [usize; 4], 4) {
+        writer.write([1; 4]);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            reader.read_with(|_| panic!("failed to look at the item"));
+        }));
+        assert!(result.is_err());
+
+        // The item was unlocked again, so the writer can overwrite it without
+        // waiting, and the reader is still where it was
+        assert_eq!(reader.read(), ReadResult::Ok([1; 4]));
+        for i in 2..=4 {
+            writer.write([i; 4]);
+        }
+        assert_eq!(writer.try_write([5; 4]), Ok(()));
+        assert_eq!(reader.read(), ReadResult::Ok([2; 4]));
+        assert_eq!(reader.read_with(|value| value[0]), ReadResult::Ok(3));
+    }
+}
+
+storage_test! {
+    fn test_write_returning_evicted(reader, writer: usize, 4) {
+        for i in 0..4 {
+            assert_eq!(writer.write_returning_evicted(10 + i), None);
+        }
+        for i in 4..11 {
+            assert_eq!(writer.write_returning_evicted(10 + i), Some(10 + i - 4));
+        }
+
+        // Mixing with other kinds of writes
+        writer.write_slice(&[21, 22]);
+        assert_eq!(writer.write_returning_evicted(23), Some(19));
+        assert_eq!(writer.next_sequence(), 14);
+        assert_eq!(reader.read(), ReadResult::Dropout(22));
+        assert_eq!(reader.read_all_available(), (vec![23], false));
+    }
+}
+
+storage_test! {
+    fn test_last_written(reader, writer: usize, 4) {
+        assert_eq!(writer.last_written(), None);
+
+        writer.write(1);
+        assert_eq!(writer.last_written(), Some(1));
+
+        // Right after wrapping around
+        writer.write_slice(&[2, 3, 4]);
+        assert_eq!(writer.last_written(), Some(4));
+        writer.write(5);
+        assert_eq!(writer.last_written(), Some(5));
+
+        // Aborted writes don't count
+        let mut guard = writer.reserve();
+        *guard = 6;
+        drop(guard);
+        assert_eq!(writer.last_written(), Some(5));
+
+        assert_eq!(reader.read(), ReadResult::Dropout(5));
+        assert_eq!(writer.last_written(), Some(5));
+    }
+}
+
+storage_test! {
+    fn test_snapshot_one_thread(reader, writer: usize, 8) {
+        assert_eq!(reader.snapshot(), vec![]);
+
+        // Partially filled
+        writer.write_slice(&[0, 1, 2]);
+        assert_eq!(reader.snapshot(), vec![0, 1, 2]);
+        assert_eq!(reader.read(), ReadResult::Ok(0));
+        assert_eq!(reader.snapshot(), vec![0, 1, 2]);
+
+        // Wrapped several times
+        writer.write_iter(3..35);
+        assert_eq!(reader.snapshot(), (27..35).collect::<Vec<_>>());
+        writer.write(35);
+        assert_eq!(reader.snapshot(), (28..36).collect::<Vec<_>>());
+
+        // The reader didn't move
+        assert_eq!(reader.read(), ReadResult::Dropout(33));
+    }
+}
+
+#[test]
+fn test_snapshot_detailed() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(4);
+    writer.write_slice(&[0, 1]);
+    let snapshot = reader.snapshot_detailed();
+    assert_eq!(snapshot.first_sequence, 0);
+    assert_eq!(snapshot.write_sequence, 2);
+    assert_eq!(snapshot.read_sequence, 0);
+
+    // After skipping ahead, the reader is going to read the latest item,
+    // even before the writer wrapped around
+    reader.skip_ahead();
+    let snapshot = reader.snapshot_detailed();
+    assert_eq!(snapshot.read_sequence, 1);
+    assert_eq!(snapshot.items, [0, 1]);
+    assert_eq!(reader.read_indexed(), ReadResult::Dropout((1, 1)));
+
+    writer.write_iter(2..7);
+    reader.skip_ahead();
+    assert_eq!(reader.snapshot_detailed().read_sequence, 6);
+}
+
+#[test]
+fn test_snapshot_concurrent_writer() {
+    let (reader, mut writer) = ring_buffer::<usize>(16);
+    let count = iterations(200_000, 1000);
+
+    std::thread::scope(|s| {
+        s.spawn(move || {
+            for i in 0..count {
+                writer.write(i);
+            }
+        });
+
+        while !reader.is_disconnected() {
+            let values = reader.snapshot();
+            assert!(values.len() <= 16);
+            assert!(values.windows(2).all(|w| w[0] + 1 == w[1]));
+        }
+        assert_eq!(reader.snapshot(), (count - 16..count).collect::<Vec<_>>());
+    });
+}
+
+storage_test! {
+    fn test_read_last_n_one_thread(reader, writer: usize, 8) {
+        let mut out = vec![99];
+        reader.read_last_n(4, &mut out);
+        assert_eq!(out, vec![]);
+        assert_eq!(reader.last_sequence(), None);
+
+        // Fewer writes than capacity
+        writer.write_slice(&[0, 1, 2, 3, 4]);
+        reader.read_last_n(2, &mut out);
+        assert_eq!(out, vec![3, 4]);
+        assert_eq!(reader.read(), ReadResult::Empty);
+        assert_eq!(reader.last_sequence(), Some(4));
+
+        writer.write_slice(&[5, 6]);
+        reader.read_last_n(7, &mut out);
+        assert_eq!(out, (0..7).collect::<Vec<_>>());
+        reader.read_last_n(8, &mut out);
+        assert_eq!(out, (0..7).collect::<Vec<_>>());
+        reader.read_last_n(100, &mut out);
+        assert_eq!(out, (0..7).collect::<Vec<_>>());
+
+        // More writes than capacity
+        writer.write_iter(7..30);
+        reader.read_last_n(3, &mut out);
+        assert_eq!(out, vec![27, 28, 29]);
+        reader.read_last_n(8, &mut out);
+        assert_eq!(out, (22..30).collect::<Vec<_>>());
+        reader.read_last_n(30, &mut out);
+        assert_eq!(out, (22..30).collect::<Vec<_>>());
+        reader.read_last_n(0, &mut out);
+        assert_eq!(out, vec![]);
+
+        // The reader continues after the latest item
+        assert_eq!(reader.read(), ReadResult::Empty);
+        writer.write(30);
+        assert_eq!(reader.read(), ReadResult::Ok(30));
+    }
+}
+
+#[test]
+fn test_read_last_n_concurrent_writer() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(16);
+    let count = iterations(200_000, 1000);
+
+    std::thread::scope(|s| {
+        s.spawn(move || {
+            for i in 0..count {
+                writer.write(i);
+            }
+        });
+
+        let mut out = Vec::new();
+        while !reader.is_disconnected() {
+            reader.read_last_n(10, &mut out);
+            assert!(out.len() <= 10);
+            assert!(out.windows(2).all(|w| w[0] + 1 == w[1]));
+        }
+        reader.read_last_n(10, &mut out);
+        assert_eq!(out, (count - 10..count).collect::<Vec<_>>());
+        assert_eq!(reader.read(), ReadResult::Closed);
+    });
+}
+
+storage_test! {
+    fn test_iter_available_one_thread(reader, writer: usize, 8) {
+        assert_eq!(reader.iter_available().count(), 0);
+
+        writer.write_slice(&[0, 1, 2, 3]);
+        assert_eq!(reader.iter_available().collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+
+        // Interleaved with reads
+        assert_eq!(reader.read(), ReadResult::Ok(0));
+        assert_eq!(reader.iter_available().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(reader.read(), ReadResult::Ok(1));
+
+        // Across the wrap and up to a full buffer
+        writer.write_iter(4..10);
+        assert_eq!(reader.iter_available().collect::<Vec<_>>(), (2..10).collect::<Vec<_>>());
+        assert_eq!(reader.read_all_available(), ((2..10).collect(), false));
+        assert_eq!(reader.iter_available().count(), 0);
+
+        // After being overtaken, the next read is a dropout
+        writer.write_iter(10..30);
+        assert_eq!(reader.iter_available().count(), 0);
+        assert_eq!(reader.read(), ReadResult::Dropout(26));
+        assert_eq!(reader.iter_available().collect::<Vec<_>>(), vec![27, 28, 29]);
+
+        // Stopping partway
+        let mut iter = reader.iter_available();
+        assert_eq!(iter.next(), Some(27));
+        drop(iter);
+        assert_eq!(reader.read(), ReadResult::Ok(27));
+    }
+}
+
+#[test]
+fn test_iter_available_concurrent_writer() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(16);
+
+    std::thread::scope(|s| {
+        s.spawn(move || {
+            for i in 0..iterations(200_000, 1000) {
+                writer.write(i);
+            }
+        });
+
+        while !reader.is_disconnected() {
+            let expected_next = reader.last_sequence().map_or(0, |s| s as usize + 1);
+            let values: Vec<usize> = reader.iter_available().collect();
+            assert!(values.len() <= 16);
+            assert!(values.windows(2).all(|w| w[0] + 1 == w[1]));
+            if let Some(first) = values.first() {
+                assert_eq!(*first, expected_next);
+            }
+
+            // Reading afterwards is unaffected, and either starts with the
+            // same item or has been overtaken in the meantime
+            match reader.read() {
+                ReadResult::Ok(v) => assert_eq!(v, expected_next),
+                ReadResult::Dropout(v) => assert!(v > expected_next),
+                ReadResult::Empty => assert!(values.is_empty()),
+                ReadResult::Closed => break,
+            }
+        }
+    });
+}
+
+storage_test! {
+    fn test_try_iter_one_thread(reader, writer: usize, 8) {
+        assert_eq!(reader.try_iter().next(), None);
+
+        writer.write_slice(&[0, 1, 2]);
+        let mut received = Vec::new();
+        for v in reader.try_iter() {
+            received.push(v);
+        }
+        assert_eq!(received, vec![0, 1, 2]);
+
+        // A second call picks up new writes
+        writer.write_slice(&[3, 4]);
+        let mut iter = reader.try_iter();
+        assert_eq!(iter.by_ref().collect::<Vec<_>>(), vec![3, 4]);
+        assert!(!iter.dropped());
+        assert_eq!(iter.next(), None);
+
+        // Dropouts don't end the iterator
+        writer.write_iter(5..20);
+        let mut iter = reader.try_iter();
+        assert_eq!(iter.by_ref().collect::<Vec<_>>(), vec![13, 14, 15, 16, 17, 18, 19]);
+        assert!(iter.dropped());
+
+        writer.write(20);
+        writer.close();
+        assert_eq!(reader.try_iter().collect::<Vec<_>>(), vec![20]);
+        assert_eq!(reader.read(), ReadResult::Closed);
+    }
+}
+
+storage_test! {
+    fn test_into_iter_dropout_policy(reader, writer: usize, 4) {
+        let mut skipping = reader.clone().into_iter().with_dropout_policy(DropoutPolicy::Skip);
+        let mut stopping = reader.clone().into_iter().with_dropout_policy(DropoutPolicy::Stop);
+
+        writer.write_iter(0..10);
+        writer.close();
+
+        // The dropout delivers 8, which is only yielded by default
+        assert_eq!(reader.into_iter().collect::<Vec<_>>(), vec![8, 9]);
+        assert_eq!(skipping.by_ref().collect::<Vec<_>>(), vec![9]);
+        assert_eq!(skipping.next(), None);
+        assert_eq!(stopping.by_ref().collect::<Vec<_>>(), vec![]);
+        assert_eq!(stopping.into_inner().read(), ReadResult::Ok(9));
+    }
+}
+
+#[test]
+fn test_into_iter_for_loop() {
+    let (reader, mut writer) = ring_buffer::<usize>(64);
+    const COUNT: usize = iterations(100_000, 1000);
+
+    let writer_thread = std::thread::spawn(move || {
+        for i in 0..COUNT {
+            writer.write(i);
+            if i % 64 == 0 {
+                std::thread::yield_now();
+            }
+        }
+    });
+
+    let mut last_value = None;
+    let mut count = 0;
+    for value in reader {
+        assert!(last_value < Some(value));
+        last_value = Some(value);
+        count += 1;
+    }
+
+    writer_thread.join().unwrap();
+    assert_eq!(last_value, Some(COUNT - 1));
+    assert!(count > 0);
+}
+
+storage_test! {
+    fn test_dispatch_one_thread(reader, writer: usize, 8) {
+        let broadcast = reader.clone();
+        let mut worker1 = reader.into_dispatch();
+        let mut worker2 = worker1.clone();
+        assert_eq!(worker1.read(), ReadResult::Empty);
+
+        // Each item goes to only one of the workers
+        writer.write_slice(&[0, 1, 2, 3]);
+        assert_eq!(worker1.read(), ReadResult::Ok(0));
+        assert_eq!(worker2.read(), ReadResult::Ok(1));
+        assert_eq!(worker2.read(), ReadResult::Ok(2));
+        assert_eq!(worker1.read(), ReadResult::Ok(3));
+        assert_eq!(worker1.read(), ReadResult::Empty);
+        assert_eq!(worker2.read(), ReadResult::Empty);
+
+        // Falling behind continues with the oldest item
+        writer.write_iter(4..20);
+        assert_eq!(worker2.read(), ReadResult::Dropout(12));
+        assert_eq!(worker1.read(), ReadResult::Ok(13));
+
+        // Other readers still receive everything
+        let mut broadcast = broadcast;
+        assert_eq!(broadcast.read(), ReadResult::Dropout(16));
+
+        // A dispatch reader created after skipping ahead starts at the latest item
+        let mut skipped = broadcast.clone();
+        skipped.skip_ahead();
+        let mut skipped = skipped.into_dispatch();
+        assert_eq!(skipped.read(), ReadResult::Ok(19));
+
+        writer.close();
+        for _ in 14..20 {
+            assert!(worker1.read().is_ok());
+        }
+        assert_eq!(worker1.read(), ReadResult::Closed);
+        assert_eq!(worker2.read(), ReadResult::Closed);
+    }
+}
+
+#[test]
+fn test_dispatch_workers_no_duplicates() {
+    let (reader, mut writer) = ring_buffer::<usize>(32);
+    let worker: DispatchReader<usize> = reader.into_dispatch();
+
+    std::thread::scope(|s| {
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let mut worker = worker.clone();
+                s.spawn(move || {
+                    let mut received = Vec::new();
+                    loop {
+                        match worker.read() {
+                            ReadResult::Ok(v) | ReadResult::Dropout(v) => received.push(v),
+                            ReadResult::Empty => std::thread::yield_now(),
+                            ReadResult::Closed => return received,
+                        }
+                    }
+                })
+            })
+            .collect();
+        drop(worker);
+
+        for i in 0..iterations(100_000, 1000) {
+            writer.write(i);
+            if i % 16 == 0 {
+                std::thread::yield_now();
+            }
+        }
+        writer.close();
+
+        let mut all = Vec::new();
+        for worker in workers {
+            let received = worker.join().unwrap();
+
+            // Each worker receives its items in order
+            assert!(received.windows(2).all(|w| w[0] < w[1]));
+            all.extend(received);
+        }
+
+        // No item was received twice
+        let count = all.len();
+        all.sort();
+        all.dedup();
+        assert_eq!(all.len(), count);
+        assert!(count > 0);
+    });
+}
+
+storage_test! {
+    fn test_filtered_one_thread(reader, writer: usize, 8) {
+        let mut nothing = reader.clone().filtered(|_| false);
+        let mut everything = reader.clone().filtered(|_| true);
+        let mut even = reader.filtered(|v| v % 2 == 0);
+
+        writer.write_slice(&[1, 2, 3, 4, 5]);
+        assert_eq!(nothing.read(), ReadResult::Empty);
+        assert_eq!(nothing.read(), ReadResult::Empty);
+        assert_eq!(everything.read(), ReadResult::Ok(1));
+        assert_eq!(everything.read(), ReadResult::Ok(2));
+        assert_eq!(even.read(), ReadResult::Ok(2));
+        assert_eq!(even.read(), ReadResult::Ok(4));
+        assert_eq!(even.read(), ReadResult::Empty);
+
+        // A dropout among skipped items is reported with the next match
+        writer.write_iter(6..16);
+        writer.write(17);
+        assert_eq!(even.read(), ReadResult::Dropout(14));
+        assert_eq!(even.read(), ReadResult::Empty);
+        writer.write_iter([19, 21, 23, 25, 27, 29, 31, 33, 35]);
+        assert_eq!(even.read(), ReadResult::Empty);
+        writer.write(36);
+        assert_eq!(even.read(), ReadResult::Dropout(36));
+        assert_eq!(nothing.read(), ReadResult::Empty);
+
+        // Skipping ahead
+        writer.write_slice(&[38, 39]);
+        even.skip_ahead();
+        assert_eq!(even.read(), ReadResult::Empty);
+        writer.write(40);
+        assert_eq!(even.read(), ReadResult::Dropout(40));
+        everything.skip_ahead();
+        assert_eq!(everything.read(), ReadResult::Dropout(40));
+        assert_eq!(everything.read(), ReadResult::Empty);
+
+        writer.close();
+        assert_eq!(even.read(), ReadResult::Closed);
+        assert_eq!(nothing.read(), ReadResult::Closed);
+        assert_eq!(even.into_inner().read(), ReadResult::Closed);
+    }
+}
+
+#[test]
+fn test_select_one_thread() {
+    let buffers: Vec<_> = (0..3).map(|_| ring_buffer::<usize>(16)).collect();
+    let (readers, mut writers): (Vec<_>, Vec<_>) = buffers.into_iter().unzip();
+    let mut select = ReadSelect::new(readers);
+    assert_eq!(select.try_select(), None);
+
+    // A busy reader takes turns with the others
+    writers[0].write_slice(&[0, 1, 2, 3]);
+    writers[1].write(10);
+    writers[2].write(20);
+    assert_eq!(select.select(), (0, ReadResult::Ok(0)));
+    assert_eq!(select.select(), (1, ReadResult::Ok(10)));
+    assert_eq!(select.select(), (2, ReadResult::Ok(20)));
+    assert_eq!(select.select(), (0, ReadResult::Ok(1)));
+    writers[2].write(21);
+    assert_eq!(select.select(), (2, ReadResult::Ok(21)));
+    assert_eq!(select.select(), (0, ReadResult::Ok(2)));
+    assert_eq!(select.select(), (0, ReadResult::Ok(3)));
+    assert_eq!(select.try_select(), None);
+
+    writers.remove(1).close();
+    assert_eq!(select.select(), (1, ReadResult::Closed));
+    assert_eq!(select.remove(1).read(), ReadResult::Closed);
+    writers[1].write(22);
+    assert_eq!(select.select(), (1, ReadResult::Ok(22)));
+    assert_eq!(select.len(), 2);
+}
+
+#[test]
+#[cfg_attr(miri, ignore = "depends on timing, which Miri doesn't reproduce")]
+fn test_select_three_writers() {
+    let buffers: Vec<_> = (0..3).map(|_| ring_buffer::<(usize, usize)>(64)).collect();
+    let (readers, writers): (Vec<_>, Vec<_>) = buffers.into_iter().unzip();
+    let mut select = ReadSelect::new(readers);
+
+    std::thread::scope(|s| {
+        for (source, mut writer) in writers.into_iter().enumerate() {
+            s.spawn(move || {
+                // The first source is much busier than the others
+                let count = if source == 0 {
+                    iterations(100_000, 1000)
+                } else {
+                    100
+                };
+                for i in 0..count {
+                    writer.write((source, i));
+                    if source != 0 || i % 64 == 0 {
+                        std::thread::yield_now();
+                    }
+                }
+            });
+        }
+
+        let mut sources = vec![0, 1, 2];
+        let mut received = [0; 3];
+        while !select.is_empty() {
+            let (index, result) = select.select();
+            match result {
+                ReadResult::Ok((source, _)) | ReadResult::Dropout((source, _)) => {
+                    assert_eq!(source, sources[index]);
+                    received[source] += 1;
+                }
+                ReadResult::Empty => panic!("select returned Empty"),
+                ReadResult::Closed => {
+                    select.remove(index);
+                    sources.remove(index);
+                }
+            }
+        }
+
+        // The quieter sources were serviced in full despite the busy one
+        assert!(received[0] > 0);
+        assert_eq!(received[1], 100);
+        assert_eq!(received[2], 100);
+    });
+}
+
+#[test]
+fn test_channel_smoke() {
+    let (mut tx, mut rx) = channel::<i32>(16);
+    tx.send(1).unwrap();
+    assert_eq!(rx.recv(), Ok(1));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+}
+
+#[test]
+fn test_channel_smoke_threads() {
+    let (mut tx, mut rx) = channel::<i32>(16);
+    std::thread::scope(|s| {
+        s.spawn(move || {
+            tx.send(1).unwrap();
+        });
+        assert_eq!(rx.recv(), Ok(1));
+    });
+}
+
+#[test]
+fn test_channel_sender_gone() {
+    let (tx, mut rx) = channel::<i32>(16);
+    drop(tx);
+    assert_eq!(rx.recv(), Err(RecvError::Disconnected));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(1)),
+        Err(RecvTimeoutError::Disconnected)
+    );
+}
+
+#[test]
+fn test_channel_receiver_gone() {
+    let (mut tx, rx) = channel::<i32>(16);
+    drop(rx);
+    assert_eq!(tx.send(1), Err(SendError(1)));
+}
+
+#[test]
+fn test_channel_data_before_disconnect() {
+    let (mut tx, mut rx) = channel::<i32>(16);
+    for i in 0..10 {
+        tx.send(i).unwrap();
+    }
+    drop(tx);
+    for i in 0..10 {
+        assert_eq!(rx.recv(), Ok(i));
+    }
+    assert_eq!(rx.recv(), Err(RecvError::Disconnected));
+}
+
+#[test]
+fn test_channel_recv_timeout() {
+    let (mut tx, mut rx) = channel::<i32>(16);
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(1)),
+        Err(RecvTimeoutError::Timeout)
+    );
+    tx.send(1).unwrap();
+    assert_eq!(rx.recv_timeout(Duration::from_millis(1)), Ok(1));
+
+    std::thread::scope(|s| {
+        s.spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            tx.send(2).unwrap();
+        });
+        assert_eq!(rx.recv_timeout(Duration::from_secs(10)), Ok(2));
+    });
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(1)),
+        Err(RecvTimeoutError::Disconnected)
+    );
+}
+
+#[test]
+fn test_channel_lagged() {
+    let (mut tx, mut rx) = channel::<i32>(4);
+    for i in 0..6 {
+        tx.send(i).unwrap();
+    }
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Lagged(4)));
+    assert_eq!(rx.try_recv(), Ok(4));
+    assert_eq!(rx.try_recv(), Ok(5));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+    for i in 6..14 {
+        tx.send(i).unwrap();
+    }
+    assert_eq!(rx.recv(), Err(RecvError::Lagged(4)));
+    assert_eq!(rx.recv(), Ok(10));
+
+    for i in 14..20 {
+        tx.send(i).unwrap();
+    }
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(1)),
+        Err(RecvTimeoutError::Lagged(8))
+    );
+
+    // The held back message is cloned along with the receiver
+    assert_eq!(rx.clone().recv(), Ok(19));
+    assert_eq!(rx.recv_timeout(Duration::from_millis(1)), Ok(19));
+}
+
+#[test]
+fn test_channel_clone_broadcast() {
+    let (mut tx, mut rx) = channel::<i32>(16);
+    tx.send(1).unwrap();
+    let mut rx2 = rx.clone();
+    tx.send(2).unwrap();
+    assert_eq!(rx.recv(), Ok(1));
+    assert_eq!(rx.recv(), Ok(2));
+    assert_eq!(rx2.recv(), Ok(1));
+    assert_eq!(rx2.recv(), Ok(2));
+
+    drop(rx);
+    tx.send(3).unwrap();
+    drop(rx2);
+    assert_eq!(tx.send(4), Err(SendError(4)));
+}
+
+#[test]
+fn test_channel_iter() {
+    let (mut tx, mut rx) = channel::<i32>(16);
+    for i in 0..3 {
+        tx.send(i).unwrap();
+    }
+    assert!(rx.try_iter().eq(0..3));
+    assert_eq!(rx.try_iter().next(), None);
+
+    std::thread::scope(|s| {
+        s.spawn(move || {
+            for i in 3..6 {
+                tx.send(i).unwrap();
+            }
+        });
+        assert!(rx.iter().eq(3..6));
+    });
+}
+
+#[test]
+fn test_channel_stress() {
+    const COUNT: i32 = iterations(10_000, 100) as i32;
+    let (mut tx, rx) = channel::<i32>(1024);
+    std::thread::scope(|s| {
+        let receivers: Vec<_> = (0..4)
+            .map(|_| {
+                let mut rx = rx.clone();
+                s.spawn(move || {
+                    let mut last = -1;
+                    let mut lags = 0;
+                    loop {
+                        match rx.recv() {
+                            Ok(i) => {
+                                assert!(i > last);
+                                if lags == 0 {
+                                    assert_eq!(i, last + 1);
+                                }
+                                last = i;
+                            }
+                            Err(RecvError::Lagged(_)) => lags += 1,
+                            Err(RecvError::Disconnected) => return last,
+                        }
+                    }
+                })
+            })
+            .collect();
+        drop(rx);
+
+        for i in 0..COUNT {
+            tx.send(i).unwrap();
+            if i % 256 == 0 {
+                std::thread::yield_now();
+            }
+        }
+        drop(tx);
+
+        for receiver in receivers {
+            assert_eq!(receiver.join().unwrap(), COUNT - 1);
+        }
+    });
+}
+
+#[test]
+fn test_watch_one_thread() {
+    let (mut tx, mut rx) = crate::watch::channel(1);
+    assert!(!rx.changed());
+    assert_eq!(rx.latest(), 1);
+    assert_eq!(tx.latest(), 1);
+
+    tx.send(2);
+    let mut rx2 = rx.clone();
+    assert!(rx.changed());
+    assert_eq!(rx.seen(), 1);
+    assert_eq!(rx.latest(), 2);
+    assert!(!rx.changed());
+    assert_eq!(rx.latest(), 2);
+
+    for i in 3..10 {
+        tx.send(i);
+    }
+    assert_eq!(tx.latest(), 9);
+    assert!(rx2.changed());
+    assert_eq!(rx2.latest(), 9);
+    assert_eq!(rx.latest(), 9);
+
+    // Sending the same value again counts as a change
+    tx.send(9);
+    assert!(rx.changed());
+    assert_eq!(rx.latest(), 9);
+    assert!(!rx.changed());
+
+    assert!(tx.has_receivers());
+    drop(tx);
+    assert!(rx.is_closed());
+    assert_eq!(rx.latest(), 9);
+    drop((rx, rx2));
+}
+
+#[test]
+fn test_watch_receivers_converge() {
+    const LAST: u64 = iterations(100_000, 1000) as u64;
+    let (mut tx, rx) = crate::watch::channel(0u64);
+
+    std::thread::scope(|s| {
+        let receivers: Vec<_> = (0..4)
+            .map(|_| {
+                let mut rx = rx.clone();
+                s.spawn(move || {
+                    let mut previous = 0;
+                    while !rx.is_closed() {
+                        let value = rx.latest();
+                        assert!(value >= previous);
+                        previous = value;
+                        std::thread::yield_now();
+                    }
+                    rx.latest()
+                })
+            })
+            .collect();
+
+        for i in 1..=LAST {
+            tx.send(i);
+            if i % 1024 == 0 {
+                std::thread::yield_now();
+            }
+        }
+        drop(tx);
+
+        for receiver in receivers {
+            assert_eq!(receiver.join().unwrap(), LAST);
+        }
+    });
+    assert_eq!(rx.seen(), 0);
+}
+
+#[test]
+fn test_triple_buffer_one_thread() {
+    let (mut reader, mut writer) = crate::triple_buffer::<u32>();
+    assert!(!reader.has_new());
+    assert_eq!(reader.latest(), 0);
+
+    writer.publish(1);
+    assert!(reader.has_new());
+    assert_eq!(reader.latest(), 1);
+    assert!(!reader.has_new());
+    assert_eq!(reader.latest(), 1);
+
+    for i in 2..10 {
+        writer.publish(i);
+    }
+    assert_eq!(reader.latest(), 9);
+    writer.publish(10);
+    writer.publish(11);
+    assert_eq!(reader.latest(), 11);
+    assert_eq!(reader.latest(), 11);
+}
+
+#[test]
+fn test_triple_buffer_no_torn_values() {
+    #[derive(Clone, Copy, Default)]
+    struct Frame([u64; 32]);
+
+    const LAST: u64 = iterations(1_000_000, 1000) as u64;
+    let (mut reader, mut writer) = crate::triple_buffer::<Frame>();
+    let done = std::sync::atomic::AtomicBool::new(false);
+
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            for i in 1..=LAST {
+                writer.publish(Frame([i; 32]));
+                if i % 4096 == 0 {
+                    std::thread::yield_now();
+                }
+            }
+            done.store(true, std::sync::atomic::Ordering::SeqCst);
+        });
+
+        let mut previous = 0;
+        let mut distinct = 0;
+        loop {
+            let finished = done.load(std::sync::atomic::Ordering::SeqCst);
+            let Frame(frame) = reader.latest();
+            assert!(frame.iter().all(|v| *v == frame[0]), "torn value");
+            assert!(frame[0] >= previous);
+            if frame[0] > previous {
+                distinct += 1;
+            }
+            previous = frame[0];
+            if finished {
+                break;
+            }
+        }
+
+        // Once the writer is done, the last value is what the reader sees
+        assert_eq!(previous, LAST);
+        assert!(distinct > 0);
+    });
+}
+
+#[test]
+fn test_bytes_one_thread() {
+    use std::io::{Read, Write};
+
+    let (mut reader, mut writer) = byte_ring_buffer::<4>(4);
+    let mut buf = [0; 16];
+
+    writer.write_all(b"hello").unwrap();
+    assert_eq!(reader.read(&mut buf).unwrap(), 4);
+    assert_eq!(&buf[..4], b"hell");
+    writer.write_all(b", world").unwrap();
+    writer.flush().unwrap();
+    assert_eq!(reader.read(&mut buf).unwrap(), 8);
+    assert_eq!(&buf[..8], b"o, world");
+
+    // Overtaking the reader loses whole chunks
+    let data: Vec<u8> = (0..40).collect();
+    writer.write_all(&data).unwrap();
+    let err = reader.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Other);
+    let lost = err.get_ref().unwrap().downcast_ref::<BytesLost>().unwrap();
+    assert_eq!(*lost, BytesLost(32));
+    assert_eq!(reader.read(&mut buf).unwrap(), 8);
+    assert_eq!(&buf[..8], &data[32..40]);
+
+    writer.write_all(&data[..2]).unwrap();
+    drop(writer);
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, &data[..2]);
+    assert_eq!(reader.read(&mut buf).unwrap(), 0);
+}
+
+#[test]
+fn test_bytes_round_trip() {
+    use std::io::{Read, Write};
+
+    const TOTAL: usize = iterations(4 << 20, 16 << 10);
+    let pattern = |offset: usize| (offset % 251) as u8;
+    let (mut reader, mut writer) = byte_ring_buffer::<1024>(64);
+
+    std::thread::scope(|s| {
+        s.spawn(move || {
+            let data: Vec<u8> = (0..TOTAL).map(pattern).collect();
+            for piece in data.chunks(3000) {
+                writer.write_all(piece).unwrap();
+                std::thread::yield_now();
+            }
+        });
+
+        let mut offset = 0;
+        let mut buf = vec![0; 5000];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    // Whatever was received arrives intact and in place
+                    let expected = (offset..offset + n).map(pattern);
+                    assert!(buf[..n].iter().copied().eq(expected));
+                    offset += n;
+                }
+                Err(err) => {
+                    let lost = err.get_ref().unwrap().downcast_ref::<BytesLost>().unwrap();
+                    offset += lost.0 as usize;
+                }
+            }
+        }
+
+        // Every byte was either received or reported as lost
+        assert_eq!(offset, TOTAL);
+    });
+}
+
+#[test]
+fn test_frames_one_thread() {
+    let (mut reader, mut writer) = frame_buffer(512);
+    let mut frame = Vec::new();
+    assert_eq!(writer.max_frame_len(), 512);
+    assert_eq!(reader.read_frame(&mut frame), ReadResult::Empty);
+
+    let long: Vec<u8> = (0..200).collect();
+    writer.write_frame(b"a").unwrap();
+    writer.write_frame(&[]).unwrap();
+    writer.write_frame(&long).unwrap();
+    assert_eq!(reader.read_frame(&mut frame), ReadResult::Ok(1));
+    assert_eq!(frame, b"a");
+    assert_eq!(reader.read_frame(&mut frame), ReadResult::Ok(0));
+    assert!(frame.is_empty());
+    assert_eq!(reader.read_frame(&mut frame), ReadResult::Ok(200));
+    assert_eq!(frame, long);
+    assert_eq!(reader.read_frame(&mut frame), ReadResult::Empty);
+
+    assert_eq!(
+        writer.write_frame(&[0; 513]),
+        Err(FrameTooLarge {
+            len: 513,
+            max_len: 512
+        })
+    );
+    assert_eq!(reader.read_frame(&mut frame), ReadResult::Empty);
+
+    // Writing more than fits loses whole frames, and the rest of a frame
+    // whose beginning was lost is passed over
+    let mut lagging = reader.clone();
+    writer.write_frame(b"b").unwrap();
+    writer.write_frame(&long).unwrap();
+    writer.write_frame(b"c").unwrap();
+    writer.write_frame(&long[..150]).unwrap();
+    assert_eq!(reader.read_frame(&mut frame), ReadResult::Empty);
+    writer.write_frame(b"d").unwrap();
+    assert_eq!(reader.read_frame(&mut frame), ReadResult::Dropout(1));
+    assert_eq!(frame, b"d");
+    assert_eq!(lagging.read_frame(&mut frame), ReadResult::Dropout(1));
+    assert_eq!(frame, b"d");
+
+    // A frame as large as the whole buffer fits
+    writer.write_frame(&[7; 512]).unwrap();
+    assert_eq!(reader.read_frame(&mut frame), ReadResult::Ok(512));
+    assert_eq!(frame, [7; 512]);
+    drop(writer);
+    assert_eq!(reader.read_frame(&mut frame), ReadResult::Closed);
+    assert!(reader.is_disconnected());
+}
+
+#[test]
+fn test_frames_never_torn() {
+    const CAPACITY: usize = 4096;
+    const COUNT: usize = iterations(20_000, 50);
+    let (reader, mut writer) = frame_buffer(CAPACITY);
+
+    // The content of each frame depends on its length, so that a frame
+    // pieced together from several different ones is caught
+    let fill = |len: usize| (0..len).map(move |i| (len * 7 + i) as u8);
+
+    let mut rng = 12345u64;
+    let lens: Vec<usize> = (0..COUNT)
+        .map(|_| {
+            rng = rng
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            1 + (rng >> 33) as usize % (CAPACITY / 2)
+        })
+        .collect();
+
+    std::thread::scope(|s| {
+        let readers: Vec<_> = (0..2)
+            .map(|_| {
+                let mut reader = reader.clone();
+                let lens = &lens;
+                s.spawn(move || {
+                    let mut frame = Vec::new();
+                    let mut next = 0;
+                    let mut dropouts = 0;
+                    loop {
+                        match reader.read_frame(&mut frame) {
+                            ReadResult::Ok(len) => {
+                                assert!(frame.iter().copied().eq(fill(len)));
+                                if dropouts == 0 {
+                                    assert_eq!(len, lens[next]);
+                                }
+                                next += 1;
+                            }
+                            ReadResult::Dropout(len) => {
+                                assert!(frame.iter().copied().eq(fill(len)));
+                                dropouts += 1;
+                            }
+                            ReadResult::Empty => std::thread::yield_now(),
+                            ReadResult::Closed => return (next, dropouts),
+                        }
+                    }
+                })
+            })
+            .collect();
+        drop(reader);
+
+        for (i, len) in lens.iter().enumerate() {
+            writer.write_frame(&fill(*len).collect::<Vec<_>>()).unwrap();
+            if i % 4 == 0 {
+                std::thread::yield_now();
+            }
+        }
+        drop(writer);
+
+        for reader in readers {
+            let (received, dropouts) = reader.join().unwrap();
+            if dropouts == 0 {
+                assert_eq!(received, COUNT);
+            }
+        }
+    });
+}
+
+fn check_fixed_frames(frame_len: usize) {
+    let frame = |i: usize| (0..frame_len).map(|j| i * 10_000 + j).collect::<Vec<_>>();
+    let (mut reader, mut writer) = ring_buffer_frames::<usize>(4, frame_len);
+    assert_eq!(reader.frame_len(), frame_len);
+    assert_eq!(writer.frame_len(), frame_len);
+
+    let mut out = vec![usize::MAX; frame_len];
+    assert_eq!(reader.read_frame(&mut out), ReadResult::Empty);
+    assert!(out.iter().all(|v| *v == usize::MAX));
+
+    writer.write_frame(&frame(0));
+    writer.write_frame(&frame(1));
+    assert_eq!(reader.read_frame(&mut out), ReadResult::Ok(()));
+    assert_eq!(out, frame(0));
+    assert_eq!(reader.read_frame(&mut out), ReadResult::Ok(()));
+    assert_eq!(out, frame(1));
+    assert_eq!(reader.read_frame(&mut out), ReadResult::Empty);
+    assert_eq!(out, frame(1));
+
+    // Lapped once
+    let mut lagging = reader.clone();
+    for i in 2..7 {
+        writer.write_frame(&frame(i));
+    }
+    assert_eq!(reader.read_frame(&mut out), ReadResult::Dropout(()));
+    assert_eq!(out, frame(6));
+    assert_eq!(reader.read_frame(&mut out), ReadResult::Empty);
+
+    // Lapped twice
+    for i in 7..16 {
+        writer.write_frame(&frame(i));
+    }
+    assert_eq!(lagging.read_frame(&mut out), ReadResult::Dropout(()));
+    assert_eq!(out, frame(14));
+    assert_eq!(lagging.read_frame(&mut out), ReadResult::Ok(()));
+    assert_eq!(out, frame(15));
+    assert_eq!(reader.read_frame(&mut out), ReadResult::Dropout(()));
+    assert_eq!(out, frame(15));
+
+    drop(writer);
+    assert!(reader.is_disconnected());
+    assert_eq!(reader.read_frame(&mut out), ReadResult::Closed);
+    assert_eq!(lagging.read_frame(&mut out), ReadResult::Closed);
+}
+
+#[test]
+fn test_fixed_frames_one_thread() {
+    check_fixed_frames(1);
+    check_fixed_frames(480);
+    check_fixed_frames(4096);
+}
+
+#[test]
+#[should_panic(expected = "frame length doesn't match the ring buffer")]
+fn test_fixed_frames_wrong_len() {
+    let (_reader, mut writer) = ring_buffer_frames::<f32>(4, 480);
+    writer.write_frame(&[0.0; 479]);
+}
+
+#[test]
+fn test_fixed_frames_never_torn() {
+    const COUNT: u64 = iterations(20_000, 200) as u64;
+    for frame_len in [1, iterations(480, 16), iterations(4096, 64)] {
+        let (mut reader, mut writer) = ring_buffer_frames::<u64>(8, frame_len);
+
+        std::thread::scope(|s| {
+            s.spawn(move || {
+                let mut frame = vec![0; frame_len];
+                for i in 1..=COUNT {
+                    frame.fill(i);
+                    writer.write_frame(&frame);
+                    if i % 8 == 0 {
+                        std::thread::yield_now();
+                    }
+                }
+            });
+
+            let mut frame = vec![0; frame_len];
+            let mut previous = 0;
+            loop {
+                match reader.read_frame(&mut frame) {
+                    ReadResult::Ok(()) | ReadResult::Dropout(()) => {
+                        assert!(frame.iter().all(|v| *v == frame[0]), "torn frame");
+                        assert!(frame[0] > previous);
+                        previous = frame[0];
+                    }
+                    ReadResult::Empty => std::thread::yield_now(),
+                    ReadResult::Closed => break,
+                }
+            }
+            assert_eq!(previous, COUNT);
+        });
+    }
+}
+
+storage_test! {
+    fn test_cache_padded_capacity_2(reader, writer: u64, 2) {
+        if cfg!(feature = "cache-padded") {
+            use crate::storage::sealed::Sealed;
+
+            assert_eq!(writer.memory_footprint(), 2 * 64);
+            let items = reader.storage.items();
+            let base = items.as_ptr() as usize;
+            assert_eq!(base % 64, 0);
+            assert_eq!(&items[1] as *const _ as usize - base, 64);
+        }
+
+        writer.write(1);
+        writer.write(2);
+        assert_eq!(reader.read(), ReadResult::Ok(1));
+        writer.write(3);
+        writer.write(4);
+        assert_eq!(reader.read(), ReadResult::Dropout(4));
+        assert_eq!(reader.read(), ReadResult::Empty);
+        writer.write(5);
+        assert_eq!(reader.read(), ReadResult::Ok(5));
+    }
+}
+
+#[test]
+fn test_indexing_power_of_two_matches_modulo() {
+    for shift in 0..8 {
+        let capacity = 1 << shift;
+        let pow2 = Indexing::new(capacity);
+        let modulo = Indexing::Modulo { capacity };
+        assert_eq!(pow2, Indexing::PowerOfTwo { shift });
+
+        for sequence in (0..1000).chain(u64::MAX - 1000..=u64::MAX) {
+            assert_eq!(pow2.index_of(sequence), modulo.index_of(sequence));
+            assert_eq!(pow2.lap_of(sequence), modulo.lap_of(sequence));
+        }
+        for index in 0..capacity {
+            assert_eq!(pow2.next(index), modulo.next(index));
+        }
+    }
+
+    assert_eq!(Indexing::new(12), Indexing::Modulo { capacity: 12 });
+}
+
+storage_test! {
+    fn test_read_empty_without_locking(reader, writer: usize, 4) {
+        use crate::storage::sealed::Sealed;
+
+        // Lock the next item for writing as if the writer was busy with it.
+        // Reading would spin forever if it locked the item.
+        let item = &writer.storage.items()[0];
+        item.acquire_write(&SpinPolicy::default());
+        assert_eq!(reader.read(), ReadResult::Empty);
+        assert_eq!(reader.peek(), ReadResult::Empty);
+        item.release_write(UNWRITTEN_LAP);
+
+        writer.write(1);
+        assert_eq!(reader.read(), ReadResult::Ok(1));
+        assert_eq!(reader.read(), ReadResult::Empty);
+    }
+}
+
+storage_test! {
+    fn test_read_truncated_lap_matches(reader, writer: usize, 4) {
+        writer.write_slice(&[1, 2, 3, 4]);
+        for i in 1..=4 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+
+        // Jump ahead by exactly 2^31 laps, so that the truncated lap of the
+        // next item matches the lap before the one that the reader expects
+        writer.sequence = 4 << 31;
+        writer.write(5);
+        assert_eq!(reader.read(), ReadResult::Dropout(5));
+    }
+}
+
+storage_test! {
+    fn test_read_after_many_laps(reader, writer: usize, 32) {
+        for i in 0..32 {
+            writer.write(i);
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+
+        // Simulate the reader sleeping while the writer performs exactly
+        // 2^16 laps, and then 2^16 - 1 more, which would make a 16 bit lap
+        // count match what the reader expects, or the lap before it
+        for skipped in [32 << 16, (32 << 16) - 32] {
+            writer.sequence += skipped;
+            writer.write_iter(100..132);
+            assert_eq!(reader.read(), ReadResult::Dropout(100));
+            for i in 101..132 {
+                assert_eq!(reader.read(), ReadResult::Ok(i));
+            }
+            assert_eq!(reader.read(), ReadResult::Empty);
+        }
+    }
+}
+
+storage_test! {
+    #[cfg_attr(miri, ignore = "depends on timing, which Miri doesn't reproduce")]
+    fn test_read_backs_off_while_writer_holds_item(reader, writer: usize, 4) {
+        use crate::{storage::sealed::Sealed, SPIN_LIMIT, YIELD_LIMIT};
+
+        reader.set_spin_policy(SpinPolicy::default());
+        writer.write_slice(&[0, 1, 2, 3]);
+
+        // Hold the item that the reader reads next, as if the writer was
+        // descheduled while overwriting it
+        let item = &writer.storage.items()[0];
+        item.acquire_write(&SpinPolicy::default());
+
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(20));
+                item.release_write(0);
+            });
+
+            assert_eq!(reader.read(), ReadResult::Ok(0));
+        });
+
+        // Spinning for the whole time would have taken millions of checks
+        let stats = reader.stats();
+        assert_eq!(stats.contended_reads, 1);
+        assert!(stats.max_spins > u64::from(SPIN_LIMIT + YIELD_LIMIT));
+        assert!(stats.max_spins < 1000, "{} checks", stats.max_spins);
+    }
+}
+
+storage_test! {
+    #[cfg_attr(miri, ignore = "depends on timing, which Miri doesn't reproduce")]
+    fn test_write_backs_off_while_reader_holds_item(reader, writer: usize, 4) {
+        use crate::{storage::sealed::Sealed, SPIN_LIMIT, YIELD_LIMIT};
+
+        writer.set_spin_policy(SpinPolicy::default());
+
+        // Hold a read lock on the item that the writer writes to next, as if
+        // a reader was descheduled while copying it
+        let item = &reader.storage.items()[0];
+        item.acquire_read(&SpinPolicy::default());
+
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(20));
+                item.release_read();
+            });
+
+            writer.write(1);
+        });
+
+        // Spinning for the whole time would have taken millions of checks
+        let stats = writer.stats();
+        assert_eq!(stats.contended_writes, 1);
+        assert!(stats.max_spins > u64::from(SPIN_LIMIT + YIELD_LIMIT));
+        assert!(stats.max_spins < 1000, "{} checks", stats.max_spins);
+        assert_eq!(reader.read(), ReadResult::Ok(1));
+    }
+}
+
+storage_test! {
+    fn test_waiting_writer_keeps_new_readers_out(reader, writer: usize, 4) {
+        use crate::storage::sealed::Sealed;
+
+        writer.write_slice(&[0, 1, 2, 3]);
+
+        // Hold a read lock on the item that the writer overwrites next
+        let holder = reader.clone();
+        let item = &holder.storage.items()[0];
+        item.acquire_read(&SpinPolicy::default());
+
+        std::thread::scope(|s| {
+            s.spawn(|| writer.write(4));
+
+            while item.state.load(crate::sync::Ordering::SeqCst) & WRITER_WAITING == 0 {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(20));
+                item.release_read();
+            });
+
+            // The old item is still there, but only the writer may lock it now
+            assert_eq!(reader.read(), ReadResult::Dropout(4));
+        });
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore = "depends on timing, which Miri doesn't reproduce")]
+fn test_write_not_starved_by_read_latest() {
+    const READERS: usize = 16;
+    const RUN_TIME: Duration = Duration::from_millis(500);
+
+    let (reader, mut writer) = ring_buffer::<[u64; 16]>(2);
+
+    let writes = std::thread::scope(|s| {
+        for _ in 0..READERS {
+            let mut reader = reader.clone();
+            s.spawn(move || loop {
+                if let ReadResult::Closed = reader.read_latest() {
+                    return;
+                }
+            });
+        }
+
+        let start = std::time::Instant::now();
+        let mut writes: u64 = 0;
+        while start.elapsed() < RUN_TIME {
+            writer.write([writes; 16]);
+            writes += 1;
+        }
+        drop(writer);
+        writes
+    });
+
+    // Even with every core busy reading, the writer gets through at least
+    // a few thousand times a second
+    assert!(writes >= 1000, "only {} writes", writes);
+}
+
+storage_test! {
+    fn test_try_read(reader, writer: usize, 4) {
+        assert_eq!(reader.try_read(), TryReadResult::Empty);
+
+        writer.write(1);
+        assert_eq!(reader.try_read(), TryReadResult::Ok(1));
+
+        for i in 2..8 {
+            writer.write(i);
+        }
+        let expected = reader.clone().read();
+        assert!(expected.is_dropout());
+        assert_eq!(reader.try_read(), expected.into());
+        assert_eq!(reader.try_read(), TryReadResult::Ok(7));
+        assert_eq!(reader.try_read(), TryReadResult::Empty);
+
+        drop(writer);
+        assert_eq!(reader.try_read(), TryReadResult::Closed);
+    }
+}
+
+storage_test! {
+    fn test_try_read_busy(reader, writer: usize, 4) {
+        use crate::storage::sealed::Sealed;
+
+        writer.write_slice(&[0, 1]);
+
+        // Hold the next item as if the writer was descheduled while
+        // overwriting it. Reading would wait for it, trying doesn't.
+        let item = &writer.storage.items()[0];
+        item.acquire_write(&SpinPolicy::spin());
+        let start = std::time::Instant::now();
+        assert_eq!(reader.try_read(), TryReadResult::Busy);
+        assert!(start.elapsed() < Duration::from_millis(100));
+        item.release_write(0);
+
+        // A writer waiting for other readers to leave keeps it out as well
+        let holder = reader.clone();
+        let item = &holder.storage.items()[1];
+        item.acquire_read(&SpinPolicy::spin());
+        item.state
+            .fetch_or(WRITER_WAITING, crate::sync::Ordering::Relaxed);
+        assert_eq!(reader.try_read(), TryReadResult::Ok(0));
+        assert_eq!(reader.try_read(), TryReadResult::Busy);
+        item.state
+            .fetch_and(!WRITER_WAITING, crate::sync::Ordering::Relaxed);
+        item.release_read();
+
+        // Being busy leaves the reader where it was
+        assert_eq!(reader.try_read(), TryReadResult::Ok(1));
+        assert_eq!(reader.try_read(), TryReadResult::Empty);
+
+        let stats = reader.stats();
+        assert_eq!(stats.reads, 5);
+        assert_eq!(stats.busy, 2);
+        assert_eq!(stats.contended_reads, 0);
+    }
+}
+
+storage_test! {
+    fn test_try_write_busy(reader, writer: usize, 4) {
+        use crate::storage::sealed::Sealed;
+
+        // Hold a read lock on the item that the writer writes to next, as if
+        // a reader was descheduled while copying it. Writing would wait for
+        // it, trying doesn't.
+        let holder = reader.clone();
+        let item = &holder.storage.items()[0];
+        item.acquire_read(&SpinPolicy::spin());
+
+        let start = std::time::Instant::now();
+        assert_eq!(writer.try_write(1), Err(TryWriteError(1)));
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        // Nothing was written, and the writer didn't move
+        assert_eq!(writer.next_sequence(), 0);
+        assert_eq!(writer.stats().writes, 0);
+        assert_eq!(writer.stats().busy, 1);
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        item.release_read();
+    }
+}
+
+storage_test! {
+    fn test_try_write_after_reader_releases(reader, writer: usize, 4) {
+        use crate::storage::sealed::Sealed;
+
+        let holder = reader.clone();
+        let item = &holder.storage.items()[0];
+        item.acquire_read(&SpinPolicy::spin());
+        for _ in 0..3 {
+            assert_eq!(writer.try_write(1), Err(TryWriteError(1)));
+        }
+        item.release_read();
+
+        for i in 1..=4 {
+            assert_eq!(writer.try_write(i), Ok(()));
+        }
+        for i in 1..=4 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        let stats = writer.stats();
+        assert_eq!(stats.writes, 4);
+        assert_eq!(stats.busy, 3);
+        assert_eq!(stats.contended_writes, 0);
+    }
+}
+
+storage_test! {
+    fn test_write_timeout_uncontended(reader, writer: usize, 4) {
+        let start = std::time::Instant::now();
+        for i in 0..4 {
+            assert_eq!(writer.write_timeout(i, Duration::from_secs(10)), Ok(()));
+        }
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        for i in 0..4 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(writer.stats().busy, 0);
+    }
+}
+
+storage_test! {
+    fn test_write_timeout_reader_holds_item(reader, writer: usize, 4) {
+        use crate::storage::sealed::Sealed;
+
+        // Hold a read lock on the item that the writer writes to next, as if
+        // a reader was descheduled while copying it
+        let holder = reader.clone();
+        let item = &holder.storage.items()[0];
+        item.acquire_read(&SpinPolicy::spin());
+
+        let start = std::time::Instant::now();
+        assert_eq!(
+            writer.write_timeout(1, Duration::from_millis(50)),
+            Err(WriteTimeout(1))
+        );
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(50));
+        assert!(elapsed < Duration::from_secs(2), "took {:?}", elapsed);
+
+        // Nothing was written, and readers aren't kept out any longer
+        assert_eq!(writer.next_sequence(), 0);
+        assert_eq!(writer.stats().busy, 1);
+        let state = item.state.load(crate::sync::Ordering::SeqCst);
+        assert_eq!(state & WRITER_WAITING, 0);
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        item.release_read();
+        assert_eq!(writer.write_timeout(2, Duration::ZERO), Ok(()));
+        assert_eq!(reader.read(), ReadResult::Ok(2));
+    }
+}
+
+storage_test! {
+    fn test_health_slot_stuck(reader, writer: usize, 4) {
+        use crate::{storage::sealed::Sealed, STUCK_TIMEOUT};
+
+        writer.write_slice(&[0, 1, 2, 3]);
+        assert_eq!(reader.health(), ReaderHealth::Ok);
+
+        // Lock the next item and start overwriting it, as if the writer died
+        // halfway through
+        let item = &writer.storage.items()[0];
+        item.acquire_write(&SpinPolicy::spin());
+        unsafe { item.set_sequence(4) };
+        item.data
+            .with_mut(|data| unsafe { *data = std::mem::MaybeUninit::new(99) });
+
+        let start = std::time::Instant::now();
+        assert_eq!(reader.health(), ReaderHealth::SlotStuck { index: 0 });
+        let elapsed = start.elapsed();
+        assert!(elapsed >= STUCK_TIMEOUT);
+        assert!(elapsed < STUCK_TIMEOUT * 20, "took {:?}", elapsed);
+        assert_eq!(reader.try_read(), TryReadResult::Busy);
+
+        // After forcing the lock open, the item counts as unwritten again,
+        // but holds the partially written value
+        assert!(unsafe { reader.force_unlock(0) });
+        assert!(!unsafe { reader.force_unlock(0) });
+        assert_eq!(reader.health(), ReaderHealth::Ok);
+        assert_eq!(reader.read(), ReadResult::Ok(99));
+        for i in 1..4 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(reader.read(), ReadResult::Empty);
+    }
+}
+
+#[test]
+fn test_health_busy_writer() {
+    let (reader, mut writer) = ring_buffer::<[u64; 16]>(1);
+    let running = std::sync::atomic::AtomicBool::new(true);
+
+    std::thread::scope(|s| {
+        let running = &running;
+        s.spawn(move || {
+            let mut i = 0;
+            while running.load(std::sync::atomic::Ordering::Relaxed) {
+                writer.write([i; 16]);
+                i += 1;
+            }
+        });
+
+        // The writer holds the only item most of the time, but keeps writing
+        for _ in 0..10 {
+            assert_eq!(reader.health(), ReaderHealth::Ok);
+        }
+        running.store(false, std::sync::atomic::Ordering::Relaxed);
+    });
+}
+
+#[test]
+fn test_spin_policy_plumbs_through() {
+    let (reader, writer) = ring_buffer::<u32>(4);
+    assert_eq!(reader.spin_policy(), SpinPolicy::default());
+    assert_eq!(writer.spin_policy(), SpinPolicy::default());
+
+    let policy = SpinPolicy {
+        spin_iters: 3,
+        yield_iters: 2,
+        park: None,
+    };
+    let (mut reader, writer) = ring_buffer_with_policy::<u32>(4, policy);
+    assert_eq!(reader.spin_policy(), policy);
+    assert_eq!(writer.spin_policy(), policy);
+    assert_eq!(reader.clone().spin_policy(), policy);
+
+    reader.set_spin_policy(SpinPolicy::spin());
+    assert_eq!(reader.clone().spin_policy(), SpinPolicy::spin());
+
+    let mut buffer = StaticRingBuffer::<u32, 4>::new();
+    let (mut reader, _writer) = buffer.split();
+    reader.set_spin_policy(policy);
+    assert_eq!(reader.spin_policy(), policy);
+}
+
+storage_test! {
+    #[cfg_attr(miri, ignore = "depends on timing, which Miri doesn't reproduce")]
+    fn test_spin_policy_waits(reader, writer: usize, 4) {
+        use crate::storage::sealed::Sealed;
+
+        // Sleep right away, for long enough that the item is free after a
+        // few checks
+        reader.set_spin_policy(SpinPolicy {
+            spin_iters: 0,
+            yield_iters: 0,
+            park: Some(Duration::from_millis(5)),
+        });
+
+        writer.write_slice(&[0, 1, 2, 3]);
+        let item = &writer.storage.items()[0];
+        item.acquire_write(&SpinPolicy::default());
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(20));
+                item.release_write(0);
+            });
+
+            assert_eq!(reader.read(), ReadResult::Ok(0));
+        });
+        assert!(reader.stats().max_spins <= 5);
+
+        // Spinning only ever takes many more checks
+        reader.reset_stats();
+        reader.set_spin_policy(SpinPolicy::spin());
+        let item = &writer.storage.items()[1];
+        item.acquire_write(&SpinPolicy::default());
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(20));
+                item.release_write(0);
+            });
+
+            assert_eq!(reader.read(), ReadResult::Ok(1));
+        });
+        assert!(reader.stats().max_spins > 1000);
+    }
+}
+
+storage_test! {
+    fn test_reserve_commit(reader, writer: usize, 4) {
+        let mut guard = writer.reserve();
+        assert_eq!(*guard, 0);
+        *guard = 1;
+        *guard += 1;
+        guard.commit();
+
+        assert_eq!(writer.next_sequence(), 1);
+        assert_eq!(reader.read(), ReadResult::Ok(2));
+
+        // Committing on drop
+        let mut guard = writer.reserve();
+        guard.set_commit_on_drop(true);
+        *guard = 3;
+        drop(guard);
+        assert_eq!(reader.read(), ReadResult::Ok(3));
+        assert_eq!(writer.stats().writes, 2);
+    }
+}
+
+storage_test! {
+    fn test_reserve_abort(reader, writer: usize, 4) {
+        // Aborting before anything was written
+        let mut guard = writer.reserve();
+        *guard = 1;
+        drop(guard);
+        assert_eq!(writer.next_sequence(), 0);
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        for i in 0..6 {
+            writer.write(i);
+        }
+        assert_eq!(reader.read(), ReadResult::Dropout(4));
+
+        // Aborting leaves the oldest item in place for readers to read
+        let mut guard = writer.reserve();
+        assert_eq!(*guard, 2);
+        *guard = 10;
+        drop(guard);
+        assert_eq!(writer.next_sequence(), 6);
+
+        let mut clone = reader.clone();
+        clone.seek_to_oldest();
+        assert_eq!(clone.read_all_available(), (vec![3, 4, 5], false));
+        clone.rewind(3);
+        assert_eq!(clone.read(), ReadResult::Ok(3));
+
+        // Committing on drop is undone by aborting explicitly
+        let mut guard = writer.reserve();
+        guard.set_commit_on_drop(true);
+        guard.set_commit_on_drop(false);
+        *guard = 11;
+        drop(guard);
+
+        assert_eq!(reader.read(), ReadResult::Ok(5));
+        assert_eq!(reader.read(), ReadResult::Empty);
+        writer.write(6);
+        assert_eq!(reader.read(), ReadResult::Ok(6));
+    }
+}
+
+storage_test! {
+    fn test_reserve_panic(reader, writer: usize, 4) {
+        writer.write(1);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let mut guard = writer.reserve();
+            guard.set_commit_on_drop(true);
+            *guard = 2;
+            panic!("failed to fill the item");
+        }));
+        assert!(result.is_err());
+
+        // Nothing was written, and the writer is still usable
+        assert_eq!(writer.next_sequence(), 1);
+        assert_eq!(reader.read(), ReadResult::Ok(1));
+        assert_eq!(reader.read(), ReadResult::Empty);
+        writer.write(3);
+        assert_eq!(reader.read(), ReadResult::Ok(3));
+    }
+}
+
+/// A large item which is only ever written as a whole
+#[derive(Clone, Copy)]
+struct Frame([u64; 1024]);
+
+impl Default for Frame {
+    fn default() -> Self {
+        Frame([0; 1024])
+    }
+}
+
+#[test]
+fn test_write_with_no_torn_frames() {
+    let (mut reader, mut writer) = ring_buffer::<Frame>(4);
+
+    std::thread::scope(|s| {
+        let reader_thread = s.spawn(move || {
+            let mut frames = 0;
+            loop {
+                match reader.read() {
+                    ReadResult::Ok(frame) | ReadResult::Dropout(frame) => {
+                        assert!(frame.0.iter().all(|v| *v == frame.0[0]));
+                        frames += 1;
+                    }
+                    ReadResult::Empty => std::thread::yield_now(),
+                    ReadResult::Closed => break,
+                }
+            }
+            frames
+        });
+
+        for i in 1..=iterations(10_000, 100) as u64 {
+            writer.write_with(|frame| frame.0.fill(i));
+        }
+        writer.close();
+
+        assert!(reader_thread.join().unwrap() > 0);
+    });
+}
+
+storage_test! {
+    fn test_read_detailed_one_thread(reader, writer: usize, 8) {
+        assert_eq!(reader.read_detailed(), Detailed::Empty);
+
+        writer.write(0);
+        writer.write(1);
+        assert_eq!(reader.read_detailed(), Detailed::Ok(0));
+        assert_eq!(reader.read_detailed(), Detailed::Ok(1));
+        assert_eq!(reader.read_detailed().lost(), 0);
+
+        // Overwriting items 2 to 9 loses them
+        for i in 2..13 {
+            writer.write(i);
+        }
+        assert_eq!(dropout(reader.read_detailed()), (10, 8));
+        assert_eq!(reader.read_detailed(), Detailed::Ok(11));
+        assert_eq!(reader.read_detailed(), Detailed::Ok(12));
+        assert_eq!(reader.read_detailed(), Detailed::Empty);
+
+        // Items passed over by skipping ahead count as lost
+        for i in 13..18 {
+            writer.write(i);
+        }
+        assert_eq!(reader.read_detailed(), Detailed::Ok(13));
+        reader.skip_ahead();
+        reader.skip_ahead();
+        assert_eq!(dropout(reader.read_detailed()), (17, 3));
+        assert_eq!(reader.read_detailed(), Detailed::Empty);
+
+        // Skipping ahead while caught up rereads the latest item
+        reader.skip_ahead();
+        assert_eq!(dropout(reader.read_detailed()), (17, 0));
+
+        writer.close();
+        assert_eq!(reader.read_detailed(), Detailed::Closed);
+        assert_eq!(ReadResult::from(Detailed::Ok(3)), ReadResult::Ok(3));
+    }
+}
+
+storage_test! {
+    fn test_read_indexed_one_thread(reader, writer: usize, 8) {
+        assert_eq!(reader.read_indexed(), ReadResult::Empty);
+
+        for i in 0..3 {
+            writer.write(10 * i);
+        }
+        assert_eq!(reader.read_indexed(), ReadResult::Ok((0, 0)));
+        assert_eq!(reader.read_indexed(), ReadResult::Ok((1, 10)));
+        assert_eq!(reader.read_indexed(), ReadResult::Ok((2, 20)));
+        assert_eq!(reader.read_indexed(), ReadResult::Empty);
+
+        // A dropout carries the sequence number of the item delivered
+        for i in 3..20 {
+            writer.write(10 * i);
+        }
+        assert_eq!(reader.read_indexed(), ReadResult::Dropout((19, 190)));
+        assert_eq!(reader.read_indexed(), ReadResult::Empty);
+
+        // After skipping ahead, the latest item is reported
+        for i in 20..23 {
+            writer.write(10 * i);
+        }
+        assert_eq!(reader.read_indexed(), ReadResult::Ok((20, 200)));
+        reader.skip_ahead();
+        assert_eq!(reader.read_indexed(), ReadResult::Dropout((22, 220)));
+        assert_eq!(reader.read_indexed(), ReadResult::Empty);
+
+        writer.close();
+        assert_eq!(reader.read_indexed(), ReadResult::Closed);
+    }
+}
+
+storage_test! {
+    fn test_read_indexed_gaps_iff_dropout(reader, writer: u64, 4) {
+        const COUNT: u64 = iterations(100_000, 1000) as u64;
+
+        std::thread::scope(|s| {
+            let reader_thread = s.spawn(move || {
+                let mut last_sequence = None;
+                loop {
+                    let (sequence, value) = match reader.read_indexed() {
+                        ReadResult::Ok((sequence, value)) => {
+                            assert_eq!(sequence, last_sequence.map_or(0, |s| s + 1));
+                            (sequence, value)
+                        }
+                        ReadResult::Dropout((sequence, value)) => {
+                            assert!(sequence > last_sequence.map_or(0, |s| s + 1));
+                            (sequence, value)
+                        }
+                        ReadResult::Empty => {
+                            std::thread::yield_now();
+                            continue;
+                        }
+                        ReadResult::Closed => break,
+                    };
+                    // The sequence number always belongs to the value
+                    assert_eq!(sequence, value);
+                    last_sequence = Some(sequence);
+                }
+                assert_eq!(last_sequence, Some(COUNT - 1));
+            });
+
+            for i in 0..COUNT {
+                writer.write(i);
+            }
+            writer.close();
+
+            reader_thread.join().unwrap();
+        });
+    }
+}
+
+storage_test! {
+    fn test_write_returns_sequence(reader, writer: u64, 4) {
+        const COUNT: u64 = iterations(100_000, 1000) as u64;
+
+        std::thread::scope(|s| {
+            let reader_thread = s.spawn(move || {
+                let mut received = Vec::new();
+                loop {
+                    match reader.read_indexed() {
+                        ReadResult::Ok(item) | ReadResult::Dropout(item) => received.push(item),
+                        ReadResult::Empty => std::thread::yield_now(),
+                        ReadResult::Closed => break,
+                    }
+                }
+                received
+            });
+
+            let sequences: Vec<u64> = (0..COUNT).map(|i| writer.write(3 * i)).collect();
+            writer.close();
+
+            // Every item that was read has the sequence number that the
+            // writer returned for it
+            let received = reader_thread.join().unwrap();
+            assert!(!received.is_empty());
+            for (sequence, value) in received {
+                assert_eq!(sequences[(value / 3) as usize], sequence);
+            }
+            assert!(sequences.iter().copied().eq(0..COUNT));
+        });
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore = "depends on timing, which Miri doesn't reproduce")]
+fn test_read_timed_immediate() {
+    let (mut reader, mut writer) = ring_buffer::<Timestamped<usize>>(8);
+    assert_eq!(reader.read_timed(), ReadResult::Empty);
+
+    for i in 0..100 {
+        assert_eq!(writer.write_timestamped(i), i as u64);
+        let (value, age) = reader.read_timed().unwrap();
+        assert_eq!(value, i);
+        assert!(age < Duration::from_millis(50), "{:?}", age);
+    }
+
+    // Dropouts are timed as well
+    let before = std::time::Instant::now();
+    for i in 0..20 {
+        writer.write_timestamped(i);
+    }
+    let result = reader.read_timed();
+    assert!(result.is_dropout());
+    let (value, age) = result.unwrap();
+    assert_eq!(value, 16);
+    assert!(age <= before.elapsed());
+}
+
+#[test]
+#[cfg_attr(miri, ignore = "depends on timing, which Miri doesn't reproduce")]
+fn test_read_timed_slow_reader() {
+    let (mut reader, mut writer) = ring_buffer::<Timestamped<usize>>(8);
+
+    for i in 0..4 {
+        writer.write_timestamped(i);
+    }
+
+    // Each item waits for all reads before it
+    let delay = Duration::from_millis(10);
+    let mut last_age = Duration::ZERO;
+    for i in 0..4 {
+        std::thread::sleep(delay);
+        let (value, age) = reader.read_timed().unwrap();
+        assert_eq!(value, i);
+        assert!(age >= delay * (i as u32 + 1), "{:?}", age);
+        assert!(age > last_age);
+        last_age = age;
+    }
+    assert_eq!(reader.read_timed(), ReadResult::Empty);
+}
+
+/// A tracing subscriber that keeps the messages and fields of all events
+#[cfg(feature = "tracing")]
+#[derive(Clone, Default)]
+struct EventCollector {
+    events: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+#[cfg(feature = "tracing")]
+impl tracing::Subscriber for EventCollector {
+    fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        tracing::span::Id::from_u64(1)
+    }
+
+    fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        struct Fields(String);
+
+        impl tracing::field::Visit for Fields {
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                self.0 += &format!(" {}={:?}", field.name(), value);
+            }
+        }
+
+        let mut fields = Fields(event.metadata().level().to_string());
+        event.record(&mut fields);
+        self.events.lock().unwrap().push(fields.0);
+    }
+
+    fn enter(&self, _span: &tracing::span::Id) {}
+
+    fn exit(&self, _span: &tracing::span::Id) {}
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_tracing_dropout_lapped_once() {
+    let collector = EventCollector::default();
+    let events = collector.events.clone();
+
+    tracing::subscriber::with_default(collector, || {
+        let (mut reader, mut writer) = ring_buffer::<usize>(32);
+
+        for _ in 0..33 {
+            writer.write(0);
+        }
+        assert_eq!(reader.read(), ReadResult::Dropout(0));
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        reader.skip_ahead();
+    });
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            "TRACE message=reader dropout reader=0 sequence=32 index=0 lost=32 laps=1 skipped=false",
+            "DEBUG message=reader skipping ahead reader=0 sequence=33 write_sequence=33",
+        ]
+    );
+}
+
+/// Check that a result received after `last_value` continues its sequence,
+/// i.e. that Ok results never skip values and dropouts always do, and
+/// return the received value.
+#[cfg(feature = "async")]
+fn check_next(last_value: Option<usize>, result: ReadResult<usize>) -> usize {
+    let expected = last_value.map_or(0, |v| v + 1);
+    match result {
+        ReadResult::Ok(v) => {
+            assert_eq!(v, expected);
+            v
+        }
+        ReadResult::Dropout(v) => {
+            assert!(v > expected);
+            v
+        }
+        ReadResult::Empty => panic!("async reads never return Empty"),
+        ReadResult::Closed => panic!("the writer is still open"),
+    }
+}
+
+#[cfg(feature = "async")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_read_async_one_writer_two_readers() {
+    let (mut reader1, mut writer) = ring_buffer::<usize>(32);
+    let mut reader2 = reader1.clone();
+
+    const ITERATIONS: usize = iterations(10_000, 200);
+
+    let reader1_task = tokio::spawn(async move {
+        let mut last_value = None;
+        while last_value != Some(ITERATIONS - 1) {
+            last_value = Some(check_next(last_value, reader1.read_async().await));
+        }
+    });
+
+    let reader2_task = tokio::spawn(async move {
+        let mut last_value = None;
+        while last_value != Some(ITERATIONS - 1) {
+            last_value = Some(check_next(last_value, reader2.read_async().await));
+        }
+    });
+
+    let writer_task = tokio::spawn(async move {
+        for i in 0..ITERATIONS {
+            writer.write(i);
+            // Give the readers a chance to keep up so that they're never
+            // overtaken, but the queue regularly runs empty
+            if i % 16 == 15 {
+                tokio::time::sleep(std::time::Duration::from_micros(100)).await;
+            }
+        }
+    });
+
+    reader1_task.await.unwrap();
+    reader2_task.await.unwrap();
+    writer_task.await.unwrap();
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_read_async_data_available() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+
+    writer.write(1);
+    writer.write(2);
+
+    assert_eq!(reader.read_async().await, ReadResult::Ok(1));
+    assert_eq!(reader.read_async().await, ReadResult::Ok(2));
+    assert_eq!(reader.read(), ReadResult::Empty);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_read_async_cancelled() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+
+    // A future that gets dropped while pending must not leave anything
+    // behind that prevents later reads from working
+    let timeout =
+        tokio::time::timeout(std::time::Duration::from_millis(50), reader.read_async()).await;
+    assert!(timeout.is_err());
+
+    writer.write(1);
+    assert_eq!(reader.read_async().await, ReadResult::Ok(1));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_read_async_closed() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+
+    let writer_task = tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        writer.write(1);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        drop(writer);
+    });
+
+    assert_eq!(reader.read_async().await, ReadResult::Ok(1));
+    assert_eq!(reader.read_async().await, ReadResult::Closed);
+    assert_eq!(reader.read_async().await, ReadResult::Closed);
+
+    writer_task.await.unwrap();
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn test_stream_take() {
+    use futures::StreamExt;
+
+    let (reader, mut writer) = ring_buffer::<usize>(32);
+
+    for i in 0..10 {
+        writer.write(i);
+    }
+
+    let results: Vec<ReadResult<usize>> =
+        futures::executor::block_on(reader.into_stream().take(10).collect());
+    let expected: Vec<ReadResult<usize>> = (0..10).map(ReadResult::Ok).collect();
+    assert_eq!(results, expected);
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn test_stream_two_threads() {
+    use futures::StreamExt;
+
+    let (reader, mut writer) = ring_buffer::<usize>(32);
+
+    const ITERATIONS: usize = iterations(10_000, 200);
+
+    let reader_thread = std::thread::spawn(move || {
+        let mut stream = reader.into_stream();
+        futures::executor::block_on(async {
+            let mut last_value = None;
+            while last_value != Some(ITERATIONS - 1) {
+                let result = stream.next().await.unwrap();
+                last_value = Some(check_next(last_value, result));
+            }
+        });
+
+        // The reader can be recovered and continues where the stream left off
+        let mut reader = stream.into_inner();
+        assert_eq!(reader.read(), ReadResult::Empty);
+    });
+
+    let writer_thread = std::thread::spawn(move || {
+        for i in 0..ITERATIONS {
+            writer.write(i);
+            if i % 16 == 15 {
+                std::thread::sleep(Duration::from_micros(100));
+            }
+        }
+    });
+
+    reader_thread.join().unwrap();
+    writer_thread.join().unwrap();
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn test_stream_ends_when_closed() {
+    use futures::StreamExt;
+
+    let (reader, mut writer) = ring_buffer::<usize>(32);
+
+    let writer_thread = std::thread::spawn(move || {
+        for i in 0..10 {
+            writer.write(i);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    });
+
+    let results: Vec<ReadResult<usize>> =
+        futures::executor::block_on(reader.into_stream().collect());
+    let expected: Vec<ReadResult<usize>> = (0..10).map(ReadResult::Ok).collect();
+    assert_eq!(results, expected);
+
+    writer_thread.join().unwrap();
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn test_sink_forward() {
+    use futures::StreamExt;
+
+    let (mut reader, writer) = ring_buffer::<usize>(1024);
+    let mut sink = writer.into_sink();
+
+    futures::executor::block_on(futures::stream::iter(0..1000).map(Ok).forward(&mut sink)).unwrap();
+
+    for i in 0..1000 {
+        assert_eq!(reader.read(), ReadResult::Ok(i));
+    }
+    assert_eq!(reader.read(), ReadResult::Empty);
+
+    // Forwarding closes the sink at the end
+    assert!(sink.is_closed());
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn test_sink_closed() {
+    use futures::SinkExt;
+
+    let (mut reader, writer) = ring_buffer::<usize>(32);
+    let mut sink = writer.into_sink();
+
+    futures::executor::block_on(async {
+        sink.send(1).await.unwrap();
+        sink.send(2).await.unwrap();
+        sink.close().await.unwrap();
+        assert_eq!(sink.send(3).await, Err(crate::SinkClosed));
+    });
+
+    assert_eq!(reader.read(), ReadResult::Ok(1));
+    assert_eq!(reader.read(), ReadResult::Ok(2));
+    assert_eq!(reader.read(), ReadResult::Empty);
+
+    // The writer can be recovered and still works
+    let mut writer = sink.into_inner();
+    writer.write(4);
+    assert_eq!(reader.read(), ReadResult::Ok(4));
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_recv_one_writer_three_readers() {
+    let (reader, mut writer) = ring_buffer::<usize>(32);
+
+    const ITERATIONS: usize = iterations(10_000, 200);
+
+    let reader_tasks: Vec<_> = (0..3)
+        .map(|_| {
+            let mut reader = reader.clone();
+            tokio::spawn(async move {
+                let mut last_value = None;
+                while last_value != Some(ITERATIONS - 1) {
+                    last_value = Some(check_next(last_value, reader.recv().await));
+                }
+            })
+        })
+        .collect();
+
+    let writer_task = tokio::spawn(async move {
+        for i in 0..ITERATIONS {
+            writer.write(i);
+            if i % 16 == 15 {
+                tokio::time::sleep(std::time::Duration::from_micros(100)).await;
+            }
+        }
+    });
+
+    for task in reader_tasks {
+        task.await.unwrap();
+    }
+    writer_task.await.unwrap();
+
+    // Nobody is left waiting, so the writer only checks the counters
+    use crate::storage::sealed::Sealed;
+    assert!(reader.storage.signals().waiters.is_empty());
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_recv_cancelled() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+
+    let timeout = tokio::time::timeout(std::time::Duration::from_millis(50), reader.recv()).await;
+    assert!(timeout.is_err());
+
+    // The cancelled recv doesn't count as waiting anymore
+    use crate::storage::sealed::Sealed;
+    assert!(reader.storage.signals().waiters.is_empty());
+
+    writer.write(1);
+    assert_eq!(reader.recv().await, ReadResult::Ok(1));
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_recv_closed() {
+    let (mut reader, writer) = ring_buffer::<usize>(32);
+
+    let writer_task = tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        writer.close();
+    });
+
+    assert_eq!(reader.recv().await, ReadResult::Closed);
+
+    writer_task.await.unwrap();
+}
+
+#[cfg(all(unix, feature = "readiness"))]
+#[test]
+fn test_readiness_mio_poll() {
+    use mio::{unix::SourceFd, Events, Interest, Poll, Token};
+
+    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+
+    let mut poll = Poll::new().unwrap();
+    let mut events = Events::with_capacity(8);
+    let fd = reader.as_raw_fd().unwrap();
+    poll.registry()
+        .register(&mut SourceFd(&fd), Token(0), Interest::READABLE)
+        .unwrap();
+
+    // The file descriptor starts out readable
+    poll.poll(&mut events, Some(Duration::from_secs(1)))
+        .unwrap();
+    assert_eq!(events.iter().count(), 1);
+    reader.clear_readiness();
+    assert_eq!(reader.read(), ReadResult::Empty);
+
+    let writer_thread = std::thread::spawn(move || {
+        for i in 0..100 {
+            writer.write(i);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        writer
+    });
+
+    // Consume everything the way an event loop would
+    let mut received = Vec::new();
+    while received.len() < 100 {
+        events.clear();
+        poll.poll(&mut events, Some(Duration::from_secs(5)))
+            .unwrap();
+        assert!(events
+            .iter()
+            .all(|e| e.token() == Token(0) && e.is_readable()));
+        assert!(!events.is_empty(), "timed out waiting for readiness");
+
+        reader.clear_readiness();
+        while let Some(value) = reader.read().value() {
+            received.push(value);
+        }
+    }
+
+    writer_thread.join().unwrap();
+    assert_eq!(received, (0..100).collect::<Vec<_>>());
+}
+
+#[cfg(all(unix, feature = "readiness"))]
+#[test]
+fn test_readiness_signals_coalesced() {
+    let (mut reader1, mut writer) = ring_buffer::<usize>(32);
+    let mut reader2 = reader1.clone();
+
+    reader1.as_raw_fd().unwrap();
+    reader2.as_raw_fd().unwrap();
+    let readiness1 = std::sync::Arc::clone(reader1.readiness.as_ref().unwrap());
+    let readiness2 = std::sync::Arc::clone(reader2.readiness.as_ref().unwrap());
+
+    // Freshly created file descriptors are signalled once
+    assert_eq!(readiness1.pending_bytes(), 1);
+    assert_eq!(readiness2.pending_bytes(), 1);
+
+    reader1.clear_readiness();
+    reader2.clear_readiness();
+    assert_eq!(readiness1.pending_bytes(), 0);
+
+    // Many writes only signal each reader once
+    for i in 0..10 {
+        writer.write(i);
+    }
+    assert_eq!(readiness1.pending_bytes(), 1);
+    assert_eq!(readiness2.pending_bytes(), 1);
+
+    // Clearing one reader doesn't affect the other
+    reader1.clear_readiness();
+    assert_eq!(readiness1.pending_bytes(), 0);
+    assert_eq!(readiness2.pending_bytes(), 1);
+
+    writer.write(10);
+    assert_eq!(readiness1.pending_bytes(), 1);
+    assert_eq!(readiness2.pending_bytes(), 1);
+
+    // Dropping a reader unregisters its file descriptor
+    drop(reader2);
+    assert_eq!(std::sync::Arc::strong_count(&readiness2), 1);
+    writer.write(11);
+
+    // Clones don't share the file descriptor
+    let mut reader3 = reader1.clone();
+    assert_ne!(reader3.as_raw_fd().unwrap(), reader1.as_raw_fd().unwrap());
+}
+
+#[cfg(all(unix, feature = "readiness"))]
+#[test]
+fn test_readiness_retarget() {
+    let (mut reader, mut writer1) = ring_buffer::<usize>(32);
+    let (_reader2, mut writer2) = ring_buffer::<usize>(32);
+
+    reader.as_raw_fd().unwrap();
+    reader.clear_readiness();
+    let readiness = std::sync::Arc::clone(reader.readiness.as_ref().unwrap());
+
+    // After retargeting, only the new writer signals the file descriptor
+    reader.retarget_to_writer(&writer2);
+    writer1.write(1);
+    assert_eq!(readiness.pending_bytes(), 0);
+    writer2.write(2);
+    assert_eq!(readiness.pending_bytes(), 1);
+
+    drop(reader);
+    assert_eq!(std::sync::Arc::strong_count(&readiness), 1);
+}
+
+#[test]
+fn test_atomic_one_thread() {
+    let (mut reader, mut writer) = ring_buffer_atomic::<u64>(4);
+    assert_eq!(reader.read(), ReadResult::Empty);
+    assert!(!reader.has_data());
+
+    writer.write(1);
+    writer.write(2);
+    assert!(reader.has_data());
+    assert_eq!(reader.read(), ReadResult::Ok(1));
+    assert_eq!(reader.read(), ReadResult::Ok(2));
+    assert_eq!(reader.read(), ReadResult::Empty);
+
+    // Overtaken by exactly one lap, the reader gets the item that replaced
+    // the one it was going to read
+    for i in 3..=7 {
+        writer.write(i);
+    }
+    assert_eq!(reader.read(), ReadResult::Dropout(7));
+    assert_eq!(reader.read(), ReadResult::Empty);
+
+    // Overtaken by less than a lap
+    for i in 8..=13 {
+        writer.write(i);
+    }
+    assert_eq!(reader.read(), ReadResult::Dropout(12));
+    assert_eq!(reader.read(), ReadResult::Ok(13));
+
+    // Skipping ahead lands on the latest item
+    for i in 14..=16 {
+        writer.write(i);
+    }
+    let mut clone = reader.clone();
+    reader.skip_ahead();
+    assert_eq!(reader.read(), ReadResult::Dropout(16));
+    assert_eq!(clone.read(), ReadResult::Ok(14));
+
+    writer.write(17);
+    drop(writer);
+    assert!(reader.is_disconnected());
+    assert_eq!(reader.read(), ReadResult::Ok(17));
+    assert_eq!(reader.read(), ReadResult::Closed);
+    assert_eq!(clone.read(), ReadResult::Ok(15));
+}
+
+#[test]
+fn test_atomic_capacity_1() {
+    let (mut reader, mut writer) = ring_buffer_atomic::<i8>(1);
+    writer.write(-1);
+    assert_eq!(reader.read(), ReadResult::Ok(-1));
+    writer.write(-2);
+    writer.write(-3);
+    assert_eq!(reader.read(), ReadResult::Dropout(-3));
+    assert_eq!(reader.read(), ReadResult::Empty);
+}
+
+#[test]
+fn test_atomic_storable_round_trip() {
+    fn round_trip<T: AtomicStorable + PartialEq + std::fmt::Debug>(values: &[T]) {
+        let (mut reader, mut writer) = ring_buffer_atomic::<T>(values.len());
+        for value in values {
+            writer.write(*value);
+        }
+        for value in values {
+            assert_eq!(reader.read(), ReadResult::Ok(*value));
+        }
+    }
+
+    round_trip(&[0u8, 1, u8::MAX]);
+    round_trip(&[i16::MIN, -1, 0, i16::MAX]);
+    round_trip(&[i32::MIN, -1, i32::MAX]);
+    round_trip(&[i64::MIN, -1, i64::MAX]);
+    round_trip(&[usize::MAX, 0]);
+    round_trip(&[-0.0f32, 1.5, f32::INFINITY, f32::MIN_POSITIVE]);
+    round_trip(&[-0.0f64, 1.5, f64::NEG_INFINITY, f64::MAX]);
+    round_trip(&[true, false]);
+    round_trip(&['a', '\u{10FFFF}', '\0']);
+}
+
+#[test]
+fn test_atomic_no_torn_values() {
+    /// Two copies of the same number, as in the loom tests
+    #[derive(Clone, Copy)]
+    struct Pair(u32, u32);
+
+    impl AtomicStorable for Pair {
+        fn into_bits(self) -> u64 {
+            (self.0 as u64) << 32 | self.1 as u64
+        }
+
+        fn from_bits(bits: u64) -> Pair {
+            Pair((bits >> 32) as u32, bits as u32)
+        }
+    }
+
+    const LAST: u32 = iterations(1_000_000, 1000) as u32;
+    let (reader, mut writer) = ring_buffer_atomic::<Pair>(8);
+
+    std::thread::scope(|s| {
+        for _ in 0..3 {
+            let mut reader = reader.clone();
+            s.spawn(move || {
+                let mut next = 0;
+                let mut received = 0;
+                loop {
+                    let (Pair(a, b), dropout) = match reader.read() {
+                        ReadResult::Ok(pair) => (pair, false),
+                        ReadResult::Dropout(pair) => (pair, true),
+                        ReadResult::Empty => {
+                            std::thread::yield_now();
+                            continue;
+                        }
+                        ReadResult::Closed => break,
+                    };
+                    assert_eq!(a, b, "torn value");
+                    assert!(a >= next, "read {} after {}", a, next);
+                    assert!(dropout || a == next, "Ok skipped from {} to {}", next, a);
+                    next = a + 1;
+                    received += 1;
+                }
+
+                // The last value is never overwritten, so every reader gets it
+                assert_eq!(next, LAST + 1);
+                assert!(received > 0);
+            });
+        }
+        drop(reader);
+
+        for i in 0..=LAST {
+            writer.write(Pair(i, i));
+            if i % 4096 == 0 {
+                std::thread::yield_now();
+            }
+        }
+        drop(writer);
+    });
+}
+
+#[test]
+fn test_zeroed_items_are_unwritten() {
+    use crate::storage::{sealed::Sealed, HeapStorage, Item};
+
+    // HeapStorage leaves the items zeroed rather than writing Item::new
+    let storage = HeapStorage::<u64>::new(4);
+    let fresh = Item::<u64>::new();
+    for item in storage.items() {
+        let ordering = crate::sync::Ordering::SeqCst;
+        assert_eq!(item.state.load(ordering), fresh.state.load(ordering));
+        assert_eq!(unsafe { item.sequence() }, UNWRITTEN_SEQUENCE);
+    }
+    assert_eq!(fresh.state.load(crate::sync::Ordering::SeqCst), 0);
+}
+
+#[test]
+#[cfg_attr(miri, ignore = "allocates a gigabyte")]
+fn test_large_buffer_constructs_quickly() {
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    struct Page([u8; 4096]);
+
+    impl Default for Page {
+        fn default() -> Self {
+            Page([0; 4096])
+        }
+    }
+
+    // A gigabyte of items, none of which is touched before it's written
+    let start = std::time::Instant::now();
+    let (mut reader, mut writer) = ring_buffer::<Page>(1 << 18);
+    let elapsed = start.elapsed();
+    assert!(elapsed < Duration::from_millis(100), "took {:?}", elapsed);
+
+    assert_eq!(reader.read(), ReadResult::Empty);
+    writer.write(Page([7; 4096]));
+    assert_eq!(reader.read(), ReadResult::Ok(Page([7; 4096])));
+    assert_eq!(reader.read(), ReadResult::Empty);
+}
+
+#[test]
+fn test_values_that_need_drop() {
+    use std::sync::Arc;
+
+    let value = Arc::new(());
+    {
+        let (_reader, mut writer) = ring_buffer::<Option<Arc<()>>>(4);
+        for _ in 0..3 {
+            writer.write(Some(Arc::clone(&value)));
+        }
+        assert_eq!(Arc::strong_count(&value), 4);
+
+        // Overwriting an item drops the value that it held
+        for _ in 0..3 {
+            writer.write(None);
+        }
+        assert_eq!(Arc::strong_count(&value), 2);
+
+        // Nothing is evicted from an item that was never written to
+        let (_reader, mut writer) = ring_buffer::<Option<Arc<()>>>(2);
+        assert_eq!(
+            writer.write_returning_evicted(Some(Arc::clone(&value))),
+            None
+        );
+        assert_eq!(writer.write_returning_evicted(None), None);
+        let evicted = writer.write_returning_evicted(None);
+        assert!(Arc::ptr_eq(evicted.unwrap().as_ref().unwrap(), &value));
+        assert_eq!(Arc::strong_count(&value), 2);
+    }
+
+    // Dropping the ring buffer drops whatever is left
+    assert_eq!(Arc::strong_count(&value), 1);
+
+    let mut buffer = StaticRingBuffer::<Option<Arc<()>>, 4>::new();
+    let (_reader, mut writer) = buffer.split();
+    writer.write(Some(Arc::clone(&value)));
+    assert_eq!(Arc::strong_count(&value), 2);
+    drop(writer);
+    drop(_reader);
+
+    // Splitting again discards the values along with everything else
+    let (_reader, _writer) = buffer.split();
+    assert_eq!(Arc::strong_count(&value), 1);
+}
+
+storage_test! {
+    fn test_fill_unwritten_in_place(reader, writer: [usize; 4], 2) {
+        // Items that were never written hold the default value when filled
+        // in place
+        writer.write_with(|value| {
+            assert_eq!(*value, [0; 4]);
+            value[0] = 1;
+        });
+        let guard = writer.reserve();
+        assert_eq!(*guard, [0; 4]);
+        drop(guard);
+
+        assert_eq!(reader.read(), ReadResult::Ok([1, 0, 0, 0]));
+        assert_eq!(reader.read(), ReadResult::Empty);
+        assert_eq!(reader.snapshot(), vec![[1, 0, 0, 0]]);
+    }
+}
+
+fn encode_u64(value: &u64, w: &mut &mut Vec<u8>) -> std::io::Result<()> {
+    std::io::Write::write_all(w, &value.to_le_bytes())
+}
+
+fn decode_u64(r: &mut &[u8]) -> std::io::Result<u64> {
+    let mut bytes = [0; 8];
+    std::io::Read::read_exact(r, &mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+storage_test! {
+    fn test_dump_and_restore(reader, writer: u64, 5) {
+        let restore = |writer: &mut Writer<u64, _>| {
+            let mut dump = Vec::new();
+            writer.dump_to(&mut dump, encode_u64).unwrap();
+            ring_buffer_restore(dump.as_slice(), decode_u64).unwrap()
+        };
+
+        // Empty
+        let (mut restored, restored_writer) = restore(&mut writer);
+        assert_eq!(restored_writer.capacity(), 5);
+        assert_eq!(restored_writer.next_sequence(), 0);
+        assert_eq!(restored.read(), ReadResult::Empty);
+
+        // Partially filled
+        writer.write_slice(&[10, 11, 12]);
+        let (mut restored, _restored_writer) = restore(&mut writer);
+        assert_eq!(restored.read(), ReadResult::Empty);
+        restored.seek_to_oldest();
+        assert_eq!(restored.read_indexed(), ReadResult::Ok((0, 10)));
+        assert_eq!(restored.read(), ReadResult::Ok(11));
+        assert_eq!(restored.read(), ReadResult::Ok(12));
+
+        // Wrapped, with readers reading in the meantime
+        writer.write_iter(13..31);
+        assert!(reader.read().is_dropout());
+        let (mut restored, mut restored_writer) = restore(&mut writer);
+        assert_eq!(restored_writer.next_sequence(), writer.next_sequence());
+        assert_eq!(restored_writer.last_written(), Some(30));
+        assert_eq!(restored.snapshot(), reader.snapshot());
+
+        // A fresh reader replays the same values from both
+        let mut fresh = reader.clone();
+        fresh.seek_to_oldest();
+        restored.seek_to_oldest();
+        loop {
+            let expected = fresh.read_indexed();
+            assert_eq!(restored.read_indexed(), expected);
+            if expected.is_empty() {
+                break;
+            }
+        }
+
+        // Both go on the same way
+        writer.write(31);
+        restored_writer.write(31);
+        assert_eq!(restored.read_indexed(), fresh.read_indexed());
+    }
+}
+
+#[test]
+fn test_dump_and_restore_after_clear() {
+    let (mut reader, mut writer) = ring_buffer::<u64>(4);
+    writer.write_iter(0..10);
+    writer.clear();
+    writer.write(42);
+
+    let mut dump = Vec::new();
+    writer.dump_to(&mut dump, encode_u64).unwrap();
+    let (mut restored, mut restored_writer) =
+        ring_buffer_restore(dump.as_slice(), decode_u64).unwrap();
+    assert_eq!(restored_writer.next_sequence(), 11);
+    assert_eq!(restored_writer.last_written(), Some(42));
+
+    // Nothing from before the clear is replayed
+    restored.seek_to_oldest();
+    reader.seek_to_oldest();
+    assert_eq!(restored.read_indexed(), ReadResult::Ok((10, 42)));
+    assert_eq!(reader.read_indexed(), ReadResult::Ok((10, 42)));
+    assert_eq!(restored.rewind(4), 1);
+    assert_eq!(restored.snapshot(), [42]);
+
+    // Both go on the same way
+    writer.write(43);
+    restored_writer.write(43);
+    assert_eq!(restored.snapshot(), reader.snapshot());
+}
+
+#[test]
+fn test_dump_and_restore_cleared_empty() {
+    let (_, mut writer) = ring_buffer::<u64>(4);
+    writer.write_iter(0..6);
+    writer.clear();
+
+    let mut dump = Vec::new();
+    writer.dump_to(&mut dump, encode_u64).unwrap();
+    let (restored, mut restored_writer) = ring_buffer_restore(dump.as_slice(), decode_u64).unwrap();
+    assert_eq!(restored_writer.next_sequence(), 6);
+    assert_eq!(restored_writer.last_written(), None);
+    assert_eq!(restored.total_written(), 6);
+    assert!(!restored.has_data());
+    assert_eq!(restored.lag(), 0);
+    assert_eq!(restored.available(), 0);
+
+    let mut dispatch = restored.into_dispatch();
+    assert_eq!(dispatch.read(), ReadResult::Empty);
+    restored_writer.write(6);
+    assert_eq!(dispatch.read(), ReadResult::Ok(6));
+}
+
+#[test]
+fn test_restore_invalid() {
+    let (_, mut writer) = ring_buffer::<u64>(4);
+    writer.write(1);
+    let mut dump = Vec::new();
+    writer.dump_to(&mut dump, encode_u64).unwrap();
+
+    // Items of a different size
+    let err = ring_buffer_restore(dump.as_slice(), |_| Ok(0u32))
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    // Not a dump
+    let mut corrupt = dump.clone();
+    corrupt[0] ^= 1;
+    let err = ring_buffer_restore(corrupt.as_slice(), decode_u64)
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    // More items than were written
+    let mut corrupt = dump.clone();
+    corrupt[32..40].copy_from_slice(&2u64.to_le_bytes());
+    corrupt.extend_from_slice(&1u64.to_le_bytes());
+    let err = ring_buffer_restore(corrupt.as_slice(), decode_u64)
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    // Cut short
+    let err = ring_buffer_restore(&dump[..dump.len() - 1], decode_u64)
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+    let (mut reader, _writer) = ring_buffer_restore(dump.as_slice(), decode_u64).unwrap();
+    reader.seek_to_oldest();
+    assert_eq!(reader.read(), ReadResult::Ok(1));
+}
+
+#[test]
+fn test_reader_ids() {
+    let (reader, _writer) = ring_buffer::<usize>(4);
+    assert_eq!(reader.id().as_u64(), 0);
+
+    // Clone a chain of readers, each from the last
+    let mut readers = vec![reader];
+    for _ in 0..15 {
+        let clone = readers.last().unwrap().clone();
+        readers.push(clone);
+    }
+    let ids: Vec<u64> = readers.iter().map(|r| r.id().as_u64()).collect();
+    assert_eq!(ids, (0..16).collect::<Vec<_>>());
+
+    // Ids are never reused, even after a reader is dropped
+    readers.truncate(2);
+    assert_eq!(readers[0].try_clone().unwrap().id().as_u64(), 16);
+    assert_eq!(readers[1].clone().id().to_string(), "reader 17");
+
+    // Retargeting keeps the id
+    let (other, _other_writer) = ring_buffer::<usize>(4);
+    let id = readers[1].id();
+    readers[1].retarget(&other);
+    assert_eq!(readers[1].id(), id);
+}
+
+#[test]
+fn test_debug_format() {
+    // Neither requires the items to be Debug
+    #[derive(Clone, Copy, Default)]
+    struct Opaque;
+
+    let (mut reader, mut writer) = ring_buffer::<Opaque>(4);
+    assert_eq!(
+        format!("{:?}", reader),
+        "Reader { id: 0, capacity: 4, read_index: 0, lap: 0, sequence: 0, write_index: 0, \
+         write_sequence: 0, behind: 0 }"
+    );
+
+    for _ in 0..6 {
+        writer.write(Opaque);
+    }
+    reader.read();
+    assert_eq!(
+        format!("{:?}", writer),
+        "Writer { capacity: 4, index: 2, lap: 1, sequence: 6, writes: 6, readers: 1 }"
+    );
+    let debug = format!("{:?}", reader);
+    assert!(
+        debug.contains("write_index: 2, write_sequence: 6"),
+        "{}",
+        debug
+    );
+
+    writer.write(Opaque);
+    reader.skip_ahead();
+    let debug = format!("{:?}", reader);
+    // The reader is going to read the latest item
+    assert!(
+        debug.contains("read_index: 2, lap: 1, sequence: 6"),
+        "{}",
+        debug
+    );
+    assert!(
+        debug.contains("write_index: 3, write_sequence: 7"),
+        "{}",
+        debug
+    );
+    assert!(debug.contains("behind: 4"), "{}", debug);
+    assert!(format!("{:#?}", writer).contains("    writes: 7,"));
+
+    // Also before the writer wrapped around
+    let (mut reader, mut writer) = ring_buffer::<Opaque>(4);
+    writer.write(Opaque);
+    writer.write(Opaque);
+    reader.skip_ahead();
+    let debug = format!("{:?}", reader);
+    assert!(
+        debug.contains("read_index: 1, lap: 0, sequence: 1"),
+        "{}",
+        debug
+    );
+}
+
+#[test]
+fn test_read_result_combinators() {
+    let results = [
+        ReadResult::Ok(1),
+        ReadResult::Dropout(2),
+        ReadResult::Empty,
+        ReadResult::Closed,
+    ];
+
+    let mapped: Vec<_> = results.iter().map(|r| r.map(|v| v * 10)).collect();
+    assert_eq!(
+        mapped,
+        [
+            ReadResult::Ok(10),
+            ReadResult::Dropout(20),
+            ReadResult::Empty,
+            ReadResult::Closed
+        ]
+    );
+
+    let refs: Vec<_> = results.iter().map(ReadResult::as_ref).collect();
+    assert_eq!(
+        refs,
+        [
+            ReadResult::Ok(&1),
+            ReadResult::Dropout(&2),
+            ReadResult::Empty,
+            ReadResult::Closed
+        ]
+    );
+
+    let unwrapped: Vec<_> = results.iter().map(|r| r.unwrap_or(0)).collect();
+    assert_eq!(unwrapped, [1, 2, 0, 0]);
+
+    let oks: Vec<_> = results.iter().map(|r| r.ok_or("none")).collect();
+    assert_eq!(oks, [Ok(1), Ok(2), Err("none"), Err("none")]);
+
+    let options: Vec<Option<i32>> = results.iter().map(|&r| r.into()).collect();
+    assert_eq!(options, [Some(1), Some(2), None, None]);
+
+    assert_eq!(ReadResult::Ok(1).unwrap(), 1);
+    assert_eq!(ReadResult::Dropout(2).unwrap(), 2);
+    assert_eq!(ReadResult::Dropout(2).expect("no value"), 2);
+
+    // Not Copy, so that as_ref doesn't move out of the result
+    let result = ReadResult::Dropout(vec![1, 2, 3]);
+    assert_eq!(result.as_ref().map(Vec::len), ReadResult::Dropout(3));
+    assert_eq!(result.unwrap(), [1, 2, 3]);
+}
+
+#[test]
+#[should_panic(expected = "empty")]
+fn test_read_result_unwrap_empty() {
+    ReadResult::<u64>::Empty.unwrap();
+}
+
+#[test]
+#[should_panic(expected = "no value: the queue is closed")]
+fn test_read_result_expect_closed() {
+    ReadResult::<u64>::Closed.expect("no value");
+}
+
+#[test]
+fn test_lossless_one_thread() {
+    let (mut reader, mut writer) = ring_buffer_lossless::<usize>(4);
+    for i in 0..4 {
+        writer.write(i);
+    }
+
+    // The writer can't get ahead of the reader
+    assert_eq!(writer.try_write(4), Err(TryWriteError(4)));
+    assert_eq!(
+        writer.write_timeout(4, Duration::from_millis(1)),
+        Err(WriteTimeout(4))
+    );
+    assert_eq!(writer.stats().busy, 2);
+    assert_eq!(reader.read(), ReadResult::Ok(0));
+    assert_eq!(writer.try_write(4), Ok(()));
+    assert_eq!(writer.try_write(5), Err(TryWriteError(5)));
+
+    // Every reader holds it back, and clones start where they were cloned
+    let mut reader2 = reader.clone();
+    for i in 1..5 {
+        assert_eq!(reader.read(), ReadResult::Ok(i));
+    }
+    assert_eq!(writer.try_write(5), Err(TryWriteError(5)));
+    assert_eq!(reader2.read(), ReadResult::Ok(1));
+    assert_eq!(writer.try_write(5), Ok(()));
+
+    // Dropping a reader lets the writer go on
+    drop(reader2);
+    for i in 6..9 {
+        assert_eq!(writer.try_write(i), Ok(()));
+    }
+    assert_eq!(writer.try_write(9), Err(TryWriteError(9)));
+
+    // Skipping ahead releases the items skipped over
+    reader.skip_ahead();
+    assert_eq!(reader.read(), ReadResult::Dropout(8));
+    for i in 9..13 {
+        assert_eq!(writer.try_write(i), Ok(()));
+    }
+    assert_eq!(writer.try_write(13), Err(TryWriteError(13)));
+
+    // Without any readers, nothing holds back the writer
+    drop(reader);
+    for i in 13..100 {
+        assert_eq!(writer.try_write(i), Ok(()));
+    }
+}
+
+#[test]
+fn test_lossless_reader_moves_back() {
+    let (mut reader, mut writer) = ring_buffer_lossless::<usize>(4);
+    for i in 0..4 {
+        writer.write(i);
+    }
+    for i in 0..4 {
+        assert_eq!(reader.read(), ReadResult::Ok(i));
+    }
+    writer.write(4);
+
+    // The writer waits for the items that the reader rewound to
+    assert_eq!(reader.rewind(2), 2);
+    assert_eq!(writer.try_write(5), Ok(()));
+    assert!(writer.try_write(6).is_err());
+    for i in 2..6 {
+        assert_eq!(reader.read(), ReadResult::Ok(i));
+    }
+    assert_eq!(writer.try_write(6), Ok(()));
+    assert_eq!(reader.read(), ReadResult::Ok(6));
+
+    // And for a reader that starts at the oldest retained item
+    let mut back = reader.clone_at_back();
+    assert_eq!(writer.try_write(7), Ok(()));
+    assert!(writer.try_write(8).is_err());
+    for i in 4..8 {
+        assert_eq!(back.read(), ReadResult::Ok(i));
+    }
+    assert_eq!(writer.try_write(8), Ok(()));
+    assert_eq!(back.read(), ReadResult::Ok(8));
+    assert_eq!(reader.read(), ReadResult::Ok(7));
+    assert_eq!(writer.observed_dropouts(), 0);
+}
+
+#[test]
+fn test_lossless_no_value_lost() {
+    const COUNT: usize = iterations(100_000, 500);
+
+    let (mut reader1, mut writer) = ring_buffer_lossless::<usize>(4);
+    let mut reader2 = reader1.clone();
+
+    std::thread::scope(|s| {
+        for (reader, slow) in [(&mut reader1, false), (&mut reader2, true)] {
+            s.spawn(move || {
+                let mut next = 0;
+                loop {
+                    match reader.read() {
+                        ReadResult::Ok(value) => {
+                            assert_eq!(value, next);
+                            next += 1;
+                            if slow && next % 1000 == 0 {
+                                std::thread::sleep(Duration::from_micros(100));
+                            }
+                        }
+                        ReadResult::Dropout(value) => panic!("lost values before {}", value),
+                        ReadResult::Empty => std::thread::yield_now(),
+                        ReadResult::Closed => break,
+                    }
+                }
+                assert_eq!(next, COUNT);
+            });
+        }
+
+        // Longer slices than the ring buffer are written in full as well
+        let values: Vec<usize> = (0..COUNT).collect();
+        for chunk in values.chunks(7) {
+            writer.write_slice(chunk);
+        }
+        writer.close();
+    });
+}
+
+#[test]
+#[cfg_attr(miri, ignore = "depends on timing, which Miri doesn't reproduce")]
+fn test_lossless_writer_stalls_until_reader_dropped() {
+    let (reader, mut writer) = ring_buffer_lossless::<usize>(4);
+
+    std::thread::scope(|s| {
+        let writer_thread = s.spawn(move || {
+            for i in 0..10 {
+                writer.write(i);
+            }
+        });
+
+        // The writer fills the ring buffer and then waits for the reader,
+        // which never reads
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(reader.total_written(), 4);
+        assert!(!writer_thread.is_finished());
+
+        drop(reader);
+        writer_thread.join().unwrap();
+    });
+}
+
+/// Reads two of four items, lets the writer write four more and close the
+/// ring buffer, waiting for a while in case it can't, and then reads
+/// everything. Returns what the reader read and the writer's stats.
+fn overwrite_policy_script(policy: OverwritePolicy) -> (Vec<ReadResult<usize>>, WriterStats) {
+    let (mut reader, mut writer) = ring_buffer_with_overwrite_policy::<usize>(4, policy);
+    assert_eq!(writer.overwrite_policy(), policy);
+    for i in 0..4 {
+        writer.write(i);
+    }
+    assert_eq!(reader.read(), ReadResult::Ok(0));
+    assert_eq!(reader.read(), ReadResult::Ok(1));
+
+    std::thread::scope(|s| {
+        let writer_thread = s.spawn(move || {
+            for i in 4..8 {
+                writer.write(i);
+            }
+            let stats = writer.stats();
+            writer.close();
+            stats
+        });
+
+        let start = std::time::Instant::now();
+        while !reader.is_disconnected() && start.elapsed() < Duration::from_millis(50) {
+            std::thread::yield_now();
+        }
+
+        let mut results = Vec::new();
+        loop {
+            match reader.read() {
+                ReadResult::Empty => std::thread::yield_now(),
+                ReadResult::Closed => break,
+                result => results.push(result),
+            }
+        }
+        (results, writer_thread.join().unwrap())
+    })
+}
+
+#[test]
+#[cfg_attr(miri, ignore = "depends on timing, which Miri doesn't reproduce")]
+fn test_overwrite_policies() {
+    // The writer overtakes the reader
+    let (results, stats) = overwrite_policy_script(OverwritePolicy::Overwrite);
+    assert_eq!(results, [ReadResult::Dropout(6), ReadResult::Ok(7)]);
+    assert_eq!((stats.writes, stats.rejected), (8, 0));
+
+    // The writer waits until the reader has read everything
+    let (results, stats) = overwrite_policy_script(OverwritePolicy::Block);
+    assert_eq!(results, (2..8).map(ReadResult::Ok).collect::<Vec<_>>());
+    assert_eq!((stats.writes, stats.rejected), (8, 0));
+
+    // The writer drops what doesn't fit
+    let (results, stats) = overwrite_policy_script(OverwritePolicy::Reject);
+    assert_eq!(results, (2..6).map(ReadResult::Ok).collect::<Vec<_>>());
+    assert_eq!((stats.writes, stats.rejected), (6, 2));
+}
+
+#[test]
+fn test_overwrite_policy_reject_one_thread() {
+    let (mut reader, mut writer) =
+        ring_buffer_with_overwrite_policy::<usize>(4, OverwritePolicy::Reject);
+    assert_eq!(writer.write_checked(0), Ok(0));
+    writer.write_slice(&[1, 2, 3, 4, 5]);
+    assert_eq!(writer.write_checked(6), Err(WouldOvertake(6)));
+    assert_eq!(writer.try_write(6), Err(TryWriteError(6)));
+    assert_eq!(
+        writer.write_timeout(6, Duration::from_millis(1)),
+        Err(WriteTimeout(6))
+    );
+    assert_eq!(writer.write_returning_evicted(6), None);
+    assert_eq!(writer.write_iter(6..9), 0);
+    assert_eq!(writer.stats().rejected, 7);
+
+    // Reading makes room again, and rejected values were never written
+    assert_eq!(reader.read(), ReadResult::Ok(0));
+    assert_eq!(writer.write_returning_evicted(10), Some(0));
+    assert_eq!(writer.write(11), 5);
+    for i in 1..4 {
+        assert_eq!(reader.read(), ReadResult::Ok(i));
+    }
+    assert_eq!(reader.read(), ReadResult::Ok(10));
+    assert_eq!(reader.read(), ReadResult::Empty);
+    assert_eq!(writer.total_written(), 5);
+
+    // Without readers, nothing is rejected
+    drop(reader);
+    assert_eq!(writer.write_iter(0..10), 10);
+}
+
+#[test]
+fn test_lossless_retarget() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(4);
+    let (lossless_reader, mut lossless_writer) = ring_buffer_lossless::<usize>(4);
+    drop(lossless_reader);
+
+    lossless_writer.write(0);
+    reader.retarget_to_writer(&lossless_writer);
+    for i in 1..5 {
+        assert_eq!(lossless_writer.try_write(i), Ok(()));
+    }
+    assert_eq!(lossless_writer.try_write(5), Err(TryWriteError(5)));
+
+    // Moving the reader away again no longer holds back the writer
+    let (source, _writer) = ring_buffer::<usize>(4);
+    reader.retarget(&source);
+    assert_eq!(lossless_writer.try_write(5), Ok(()));
+    writer.write(0);
+}
+
+storage_test! {
+    fn test_slowest_reader_lag(reader, writer: usize, 16) {
+        let mut fast = reader;
+        let mut medium = fast.clone();
+        let mut slow = fast.clone();
+        assert_eq!(writer.slowest_reader_lag(), Some(0));
+
+        for i in 0..10 {
+            writer.write(i);
+        }
+        for _ in 0..10 {
+            fast.read();
+        }
+        for _ in 0..5 {
+            medium.read();
+        }
+        for _ in 0..2 {
+            slow.read();
+        }
+        assert_eq!(writer.slowest_reader_lag(), Some(8));
+        assert_eq!(writer.slowest_reader_lag(), Some(slow.lag()));
+
+        // Dropping the slowest reader leaves the next slowest
+        drop(slow);
+        assert_eq!(writer.slowest_reader_lag(), Some(5));
+        medium.skip_ahead();
+        medium.read();
+        assert_eq!(writer.slowest_reader_lag(), Some(0));
+
+        // An overtaken reader lags by at most the capacity
+        for i in 10..50 {
+            writer.write(i);
+        }
+        assert_eq!(writer.slowest_reader_lag(), Some(16));
+
+        drop(medium);
+        drop(fast);
+        assert_eq!(writer.slowest_reader_lag(), None);
+    }
+}
+
+storage_test! {
+    fn test_slowest_reader_lag_many_readers(reader, writer: usize, 16) {
+        // More readers than fit inline, where the slowest is one of the last
+        let mut readers = vec![reader];
+        for _ in 0..99 {
+            readers.push(readers[0].clone());
+        }
+        for i in 0..10 {
+            writer.write(i);
+        }
+        for reader in &mut readers[..99] {
+            while reader.read().is_ok() {}
+        }
+        assert_eq!(writer.slowest_reader_lag(), Some(10));
+        readers.pop();
+        assert_eq!(writer.slowest_reader_lag(), Some(0));
+
+        // Readers take the slots of those that are gone
+        readers.truncate(10);
+        let mut late = readers[0].clone_at_front();
+        assert_eq!(writer.slowest_reader_lag(), Some(0));
+        readers.push(readers[0].clone());
+        writer.write(10);
+        assert_eq!(writer.slowest_reader_lag(), Some(1));
+        assert_eq!(late.read(), ReadResult::Ok(10));
+
+        readers.clear();
+        assert_eq!(writer.slowest_reader_lag(), Some(0));
+        drop(late);
+        assert_eq!(writer.slowest_reader_lag(), None);
+    }
+}
+
+storage_test! {
+    fn test_promote_writer(reader, writer: usize, 8) {
+        let mut reader2 = reader.clone();
+        assert_eq!(reader.try_promote_writer().unwrap_err(), WriterStillAlive);
+
+        for i in 0..5 {
+            writer.write(i);
+        }
+        for i in 0..3 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        drop(writer);
+        for i in 3..5 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(reader.read(), ReadResult::Closed);
+        for i in 0..4 {
+            assert_eq!(reader2.read(), ReadResult::Ok(i));
+        }
+
+        // Only one reader gets to promote a writer
+        let mut writer = reader.try_promote_writer().unwrap();
+        assert_eq!(reader2.try_promote_writer().unwrap_err(), WriterStillAlive);
+        assert!(!reader.is_disconnected());
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        // Both readers see one stream, across the end of the ring buffer
+        assert_eq!(writer.write(5), 5);
+        for i in 6..12 {
+            writer.write(i);
+        }
+        for i in 5..12 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(reader.read(), ReadResult::Empty);
+        for i in 4..12 {
+            assert_eq!(reader2.read(), ReadResult::Ok(i));
+        }
+
+        writer.close();
+        assert_eq!(reader.read(), ReadResult::Closed);
+        assert!(reader2.try_promote_writer().is_ok());
+    }
+}
+
+#[test]
+fn test_promote_writer_after_panic() {
+    let (mut reader, mut writer) = ring_buffer::<usize>(32);
+
+    // The writer is dropped while unwinding
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
+        for i in 0..10 {
+            writer.write(i);
+        }
+        panic!("the producer crashed");
+    }));
+    assert!(result.is_err());
+
+    let mut writer = reader.try_promote_writer().unwrap();
+    for i in 10..20 {
+        writer.write(i);
+    }
+    for i in 0..20 {
+        assert_eq!(reader.read(), ReadResult::Ok(i));
+    }
+    assert_eq!(reader.read(), ReadResult::Empty);
+}
+
+storage_test! {
+    fn test_writer_into_reader(reader, writer: usize, 32) {
+        for i in 0..100 {
+            writer.write(i);
+        }
+
+        let mut replay = writer.into_reader();
+        assert!(replay.is_disconnected());
+        for i in 69..100 {
+            assert_eq!(replay.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(replay.read(), ReadResult::Closed);
+
+        // Other readers see the writer hang up as usual
+        assert!(reader.is_disconnected());
+        assert!(reader.read().is_dropout());
+    }
+}
+
+storage_test! {
+    fn test_clone_at_front_and_back(reader, writer: usize, 8) {
+        for i in 0..20 {
+            writer.write(i);
+        }
+        assert_eq!(reader.read(), ReadResult::Dropout(16));
+
+        // The front starts out empty, without a dropout
+        let mut front = reader.clone_at_front();
+        assert_eq!(front.read(), ReadResult::Empty);
+        assert_eq!(front.stats().dropouts, 0);
+
+        // The back has everything but the item about to be overwritten
+        let mut back = reader.clone_at_back();
+        for i in 13..20 {
+            assert_eq!(back.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(back.read(), ReadResult::Empty);
+
+        writer.write(20);
+        assert_eq!(front.read(), ReadResult::Ok(20));
+        assert_eq!(back.read(), ReadResult::Ok(20));
+
+        // The source reader stays where it was
+        assert_eq!(reader.read(), ReadResult::Ok(17));
+    }
+}
+
+storage_test! {
+    fn test_writer_add_reader(reader, writer: usize, 16) {
+        drop(reader);
+        for i in 0..1000 {
+            writer.write(i);
+        }
+
+        let mut reader = writer.add_reader();
+        assert_eq!(writer.reader_count(), 1);
+        assert_eq!(writer.slowest_reader_lag(), Some(0));
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        for i in 1000..1010 {
+            writer.write(i);
+        }
+        for i in 1000..1010 {
+            assert_eq!(reader.read(), ReadResult::Ok(i));
+        }
+        assert_eq!(reader.read(), ReadResult::Empty);
+
+        // The reader limit applies as for cloning
+        writer.set_max_readers(1);
+        assert_eq!(writer.try_add_reader().unwrap_err(), TooManyReaders(1));
+    }
+}
+
+storage_test! {
+    fn test_writer_clear(reader, writer: usize, 8) {
+        for i in 0..6 {
+            writer.write(i);
+        }
+
+        // One reader is partway through the old items, one has just skipped
+        // ahead, and one is about to be overtaken
+        let mut midway = reader;
+        for i in 0..3 {
+            assert_eq!(midway.read(), ReadResult::Ok(i));
+        }
+        let mut skipped = midway.clone();
+        skipped.skip_ahead();
+        let mut overtaken = midway.clone();
+        for i in 6..12 {
+            writer.write(i);
+        }
+        assert_eq!(writer.last_written(), Some(11));
+
+        assert_eq!(writer.slowest_reader_lag(), Some(8));
+        writer.clear();
+        assert_eq!(writer.last_written(), None);
+        assert_eq!(writer.next_sequence(), 12);
+        assert_eq!(writer.slowest_reader_lag(), Some(0));
+        for reader in [&mut midway, &mut skipped, &mut overtaken] {
+            assert!(!reader.has_data());
+            assert_eq!(reader.available(), 0);
+            assert_eq!(reader.lag(), 0);
+            assert!(reader.snapshot().is_empty());
+            assert_eq!(reader.peek(), ReadResult::Empty);
+            assert_eq!(reader.read(), ReadResult::Empty);
+            assert_eq!(reader.stats().dropouts, 0);
+            assert_eq!(reader.stats().lost, 0);
+
+            // Nothing from before the clear is read again
+            assert_eq!(reader.rewind(5), 0);
+            assert_eq!(reader.seek_to_sequence(11), SeekResult::TooOld);
+            reader.skip_ahead();
+            assert_eq!(reader.read(), ReadResult::Empty);
+        }
+        assert_eq!(writer.observed_dropouts(), 0);
+
+        // Everything afterwards is read as usual
+        for i in 12..15 {
+            writer.write(i);
+        }
+        let mut fresh = midway.clone_at_back();
+        for reader in [&mut midway, &mut skipped, &mut overtaken, &mut fresh] {
+            for i in 12..15 {
+                assert_eq!(reader.read(), ReadResult::Ok(i));
+            }
+            assert_eq!(reader.read(), ReadResult::Empty);
+        }
+        assert_eq!(midway.snapshot(), [12, 13, 14]);
+
+        // Reading the latest item after a clear doesn't skip the cleared ones
+        for i in 15..21 {
+            writer.write(i);
+        }
+        assert_eq!(midway.read(), ReadResult::Ok(15));
+        midway.reset_stats();
+        writer.clear();
+        writer.write(100);
+        assert_eq!(midway.read_latest(), ReadResult::Ok(100));
+        assert_eq!(midway.stats().skips, 0);
+        assert_eq!(midway.stats().skipped, 0);
+    }
+}
+
+storage_test! {
+    fn test_writer_clear_then_skip_ahead(reader, writer: usize, 8) {
+        for i in 0..6 {
+            writer.write(i);
+        }
+        writer.clear();
+        writer.write(100);
+
+        // The reader skips ahead from where the writer cleared, not from
+        // before it
+        let mut reader = reader;
+        reader.skip_ahead();
+        let info = DropoutInfo {
+            lost: 0,
+            expected_sequence: 6,
+            expected_lap: 0,
+            sequence: 6,
+            lap: 0,
+            index: 6,
+        };
+        assert_eq!(
+            reader.read_detailed(),
+            Detailed::Dropout { value: 100, info }
+        );
+        assert_eq!(reader.read_detailed(), Detailed::Empty);
+        assert_eq!(reader.stats().lost, 0);
+    }
+}
+
+#[test]
+fn test_writer_clear_unblocks_lossless_writer() {
+    let (mut reader, mut writer) = ring_buffer_lossless::<usize>(4);
+    for i in 0..4 {
+        writer.write(i);
+    }
+    assert_eq!(writer.try_write(4), Err(TryWriteError(4)));
+
+    // The cleared items don't have to be read first
+    writer.clear();
+    for i in 4..8 {
+        assert_eq!(writer.try_write(i), Ok(()));
+    }
+    assert_eq!(writer.try_write(8), Err(TryWriteError(8)));
+    for i in 4..8 {
+        assert_eq!(reader.read(), ReadResult::Ok(i));
+    }
+}
+
+#[test]
+fn test_writer_clear_dispatch() {
+    let (reader, mut writer) = ring_buffer::<usize>(8);
+    let mut worker = reader.into_dispatch();
+    for i in 0..4 {
+        writer.write(i);
+    }
+    assert_eq!(worker.read(), ReadResult::Ok(0));
+
+    writer.clear();
+    assert_eq!(worker.read(), ReadResult::Empty);
+    writer.write(4);
+    assert_eq!(worker.read(), ReadResult::Ok(4));
+}
@@ -0,0 +1,74 @@
+//! Items that carry the time at which they were written, for measuring how
+//! long they take to reach the readers.
+
+use std::time::{Duration, Instant};
+
+use crate::{PendingWrites, ReadResult, Reader, Storage, Writer};
+
+/// An item together with the time at which it was written, see
+/// [Writer::write_timestamped] and [Reader::read_timed]. Create a ring buffer
+/// of these to measure the latency between writing and reading.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Timestamped<T> {
+    /// The item that was written
+    pub value: T,
+
+    /// When the writer was about to make the item visible to readers
+    pub written_at: Instant,
+}
+
+impl<T: Default> Default for Timestamped<T> {
+    /// The default value, stamped with the current time, so that ring buffers
+    /// of timestamped items can be created with [ring_buffer](crate::ring_buffer)
+    fn default() -> Self {
+        Timestamped {
+            value: T::default(),
+            written_at: Instant::now(),
+        }
+    }
+}
+
+impl<T, S: Storage<Timestamped<T>>> Writer<Timestamped<T>, S> {
+    /// Write new data onto the queue like [Writer::write], stamped with the
+    /// current time. The time is taken after the item was locked, right
+    /// before it is made visible to readers, so that waiting for readers
+    /// doesn't count towards the item's age. Returns the sequence number of
+    /// the written item, which a rejected value would have had, see
+    /// [Writer::write].
+    pub fn write_timestamped(&mut self, value: T) -> u64 {
+        let sequence = self.sequence;
+        let mut pending = PendingWrites::new(self);
+        if pending.rejects() {
+            pending.stats.rejected += 1;
+        } else {
+            pending.push_timestamped(value);
+        }
+        sequence
+    }
+}
+
+impl<T, S: Storage<Timestamped<T>>> Reader<Timestamped<T>, S>
+where
+    T: Copy,
+{
+    /// Receive the next item in the queue like [Reader::read], along with
+    /// how long ago it was written, see [Writer::write_timestamped]. The age
+    /// is measured right after the item was copied out of the ring buffer.
+    pub fn read_timed(&mut self) -> ReadResult<(T, Duration)> {
+        self.read()
+            .map(|item| (item.value, item.written_at.elapsed()))
+    }
+}
+
+impl<T> PendingWrites<'_, Timestamped<T>> {
+    /// Lock and fill the next item like [PendingWrites::push], taking the
+    /// time only once the item is locked. There must be space left.
+    fn push_timestamped(&mut self, value: T) {
+        let (item, held) = self.lock_next();
+        let value = Timestamped {
+            value,
+            written_at: Instant::now(),
+        };
+        Self::fill(item, held, value);
+    }
+}
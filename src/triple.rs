@@ -0,0 +1,110 @@
+//! A triple buffer, for handing the latest value from one thread to another
+//! without either of them ever waiting. Unlike the ring buffer, where a
+//! reader and the writer that collide on an item spin until the other is
+//! done with it, the writer and reader of a triple buffer each own one of
+//! three slots and exchange them through a single atomic swap. The price is
+//! that only one reader is supported and only the latest value can be read.
+//!
+//! ```
+//! use spmcq::triple_buffer;
+//!
+//! let (mut reader, mut writer) = triple_buffer::<u32>();
+//! assert_eq!(reader.latest(), 0);
+//!
+//! writer.publish(1);
+//! writer.publish(2);
+//! assert_eq!(reader.latest(), 2);
+//! assert_eq!(reader.latest(), 2);
+//! ```
+
+use std::{cell::UnsafeCell, sync::Arc};
+
+use crate::sync::{AtomicUsize, Ordering};
+
+// Set in the back index when the slot it refers to was published after the
+// reader last took a slot
+const NEW: usize = 0b100;
+
+// The bits of the back index that identify a slot
+const SLOT: usize = 0b011;
+
+struct Shared<T> {
+    slots: [UnsafeCell<T>; 3],
+
+    // The slot that neither the writer nor the reader currently own, which
+    // holds the latest published value if NEW is set
+    back: AtomicUsize,
+}
+
+// SAFETY: each slot is only ever accessed by whichever of the writer and the
+// reader owns it, and ownership changes hands through the atomic swap of the
+// back index.
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+/// Construct a new triple buffer consisting of a [Reader] and a [Writer].
+/// Until the first value is published, the reader sees `T::default()`.
+pub fn triple_buffer<T>() -> (Reader<T>, Writer<T>)
+where
+    T: Copy + Default,
+{
+    let shared = Arc::new(Shared {
+        slots: std::array::from_fn(|_| UnsafeCell::new(T::default())),
+        back: AtomicUsize::new(2),
+    });
+
+    let reader = Reader {
+        shared: Arc::clone(&shared),
+        slot: 1,
+    };
+    let writer = Writer { shared, slot: 0 };
+
+    (reader, writer)
+}
+
+/// The reading end of a [triple_buffer]
+pub struct Reader<T> {
+    shared: Arc<Shared<T>>,
+
+    // The slot owned by the reader, holding the latest value it has seen
+    slot: usize,
+}
+
+/// The writing end of a [triple_buffer]
+pub struct Writer<T> {
+    shared: Arc<Shared<T>>,
+
+    // The slot owned by the writer, which the next value is written into
+    slot: usize,
+}
+
+impl<T: Copy> Writer<T> {
+    /// Publish a new value, replacing the previous one whether or not the
+    /// reader has seen it. This is wait-free.
+    pub fn publish(&mut self, value: T) {
+        // SAFETY: the writer owns its slot until handing it over below
+        unsafe { *self.shared.slots[self.slot].get() = value };
+
+        let back = self.shared.back.swap(self.slot | NEW, Ordering::SeqCst);
+        self.slot = back & SLOT;
+    }
+}
+
+impl<T: Copy> Reader<T> {
+    /// Returns the value that was published most recently, or the same value
+    /// as before if nothing new was published since. This is wait-free.
+    pub fn latest(&mut self) -> T {
+        if self.has_new() {
+            let back = self.shared.back.swap(self.slot, Ordering::SeqCst);
+            self.slot = back & SLOT;
+        }
+
+        // SAFETY: the reader owns its slot until handing it back above
+        unsafe { *self.shared.slots[self.slot].get() }
+    }
+
+    /// Returns whether a value was published since [Reader::latest] was last
+    /// called
+    pub fn has_new(&self) -> bool {
+        self.shared.back.load(Ordering::SeqCst) & NEW != 0
+    }
+}
@@ -0,0 +1,213 @@
+//! A small registry of threads that are parked, and with the `async` and `tokio`
+//! features, tasks that are pending while waiting for the writer to publish new
+//! data.
+
+use std::{
+    sync::Mutex,
+    thread::{self, Thread},
+};
+
+#[cfg(feature = "async")]
+use std::task::Waker;
+
+use crate::sync::{AtomicUsize, Ordering};
+
+pub(crate) struct WaitList {
+    // The number of threads and wakers in the list. Kept in sync with the list
+    // itself while holding the lock, but readable without locking so that the
+    // writer can cheaply skip waking anyone when nobody is waiting.
+    count: AtomicUsize,
+
+    waiters: Mutex<Waiters>,
+
+    // Readers awaiting Reader::recv with the `tokio` feature. Counted separately
+    // so that notify_waiters, which always locks, is only called when needed.
+    #[cfg(feature = "tokio")]
+    notify_count: AtomicUsize,
+
+    #[cfg(feature = "tokio")]
+    notify: tokio::sync::Notify,
+}
+
+struct Waiters {
+    threads: Vec<Thread>,
+
+    // Wakers of pending read futures, each tagged with a key that is unique to
+    // the future that registered it
+    #[cfg(feature = "async")]
+    wakers: Vec<(usize, Waker)>,
+
+    #[cfg(feature = "async")]
+    next_key: usize,
+}
+
+impl Waiters {
+    fn len(&self) -> usize {
+        #[cfg(feature = "async")]
+        return self.threads.len() + self.wakers.len();
+
+        #[cfg(not(feature = "async"))]
+        return self.threads.len();
+    }
+}
+
+impl WaitList {
+    pub(crate) fn new() -> WaitList {
+        WaitList {
+            count: AtomicUsize::new(0),
+            waiters: Mutex::new(Waiters {
+                threads: Vec::new(),
+                #[cfg(feature = "async")]
+                wakers: Vec::new(),
+                #[cfg(feature = "async")]
+                next_key: 0,
+            }),
+            #[cfg(feature = "tokio")]
+            notify_count: AtomicUsize::new(0),
+            #[cfg(feature = "tokio")]
+            notify: tokio::sync::Notify::new(),
+        }
+    }
+
+    /// Remove all waiting threads and tasks. Requires exclusive access.
+    pub(crate) fn reset(&mut self) {
+        self.count = AtomicUsize::new(0);
+        #[cfg(feature = "tokio")]
+        {
+            self.notify_count = AtomicUsize::new(0);
+        }
+        let waiters = self.waiters.get_mut().unwrap_or_else(|e| e.into_inner());
+        waiters.threads.clear();
+        #[cfg(feature = "async")]
+        waiters.wakers.clear();
+    }
+
+    /// Add the current thread to the list if it isn't in there already.
+    /// This must happen before the last check for new data prior to parking,
+    /// so that a write in between can't go unnoticed.
+    pub(crate) fn register(&self) {
+        let mut waiters = self.waiters.lock().unwrap_or_else(|e| e.into_inner());
+        let current = thread::current();
+        if waiters.threads.iter().all(|t| t.id() != current.id()) {
+            waiters.threads.push(current);
+            self.count.store(waiters.len(), Ordering::SeqCst);
+        }
+    }
+
+    /// Remove the current thread from the list if it's in there.
+    pub(crate) fn unregister(&self) {
+        let mut waiters = self.waiters.lock().unwrap_or_else(|e| e.into_inner());
+        let id = thread::current().id();
+        waiters.threads.retain(|t| t.id() != id);
+        self.count.store(waiters.len(), Ordering::SeqCst);
+    }
+
+    /// Add a waker to the list, or update the one that was registered before
+    /// with the same key if it's still in there. Returns the key to pass back
+    /// in when registering again or unregistering. As with threads, this must
+    /// happen before the last check for new data prior to returning pending.
+    #[cfg(feature = "async")]
+    pub(crate) fn register_waker(&self, key: Option<usize>, waker: &Waker) -> usize {
+        let mut waiters = self.waiters.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(key) = key {
+            if let Some((_, w)) = waiters.wakers.iter_mut().find(|(k, _)| *k == key) {
+                w.clone_from(waker);
+                return key;
+            }
+        }
+
+        let key = waiters.next_key;
+        waiters.next_key = waiters.next_key.wrapping_add(1);
+        waiters.wakers.push((key, waker.clone()));
+        self.count.store(waiters.len(), Ordering::SeqCst);
+        key
+    }
+
+    /// Remove the waker with the given key if it's still in the list.
+    #[cfg(feature = "async")]
+    pub(crate) fn unregister_waker(&self, key: usize) {
+        let mut waiters = self.waiters.lock().unwrap_or_else(|e| e.into_inner());
+        waiters.wakers.retain(|(k, _)| *k != key);
+        self.count.store(waiters.len(), Ordering::SeqCst);
+    }
+
+    /// Start waiting on the shared [tokio::sync::Notify]. The returned future
+    /// must be created before the last check for new data prior to awaiting
+    /// it. The notify count is decremented again when the guard is dropped.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn notified(&self) -> (tokio::sync::futures::Notified<'_>, NotifyGuard<'_>) {
+        let notified = self.notify.notified();
+        self.notify_count.fetch_add(1, Ordering::SeqCst);
+        let guard = NotifyGuard {
+            notify_count: &self.notify_count,
+        };
+        (notified, guard)
+    }
+
+    /// Wake and remove every waiting thread and task. When nobody is waiting,
+    /// this costs only a single atomic load, plus one more with the `tokio`
+    /// feature.
+    #[inline]
+    pub(crate) fn wake_all(&self) {
+        // NOTE: these loads must be SeqCst and come after the writer has released
+        // the item it just wrote. Together with the SeqCst store when registering
+        // and the reader's SeqCst lock on the item, this guarantees that either
+        // the writer sees the waiting reader here or the reader sees the new
+        // data when it checks again after registering. Acquire and Release would
+        // not be enough, since each side stores to one location and then loads
+        // the other. That is why locking and releasing items stay SeqCst, while
+        // the orderings that readers and the writer need among themselves are
+        // relaxed, see Item.
+        #[cfg(feature = "tokio")]
+        if self.notify_count.load(Ordering::SeqCst) != 0 {
+            self.notify.notify_waiters();
+        }
+
+        if self.count.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        self.wake_all_slow();
+    }
+
+    #[cfg(all(test, not(feature = "shuttle-tests")))]
+    pub(crate) fn is_empty(&self) -> bool {
+        #[cfg(feature = "tokio")]
+        if self.notify_count.load(Ordering::SeqCst) != 0 {
+            return false;
+        }
+        self.count.load(Ordering::SeqCst) == 0
+    }
+
+    #[cold]
+    fn wake_all_slow(&self) {
+        let mut waiters = self.waiters.lock().unwrap_or_else(|e| e.into_inner());
+        self.count.store(0, Ordering::SeqCst);
+        let threads = std::mem::take(&mut waiters.threads);
+        #[cfg(feature = "async")]
+        let wakers = std::mem::take(&mut waiters.wakers);
+        drop(waiters);
+
+        for thread in threads {
+            thread.unpark();
+        }
+        #[cfg(feature = "async")]
+        for (_, waker) in wakers {
+            waker.wake();
+        }
+    }
+}
+
+/// Keeps track of the number of readers waiting on the shared
+/// [tokio::sync::Notify], see [WaitList::notified]
+#[cfg(feature = "tokio")]
+pub(crate) struct NotifyGuard<'a> {
+    notify_count: &'a AtomicUsize,
+}
+
+#[cfg(feature = "tokio")]
+impl Drop for NotifyGuard<'_> {
+    fn drop(&mut self) {
+        self.notify_count.fetch_sub(1, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,119 @@
+//! A channel that only keeps the latest value, for propagating state such as
+//! configuration to any number of receivers, similar to `tokio::sync::watch`.
+//! It is a ring buffer with a capacity of one, from which receivers always
+//! read the most recent item.
+//!
+//! ```
+//! use spmcq::watch;
+//!
+//! let (mut tx, mut rx) = watch::channel(1);
+//! assert_eq!(rx.latest(), 1);
+//! assert!(!rx.changed());
+//!
+//! tx.send(2);
+//! tx.send(3);
+//! assert!(rx.changed());
+//! assert_eq!(rx.latest(), 3);
+//! assert!(!rx.changed());
+//! ```
+
+use crate::{ring_buffer, ReadResult, Reader, Writer};
+
+/// Create a watch channel holding the initial value, which receivers have
+/// already seen, so that [Receiver::changed] returns false until the next
+/// value is sent
+pub fn channel<T>(initial: T) -> (Sender<T>, Receiver<T>)
+where
+    T: Copy + Default,
+{
+    let (mut reader, mut writer) = ring_buffer(1);
+    writer.write(initial);
+    let receiver = match reader.read() {
+        ReadResult::Ok(value) => Receiver { reader, value },
+        _ => unreachable!("the initial value was just written"),
+    };
+    (Sender { writer }, receiver)
+}
+
+/// The sending half of a watch [channel]
+pub struct Sender<T> {
+    writer: Writer<T>,
+}
+
+/// The receiving half of a watch [channel]. Clones start out having seen
+/// the same value as the original.
+pub struct Receiver<T> {
+    reader: Reader<T>,
+
+    // The latest value that was read
+    value: T,
+}
+
+impl<T: Copy> Sender<T> {
+    /// Replace the value. This never blocks, apart from spinning briefly if
+    /// a receiver happens to be copying out the previous value at the same
+    /// time, see [Writer::write].
+    pub fn send(&mut self, value: T) {
+        self.writer.write(value);
+    }
+
+    /// Returns the value that was sent last, or the initial value
+    pub fn latest(&self) -> T {
+        self.writer
+            .last_written()
+            .expect("the initial value is always written")
+    }
+}
+
+impl<T> Sender<T> {
+    /// Returns whether any receivers currently exist, see
+    /// [Writer::has_readers]
+    pub fn has_receivers(&self) -> bool {
+        self.writer.has_readers()
+    }
+}
+
+impl<T: Copy> Receiver<T> {
+    /// Returns the current value, and marks it as seen
+    pub fn latest(&mut self) -> T {
+        match self.reader.read_latest() {
+            ReadResult::Ok(value) | ReadResult::Dropout(value) => self.value = value,
+            ReadResult::Empty | ReadResult::Closed => {}
+        }
+        self.value
+    }
+
+    /// Returns the value that was seen last, without checking for a newer one
+    pub fn seen(&self) -> T {
+        self.value
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Returns whether a value was sent since the current value was last
+    /// seen through [Receiver::latest]. This stays true until then, even if
+    /// the same value was sent again.
+    pub fn changed(&self) -> bool {
+        self.reader.has_data()
+    }
+
+    /// Returns whether the sender has been dropped, after which the value
+    /// will never change again
+    pub fn is_closed(&self) -> bool {
+        self.reader.is_disconnected()
+    }
+}
+
+impl<T: Copy> Clone for Receiver<T> {
+    /// Create another receiver which has seen the same value as this one.
+    ///
+    /// # Panics
+    /// Panics if the channel already has the maximum number of receivers,
+    /// see [Reader::clone].
+    fn clone(&self) -> Self {
+        Receiver {
+            reader: self.reader.clone(),
+            value: self.value,
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Model checks of how readers and the writer share items, which explore every
+//! interleaving of the threads in each test. These fail if a reader ever sees
+//! a torn item, if an item is accessed by the writer and a reader at the same
+//! time, or if an item is never unlocked again. Run them with
+//!
+//! ```text
+//! RUSTFLAGS="--cfg loom" cargo test --test loom --release
+//! ```
+
+#![cfg(loom)]
+
+use loom::thread;
+use spmcq::{ring_buffer, ring_buffer_atomic, ReadResult, Reader};
+
+/// Each value consists of two copies of the same number, so that a read
+/// which overlaps with a write shows up as two different halves
+type Pair = [u64; 2];
+
+/// Check the result of a read and return the number that it received,
+/// if any. Numbers only ever increase, since readers never go back, except
+/// that skipping ahead while caught up reads the latest item once more.
+fn check_read(result: ReadResult<Pair>, last: u64, skipped: bool) -> u64 {
+    match result {
+        ReadResult::Ok([a, b]) | ReadResult::Dropout([a, b]) => {
+            assert_eq!(a, b, "torn read");
+            assert!(
+                a > last || (skipped && a == last),
+                "read {} after {}",
+                a,
+                last
+            );
+            a
+        }
+        ReadResult::Empty | ReadResult::Closed => last,
+    }
+}
+
+fn check(result: ReadResult<Pair>, last: u64) -> u64 {
+    check_read(result, last, false)
+}
+
+/// Read everything that's left after the writer is gone. Spins forever,
+/// which loom reports, if any item was left locked.
+fn drain(reader: &mut Reader<Pair>, mut last: u64) {
+    loop {
+        match reader.read() {
+            ReadResult::Closed => return,
+            ReadResult::Empty => panic!("empty after the writer was closed"),
+            result => last = check(result, last),
+        }
+    }
+}
+
+#[test]
+fn one_writer_one_reader() {
+    loom::model(|| {
+        let (mut reader, mut writer) = ring_buffer::<Pair>(2);
+
+        let writer_thread = thread::spawn(move || {
+            for i in 1..=3 {
+                writer.write([i; 2]);
+            }
+        });
+
+        let mut last = 0;
+        for _ in 0..2 {
+            last = check(reader.read(), last);
+        }
+
+        writer_thread.join().unwrap();
+        drain(&mut reader, last);
+    });
+}
+
+#[test]
+fn one_writer_two_readers() {
+    loom::model(|| {
+        let (mut reader1, mut writer) = ring_buffer::<Pair>(2);
+        let mut reader2 = reader1.clone();
+
+        let reader_thread = thread::spawn(move || {
+            let last = check(reader2.read(), 0);
+            (reader2, last)
+        });
+
+        for i in 1..=2 {
+            writer.write([i; 2]);
+        }
+        let last = check(reader1.read(), 0);
+        drop(writer);
+
+        let (mut reader2, last2) = reader_thread.join().unwrap();
+        drain(&mut reader1, last);
+        drain(&mut reader2, last2);
+    });
+}
+
+#[test]
+fn skip_ahead_while_writing() {
+    loom::model(|| {
+        let (mut reader, mut writer) = ring_buffer::<Pair>(2);
+
+        let writer_thread = thread::spawn(move || {
+            for i in 1..=3 {
+                writer.write([i; 2]);
+            }
+        });
+
+        let mut last = check(reader.read(), 0);
+        reader.skip_ahead();
+        last = check_read(reader.read(), last, true);
+
+        writer_thread.join().unwrap();
+        drain(&mut reader, last);
+    });
+}
+
+#[test]
+fn atomic_one_writer_one_reader() {
+    loom::model(|| {
+        let (mut reader, mut writer) = ring_buffer_atomic::<u64>(2);
+
+        let writer_thread = thread::spawn(move || {
+            for i in 1..=3 {
+                writer.write(i);
+            }
+        });
+
+        // Values only ever increase, and an Ok result is always the very
+        // next value
+        let mut last = 0;
+        loop {
+            match reader.read() {
+                ReadResult::Ok(i) => {
+                    assert_eq!(i, last + 1);
+                    last = i;
+                }
+                ReadResult::Dropout(i) => {
+                    assert!(i > last + 1, "read {} after {}", i, last);
+                    last = i;
+                }
+                ReadResult::Empty => {
+                    if last > 0 {
+                        break;
+                    }
+                    thread::yield_now();
+                }
+                ReadResult::Closed => break,
+            }
+        }
+
+        writer_thread.join().unwrap();
+
+        // The last value is never overwritten
+        while let ReadResult::Ok(i) | ReadResult::Dropout(i) = reader.read() {
+            last = i;
+        }
+        assert_eq!(last, 3);
+    });
+}
@@ -0,0 +1,230 @@
+//! Differential tests against a reference model. A byte string is read as a
+//! sequence of operations, which are carried out single-threaded both on a
+//! real ring buffer and on a model that simply keeps every value ever written
+//! along with the position of each reader. Any difference between the two
+//! is a bug. The same interpreter serves as the fuzz target in `fuzz/`, whose
+//! corpus is replayed here as well. Run the fuzzer with
+//!
+//! ```text
+//! cargo +nightly fuzz run model
+//! ```
+
+#![cfg(not(loom))]
+
+use spmcq::{ring_buffer, Detailed, DropoutInfo, Reader, Writer};
+
+/// The largest number of readers at once, which keeps the operations that
+/// pick a reader meaningful
+const MAX_READERS: usize = 8;
+
+/// A reader of the [Model]
+#[derive(Clone)]
+struct ModelReader {
+    // The sequence number of the item to read next
+    position: u64,
+
+    // Where the reader was before it last skipped ahead, until its next read
+    skipped_from: Option<u64>,
+}
+
+/// What a ring buffer should do, without any of the ring. Every value ever
+/// written is kept, and whatever is older than the capacity counts as
+/// overwritten.
+struct Model {
+    capacity: u64,
+    values: Vec<u64>,
+    closed: bool,
+    readers: Vec<ModelReader>,
+}
+
+impl Model {
+    fn written(&self) -> u64 {
+        self.values.len() as u64
+    }
+
+    fn read(&mut self, reader: usize) -> Detailed<u64> {
+        let written = self.written();
+        let capacity = self.capacity;
+        let reader = &mut self.readers[reader];
+
+        if reader.position >= written {
+            return if self.closed {
+                Detailed::Closed
+            } else {
+                Detailed::Empty
+            };
+        }
+
+        // A reader that was lapped finds the newest item that has since been
+        // written to the same place in the ring
+        let laps = (written - 1 - reader.position) / capacity;
+        let sequence = reader.position + laps * capacity;
+        let value = self.values[sequence as usize];
+
+        let expected = reader.skipped_from.unwrap_or(reader.position);
+        let dropout = reader.skipped_from.is_some() || sequence != reader.position;
+        reader.position = sequence + 1;
+        reader.skipped_from = None;
+
+        if dropout {
+            Detailed::Dropout {
+                value,
+                info: DropoutInfo {
+                    // Skipping ahead while caught up reads the latest item again
+                    lost: sequence.saturating_sub(expected),
+                    expected_sequence: expected,
+                    expected_lap: expected / capacity,
+                    sequence,
+                    lap: sequence / capacity,
+                    index: (sequence % capacity) as usize,
+                },
+            }
+        } else {
+            Detailed::Ok(value)
+        }
+    }
+
+    fn skip_ahead(&mut self, reader: usize) {
+        let written = self.written();
+        let reader = &mut self.readers[reader];
+
+        // Nothing to skip to before the first write
+        if written == 0 {
+            reader.position = 0;
+            reader.skipped_from = None;
+            return;
+        }
+
+        if reader.skipped_from.is_none() {
+            reader.skipped_from = Some(reader.position);
+        }
+        reader.position = written - 1;
+    }
+
+    fn has_data(&self, reader: usize) -> bool {
+        self.readers[reader].position < self.written()
+    }
+}
+
+/// Carry out the operations encoded in `data` on both a ring buffer and the
+/// model, panicking as soon as they disagree. The first byte picks the
+/// capacity, and every following pair of bytes is one operation and the
+/// reader that it applies to, if any.
+pub fn run(data: &[u8]) {
+    let Some((&first, mut ops)) = data.split_first() else {
+        return;
+    };
+
+    // Small capacities, with and without the power-of-two fast path, get
+    // lapped quickly
+    let capacity = 1 + usize::from(first % 8);
+    let (reader, writer) = ring_buffer::<u64>(capacity);
+    let mut writer: Option<Writer<u64>> = Some(writer);
+    let mut readers: Vec<Reader<u64>> = vec![reader];
+    let mut model = Model {
+        capacity: capacity as u64,
+        values: Vec::new(),
+        closed: false,
+        readers: vec![ModelReader {
+            position: 0,
+            skipped_from: None,
+        }],
+    };
+
+    let arg = |ops: &mut &[u8]| match ops.split_first() {
+        Some((&byte, rest)) => {
+            *ops = rest;
+            usize::from(byte)
+        }
+        None => 0,
+    };
+
+    while let Some((&op, rest)) = ops.split_first() {
+        ops = rest;
+        let which = arg(&mut ops) % readers.len();
+        match op % 8 {
+            // Writes are the most common, so that readers get lapped
+            0..=2 => {
+                if let Some(writer) = &mut writer {
+                    let value = model.written() * 1000 + op as u64;
+                    writer.write(value);
+                    model.values.push(value);
+                }
+            }
+            3 | 4 => {
+                let actual = readers[which].read_detailed();
+                let expected = model.read(which);
+                assert_eq!(actual, expected, "read by reader {}", which);
+            }
+            5 => {
+                readers[which].skip_ahead();
+                model.skip_ahead(which);
+            }
+            6 => {
+                if readers.len() < MAX_READERS {
+                    readers.push(readers[which].clone());
+                    model.readers.push(model.readers[which].clone());
+                } else {
+                    readers.swap_remove(which);
+                    model.readers.swap_remove(which);
+                }
+            }
+            _ => {
+                if op & 0x80 != 0 {
+                    writer = None;
+                    model.closed = true;
+                } else if readers.len() > 1 {
+                    readers.swap_remove(which);
+                    model.readers.swap_remove(which);
+                }
+            }
+        }
+
+        for (index, reader) in readers.iter().enumerate() {
+            assert_eq!(
+                reader.has_data(),
+                model.has_data(index),
+                "has_data of reader {}",
+                index
+            );
+        }
+    }
+
+    // Whatever is left must come out the same way
+    for (index, reader) in readers.iter_mut().enumerate() {
+        for _ in 0..=capacity {
+            assert_eq!(reader.read_detailed(), model.read(index));
+        }
+    }
+}
+
+/// Replay every input in the fuzzer's corpus
+#[test]
+fn test_model_corpus() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fuzz/corpus/model");
+    let mut count = 0;
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        run(&std::fs::read(&path).unwrap());
+        count += 1;
+    }
+    assert!(count > 0, "the corpus is empty");
+}
+
+/// Run a few thousand pseudo-random inputs of varying lengths
+#[test]
+fn test_model_random() {
+    let mut state: u64 = 12345;
+    let mut next = move || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 33) as u8
+    };
+
+    let cases = if cfg!(miri) { 20 } else { 4000 };
+    for case in 0..cases {
+        let data: Vec<u8> = (0..1 + case % 300).map(|_| next()).collect();
+        run(&data);
+    }
+}
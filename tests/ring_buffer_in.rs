@@ -0,0 +1,125 @@
+//! Ring buffers whose items live in a buffer that the caller allocated, see
+//! `ring_buffer_in`, or inline in a `StaticRingBuffer`. This has a test binary
+//! of its own because it counts the allocations of the current thread through
+//! the global allocator.
+
+#![cfg(not(loom))]
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    mem::MaybeUninit,
+};
+
+use spmcq::{ring_buffer_in, CapacityError, Item, ReadResult, StaticRingBuffer};
+
+struct CountingAllocator;
+
+thread_local! {
+    // The number of bytes that the current thread has allocated
+    static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.with(|allocated| allocated.set(allocated.get() + layout.size()));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns the number of bytes that the current thread allocates while
+/// running the given function
+fn allocated_by<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let before = ALLOCATED.with(Cell::get);
+    let result = f();
+    (result, ALLOCATED.with(Cell::get) - before)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Page([u8; 4096]);
+
+#[test]
+fn test_ring_buffer_in_reuses_items() {
+    let (items, allocated) = allocated_by(|| Box::<[Item<Page>]>::new_uninit_slice(64));
+    assert!(allocated >= 64 * std::mem::size_of::<Item<Page>>());
+
+    // Only the header is allocated, which is far smaller than a single item
+    let ((mut reader, mut writer), allocated) = allocated_by(|| ring_buffer_in(items));
+    assert!(
+        allocated < std::mem::size_of::<Item<Page>>(),
+        "allocated {} bytes",
+        allocated
+    );
+    assert_eq!(writer.capacity(), 64);
+
+    let (_, allocated) = allocated_by(|| {
+        assert_eq!(reader.read(), ReadResult::Empty);
+        for i in 0..100 {
+            writer.write(Page([i; 4096]));
+        }
+        let ReadResult::Dropout(Page(first)) = reader.read() else {
+            panic!("the reader wasn't overtaken");
+        };
+        assert!(reader.read() == ReadResult::Ok(Page([first[0] + 1; 4096])));
+    });
+    assert_eq!(allocated, 0);
+}
+
+#[test]
+fn test_ring_buffer_in_from_layout() {
+    let layout = Item::<u64>::layout(8).unwrap();
+    assert_eq!(layout.size(), 8 * std::mem::size_of::<Item<u64>>());
+
+    let items: Box<[MaybeUninit<Item<u64>>]> = unsafe {
+        let ptr = std::alloc::alloc(layout).cast::<MaybeUninit<Item<u64>>>();
+        assert!(!ptr.is_null());
+        Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, 8))
+    };
+    let (mut reader, mut writer) = ring_buffer_in(items);
+    let mut other = reader.clone();
+
+    writer.write(1);
+    drop(writer);
+    assert_eq!(reader.read(), ReadResult::Ok(1));
+    assert_eq!(reader.read(), ReadResult::Closed);
+    assert_eq!(other.read(), ReadResult::Ok(1));
+}
+
+#[test]
+fn test_item_layout_capacity() {
+    assert_eq!(Item::<u64>::layout(0), Err(CapacityError::TooSmall(0)));
+    assert_eq!(
+        Item::<u64>::layout(usize::MAX),
+        Err(CapacityError::TooLarge(usize::MAX))
+    );
+}
+
+#[test]
+#[should_panic]
+fn test_ring_buffer_in_empty() {
+    ring_buffer_in(Box::<[Item<u64>]>::new_uninit_slice(0));
+}
+
+#[test]
+fn test_static_ring_buffer_readers_dont_allocate() {
+    let mut buffer = StaticRingBuffer::<u64, 8>::new();
+    let (_, allocated) = allocated_by(|| {
+        let (mut reader, mut writer) = buffer.split();
+        let mut other = reader.clone();
+        let mut back = reader.clone_at_back();
+        writer.write(1);
+        assert_eq!(reader.read(), ReadResult::Ok(1));
+        assert_eq!(other.read(), ReadResult::Ok(1));
+        assert_eq!(back.read(), ReadResult::Ok(1));
+        assert_eq!(writer.slowest_reader_lag(), Some(0));
+        drop(other);
+    });
+    assert_eq!(allocated, 0);
+}
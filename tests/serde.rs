@@ -0,0 +1,51 @@
+//! Round trips through `serde_json` with the `serde` feature. These live in a
+//! test binary of their own, since linking `serde_json` into the unit tests
+//! would make the type inference of their comparisons ambiguous. Run with
+//! `cargo test --features serde --test serde`.
+
+#![cfg(all(feature = "serde", not(loom)))]
+
+use spmcq::{ring_buffer, ReadResult, Snapshot};
+
+#[test]
+fn test_serde_read_result() {
+    for result in [
+        ReadResult::Ok(1u32),
+        ReadResult::Dropout(2),
+        ReadResult::Empty,
+        ReadResult::Closed,
+    ] {
+        let json = serde_json::to_string(&result).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ReadResult<u32>>(&json).unwrap(),
+            result
+        );
+    }
+    assert_eq!(
+        serde_json::to_string(&ReadResult::Ok(1)).unwrap(),
+        r#"{"Ok":1}"#
+    );
+}
+
+#[test]
+fn test_serde_snapshot() {
+    let (mut reader, mut writer) = ring_buffer::<[u16; 2]>(4);
+    writer.write_iter((0..10).map(|i| [i, i * 2]));
+    let ReadResult::Dropout((sequence, _)) = reader.read_indexed() else {
+        panic!("the reader wasn't overtaken");
+    };
+    writer.write([10, 20]);
+
+    let snapshot = reader.snapshot_detailed();
+    assert_eq!(snapshot.capacity, 4);
+    assert_eq!(snapshot.first_sequence, 7);
+    assert_eq!(snapshot.write_sequence, 11);
+    assert_eq!(snapshot.read_sequence, sequence + 1);
+    assert_eq!(snapshot.items, [[7, 14], [8, 16], [9, 18], [10, 20]]);
+
+    let json = serde_json::to_string(&snapshot).unwrap();
+    assert_eq!(
+        serde_json::from_str::<Snapshot<[u16; 2]>>(&json).unwrap(),
+        snapshot
+    );
+}
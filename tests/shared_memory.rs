@@ -0,0 +1,214 @@
+//! Ring buffers in memory that is shared between mappings and processes, see
+//! `Writer::create_in_raw` and `Reader::attach_to_raw`. Run with
+//! `cargo test --features shared-memory --test shared_memory`.
+
+#![cfg(all(unix, feature = "shared-memory", not(loom)))]
+
+use spmcq::{RawError, RawStorage, ReadResult, Reader, Writer};
+
+/// Map a zeroed region of the given length that child processes share
+fn map_anonymous(len: usize) -> *mut u8 {
+    // SAFETY: a new mapping doesn't alias anything
+    let ptr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    assert_ne!(ptr, libc::MAP_FAILED);
+    ptr.cast()
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_shared_memory_two_mappings() {
+    let (layout, _) = RawStorage::<u64>::layout(4).unwrap();
+    let len = layout.size();
+
+    // Two mappings of the same file, at different addresses
+    // SAFETY: the name is a valid C string, and the file descriptor is used
+    // only while it's open
+    let (first, second) = unsafe {
+        let fd = libc::memfd_create(c"spmcq".as_ptr(), 0);
+        assert!(fd >= 0);
+        assert_eq!(libc::ftruncate(fd, len as libc::off_t), 0);
+        let map = || {
+            let ptr = libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            );
+            assert_ne!(ptr, libc::MAP_FAILED);
+            ptr.cast::<u8>()
+        };
+        let mappings = (map(), map());
+        libc::close(fd);
+        mappings
+    };
+    assert_ne!(first, second);
+
+    // SAFETY: both mappings stay mapped until the end, and hold u64s only
+    let mut writer = unsafe { Writer::<u64, _>::create_in_raw(first, len, 4) }.unwrap();
+    writer.write(1);
+    let mut reader = unsafe { Reader::<u64, _>::attach_to_raw(second, len) }.unwrap();
+
+    // The reader only sees what was written after it attached
+    assert_eq!(reader.read(), ReadResult::Empty);
+    writer.write(2);
+    writer.write(3);
+    assert_eq!(reader.read(), ReadResult::Ok(2));
+    assert_eq!(reader.read(), ReadResult::Ok(3));
+    assert_eq!(reader.read(), ReadResult::Empty);
+
+    // Overtaking the reader is detected across mappings
+    for value in 4..12 {
+        writer.write(value);
+    }
+    assert!(matches!(reader.read(), ReadResult::Dropout(value) if value > 4));
+
+    // Clones read from the same mapping
+    let mut clone = reader.clone();
+    assert_eq!(reader.read(), clone.read());
+    assert_eq!(writer.reader_count(), 2);
+
+    drop(writer);
+    while reader.read().value().is_some() {}
+    assert_eq!(reader.read(), ReadResult::Closed);
+    drop((reader, clone));
+
+    // SAFETY: nothing uses the mappings anymore
+    unsafe {
+        libc::munmap(first.cast(), len);
+        libc::munmap(second.cast(), len);
+    }
+}
+
+#[test]
+fn test_shared_memory_fork() {
+    let (layout, _) = RawStorage::<[u32; 4]>::layout(8).unwrap();
+    let len = layout.size();
+    let region = map_anonymous(len);
+
+    // SAFETY: the region stays mapped until the end, in both processes
+    let writer = unsafe { Writer::<[u32; 4], _>::create_in_raw(region, len, 8) }.unwrap();
+    let mut reader = unsafe { Reader::<[u32; 4], _>::attach_to_raw(region, len) }.unwrap();
+
+    // SAFETY: the child only writes to the ring buffer and exits
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        let mut writer = writer;
+        for value in 0..20 {
+            writer.write([value; 4]);
+        }
+        drop(writer);
+        // SAFETY: exits without running anything of the parent's
+        unsafe { libc::_exit(0) };
+    }
+    // The child closes the ring buffer when it's done
+    std::mem::forget(writer);
+
+    let mut status = 0;
+    // SAFETY: pid is the child process
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    assert!(libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0);
+
+    // The reader was overtaken, and then receives the rest in order
+    let ReadResult::Dropout([first, ..]) = reader.read() else {
+        panic!("the reader wasn't overtaken");
+    };
+    for value in first + 1..20 {
+        assert_eq!(reader.read(), ReadResult::Ok([value; 4]));
+    }
+    assert_eq!(reader.read(), ReadResult::Closed);
+    drop(reader);
+
+    // SAFETY: nothing uses the region anymore
+    unsafe { libc::munmap(region.cast(), len) };
+}
+
+#[test]
+fn test_shared_memory_errors() {
+    let (layout, offset) = RawStorage::<u64>::layout(4).unwrap();
+    assert!(offset > 0 && offset.is_multiple_of(std::mem::align_of::<u64>()));
+    let len = layout.size();
+    let region = map_anonymous(len + 8);
+
+    // SAFETY: the region stays mapped until the end, and is only used by
+    // these readers and writers
+    unsafe {
+        assert_eq!(
+            Writer::<u64, _>::create_in_raw(region, len, 0).err(),
+            Some(RawError::Capacity(spmcq::CapacityError::TooSmall(0)))
+        );
+        assert_eq!(
+            Writer::<u64, _>::create_in_raw(region, len - 1, 4).err(),
+            Some(RawError::TooShort(len))
+        );
+        assert_eq!(
+            Writer::<u64, _>::create_in_raw(region.add(1), len, 4).err(),
+            Some(RawError::Misaligned(layout.align()))
+        );
+
+        // A zeroed region holds no ring buffer
+        assert_eq!(
+            Reader::<u64, _>::attach_to_raw(region, len).err(),
+            Some(RawError::NotRingBuffer)
+        );
+
+        let mut writer = Writer::<u64, _>::create_in_raw(region, len, 4).unwrap();
+        assert_eq!(
+            Reader::<[u64; 16], _>::attach_to_raw(region, len).err(),
+            Some(RawError::NotRingBuffer)
+        );
+        assert_eq!(
+            Reader::<u64, _>::attach_to_raw(region, len - 1).err(),
+            Some(RawError::TooShort(len))
+        );
+
+        writer.set_max_readers(1);
+        let reader = Reader::<u64, _>::attach_to_raw(region, len).unwrap();
+        assert!(matches!(
+            Reader::<u64, _>::attach_to_raw(region, len),
+            Err(RawError::TooManyReaders(_))
+        ));
+        drop((reader, writer));
+
+        libc::munmap(region.cast(), len + 8);
+    }
+}
+
+#[test]
+fn test_shared_memory_read_timeout_polls() {
+    let (layout, _) = RawStorage::<u64>::layout(4).unwrap();
+    let len = layout.size();
+    let region = map_anonymous(len);
+
+    // SAFETY: the region stays mapped until both threads are done
+    let mut writer = unsafe { Writer::<u64, _>::create_in_raw(region, len, 4) }.unwrap();
+    let mut reader = unsafe { Reader::<u64, _>::attach_to_raw(region, len) }.unwrap();
+
+    // The reader's thread isn't woken up by this writer, which has signals of
+    // its own, so it notices the write by polling
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            writer.write(7);
+        });
+        assert_eq!(
+            reader.read_timeout(std::time::Duration::from_secs(10)),
+            ReadResult::Ok(7)
+        );
+    });
+    drop((reader, writer));
+
+    // SAFETY: nothing uses the region anymore
+    unsafe { libc::munmap(region.cast(), len) };
+}
@@ -0,0 +1,355 @@
+//! A configurable stress test for soak testing on real hardware. Each writer
+//! has a ring buffer of its own, with a number of readers on it, and keeps
+//! writing for the given duration while the invariants are checked on every
+//! read: no value is ever torn, the values that each reader receives only
+//! ever increase, and an `Ok` result never skips a value. Only a short run
+//! is part of the test suite, and it's ignored by default:
+//!
+//! ```text
+//! cargo test --release --test stress -- --ignored
+//! ```
+//!
+//! Longer runs go through the `stress` example, which takes the same options
+//! from the command line or the environment, see `--help`:
+//!
+//! ```text
+//! cargo run --release --example stress -- --duration 3600
+//! ```
+
+#![cfg(not(loom))]
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use spmcq::{ring_buffer, ReadResult, Reader, Writer};
+
+pub const HELP: &str = "\
+Stress test for spmcq
+
+Each writer thread writes to a ring buffer of its own, which is read by a
+number of reader threads, until the duration is over. Every read is checked
+for torn values, for values that go backwards, and for Ok results that skip
+a value. Any violation panics and exits with a non-zero status.
+
+Options, which can also be set through the environment variable in brackets:
+    --writers <N>        writer threads, each with its own ring buffer
+                         [SPMCQ_STRESS_WRITERS, default 1]
+    --readers <N>        reader threads per writer
+                         [SPMCQ_STRESS_READERS, default 4]
+    --capacity <N>       capacity of each ring buffer
+                         [SPMCQ_STRESS_CAPACITY, default 64]
+    --payload <BYTES>    size of each item, one of 8, 32, 128, 512 or 2048
+                         [SPMCQ_STRESS_PAYLOAD, default 128]
+    --duration <SECS>    how long to keep writing
+                         [SPMCQ_STRESS_DURATION, default 10]
+    --max-dropout <F>    largest fraction of items that any one reader may
+                         lose to dropouts, between 0 and 1
+                         [SPMCQ_STRESS_MAX_DROPOUT, default 1]
+    --report <SECS>      how often to print progress, 0 for never
+                         [SPMCQ_STRESS_REPORT, default 10]
+    --help               print this message
+";
+
+/// The supported sizes of items, in bytes
+const PAYLOADS: [usize; 5] = [8, 32, 128, 512, 2048];
+
+/// The options of a stress test run
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub writers: usize,
+    pub readers: usize,
+    pub capacity: usize,
+    pub payload: usize,
+    pub duration: Duration,
+    pub max_dropout: f64,
+    pub report: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            writers: 1,
+            readers: 4,
+            capacity: 64,
+            payload: 128,
+            duration: Duration::from_secs(10),
+            max_dropout: 1.0,
+            report: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Config {
+    /// Read the options from the environment first and then from the given
+    /// command line arguments, which take precedence. Returns `Ok(None)` if
+    /// help was asked for.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Config>, String> {
+        let mut config = Config::default();
+
+        for (name, var) in [
+            ("writers", "SPMCQ_STRESS_WRITERS"),
+            ("readers", "SPMCQ_STRESS_READERS"),
+            ("capacity", "SPMCQ_STRESS_CAPACITY"),
+            ("payload", "SPMCQ_STRESS_PAYLOAD"),
+            ("duration", "SPMCQ_STRESS_DURATION"),
+            ("max-dropout", "SPMCQ_STRESS_MAX_DROPOUT"),
+            ("report", "SPMCQ_STRESS_REPORT"),
+        ] {
+            if let Ok(value) = std::env::var(var) {
+                config
+                    .set(name, &value)
+                    .map_err(|err| format!("{}: {}", var, err))?;
+            }
+        }
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--help" || arg == "-h" {
+                return Ok(None);
+            }
+            let (name, value) = match arg.strip_prefix("--") {
+                Some(name) => match name.split_once('=') {
+                    Some((name, value)) => (name.to_string(), value.to_string()),
+                    None => {
+                        let value = args
+                            .next()
+                            .ok_or_else(|| format!("--{} needs a value", name))?;
+                        (name.to_string(), value)
+                    }
+                },
+                None => return Err(format!("unexpected argument '{}'", arg)),
+            };
+            config
+                .set(&name, &value)
+                .map_err(|err| format!("--{}: {}", name, err))?;
+        }
+
+        Ok(Some(config))
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        fn number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+            value
+                .parse()
+                .map_err(|_| format!("'{}' isn't a valid number", value))
+        }
+
+        match name {
+            "writers" => self.writers = number(value)?,
+            "readers" => self.readers = number(value)?,
+            "capacity" => self.capacity = number(value)?,
+            "payload" => self.payload = number(value)?,
+            "duration" => self.duration = Duration::from_secs_f64(number(value)?),
+            "max-dropout" => self.max_dropout = number(value)?,
+            "report" => self.report = Duration::from_secs_f64(number(value)?),
+            _ => return Err(format!("unknown option '{}'", name)),
+        }
+
+        if self.writers == 0 || self.readers == 0 || self.capacity == 0 {
+            return Err("must be at least 1".to_string());
+        }
+        if !PAYLOADS.contains(&self.payload) {
+            return Err(format!("must be one of {:?}", PAYLOADS));
+        }
+        if !(0.0..=1.0).contains(&self.max_dropout) {
+            return Err("must be between 0 and 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// An item of `N` words, each of which holds the same value, so that an item
+/// pieced together from several writes shows up as different words
+#[derive(Clone, Copy)]
+struct Payload<const N: usize>([u64; N]);
+
+impl<const N: usize> Default for Payload<N> {
+    fn default() -> Self {
+        Payload([0; N])
+    }
+}
+
+/// The counters that are shared between all threads, for progress reports
+#[derive(Default)]
+struct Progress {
+    written: AtomicU64,
+    received: AtomicU64,
+    lost: AtomicU64,
+}
+
+/// What a reader saw, once the writer is done
+struct Summary {
+    received: u64,
+    lost: u64,
+}
+
+/// Keep writing until the deadline, and return the number of items written
+fn write<const N: usize>(
+    mut writer: Writer<Payload<N>>,
+    deadline: Instant,
+    progress: &Progress,
+) -> u64 {
+    let mut value = 0;
+    while Instant::now() < deadline {
+        // Check the time only every so often, it's slower than a write
+        for _ in 0..1024 {
+            writer.write(Payload([value; N]));
+            value += 1;
+        }
+        progress.written.fetch_add(1024, Ordering::Relaxed);
+    }
+    value
+}
+
+/// Read until the writer is gone, checking every value along the way
+fn read<const N: usize>(mut reader: Reader<Payload<N>>, progress: &Progress) -> Summary {
+    // The value that's expected next
+    let mut next = 0;
+    let mut summary = Summary {
+        received: 0,
+        lost: 0,
+    };
+
+    loop {
+        let (Payload(words), dropout) = match reader.read() {
+            ReadResult::Ok(payload) => (payload, false),
+            ReadResult::Dropout(payload) => (payload, true),
+            ReadResult::Empty => {
+                std::thread::yield_now();
+                continue;
+            }
+            ReadResult::Closed => break,
+        };
+
+        let value = words[0];
+        assert!(
+            words.iter().all(|w| *w == value),
+            "torn value, starting with {}",
+            value
+        );
+        assert!(value >= next, "read {} after {}", value, next - 1);
+        assert!(
+            dropout || value == next,
+            "Ok result skipped from {} to {}",
+            next,
+            value
+        );
+
+        let lost = value - next;
+        next = value + 1;
+        summary.received += 1;
+        summary.lost += lost;
+        if summary.received.is_multiple_of(1024) {
+            progress.received.fetch_add(1024, Ordering::Relaxed);
+        }
+        if lost > 0 {
+            progress.lost.fetch_add(lost, Ordering::Relaxed);
+        }
+    }
+
+    progress
+        .received
+        .fetch_add(summary.received % 1024, Ordering::Relaxed);
+    summary
+}
+
+fn run_with<const N: usize>(config: &Config) {
+    let progress = Progress::default();
+    let start = Instant::now();
+    let deadline = start + config.duration;
+
+    std::thread::scope(|s| {
+        let mut readers = Vec::new();
+        let mut writers = Vec::new();
+        for index in 0..config.writers {
+            let (reader, writer) = ring_buffer::<Payload<N>>(config.capacity);
+            for _ in 0..config.readers {
+                let reader = reader.clone();
+                readers.push((index, s.spawn(|| read(reader, &progress))));
+            }
+            writers.push(s.spawn(|| write(writer, deadline, &progress)));
+        }
+
+        if !config.report.is_zero() {
+            let mut next_report = start + config.report;
+            while next_report < deadline {
+                std::thread::sleep(next_report - Instant::now());
+                println!(
+                    "{:>6.0}s: {} written, {} received, {} lost",
+                    start.elapsed().as_secs_f64(),
+                    progress.written.load(Ordering::Relaxed),
+                    progress.received.load(Ordering::Relaxed),
+                    progress.lost.load(Ordering::Relaxed),
+                );
+                next_report += config.report;
+            }
+        }
+
+        let written: Vec<u64> = writers.into_iter().map(|w| w.join().unwrap()).collect();
+
+        for (index, (writer, reader)) in readers.into_iter().enumerate() {
+            let summary = reader.join().unwrap();
+
+            // The last item stays in the queue, so every reader gets it
+            assert_eq!(
+                summary.received + summary.lost,
+                written[writer],
+                "reader {} didn't see the end",
+                index
+            );
+
+            let dropout = summary.lost as f64 / written[writer] as f64;
+            assert!(
+                dropout <= config.max_dropout,
+                "reader {} lost {:.1}% of items, more than the {:.1}% allowed",
+                index,
+                dropout * 100.0,
+                config.max_dropout * 100.0
+            );
+        }
+
+        println!(
+            "done after {:.1}s: {} written, {} lost over {} readers",
+            start.elapsed().as_secs_f64(),
+            written.iter().sum::<u64>(),
+            progress.lost.load(Ordering::Relaxed),
+            config.writers * config.readers,
+        );
+    });
+}
+
+/// Run the stress test, panicking as soon as any invariant is violated
+pub fn run(config: &Config) {
+    match config.payload {
+        8 => run_with::<1>(config),
+        32 => run_with::<4>(config),
+        128 => run_with::<16>(config),
+        512 => run_with::<64>(config),
+        2048 => run_with::<256>(config),
+        n => panic!("unsupported payload size {}, see --help", n),
+    }
+}
+
+/// A short run with small and large items, which takes about five seconds
+#[test]
+#[ignore = "takes a few seconds, run with --ignored"]
+fn test_stress_smoke() {
+    let config = Config {
+        duration: Duration::from_millis(2500),
+        report: Duration::ZERO,
+        ..Config::default()
+    };
+    run(&Config {
+        payload: 8,
+        capacity: 4,
+        ..config.clone()
+    });
+    run(&Config {
+        payload: 2048,
+        writers: 2,
+        readers: 2,
+        ..config
+    });
+}